@@ -1,6 +1,6 @@
 use axum::{
     body::Body,
-    http::{Request, StatusCode},
+    http::{header, Extensions, HeaderMap, HeaderValue, Request, StatusCode, Version},
     middleware,
     response::{IntoResponse, Response},
     routing::get,
@@ -9,13 +9,21 @@ use axum::{
 use serde::Serialize;
 use std::path::PathBuf;
 use std::sync::Arc;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::set_header::{MakeHeaderValue, SetResponseHeaderLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
-use crate::auth::AppState;
+use crate::auth::{AppState, ShutdownSignal};
 use crate::config::Config;
+use crate::constants::{DEFAULT_API_CSP, MEDIA_CSP};
 use crate::database::DbPool;
 use crate::logging::request_logger;
-use crate::routes::api_router;
+use crate::metrics::{init_metrics, metrics_handler};
+use crate::processor::workers::init_image_workers;
+use crate::routes::{api_router, ApiDoc};
 use crate::webdav::webdav_router;
 use crate::VERSION;
 
@@ -33,9 +41,26 @@ async fn healthcheck() -> Json<HealthcheckResponse> {
 }
 
 pub fn create_app(config: Arc<Config>, pool: DbPool) -> Router {
+    create_app_with_shutdown(config, pool, ShutdownSignal::default())
+}
+
+/// Like `create_app`, sharing `shutdown` with the caller so health checks
+/// can report a drain started by the server loop.
+pub fn create_app_with_shutdown(
+    config: Arc<Config>,
+    pool: DbPool,
+    shutdown: ShutdownSignal,
+) -> Router {
+    init_metrics();
+
     let state = AppState {
         config: config.clone(),
         pool,
+        storage_report_cache: Default::default(),
+        cluster_cache: Default::default(),
+        recently_imported_hashes: Default::default(),
+        image_workers: init_image_workers(config.regenerate.num_cpus),
+        shutdown,
     };
 
     let cors = CorsLayer::new()
@@ -45,10 +70,16 @@ pub fn create_app(config: Arc<Config>, pool: DbPool) -> Router {
 
     let api_routes = Router::new()
         .route("/healthcheck", get(healthcheck))
-        .merge(api_router());
+        .merge(api_router())
+        .layer(content_security_policy_layer(
+            config.server.csp_policy.as_deref(),
+        ))
+        .layer(compression_layer(config.server.compression_min_size_bytes));
 
     let mut app = Router::new()
         .nest("/api/v1", api_routes)
+        .route("/metrics", get(metrics_handler))
+        .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", ApiDoc::openapi()))
         .merge(webdav_router(state.clone()))
         .layer(middleware::from_fn(request_logger))
         .layer(cors)
@@ -97,6 +128,63 @@ pub fn create_app(config: Arc<Config>, pool: DbPool) -> Router {
     app
 }
 
+/// Gzip/brotli for API responses above `min_size_bytes`. File downloads
+/// advertise byte ranges and are left alone so range offsets stay valid.
+fn compression_layer(min_size_bytes: u16) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(min_size_bytes)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(
+            |status: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions| {
+                status != StatusCode::PARTIAL_CONTENT
+                    && !headers.contains_key(header::ACCEPT_RANGES)
+            },
+        );
+
+    CompressionLayer::new()
+        .gzip(true)
+        .br(true)
+        .compress_when(predicate)
+}
+
+/// Sets `Content-Security-Policy` on API responses: `MEDIA_CSP` for
+/// image, video and other binary bodies, and the configured (or default)
+/// JSON policy for everything else. A policy a handler set is kept.
+fn content_security_policy_layer(
+    api_policy: Option<&str>,
+) -> SetResponseHeaderLayer<impl MakeHeaderValue<Response> + Clone> {
+    let api_policy = api_policy
+        .and_then(|policy| match HeaderValue::from_str(policy) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid server.csp_policy: {}", policy);
+                None
+            }
+        })
+        .unwrap_or_else(|| HeaderValue::from_static(DEFAULT_API_CSP));
+    let media_policy = HeaderValue::from_static(MEDIA_CSP);
+
+    SetResponseHeaderLayer::if_not_present(
+        header::CONTENT_SECURITY_POLICY,
+        move |response: &Response| {
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            let is_media = ["image/", "video/", "audio/", "application/octet-stream"]
+                .iter()
+                .any(|prefix| content_type.starts_with(prefix));
+            Some(if is_media {
+                media_policy.clone()
+            } else {
+                api_policy.clone()
+            })
+        },
+    )
+}
+
 async fn serve_static_file(path: PathBuf) -> Response {
     match tokio::fs::read(&path).await {
         Ok(contents) => {
@@ -1,13 +1,46 @@
 use crate::auth::jwt::decode_access_token;
+use crate::cache::ClusterCache;
 use crate::config::Config;
 use crate::database::{fetch_one, queries, DbPool};
 use crate::error::AppError;
+use crate::processor::importer::RecentImportCache;
+use crate::routes::StorageReportCache;
 use axum::{
     extract::FromRequestParts,
     http::{header::AUTHORIZATION, request::Parts},
 };
 use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Set once the server starts draining; health checks report it so load
+/// balancers stop routing new traffic here.
+#[derive(Clone, Default)]
+pub struct ShutdownSignal {
+    triggered: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl ShutdownSignal {
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `trigger` has been called.
+    pub async fn triggered(&self) {
+        let notified = self.notify.notified();
+        if self.is_triggered() {
+            return;
+        }
+        notified.await;
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct CurrentUser {
@@ -22,6 +55,11 @@ pub struct CurrentUser {
 pub struct AppState {
     pub config: Arc<Config>,
     pub pool: DbPool,
+    pub storage_report_cache: Arc<StorageReportCache>,
+    pub cluster_cache: Arc<ClusterCache>,
+    pub recently_imported_hashes: Arc<RecentImportCache>,
+    pub image_workers: Arc<rayon::ThreadPool>,
+    pub shutdown: ShutdownSignal,
 }
 
 #[derive(Deserialize)]
@@ -29,6 +67,72 @@ struct TokenQuery {
     token: Option<String>,
 }
 
+/// Resolves the bearer token to an active user without looking at
+/// `must_change_password`.
+fn authenticate(parts: &Parts, app_state: &AppState) -> Result<CurrentUser, AppError> {
+    // Try to get token from Authorization header
+    let mut token_str: Option<String> = None;
+
+    if let Some(auth_header) = parts.headers.get(AUTHORIZATION) {
+        if let Ok(auth_value) = auth_header.to_str() {
+            if let Some(bearer_token) = auth_value.strip_prefix("Bearer ") {
+                token_str = Some(bearer_token.to_string());
+            }
+        }
+    }
+
+    // Fall back to query parameter
+    if token_str.is_none() {
+        if let Some(query) = parts.uri.query() {
+            if let Ok(params) = serde_urlencoded::from_str::<TokenQuery>(query) {
+                token_str = params.token;
+            }
+        }
+    }
+
+    let token =
+        token_str.ok_or_else(|| AppError::Authentication("Not authenticated".to_string()))?;
+
+    let claims = decode_access_token(&token, &app_state.config)
+        .ok_or_else(|| AppError::Authentication("Invalid or expired token".to_string()))?;
+
+    let user_id: i64 = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Authentication("Invalid token".to_string()))?;
+
+    let conn = app_state.pool.get().map_err(AppError::Pool)?;
+
+    let user = fetch_one(
+        &conn,
+        queries::auth::SELECT_USER_FOR_TOKEN,
+        &[&user_id],
+        |row| {
+            Ok(UserRow {
+                id: row.get(0)?,
+                username: row.get(1)?,
+                email: row.get(2)?,
+                role: row.get(3)?,
+                must_change_password: row.get(4)?,
+                is_active: row.get(5)?,
+            })
+        },
+    )?
+    .ok_or_else(|| AppError::Authentication("User not found".to_string()))?;
+
+    if user.is_active == 0 {
+        return Err(AppError::Authentication("User is inactive".to_string()));
+    }
+
+    Ok(CurrentUser {
+        id: user.id,
+        username: user.username,
+        email: user.email,
+        role: user.role,
+        must_change_password: user.must_change_password != 0,
+    })
+}
+
 #[axum::async_trait]
 impl<S> FromRequestParts<S> for CurrentUser
 where
@@ -38,69 +142,35 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let app_state = AppState::from_ref(state);
+        let user = authenticate(parts, &AppState::from_ref(state))?;
 
-        // Try to get token from Authorization header
-        let mut token_str: Option<String> = None;
-
-        if let Some(auth_header) = parts.headers.get(AUTHORIZATION) {
-            if let Ok(auth_value) = auth_header.to_str() {
-                if let Some(bearer_token) = auth_value.strip_prefix("Bearer ") {
-                    token_str = Some(bearer_token.to_string());
-                }
-            }
+        if user.must_change_password {
+            return Err(AppError::PasswordChangeRequired(
+                "Password must be changed before continuing".to_string(),
+            ));
         }
 
-        // Fall back to query parameter
-        if token_str.is_none() {
-            if let Some(query) = parts.uri.query() {
-                if let Ok(params) = serde_urlencoded::from_str::<TokenQuery>(query) {
-                    token_str = params.token;
-                }
-            }
-        }
+        Ok(user)
+    }
+}
 
-        let token =
-            token_str.ok_or_else(|| AppError::Authentication("Not authenticated".to_string()))?;
-
-        let claims = decode_access_token(&token, &app_state.config)
-            .ok_or_else(|| AppError::Authentication("Invalid or expired token".to_string()))?;
-
-        let user_id: i64 = claims
-            .sub
-            .parse()
-            .map_err(|_| AppError::Authentication("Invalid token".to_string()))?;
-
-        let conn = app_state.pool.get().map_err(AppError::Pool)?;
-
-        let user = fetch_one(
-            &conn,
-            queries::auth::SELECT_USER_FOR_TOKEN,
-            &[&user_id],
-            |row| {
-                Ok(UserRow {
-                    id: row.get(0)?,
-                    username: row.get(1)?,
-                    email: row.get(2)?,
-                    role: row.get(3)?,
-                    must_change_password: row.get(4)?,
-                    is_active: row.get(5)?,
-                })
-            },
-        )?
-        .ok_or_else(|| AppError::Authentication("User not found".to_string()))?;
-
-        if user.is_active == 0 {
-            return Err(AppError::Authentication("User is inactive".to_string()));
-        }
+/// Authenticated user who may still owe a password change. Only the
+/// endpoints needed to complete that change should accept it.
+pub struct AllowPasswordChange(pub CurrentUser);
 
-        Ok(CurrentUser {
-            id: user.id,
-            username: user.username,
-            email: user.email,
-            role: user.role,
-            must_change_password: user.must_change_password != 0,
-        })
+#[axum::async_trait]
+impl<S> FromRequestParts<S> for AllowPasswordChange
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(AllowPasswordChange(authenticate(
+            parts,
+            &AppState::from_ref(state),
+        )?))
     }
 }
 
@@ -1,6 +1,7 @@
 use crate::config::Config;
 use crate::error::AppResult;
 use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
@@ -57,6 +58,19 @@ pub fn create_refresh_token(
     (raw_token, token_hash, expires_at)
 }
 
+/// Returns `(raw_token, token_hash, expires_at)` for a password reset.
+/// Only the hash is stored; the raw token is sent to the user.
+pub fn create_password_reset_token(expire_minutes: i64) -> (String, String, chrono::DateTime<Utc>) {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill(&mut bytes);
+
+    let raw_token = hex::encode(bytes);
+    let token_hash = hash_refresh_token(&raw_token);
+    let expires_at = Utc::now() + Duration::minutes(expire_minutes);
+
+    (raw_token, token_hash, expires_at)
+}
+
 pub fn decode_access_token(token: &str, config: &Config) -> Option<Claims> {
     let validation = Validation::default();
 
@@ -76,6 +90,84 @@ pub fn decode_access_token(token: &str, config: &Config) -> Option<Claims> {
     }
 }
 
+/// Reads the user id from an access token without checking its signature
+/// or expiry. Only for labelling log events; never for authorization.
+pub fn peek_access_token_subject(token: &str) -> Option<i64> {
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.required_spec_claims.clear();
+
+    decode::<Claims>(token, &DecodingKey::from_secret(&[]), &validation)
+        .ok()
+        .filter(|data| data.claims.token_type == "access")
+        .and_then(|data| data.claims.sub.parse().ok())
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn download_token_mac(media_id: i64, user_id: i64, expires_at: i64, config: &Config) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(config.security.secret_key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}:{}", media_id, user_id, expires_at).as_bytes());
+    mac
+}
+
+/// Returns a `{user_id}.{expires_at}.{signature}` token allowing download of
+/// one media item until `expires_at` (unix seconds). Nothing is stored; the
+/// HMAC over the media id, issuing user and expiry is the whole proof.
+pub fn create_download_token(
+    media_id: i64,
+    user_id: i64,
+    expires_at: i64,
+    config: &Config,
+) -> String {
+    let signature = download_token_mac(media_id, user_id, expires_at, config)
+        .finalize()
+        .into_bytes();
+    format!("{}.{}.{}", user_id, expires_at, hex::encode(signature))
+}
+
+/// Returns the id of the user who issued the token, if it is genuine and
+/// has not expired. The caller still has to check that user's access.
+pub fn verify_download_token(media_id: i64, token: &str, config: &Config) -> Option<i64> {
+    let mut parts = token.splitn(3, '.');
+    let user_id = parts.next()?.parse::<i64>().ok()?;
+    let expires_at = parts.next()?.parse::<i64>().ok()?;
+    let signature = hex::decode(parts.next()?)?;
+
+    (expires_at > Utc::now().timestamp()
+        && download_token_mac(media_id, user_id, expires_at, config)
+            .verify_slice(&signature)
+            .is_ok())
+    .then_some(user_id)
+}
+
+/// Fingerprints a slideshow: an HMAC over the query that produced it and
+/// the resulting media order, so clients can tell when either changed
+/// without the server keeping any state.
+pub fn create_slideshow_sequence_id(query: &str, media_ids: &[i64], config: &Config) -> String {
+    let mut mac = HmacSha256::new_from_slice(config.security.secret_key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(query.as_bytes());
+    for media_id in media_ids {
+        mac.update(b":");
+        mac.update(media_id.to_string().as_bytes());
+    }
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Keyed hash of a client address, so stored reports can be grouped by
+/// reporter without keeping the address or allowing it to be brute-forced
+/// back from the small IPv4 space.
+pub fn hash_client_ip(ip: &str, config: &Config) -> String {
+    let mut mac = HmacSha256::new_from_slice(config.security.secret_key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(b"client-ip:");
+    mac.update(ip.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
 pub fn hash_refresh_token(token: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(token.as_bytes());
@@ -91,4 +183,15 @@ mod hex {
             .map(|b| format!("{:02x}", b))
             .collect()
     }
+
+    pub fn decode(text: &str) -> Option<Vec<u8>> {
+        if !text.len().is_multiple_of(2) {
+            return None;
+        }
+
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
 }
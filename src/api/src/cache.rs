@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::constants::{MAP_CLUSTER_CACHE_MAX_ENTRIES, MAP_CLUSTER_CACHE_SECONDS};
+use crate::models::MapClustersResponse;
+
+/// `(user_id, bounds_hash, zoom)` for one `/map/clusters` request.
+pub type ClusterCacheKey = (i64, u64, u8);
+
+/// Recent `/map/clusters` responses. Anything that changes which geotagged
+/// media a user can see, or where it sits, bumps the version, which retires
+/// every entry computed before it. Entries also expire
+/// `MAP_CLUSTER_CACHE_SECONDS` after they were stored, so changes made by
+/// background importers still show up, and at most
+/// `MAP_CLUSTER_CACHE_MAX_ENTRIES` are kept.
+#[derive(Default)]
+pub struct ClusterCache {
+    state: Mutex<ClusterCacheState>,
+}
+
+#[derive(Default)]
+struct ClusterCacheState {
+    version: u64,
+    entries: HashMap<ClusterCacheKey, (u64, Instant, MapClustersResponse)>,
+}
+
+fn is_fresh(stored_at: &Instant) -> bool {
+    stored_at.elapsed() < Duration::from_secs(MAP_CLUSTER_CACHE_SECONDS)
+}
+
+impl ClusterCache {
+    /// Marks every cached response as stale.
+    pub fn invalidate(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.version += 1;
+        state.entries.clear();
+    }
+
+    /// Returns the current version along with the cached response for
+    /// `key`, if it was computed at that version and has not expired.
+    pub(crate) fn lookup(&self, key: &ClusterCacheKey) -> (u64, Option<MapClustersResponse>) {
+        let state = self.state.lock().unwrap();
+        let cached = state
+            .entries
+            .get(key)
+            .filter(|(version, stored_at, _)| *version == state.version && is_fresh(stored_at))
+            .map(|(_, _, response)| response.clone());
+        (state.version, cached)
+    }
+
+    /// Stores a response computed at `version`, unless an invalidation
+    /// happened while it was being computed. Expired entries are dropped
+    /// first, then the oldest ones while the cache is full.
+    pub(crate) fn store(&self, key: ClusterCacheKey, version: u64, response: MapClustersResponse) {
+        let mut state = self.state.lock().unwrap();
+        if state.version != version {
+            return;
+        }
+        state
+            .entries
+            .retain(|_, (_, stored_at, _)| is_fresh(stored_at));
+        while state.entries.len() >= MAP_CLUSTER_CACHE_MAX_ENTRIES {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, stored_at, _))| *stored_at)
+                .map(|(key, _)| *key);
+            match oldest {
+                Some(oldest) => state.entries.remove(&oldest),
+                None => break,
+            };
+        }
+        state
+            .entries
+            .insert(key, (version, Instant::now(), response));
+    }
+}
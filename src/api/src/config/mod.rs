@@ -1,12 +1,17 @@
 mod settings;
 
 use crate::constants::{
-    DEFAULT_THUMBNAIL_QUALITY, DEFAULT_THUMBNAIL_SIZE, DEFAULT_TINY_THUMBNAIL_SIZE,
-    DEFAULT_VIDEO_FRAME_QUALITY,
+    BACKUPS_DIR, DEFAULT_DATABASE_IDLE_TIMEOUT_SECONDS, DEFAULT_DATABASE_INIT_TIMEOUT_SECONDS,
+    DEFAULT_DATABASE_MAX_CONNECTIONS, DEFAULT_DATABASE_MAX_LIFETIME_SECONDS,
+    DEFAULT_DATABASE_MIN_IDLE, DEFAULT_IMPORT_CACHE_TTL_CYCLES, DEFAULT_MAX_DOWNLOAD_SIZE_BYTES,
+    DEFAULT_POST_PROCESSOR_TIMEOUT_SECONDS, DEFAULT_PREVIEW_WEBP_THRESHOLD_BYTES,
+    DEFAULT_SHARE_REPORT_SUSPEND_THRESHOLD, DEFAULT_THUMBNAIL_QUALITY, DEFAULT_THUMBNAIL_SIZE,
+    DEFAULT_TINY_THUMBNAIL_SIZE, DEFAULT_TRASH_RETENTION_DAYS, DEFAULT_VIDEO_FRAME_QUALITY,
+    DEFAULT_VIDEO_THUMBNAIL_OFFSET_SECONDS,
 };
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
@@ -16,6 +21,23 @@ pub struct ServerConfig {
     pub port: u16,
     #[serde(default)]
     pub debug: bool,
+    /// Smallest response body worth compressing, up to 65535 bytes
+    #[serde(default = "default_compression_min_size_bytes")]
+    pub compression_min_size_bytes: u16,
+    /// How long to wait for in-flight requests after a shutdown signal
+    #[serde(default = "default_shutdown_timeout_seconds")]
+    pub shutdown_timeout_seconds: u64,
+    /// Public URL clients reach the server at, e.g. behind a reverse proxy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// `Content-Security-Policy` for JSON API responses; media files keep
+    /// their own policy. Defaults to `DEFAULT_API_CSP`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub csp_policy: Option<String>,
+    /// Reverse proxies, as addresses or CIDR ranges, whose
+    /// `X-Forwarded-For` / `X-Real-IP` headers are believed
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>,
 }
 
 fn default_host() -> String {
@@ -26,16 +48,47 @@ fn default_port() -> u16 {
     8000
 }
 
+fn default_compression_min_size_bytes() -> u16 {
+    1024
+}
+
+fn default_shutdown_timeout_seconds() -> u64 {
+    30
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             host: default_host(),
             port: default_port(),
             debug: false,
+            compression_min_size_bytes: default_compression_min_size_bytes(),
+            shutdown_timeout_seconds: default_shutdown_timeout_seconds(),
+            base_url: None,
+            csp_policy: None,
+            trusted_proxies: Vec::new(),
         }
     }
 }
 
+impl ServerConfig {
+    /// Absolute link to a share, or `None` when no `base_url` is configured.
+    pub fn public_share_url(&self, token: &str) -> Option<String> {
+        let base_url = self.base_url.as_deref()?.trim_end_matches('/');
+        Some(format!("{}/public/share/{}", base_url, token))
+    }
+
+    /// Path component of `base_url` without a trailing slash, so `""` when
+    /// the server is mounted at the root or no `base_url` is set.
+    pub fn base_path(&self) -> String {
+        self.base_url
+            .as_deref()
+            .and_then(|url| reqwest::Url::parse(url).ok())
+            .map(|url| url.path().trim_end_matches('/').to_string())
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
     #[serde(default = "default_secret_key")]
@@ -167,6 +220,9 @@ pub struct WebDAVProcessing {
     pub stable_file_age_seconds: u64,
     #[serde(default = "default_max_concurrent_processing")]
     pub max_concurrent_processing: usize,
+    /// File media from `<user>/<folder>/` into an album named `<folder>`
+    #[serde(default)]
+    pub auto_create_albums: bool,
 }
 
 fn default_poll_interval() -> u64 {
@@ -187,6 +243,86 @@ impl Default for WebDAVProcessing {
             poll_interval_seconds: default_poll_interval(),
             stable_file_age_seconds: default_stable_file_age(),
             max_concurrent_processing: default_max_concurrent_processing(),
+            auto_create_albums: false,
+        }
+    }
+}
+
+/// Remote SFTP drop folder. Like the WebDAV root, each directory directly
+/// under `remote_path` is named after the Momento user who owns its files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default = "default_sftp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub key_path: PathBuf,
+    #[serde(default = "default_sftp_remote_path")]
+    pub remote_path: String,
+    #[serde(default = "default_sftp_poll_interval")]
+    pub poll_interval_seconds: u64,
+}
+
+fn default_sftp_port() -> u16 {
+    22
+}
+
+fn default_sftp_remote_path() -> String {
+    ".".to_string()
+}
+
+fn default_sftp_poll_interval() -> u64 {
+    300
+}
+
+impl Default for SftpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: default_sftp_port(),
+            username: String::new(),
+            key_path: PathBuf::new(),
+            remote_path: default_sftp_remote_path(),
+            poll_interval_seconds: default_sftp_poll_interval(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl ThumbnailFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "jpg",
+            ThumbnailFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ThumbnailFormat::Jpeg => "image/jpeg",
+            ThumbnailFormat::Webp => "image/webp",
+        }
+    }
+
+    /// Format of an existing thumbnail, judged by its extension. Rows
+    /// written before the format was configurable are always JPEG.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("webp") => ThumbnailFormat::Webp,
+            _ => ThumbnailFormat::Jpeg,
         }
     }
 }
@@ -201,6 +337,14 @@ pub struct ThumbnailConfig {
     pub quality: u8,
     #[serde(default = "default_video_frame_quality")]
     pub video_frame_quality: u8,
+    #[serde(default = "default_video_thumbnail_offset_seconds")]
+    pub video_thumbnail_offset_seconds: f64,
+    #[serde(default)]
+    pub thumbnail_format: ThumbnailFormat,
+    #[serde(default)]
+    pub preview_webp_enabled: bool,
+    #[serde(default = "default_preview_webp_threshold_bytes")]
+    pub preview_webp_threshold_bytes: u64,
 }
 
 fn default_max_size() -> u32 {
@@ -219,6 +363,14 @@ fn default_video_frame_quality() -> u8 {
     DEFAULT_VIDEO_FRAME_QUALITY
 }
 
+fn default_video_thumbnail_offset_seconds() -> f64 {
+    DEFAULT_VIDEO_THUMBNAIL_OFFSET_SECONDS
+}
+
+fn default_preview_webp_threshold_bytes() -> u64 {
+    DEFAULT_PREVIEW_WEBP_THRESHOLD_BYTES
+}
+
 impl Default for ThumbnailConfig {
     fn default() -> Self {
         Self {
@@ -226,6 +378,10 @@ impl Default for ThumbnailConfig {
             tiny_size: default_tiny_size(),
             quality: default_quality(),
             video_frame_quality: default_video_frame_quality(),
+            video_thumbnail_offset_seconds: default_video_thumbnail_offset_seconds(),
+            thumbnail_format: ThumbnailFormat::default(),
+            preview_webp_enabled: false,
+            preview_webp_threshold_bytes: default_preview_webp_threshold_bytes(),
         }
     }
 }
@@ -276,6 +432,60 @@ impl Default for ReverseGeocodingConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    #[serde(default = "default_database_max_connections")]
+    pub max_connections: u32,
+    /// Connections opened at startup and kept ready, capped at `max_connections`
+    #[serde(default = "default_database_min_idle")]
+    pub min_idle: u32,
+    /// How long startup waits for the `min_idle` connections to open; also
+    /// bounds how long a request waits for a free connection
+    #[serde(default = "default_database_init_timeout_seconds")]
+    pub init_timeout_seconds: u64,
+    /// Idle connections above `min_idle` are closed after this long, so a
+    /// quiet server releases its SQLite file handles; `None` or 0 keeps them
+    #[serde(default = "default_database_idle_timeout_seconds")]
+    pub idle_timeout_seconds: Option<u64>,
+    /// Connections are recycled after this long even when busy, so a stale
+    /// handle (e.g. on an NFS mount) cannot hold back WAL checkpoints forever;
+    /// `None` or 0 keeps them
+    #[serde(default = "default_database_max_lifetime_seconds")]
+    pub max_lifetime_seconds: Option<u64>,
+}
+
+fn default_database_max_connections() -> u32 {
+    DEFAULT_DATABASE_MAX_CONNECTIONS
+}
+
+fn default_database_min_idle() -> u32 {
+    DEFAULT_DATABASE_MIN_IDLE
+}
+
+fn default_database_init_timeout_seconds() -> u64 {
+    DEFAULT_DATABASE_INIT_TIMEOUT_SECONDS
+}
+
+fn default_database_idle_timeout_seconds() -> Option<u64> {
+    Some(DEFAULT_DATABASE_IDLE_TIMEOUT_SECONDS)
+}
+
+fn default_database_max_lifetime_seconds() -> Option<u64> {
+    Some(DEFAULT_DATABASE_MAX_LIFETIME_SECONDS)
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: default_database_max_connections(),
+            min_idle: default_database_min_idle(),
+            init_timeout_seconds: default_database_init_timeout_seconds(),
+            idle_timeout_seconds: default_database_idle_timeout_seconds(),
+            max_lifetime_seconds: default_database_max_lifetime_seconds(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegenerateConfig {
     #[serde(default = "default_regenerate_num_cpus")]
@@ -294,6 +504,240 @@ impl Default for RegenerateConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    #[serde(default)]
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+    #[serde(default = "default_smtp_from_address")]
+    pub from_address: String,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_smtp_from_address() -> String {
+    "momento@localhost".to_string()
+}
+
+impl Default for SmtpConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: default_smtp_port(),
+            username: String::new(),
+            password: String::new(),
+            from_address: default_smtp_from_address(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackupDestination {
+    Local {
+        path: PathBuf,
+    },
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        access_key: String,
+        secret_key: String,
+    },
+}
+
+impl Default for BackupDestination {
+    fn default() -> Self {
+        BackupDestination::Local {
+            path: BACKUPS_DIR.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_backup_interval_hours")]
+    pub interval_hours: u64,
+    #[serde(default)]
+    pub destination: BackupDestination,
+}
+
+fn default_backup_interval_hours() -> u64 {
+    24
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_hours: default_backup_interval_hours(),
+            destination: BackupDestination::default(),
+        }
+    }
+}
+
+/// How long trashed media is kept before `cleanup_expired_trash` purges
+/// it, chosen by the role of the user who trashed it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashConfig {
+    #[serde(default = "default_trash_retention_days")]
+    pub retention_days_user: i64,
+    #[serde(default = "default_trash_retention_days")]
+    pub retention_days_admin: i64,
+}
+
+fn default_trash_retention_days() -> i64 {
+    DEFAULT_TRASH_RETENTION_DAYS
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            retention_days_user: default_trash_retention_days(),
+            retention_days_admin: default_trash_retention_days(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareConfig {
+    /// A public share is suspended once more than this many distinct
+    /// clients have reported it.
+    #[serde(default = "default_report_suspend_threshold")]
+    pub report_suspend_threshold: i64,
+}
+
+fn default_report_suspend_threshold() -> i64 {
+    DEFAULT_SHARE_REPORT_SUSPEND_THRESHOLD
+}
+
+impl Default for ShareConfig {
+    fn default() -> Self {
+        Self {
+            report_suspend_threshold: default_report_suspend_threshold(),
+        }
+    }
+}
+
+/// External commands run in the background after each newly imported
+/// media item, one after another.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PostProcessorConfig {
+    #[serde(default)]
+    pub scripts: Vec<PostProcessorScript>,
+}
+
+/// One post-processing command. It receives the stored file path and the
+/// media id as `MOMENTO_MEDIA_PATH` and `MOMENTO_MEDIA_ID`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostProcessorScript {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_post_processor_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+fn default_post_processor_timeout_seconds() -> u64 {
+    DEFAULT_POST_PROCESSOR_TIMEOUT_SECONDS
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Plain,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    #[serde(default)]
+    pub format: LogFormat,
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+}
+
+fn default_log_level() -> String {
+    "momento_api=info,tower_http=warn".to_string()
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            level: default_log_level(),
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportConfig {
+    /// Files larger than this are rejected before any processing. Unlimited
+    /// when unset.
+    #[serde(default)]
+    pub max_file_size_bytes: Option<u64>,
+    /// Downloads through `/media/import-url` are aborted past this size.
+    #[serde(default = "default_max_download_size_bytes")]
+    pub max_download_size_bytes: u64,
+    /// Local import cycles for which an imported file's quick fingerprint
+    /// is remembered, so unchanged files are skipped without full hashing.
+    /// `0` disables the cache.
+    #[serde(default = "default_import_cache_ttl_cycles")]
+    pub cache_ttl_cycles: u8,
+    /// Directories `/import/local-directory` may read from, subdirectories
+    /// included. The endpoint refuses every path while this is empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_import_roots: Vec<PathBuf>,
+}
+
+fn default_max_download_size_bytes() -> u64 {
+    DEFAULT_MAX_DOWNLOAD_SIZE_BYTES
+}
+
+fn default_import_cache_ttl_cycles() -> u8 {
+    DEFAULT_IMPORT_CACHE_TTL_CYCLES
+}
+
+impl Default for ImportConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size_bytes: None,
+            max_download_size_bytes: default_max_download_size_bytes(),
+            cache_ttl_cycles: default_import_cache_ttl_cycles(),
+            allowed_import_roots: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ObservabilityConfig {
+    /// OTLP/HTTP collector endpoint. Tracing export is disabled when unset.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MetricsConfig {
+    /// When set, `GET /metrics` requires `Authorization: Bearer <token>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     #[serde(default)]
@@ -301,26 +745,54 @@ pub struct Config {
     #[serde(default)]
     pub security: SecurityConfig,
     #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
     pub admin: AdminConfig,
     #[serde(default)]
     pub webdav: WebDAVConfig,
     #[serde(default)]
+    pub sftp: SftpConfig,
+    #[serde(default)]
     pub thumbnails: ThumbnailConfig,
     #[serde(default)]
     pub reverse_geocoding: ReverseGeocodingConfig,
     #[serde(default)]
+    pub import: ImportConfig,
+    #[serde(default)]
     pub regenerate: RegenerateConfig,
+    #[serde(default)]
+    pub smtp: SmtpConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub observability: ObservabilityConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub trash: TrashConfig,
+    #[serde(default)]
+    pub share: ShareConfig,
+    #[serde(default)]
+    pub post_processors: PostProcessorConfig,
 }
 
 pub fn load_config(config_path: &Path) -> Config {
+    try_load_config(config_path).unwrap_or_default()
+}
+
+/// Like `load_config`, but reports read and parse errors instead of
+/// silently falling back to defaults. A missing file is not an error.
+pub fn try_load_config(config_path: &Path) -> Result<Config, String> {
     if !config_path.exists() {
-        return Config::default();
+        return Ok(Config::default());
     }
 
-    match fs::read_to_string(config_path) {
-        Ok(content) => serde_yaml::from_str(&content).unwrap_or_default(),
-        Err(_) => Config::default(),
-    }
+    let content = fs::read_to_string(config_path)
+        .map_err(|e| format!("Failed to read {}: {}", config_path.display(), e))?;
+    serde_yaml::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", config_path.display(), e))
 }
 
 pub fn save_default_config(config_path: &Path) -> std::io::Result<()> {
@@ -330,5 +802,35 @@ pub fn save_default_config(config_path: &Path) -> std::io::Result<()> {
 
     let config = Config::default();
     let yaml = serde_yaml::to_string(&config).map_err(|e| std::io::Error::other(e.to_string()))?;
-    fs::write(config_path, yaml)
+    fs::write(config_path, with_base_url_hint(&yaml))
+}
+
+const BASE_URL_HINT: &str =
+    "  # Public URL clients use to reach Momento, including any reverse proxy
+  # path. When set, share links are returned as absolute URLs and WebDAV
+  # listings use its path as their prefix.
+  # base_url: https://photos.example.com
+";
+
+/// serde_yaml cannot emit comments, so the unset `base_url` is documented
+/// by inserting a commented-out entry at the end of the `server` section.
+fn with_base_url_hint(yaml: &str) -> String {
+    let mut output = String::with_capacity(yaml.len() + BASE_URL_HINT.len());
+    let mut in_server = false;
+    for line in yaml.lines() {
+        let top_level = !line.starts_with(' ') && !line.is_empty();
+        if in_server && top_level {
+            output.push_str(BASE_URL_HINT);
+            in_server = false;
+        }
+        if line == "server:" {
+            in_server = true;
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    if in_server {
+        output.push_str(BASE_URL_HINT);
+    }
+    output
 }
@@ -13,13 +13,53 @@ pub static DATABASE_PATH: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("database.s
 pub static ORIGINALS_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("originals"));
 pub static THUMBNAILS_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("thumbnails"));
 pub static THUMBNAILS_TINY_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("thumbnails_tiny"));
+pub static ALBUM_COVERS_DIR: Lazy<PathBuf> = Lazy::new(|| THUMBNAILS_DIR.join("album_covers"));
 pub static PREVIEWS_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("previews"));
 pub static IMPORTS_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("imports"));
 pub static ALBUMS_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("albums"));
 pub static TRASH_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("trash"));
 pub static WEBDAV_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("webdav"));
+pub static SFTP_STAGING_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("sftp_staging"));
+pub static UPLOAD_STAGING_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("upload_staging"));
+pub static BACKUPS_DIR: Lazy<PathBuf> = Lazy::new(|| DATA_DIR.join("backups"));
+/// Written to a WebDAV user's directory when it is first created.
+pub const WEBDAV_WELCOME_FILENAME: &str = ".welcome.txt";
+pub const WEBDAV_WELCOME_TEXT: &str = "Welcome to Momento!
 
+Copy photos and videos into this folder to add them to your library.
+Files are imported once they have stopped changing for a little while,
+and are removed from here after a successful import. Files in subfolders
+may be added to an album named after their folder.
+
+Anything that fails to import is moved to the hidden .failed folder,
+next to a .error.txt file explaining why.
+";
+
+pub const DEFAULT_DATABASE_MAX_CONNECTIONS: u32 = 10;
+pub const DEFAULT_DATABASE_MIN_IDLE: u32 = 2;
+pub const DEFAULT_DATABASE_INIT_TIMEOUT_SECONDS: u64 = 30;
+pub const DEFAULT_DATABASE_IDLE_TIMEOUT_SECONDS: u64 = 300;
+pub const DEFAULT_DATABASE_MAX_LIFETIME_SECONDS: u64 = 3600;
+/// Fixed retention used before `TrashConfig`; kept only as a reference
+/// for migrating old deployments. Use `DEFAULT_TRASH_RETENTION_DAYS`.
 pub const TRASH_RETENTION_DAYS: i64 = 30;
+pub const DEFAULT_TRASH_RETENTION_DAYS: i64 = TRASH_RETENTION_DAYS;
+pub const IMPORT_JOB_HISTORY_LIMIT: i64 = 50;
+pub const DEFAULT_IMPORT_HISTORY_PAGE_SIZE: i64 = 20;
+pub const MAX_IMPORT_HISTORY_PAGE_SIZE: i64 = 100;
+/// Largest `limit` the paginated media and album listings accept.
+pub const MAX_LIST_PAGE_SIZE: i32 = 1000;
+/// Errors listed per job in `/import/history`; the rest are only counted.
+pub const IMPORT_HISTORY_ERRORS_LIMIT: usize = 5;
+pub const PASSWORD_RESET_TOKEN_EXPIRE_MINUTES: i64 = 60;
+pub const SHARE_TOKEN_LENGTH: usize = 22;
+pub const DEFAULT_SHARE_REPORT_SUSPEND_THRESHOLD: i64 = 3;
+pub const SHARE_REPORT_MIN_REASON_LENGTH: usize = 10;
+pub const SHARE_REPORT_MAX_REASON_LENGTH: usize = 2000;
+/// Reports one client may file within `SHARE_REPORT_RATE_WINDOW_MINUTES`
+pub const SHARE_REPORT_RATE_LIMIT: i64 = 5;
+pub const SHARE_REPORT_RATE_WINDOW_MINUTES: i64 = 60;
+pub const MAX_DEVICE_NAME_LENGTH: usize = 100;
 
 pub static IMAGE_EXTENSIONS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
     [
@@ -47,3 +87,40 @@ pub const DEFAULT_THUMBNAIL_SIZE: u32 = 400;
 pub const DEFAULT_TINY_THUMBNAIL_SIZE: u32 = 48;
 pub const DEFAULT_THUMBNAIL_QUALITY: u8 = 85;
 pub const DEFAULT_VIDEO_FRAME_QUALITY: u8 = 2;
+pub const DEFAULT_VIDEO_THUMBNAIL_OFFSET_SECONDS: f64 = 2.0;
+pub const DEFAULT_PREVIEW_WEBP_THRESHOLD_BYTES: u64 = 2 * 1024 * 1024;
+/// Every extension `/preview/get` writes previews with.
+pub const PREVIEW_EXTENSIONS: [&str; 2] = ["jpg", "webp"];
+pub const DEFAULT_SMART_ALBUM_GEOHASH_PRECISION: usize = 4;
+pub const GEOHASH_PRECISION: usize = 7;
+pub const GEOHASH_PRECISE_PRECISION: usize = 9;
+pub const LOCATION_HEATMAP_GEOHASH_PRECISION: usize = 2;
+pub const LOCATION_FACET_LIMIT: i64 = 50;
+/// Upper edges (exclusive) of the focal length histogram buckets, in mm.
+pub const FOCAL_LENGTH_BUCKET_EDGES_MM: [f64; 5] = [24.0, 50.0, 85.0, 135.0, 200.0];
+pub const DEFAULT_SMART_ALBUM_WINDOW_DAYS: i64 = 7;
+pub const MAX_DOWNLOAD_TOKEN_SECONDS: u64 = 7 * 24 * 60 * 60;
+pub const MAX_MEDIA_BATCH_SIZE: usize = 100;
+pub const MEDIA_EDIT_HISTORY_LIMIT: i64 = 100;
+pub const DEFAULT_MAX_DOWNLOAD_SIZE_BYTES: u64 = 512 * 1024 * 1024;
+pub const QUICK_FINGERPRINT_BYTES: usize = 64 * 1024;
+pub const DEFAULT_IMPORT_CACHE_TTL_CYCLES: u8 = 3;
+pub const IMPORT_URL_MAX_REDIRECTS: usize = 5;
+pub const IMPORT_URL_TIMEOUT_SECONDS: u64 = 120;
+pub const EXPORT_STREAM_BUFFER_BYTES: usize = 64 * 1024;
+/// POST bodies larger than this are not copied into the request log.
+pub const LOGGED_REQUEST_BODY_MAX_BYTES: usize = 16 * 1024;
+pub const DEFAULT_POST_PROCESSOR_TIMEOUT_SECONDS: u64 = 300;
+pub const DEFAULT_SIMILAR_MEDIA_THRESHOLD: u32 = 8;
+/// Perceptual hashes are 64 bits, so no two are further apart than this.
+pub const MAX_SIMILAR_MEDIA_THRESHOLD: u32 = 64;
+pub const STORAGE_REPORT_CACHE_SECONDS: u64 = 60;
+pub const MAP_CLUSTER_CACHE_SECONDS: u64 = 300;
+pub const MAP_CLUSTER_CACHE_MAX_ENTRIES: usize = 1024;
+pub const ORPHAN_MIN_AGE_SECONDS: u64 = 60 * 60;
+/// Sent with every JSON API response unless `server.csp_policy` is set.
+pub const DEFAULT_API_CSP: &str = "default-src 'none'; frame-ancestors 'none'";
+/// Sent with media bytes so a crafted file (an SVG, say) opened directly
+/// cannot run script: only the media itself and inline styles may load.
+pub const MEDIA_CSP: &str =
+    "default-src 'none'; img-src 'self'; media-src 'self'; style-src 'unsafe-inline'; sandbox";
@@ -0,0 +1,197 @@
+use crate::database::queries;
+use crate::database::DbConn;
+use crate::error::AppResult;
+
+/// Ordered schema migrations applied on top of `schema.sql`.
+/// The index of each entry + 1 is stored in `PRAGMA user_version`, so new
+/// migrations must only ever be appended.
+const MIGRATIONS: &[&str] = &[
+    // 1: import job history
+    r#"
+    CREATE TABLE IF NOT EXISTS import_jobs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        job_type TEXT NOT NULL,
+        status TEXT NOT NULL,
+        total_files INTEGER NOT NULL DEFAULT 0,
+        processed_files INTEGER NOT NULL DEFAULT 0,
+        successful_imports INTEGER NOT NULL DEFAULT 0,
+        failed_imports INTEGER NOT NULL DEFAULT 0,
+        started_at TEXT,
+        completed_at TEXT,
+        errors_json TEXT NOT NULL DEFAULT '[]'
+    );
+    "#,
+    // 2: password reset tokens
+    r#"
+    CREATE TABLE IF NOT EXISTS password_reset_tokens (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        token_hash TEXT NOT NULL UNIQUE,
+        expires_at TEXT NOT NULL,
+        used_at TEXT,
+        created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_password_reset_tokens_user
+        ON password_reset_tokens (user_id);
+    "#,
+    // 3: audio stream details for videos
+    r#"
+    ALTER TABLE media_metadata ADD COLUMN audio_codec TEXT;
+    ALTER TABLE media_metadata ADD COLUMN audio_channels INTEGER;
+    ALTER TABLE media_metadata ADD COLUMN audio_sample_rate INTEGER;
+    "#,
+    // 4: view-only share links
+    r#"
+    ALTER TABLE share_links ADD COLUMN permissions TEXT NOT NULL DEFAULT 'download';
+    "#,
+    // 5: denormalized search text with an external-content FTS5 index
+    r#"
+    ALTER TABLE media ADD COLUMN search_text TEXT;
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS fts_media USING fts5(
+        search_text,
+        content='media',
+        content_rowid='id'
+    );
+
+    UPDATE media
+       SET search_text = (
+           SELECT COALESCE(m.original_filename, '')
+               || ' ' || COALESCE(mm.keywords, '')
+               || ' ' || COALESCE(mm.camera_model, '')
+               || ' ' || COALESCE(mm.location_city, '')
+               || ' ' || COALESCE(mm.location_country, '')
+             FROM media AS m
+             LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+            WHERE m.id = media.id
+       );
+
+    INSERT INTO fts_media (fts_media) VALUES ('rebuild');
+    "#,
+    // 6: per-user favorites
+    r#"
+    ALTER TABLE media_access ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0;
+    "#,
+    // 7: split import successes into new media and duplicates
+    r#"
+    ALTER TABLE import_jobs ADD COLUMN new_imports INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE import_jobs ADD COLUMN duplicate_imports INTEGER NOT NULL DEFAULT 0;
+    "#,
+    // 8: perceptual hash for similar-image search
+    r#"
+    ALTER TABLE media ADD COLUMN phash INTEGER;
+    "#,
+    // 9: refresh token families for reuse detection
+    r#"
+    ALTER TABLE refresh_tokens ADD COLUMN token_family TEXT;
+
+    CREATE INDEX IF NOT EXISTS idx_refresh_tokens_family
+        ON refresh_tokens (token_family);
+    "#,
+    // 10: precision-9 geohash for point and radius queries
+    r#"
+    ALTER TABLE media_metadata ADD COLUMN geohash_precise TEXT;
+
+    CREATE INDEX IF NOT EXISTS idx_media_geohash_precise
+        ON media_metadata (geohash_precise);
+    "#,
+    // 11: user-defined album ordering
+    r#"
+    ALTER TABLE albums ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+    CREATE INDEX IF NOT EXISTS idx_albums_user_position
+        ON albums (user_id, position);
+    "#,
+    // 12: device label for session management
+    r#"
+    ALTER TABLE refresh_tokens ADD COLUMN device_name TEXT;
+    "#,
+    // 13: audit trail of manual metadata edits
+    r#"
+    CREATE TABLE IF NOT EXISTS media_edits (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        media_id INTEGER NOT NULL REFERENCES media(id) ON DELETE CASCADE,
+        user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+        field_name TEXT NOT NULL,
+        old_value TEXT,
+        new_value TEXT,
+        edited_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_media_edits_media
+        ON media_edits (media_id, id);
+    "#,
+    // 14: keep fts_media in step with media.search_text on every write
+    r#"
+    CREATE TRIGGER IF NOT EXISTS media_fts_ai AFTER INSERT ON media
+    WHEN new.search_text IS NOT NULL
+    BEGIN
+        INSERT INTO fts_media (rowid, search_text)
+        VALUES (new.id, new.search_text);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS media_fts_ad AFTER DELETE ON media
+    WHEN old.search_text IS NOT NULL
+    BEGIN
+        INSERT INTO fts_media (fts_media, rowid, search_text)
+        VALUES ('delete', old.id, old.search_text);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS media_fts_au AFTER UPDATE OF search_text ON media
+    BEGIN
+        INSERT INTO fts_media (fts_media, rowid, search_text)
+        SELECT 'delete', old.id, old.search_text
+         WHERE old.search_text IS NOT NULL;
+        INSERT INTO fts_media (rowid, search_text)
+        SELECT new.id, new.search_text
+         WHERE new.search_text IS NOT NULL;
+    END;
+    "#,
+    // 15: abuse reports against public shares, and suspension of shares
+    r#"
+    CREATE TABLE IF NOT EXISTS share_reports (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        token TEXT NOT NULL,
+        ip_hash TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        created_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_share_reports_token
+        ON share_reports (token);
+
+    ALTER TABLE share_links ADD COLUMN suspended_at TEXT;
+    "#,
+    // 16: media whose perceptual hash could not be computed, so the
+    // backfill does not retry them forever
+    r#"
+    ALTER TABLE media ADD COLUMN phash_failed_at TEXT;
+    "#,
+];
+
+fn current_version(conn: &DbConn) -> AppResult<usize> {
+    let version: i64 = conn.query_row(queries::schema::GET_USER_VERSION, [], |row| row.get(0))?;
+    Ok(version.max(0) as usize)
+}
+
+pub fn run_migrations(conn: &DbConn) -> AppResult<()> {
+    run_migrations_up_to(conn, MIGRATIONS.len())
+}
+
+/// Applies pending migrations until the schema reaches version `target`.
+pub fn run_migrations_up_to(conn: &DbConn, target: usize) -> AppResult<()> {
+    let version = current_version(conn)?;
+    let pending = &MIGRATIONS[..target.min(MIGRATIONS.len())];
+
+    for (index, migration) in pending.iter().enumerate().skip(version) {
+        let target = index + 1;
+        tracing::info!("Applying database migration {}", target);
+        conn.execute_batch(&format!(
+            "BEGIN;\n{}\nPRAGMA user_version = {};\nCOMMIT;",
+            migration, target
+        ))?;
+    }
+
+    Ok(())
+}
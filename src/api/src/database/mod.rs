@@ -1,6 +1,7 @@
+pub mod migration;
 mod pool;
 pub mod queries;
 pub mod schema;
 
 pub use pool::*;
-pub use schema::init_database;
+pub use schema::{init_database, init_database_at_version};
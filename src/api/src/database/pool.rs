@@ -1,23 +1,52 @@
+use crate::config::DatabaseConfig;
 use crate::constants::DATABASE_PATH;
 use crate::database::schema::sql;
 use crate::error::{AppError, AppResult};
 use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Row;
+use std::path::Path;
+use std::time::Duration;
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 pub type DbConn = PooledConnection<SqliteConnectionManager>;
 
-pub fn create_pool() -> AppResult<DbPool> {
-    let manager = SqliteConnectionManager::file(&*DATABASE_PATH).with_init(|conn| {
+pub fn create_pool(config: &DatabaseConfig) -> AppResult<DbPool> {
+    create_pool_at(&DATABASE_PATH, config)
+}
+
+/// Like `create_pool`, for a database file other than `DATABASE_PATH`.
+pub fn create_pool_at(path: &Path, config: &DatabaseConfig) -> AppResult<DbPool> {
+    let manager = SqliteConnectionManager::file(path).with_init(|conn| {
         conn.execute_batch(sql::PRAGMA_FOREIGN_KEYS_ON)?;
+        conn.execute_batch(sql::PRAGMA_CONNECTION_TUNING)?;
         Ok(())
     });
 
+    let max_size = config.max_connections.max(1);
+    let min_idle = config.min_idle.min(max_size);
+    let timeout = Duration::from_secs(config.init_timeout_seconds.max(1));
+    // r2d2 rejects zero durations, so 0 disables the limit like `None`
+    let seconds = |value: Option<u64>| value.filter(|s| *s > 0).map(Duration::from_secs);
+
+    // `build` blocks until `min_idle` connections are open, so the first
+    // requests after startup do not pay for opening them
     Pool::builder()
-        .max_size(10)
+        .max_size(max_size)
+        .min_idle(Some(min_idle))
+        .connection_timeout(timeout)
+        .idle_timeout(seconds(config.idle_timeout_seconds))
+        .max_lifetime(seconds(config.max_lifetime_seconds))
         .build(manager)
-        .map_err(|e| AppError::Internal(format!("Failed to create database pool: {}", e)))
+        .map_err(|e| {
+            AppError::Internal(format!(
+                "Failed to open {} database connection(s) to {} within {}s: {}",
+                min_idle,
+                path.display(),
+                timeout.as_secs(),
+                e
+            ))
+        })
 }
 
 pub fn get_connection(pool: &DbPool) -> AppResult<DbConn> {
@@ -1,3 +1,23 @@
+/// Set when the taken date and the upload date are more than a year apart,
+/// which usually means the camera clock was never set. Expects the media
+/// row as `m` and its metadata as `mm`.
+macro_rules! date_mismatch_condition {
+    () => {
+        "ABS(JULIANDAY(mm.date_taken) - JULIANDAY(m.created_at)) > 365"
+    };
+}
+
+/// `date_mismatch_condition!` as the `has_date_mismatch` result column.
+macro_rules! date_mismatch_column {
+    () => {
+        concat!(
+            "CASE WHEN ",
+            date_mismatch_condition!(),
+            " THEN 1 ELSE 0 END AS has_date_mismatch"
+        )
+    };
+}
+
 pub mod schema {
     pub const TABLE_EXISTS: &str = r#"
     SELECT COUNT(*)
@@ -5,6 +25,10 @@ pub mod schema {
      WHERE type = 'table'
        AND name = ?
     "#;
+
+    pub const GET_USER_VERSION: &str = "PRAGMA user_version";
+
+    pub const VACUUM_INTO: &str = "VACUUM INTO ?";
 }
 
 pub mod media {
@@ -33,6 +57,7 @@ pub mod media {
       , gps_longitude
       , gps_altitude
       , geohash
+      , geohash_precise
       , location_city
       , location_state
       , location_country
@@ -46,8 +71,11 @@ pub mod media {
       , focal_length
       , focal_length_35mm
       , video_codec
+      , audio_codec
+      , audio_channels
+      , audio_sample_rate
       , keywords
-    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
     "#;
 
     pub const SELECT_BY_CONTENT_HASH: &str = r#"
@@ -57,7 +85,45 @@ pub mod media {
      LIMIT 1
     "#;
 
-    pub const SELECT_ALL_FOR_USER: &str = r#"
+    pub const SELECT_ACCESSIBLE_BY_CONTENT_HASH: &str = r#"
+    SELECT m.id
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+     WHERE m.content_hash = ?
+       AND ma.user_id = ?
+       AND ma.deleted_at IS NULL
+     LIMIT 1
+    "#;
+
+    /// Slideshow order for an album: oldest first, undated media last.
+    /// Parameters: album id, user id.
+    pub const SELECT_SLIDESHOW_IDS_FOR_ALBUM: &str = r#"
+    SELECT m.id
+      FROM album_media AS am
+      JOIN media AS m ON am.media_id = m.id
+      JOIN media_access AS ma ON m.id = ma.media_id
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE am.album_id = ?
+       AND ma.user_id = ?
+       AND ma.deleted_at IS NULL
+     ORDER BY mm.date_taken IS NULL, mm.date_taken ASC, m.id ASC
+    "#;
+
+    /// Parameters: tag id, user id.
+    pub const SELECT_SLIDESHOW_IDS_FOR_TAG: &str = r#"
+    SELECT m.id
+      FROM media_tags AS mt
+      JOIN media AS m ON mt.media_id = m.id
+      JOIN media_access AS ma ON m.id = ma.media_id
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE mt.tag_id = ?
+       AND ma.user_id = ?
+       AND ma.deleted_at IS NULL
+     ORDER BY mm.date_taken IS NULL, mm.date_taken ASC, m.id ASC
+    "#;
+
+    pub const SELECT_ALL_FOR_USER: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -84,17 +150,26 @@ pub mod media {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
       FROM media AS m
       JOIN media_access AS ma ON m.id = ma.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
      WHERE ma.user_id = ?
        AND ma.deleted_at IS NULL
      ORDER BY mm.date_taken DESC, m.id DESC
-    "#;
+    "#
+    );
 
-    pub const SELECT_PAGINATED_FOR_USER: &str = r#"
+    pub const SELECT_PAGINATED_FOR_USER: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -121,8 +196,15 @@ pub mod media {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
       FROM media AS m
       JOIN media_access AS ma ON m.id = ma.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -131,9 +213,11 @@ pub mod media {
        AND (mm.date_taken < ? OR (mm.date_taken = ? AND m.id < ?))
      ORDER BY mm.date_taken DESC, m.id DESC
      LIMIT ?
-    "#;
+    "#
+    );
 
-    pub const SELECT_BY_ID: &str = r#"
+    pub const SELECT_BY_ID: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -160,14 +244,22 @@ pub mod media {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
       FROM media AS m
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
      WHERE m.id = ?
-    "#;
+    "#
+    );
 
-    pub const SELECT_BY_ID_AND_USER: &str = r#"
+    pub const SELECT_BY_ID_AND_USER: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -194,15 +286,23 @@ pub mod media {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
       FROM media AS m
       JOIN media_access AS ma ON m.id = ma.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
      WHERE m.id = ?
        AND ma.user_id = ?
        AND ma.deleted_at IS NULL
-    "#;
+    "#
+    );
 
     pub const CHECK_EXISTS: &str = r#"
     SELECT m.id
@@ -231,7 +331,20 @@ pub mod media {
        AND ma.user_id = ?
     "#;
 
-    pub const SELECT_FOR_MAP: &str = r#"
+    /// Like `SELECT_FILE_INFO`, but ignores items the user has trashed.
+    pub const SELECT_ACTIVE_FILE_INFO: &str = r#"
+    SELECT m.file_path
+         , m.mime_type
+         , m.original_filename
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+     WHERE m.id = ?
+       AND ma.user_id = ?
+       AND ma.deleted_at IS NULL
+    "#;
+
+    pub const SELECT_FOR_MAP: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -258,8 +371,15 @@ pub mod media {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
       FROM media AS m
       JOIN media_access AS ma ON m.id = ma.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -267,7 +387,8 @@ pub mod media {
        AND ma.deleted_at IS NULL
        AND mm.gps_latitude IS NOT NULL
        AND mm.gps_longitude IS NOT NULL
-    "#;
+    "#
+    );
 
     pub const SELECT_THUMBNAIL_BATCH: &str = r#"
     SELECT m.id
@@ -291,6 +412,69 @@ pub mod media {
      WHERE ma.user_id = ?
     "#;
 
+    pub const UPDATE_LOCATION: &str = r#"
+    INSERT INTO media_metadata (
+        media_id
+      , gps_latitude
+      , gps_longitude
+      , geohash
+      , geohash_precise
+      , location_city
+      , location_state
+      , location_country
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT(media_id) DO UPDATE SET
+        gps_latitude = excluded.gps_latitude
+      , gps_longitude = excluded.gps_longitude
+      , geohash = excluded.geohash
+      , geohash_precise = excluded.geohash_precise
+      , location_city = excluded.location_city
+      , location_state = excluded.location_state
+      , location_country = excluded.location_country
+    "#;
+
+    pub const CLEAR_LOCATION: &str = r#"
+    UPDATE media_metadata
+       SET gps_latitude = NULL
+         , gps_longitude = NULL
+         , gps_altitude = NULL
+         , location_city = NULL
+         , location_state = NULL
+         , location_country = NULL
+         , geohash = NULL
+         , geohash_precise = NULL
+     WHERE media_id = ?
+    "#;
+
+    pub const UPDATE_FILE_HASH_AND_SIZE: &str = r#"
+    UPDATE media
+       SET content_hash = ?
+         , file_size = ?
+     WHERE id = ?
+    "#;
+
+    pub const SWAP_DIMENSIONS: &str = r#"
+    UPDATE media_metadata
+       SET width = height
+         , height = width
+     WHERE media_id = ?
+    "#;
+
+    pub const SELECT_THUMBNAIL_SOURCE: &str = r#"
+    SELECT m.file_path
+         , m.media_type
+         , mm.thumbnail_path
+      FROM media AS m
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE m.id = ?
+    "#;
+
+    pub const SELECT_THUMBNAIL_PATH: &str = r#"
+    SELECT thumbnail_path
+      FROM media_metadata
+     WHERE media_id = ?
+    "#;
+
     pub const UPDATE_CONTENT_HASH: &str = r#"
     UPDATE media
        SET content_hash = ?
@@ -303,6 +487,351 @@ pub mod media {
      WHERE content_hash IS NULL
     "#;
 
+    pub const UPDATE_PHASH: &str = r#"
+    UPDATE media
+       SET phash = ?
+     WHERE id = ?
+    "#;
+
+    pub const MARK_PHASH_FAILED: &str = r#"
+    UPDATE media
+       SET phash_failed_at = datetime('now')
+     WHERE id = ?
+    "#;
+
+    pub fn build_select_owned_dates_taken(count: usize) -> String {
+        let placeholders = vec!["?"; count].join(", ");
+        format!(
+            r#"
+    SELECT ma.media_id
+         , mm.date_taken
+      FROM media_access AS ma
+      LEFT JOIN media_metadata AS mm ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.access_level >= 2
+       AND ma.deleted_at IS NULL
+       AND ma.media_id IN ({placeholders})
+    "#
+        )
+    }
+
+    pub const UPDATE_DATE_TAKEN: &str = r#"
+    UPDATE media_metadata
+       SET date_taken = ?
+     WHERE media_id = ?
+    "#;
+
+    pub const UPDATE_GEOHASHES: &str = r#"
+    INSERT INTO media_metadata (
+        media_id
+      , geohash
+      , geohash_precise
+    ) VALUES (?, ?, ?)
+    ON CONFLICT(media_id) DO UPDATE SET
+        geohash = excluded.geohash
+      , geohash_precise = excluded.geohash_precise
+    "#;
+
+    pub const SELECT_WITHOUT_PRECISE_GEOHASH: &str = r#"
+    SELECT media_id
+         , gps_latitude
+         , gps_longitude
+      FROM media_metadata
+     WHERE geohash_precise IS NULL
+       AND gps_latitude IS NOT NULL
+       AND gps_longitude IS NOT NULL
+    "#;
+
+    pub const SELECT_WITHOUT_PHASH: &str = r#"
+    SELECT m.id
+         , m.file_path
+         , m.media_type
+         , mm.thumbnail_path
+      FROM media AS m
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE m.phash IS NULL
+       AND m.phash_failed_at IS NULL
+    "#;
+
+    pub const SELECT_PHASH: &str = r#"
+    SELECT m.phash
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+     WHERE m.id = ?
+       AND ma.user_id = ?
+       AND ma.deleted_at IS NULL
+    "#;
+
+    /// Hashes of every other live media item the user can see; distances
+    /// are computed in Rust since SQLite has no popcount.
+    pub const SELECT_PHASH_CANDIDATES: &str = r#"
+    SELECT m.id
+         , m.phash
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND m.phash IS NOT NULL
+       AND m.id != ?
+    "#;
+
+    /// Items sharing a perceptual hash; `content_hash` is unique, so only
+    /// re-encoded or resized copies can show up here. Keeping the largest
+    /// copy of each group would free `wasted_bytes`.
+    pub const SELECT_DUPLICATE_GROUPS: &str = r#"
+    SELECT phash
+         , json_group_array(id) AS media_ids
+         , COUNT(*) AS cnt
+         , COALESCE(SUM(file_size) - MAX(file_size), 0) AS wasted_bytes
+      FROM (SELECT id, phash, file_size FROM media WHERE phash IS NOT NULL ORDER BY id)
+     GROUP BY phash
+    HAVING cnt > 1
+     ORDER BY cnt DESC, phash
+    "#;
+
+    pub const SELECT_DATE_MISMATCHES: &str = concat!(
+        r#"
+    SELECT m.id
+         , m.filename
+         , m.original_filename
+         , m.media_type
+         , m.mime_type
+         , mm.width
+         , mm.height
+         , m.file_size
+         , mm.duration_seconds
+         , mm.date_taken
+         , mm.gps_latitude
+         , mm.gps_longitude
+         , mm.camera_make
+         , mm.camera_model
+         , mm.lens_make
+         , mm.lens_model
+         , mm.iso
+         , mm.exposure_time
+         , mm.f_number
+         , mm.focal_length
+         , mm.focal_length_35mm
+         , mm.gps_altitude
+         , mm.location_city
+         , mm.location_state
+         , mm.location_country
+         , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
+         , mm.keywords
+         , m.created_at
+         , 1 AS has_date_mismatch
+         , 0 AS is_favorite
+      FROM media AS m
+      JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE "#,
+        date_mismatch_condition!(),
+        r#"
+     ORDER BY mm.date_taken, m.id
+    "#
+    );
+
+    pub const SELECT_LENS_MODELS_FOR_USER: &str = r#"
+    SELECT DISTINCT mm.lens_model
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.lens_model IS NOT NULL
+       AND TRIM(mm.lens_model) != ''
+     ORDER BY mm.lens_model
+    "#;
+
+    pub const SELECT_FOCAL_LENGTH_COUNTS_FOR_USER: &str = r#"
+    SELECT mm.focal_length_35mm, COUNT(*)
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.focal_length_35mm IS NOT NULL
+     GROUP BY mm.focal_length_35mm
+    "#;
+
+    /// Media counts per `date_taken` prefix; the prefix length (4, 7 or 10)
+    /// selects year, month or day buckets.
+    pub const SELECT_DATE_BUCKETS: &str = r#"
+    SELECT SUBSTR(mm.date_taken, 1, ?) AS bucket
+         , COUNT(*) AS cnt
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.date_taken IS NOT NULL
+     GROUP BY bucket
+     ORDER BY bucket DESC
+    "#;
+
+    pub const SELECT_STATS_FOR_USER: &str = r#"
+    SELECT COUNT(*) AS total_media
+         , COALESCE(SUM(CASE WHEN m.media_type = 'image' THEN 1 ELSE 0 END), 0) AS total_photos
+         , COALESCE(SUM(CASE WHEN m.media_type = 'video' THEN 1 ELSE 0 END), 0) AS total_videos
+         , COALESCE(SUM(m.file_size), 0) AS total_size_bytes
+         , MIN(mm.date_taken) AS oldest_date
+         , MAX(mm.date_taken) AS newest_date
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+    "#;
+
+    pub const SELECT_TOP_CAMERAS_FOR_USER: &str = r#"
+    SELECT TRIM(COALESCE(mm.camera_make, '') || ' ' || COALESCE(mm.camera_model, '')) AS camera
+         , COUNT(*) AS cnt
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND (mm.camera_make IS NOT NULL OR mm.camera_model IS NOT NULL)
+     GROUP BY camera
+     ORDER BY cnt DESC, camera
+     LIMIT 5
+    "#;
+
+    pub const SELECT_CAMERAS_FOR_USER: &str = r#"
+    SELECT COALESCE(mm.camera_make, '') AS make
+         , mm.camera_model
+         , COUNT(*) AS cnt
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.camera_model IS NOT NULL
+     GROUP BY make, mm.camera_model
+     ORDER BY cnt DESC, make, mm.camera_model
+    "#;
+
+    pub const SELECT_GEOTAGGED_FOR_USER: &str = r#"
+    SELECT m.id
+         , mm.geohash
+         , mm.date_taken
+         , mm.gps_latitude
+         , mm.gps_longitude
+         , mm.location_city
+         , mm.location_country
+      FROM media AS m
+      JOIN media_metadata AS mm ON m.id = mm.media_id
+      JOIN media_access AS ma ON m.id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.geohash IS NOT NULL
+       AND mm.date_taken IS NOT NULL
+       AND mm.gps_latitude IS NOT NULL
+       AND mm.gps_longitude IS NOT NULL
+     ORDER BY mm.date_taken, m.id
+    "#;
+
+    pub const SELECT_TOP_LOCATIONS_FOR_USER: &str = r#"
+    SELECT mm.location_city
+         , mm.location_country
+         , COUNT(*) AS cnt
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND (mm.location_city IS NOT NULL OR mm.location_country IS NOT NULL)
+     GROUP BY mm.location_city, mm.location_country
+     ORDER BY cnt DESC, mm.location_city, mm.location_country
+     LIMIT 5
+    "#;
+
+    pub const SELECT_LOCATION_FACETS_FOR_USER: &str = r#"
+    SELECT mm.location_country
+         , mm.location_state
+         , mm.location_city
+         , COUNT(*) AS cnt
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.location_country IS NOT NULL
+     GROUP BY mm.location_country, mm.location_state, mm.location_city
+     ORDER BY cnt DESC, mm.location_country, mm.location_state, mm.location_city
+     LIMIT ?
+    "#;
+
+    pub const SELECT_GEOHASH_BUCKETS_FOR_USER: &str = r#"
+    SELECT SUBSTR(mm.geohash, 1, ?) AS cell
+         , COUNT(*) AS cnt
+      FROM media_metadata AS mm
+      JOIN media_access AS ma ON mm.media_id = ma.media_id
+     WHERE ma.user_id = ?
+       AND ma.deleted_at IS NULL
+       AND mm.geohash IS NOT NULL
+     GROUP BY cell
+     ORDER BY cnt DESC, cell
+    "#;
+
+    pub fn build_select_active_ids_for_user(count: usize) -> String {
+        let placeholders = vec!["?"; count].join(", ");
+        format!(
+            r#"
+    SELECT media_id
+      FROM media_access
+     WHERE user_id = ?
+       AND deleted_at IS NULL
+       AND media_id IN ({placeholders})
+    "#
+        )
+    }
+
+    pub fn build_bulk_update_deleted_at(count: usize) -> String {
+        let placeholders = vec!["?"; count].join(", ");
+        format!(
+            r#"
+    UPDATE media_access
+       SET deleted_at = ?
+     WHERE user_id = ?
+       AND deleted_at IS NULL
+       AND media_id IN ({placeholders})
+    "#
+        )
+    }
+
+    /// Reads the given `media_metadata` columns of one media item.
+    pub fn build_select_metadata_columns(columns: &[&str]) -> String {
+        format!(
+            r#"
+    SELECT {columns}
+      FROM media_metadata
+     WHERE media_id = ?
+    "#,
+            columns = columns.join("\n         , ")
+        )
+    }
+
+    /// Upserts the given `media_metadata` columns. Parameters are the media
+    /// id followed by one value per column; a NULL value clears the column.
+    pub fn build_update_metadata_columns(columns: &[&str]) -> String {
+        let placeholders = vec!["?"; columns.len() + 1].join(", ");
+        let assignments = columns
+            .iter()
+            .map(|column| format!("{column} = excluded.{column}"))
+            .collect::<Vec<_>>()
+            .join("\n      , ");
+
+        format!(
+            r#"
+    INSERT INTO media_metadata (
+        media_id
+      , {columns}
+    ) VALUES ({placeholders})
+    ON CONFLICT(media_id) DO UPDATE SET
+        {assignments}
+    "#,
+            columns = columns.join("\n      , "),
+            placeholders = placeholders,
+            assignments = assignments
+        )
+    }
+
     pub fn build_select_by_ids(count: usize) -> String {
         let placeholders = (0..count).map(|_| "?").collect::<Vec<_>>().join(", ");
 
@@ -334,8 +863,13 @@ pub mod media {
                  , mm.location_state
                  , mm.location_country
                  , mm.video_codec
+                 , mm.audio_codec
+                 , mm.audio_channels
+                 , mm.audio_sample_rate
                  , mm.keywords
                  , m.created_at
+                 , {has_date_mismatch}
+                 , ma.is_favorite
               FROM media AS m
               JOIN media_access AS ma ON m.id = ma.media_id
               LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -343,13 +877,190 @@ pub mod media {
                AND ma.deleted_at IS NULL
                AND m.id IN ({placeholders})
             "#,
+            has_date_mismatch = date_mismatch_column!(),
             placeholders = placeholders
         )
     }
+
+    /// Media list with extra `AND ...` filter clauses. Parameters are the
+    /// user id, the filter values, then (when `with_cursor`) the cursor
+    /// date, date and id, and finally the limit.
+    pub fn build_select_filtered(filter_clauses: &str, with_cursor: bool) -> String {
+        let cursor_clause = if with_cursor {
+            "AND (mm.date_taken < ? OR (mm.date_taken = ? AND m.id < ?))"
+        } else {
+            ""
+        };
+
+        build_select_filtered_ordered(
+            filter_clauses,
+            cursor_clause,
+            "mm.date_taken DESC, m.id DESC",
+        )
+    }
+
+    /// Like `build_select_filtered` with a caller-built keyset clause and
+    /// `ORDER BY` list, for user-chosen sort orders.
+    pub fn build_select_filtered_ordered(
+        filter_clauses: &str,
+        cursor_clause: &str,
+        order_by: &str,
+    ) -> String {
+        format!(
+            r#"
+            SELECT m.id
+                 , m.filename
+                 , m.original_filename
+                 , m.media_type
+                 , m.mime_type
+                 , mm.width
+                 , mm.height
+                 , m.file_size
+                 , mm.duration_seconds
+                 , mm.date_taken
+                 , mm.gps_latitude
+                 , mm.gps_longitude
+                 , mm.camera_make
+                 , mm.camera_model
+                 , mm.lens_make
+                 , mm.lens_model
+                 , mm.iso
+                 , mm.exposure_time
+                 , mm.f_number
+                 , mm.focal_length
+                 , mm.focal_length_35mm
+                 , mm.gps_altitude
+                 , mm.location_city
+                 , mm.location_state
+                 , mm.location_country
+                 , mm.video_codec
+                 , mm.audio_codec
+                 , mm.audio_channels
+                 , mm.audio_sample_rate
+                 , mm.keywords
+                 , m.created_at
+                 , {has_date_mismatch}
+                 , ma.is_favorite
+              FROM media AS m
+              JOIN media_access AS ma ON m.id = ma.media_id
+              LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+             WHERE ma.user_id = ?
+               AND ma.deleted_at IS NULL
+               {filter_clauses}
+               {cursor_clause}
+             ORDER BY {order_by}
+             LIMIT ?
+            "#,
+            has_date_mismatch = date_mismatch_column!(),
+            filter_clauses = filter_clauses,
+            cursor_clause = cursor_clause,
+            order_by = order_by
+        )
+    }
+
+    /// Album contents in album order with extra `AND ...` filter clauses.
+    /// Parameters are the user id, the album id, the filter values, then
+    /// (when `with_cursor`) the cursor position, position and id, and
+    /// finally the limit. Rows end with `am.position` for the cursor.
+    /// Album membership must be checked by the caller.
+    pub fn build_select_album_filtered(filter_clauses: &str, with_cursor: bool) -> String {
+        let cursor_clause = if with_cursor {
+            "AND (am.position > ? OR (am.position = ? AND m.id > ?))"
+        } else {
+            ""
+        };
+
+        format!(
+            r#"
+            SELECT m.id
+                 , m.filename
+                 , m.original_filename
+                 , m.media_type
+                 , m.mime_type
+                 , mm.width
+                 , mm.height
+                 , m.file_size
+                 , mm.duration_seconds
+                 , mm.date_taken
+                 , mm.gps_latitude
+                 , mm.gps_longitude
+                 , mm.camera_make
+                 , mm.camera_model
+                 , mm.lens_make
+                 , mm.lens_model
+                 , mm.iso
+                 , mm.exposure_time
+                 , mm.f_number
+                 , mm.focal_length
+                 , mm.focal_length_35mm
+                 , mm.gps_altitude
+                 , mm.location_city
+                 , mm.location_state
+                 , mm.location_country
+                 , mm.video_codec
+                 , mm.audio_codec
+                 , mm.audio_channels
+                 , mm.audio_sample_rate
+                 , mm.keywords
+                 , m.created_at
+                 , {has_date_mismatch}
+                 , COALESCE(ma.is_favorite, 0) AS is_favorite
+                 , am.position
+              FROM media AS m
+              JOIN album_media AS am ON m.id = am.media_id
+              LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+              LEFT JOIN media_access AS ma ON m.id = ma.media_id AND ma.user_id = ?
+             WHERE am.album_id = ?
+               {filter_clauses}
+               {cursor_clause}
+             ORDER BY am.position ASC, m.id ASC
+             LIMIT ?
+            "#,
+            has_date_mismatch = date_mismatch_column!(),
+            filter_clauses = filter_clauses,
+            cursor_clause = cursor_clause
+        )
+    }
+
+    /// Total for `build_select_album_filtered`. Parameters are the user id,
+    /// the album id, then the filter values.
+    pub fn build_count_album_filtered(filter_clauses: &str) -> String {
+        format!(
+            r#"
+            SELECT COUNT(*)
+              FROM media AS m
+              JOIN album_media AS am ON m.id = am.media_id
+              LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+              LEFT JOIN media_access AS ma ON m.id = ma.media_id AND ma.user_id = ?
+             WHERE am.album_id = ?
+               {filter_clauses}
+            "#,
+            filter_clauses = filter_clauses
+        )
+    }
+
+    /// Total for `build_select_filtered` with the same filter clauses,
+    /// ignoring the cursor and limit. Parameters are the user id, then the
+    /// filter values.
+    pub fn build_count_filtered(filter_clauses: &str) -> String {
+        format!(
+            r#"
+            SELECT COUNT(*)
+              FROM media AS m
+              JOIN media_access AS ma ON m.id = ma.media_id
+              LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+             WHERE ma.user_id = ?
+               AND ma.deleted_at IS NULL
+               {filter_clauses}
+            "#,
+            filter_clauses = filter_clauses
+        )
+    }
 }
 
 pub mod timeline {
-    pub const SELECT_DEFAULT: &str = r#"
+    pub const SELECT_DEFAULT: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -376,8 +1087,15 @@ pub mod timeline {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
       FROM media AS m
       JOIN media_access AS ma ON m.id = ma.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -385,9 +1103,11 @@ pub mod timeline {
        AND ma.deleted_at IS NULL
      ORDER BY mm.date_taken DESC, m.id DESC
      LIMIT ?
-    "#;
+    "#
+    );
 
-    pub const SELECT_PAGINATED: &str = r#"
+    pub const SELECT_PAGINATED: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -414,8 +1134,15 @@ pub mod timeline {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
       FROM media AS m
       JOIN media_access AS ma ON m.id = ma.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -424,7 +1151,8 @@ pub mod timeline {
        AND (mm.date_taken < ? OR (mm.date_taken = ? AND m.id < ?))
      ORDER BY mm.date_taken DESC, m.id DESC
      LIMIT ?
-    "#;
+    "#
+    );
 }
 
 pub mod regenerator {
@@ -482,6 +1210,9 @@ pub mod regenerator {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
       FROM media AS m
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -514,9 +1245,12 @@ pub mod regenerator {
       , location_state
       , location_country
       , video_codec
+      , audio_codec
+      , audio_channels
+      , audio_sample_rate
       , keywords
       , duration_seconds
-    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
     ON CONFLICT(media_id) DO UPDATE SET
         width = excluded.width
       , height = excluded.height
@@ -537,10 +1271,108 @@ pub mod regenerator {
       , location_state = excluded.location_state
       , location_country = excluded.location_country
       , video_codec = excluded.video_codec
+      , audio_codec = excluded.audio_codec
+      , audio_channels = excluded.audio_channels
+      , audio_sample_rate = excluded.audio_sample_rate
       , keywords = excluded.keywords
       , duration_seconds = excluded.duration_seconds
     "#;
 
+    pub const SELECT_COORDINATES: &str = r#"
+    SELECT gps_latitude
+         , gps_longitude
+      FROM media_metadata
+     WHERE media_id = ?
+    "#;
+
+    /// Current values of the columns `UPDATE_REPROCESSED_METADATA` writes,
+    /// in the same order.
+    pub const SELECT_REPROCESSED_VALUES: &str = r#"
+    SELECT width
+         , height
+         , date_taken
+         , gps_latitude
+         , gps_longitude
+         , gps_altitude
+         , camera_make
+         , camera_model
+         , lens_make
+         , lens_model
+         , iso
+         , exposure_time
+         , f_number
+         , focal_length
+         , focal_length_35mm
+         , location_city
+         , location_state
+         , location_country
+         , video_codec
+         , audio_codec
+         , audio_channels
+         , audio_sample_rate
+         , keywords
+         , duration_seconds
+      FROM media_metadata
+     WHERE media_id = ?
+    "#;
+
+    /// Like `UPDATE_METADATA`, but a NULL (not extracted) value keeps the
+    /// stored one.
+    pub const UPDATE_REPROCESSED_METADATA: &str = r#"
+    INSERT INTO media_metadata (
+        media_id
+      , width
+      , height
+      , date_taken
+      , gps_latitude
+      , gps_longitude
+      , gps_altitude
+      , camera_make
+      , camera_model
+      , lens_make
+      , lens_model
+      , iso
+      , exposure_time
+      , f_number
+      , focal_length
+      , focal_length_35mm
+      , location_city
+      , location_state
+      , location_country
+      , video_codec
+      , audio_codec
+      , audio_channels
+      , audio_sample_rate
+      , keywords
+      , duration_seconds
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT(media_id) DO UPDATE SET
+        width = COALESCE(excluded.width, media_metadata.width)
+      , height = COALESCE(excluded.height, media_metadata.height)
+      , date_taken = COALESCE(excluded.date_taken, media_metadata.date_taken)
+      , gps_latitude = COALESCE(excluded.gps_latitude, media_metadata.gps_latitude)
+      , gps_longitude = COALESCE(excluded.gps_longitude, media_metadata.gps_longitude)
+      , gps_altitude = COALESCE(excluded.gps_altitude, media_metadata.gps_altitude)
+      , camera_make = COALESCE(excluded.camera_make, media_metadata.camera_make)
+      , camera_model = COALESCE(excluded.camera_model, media_metadata.camera_model)
+      , lens_make = COALESCE(excluded.lens_make, media_metadata.lens_make)
+      , lens_model = COALESCE(excluded.lens_model, media_metadata.lens_model)
+      , iso = COALESCE(excluded.iso, media_metadata.iso)
+      , exposure_time = COALESCE(excluded.exposure_time, media_metadata.exposure_time)
+      , f_number = COALESCE(excluded.f_number, media_metadata.f_number)
+      , focal_length = COALESCE(excluded.focal_length, media_metadata.focal_length)
+      , focal_length_35mm = COALESCE(excluded.focal_length_35mm, media_metadata.focal_length_35mm)
+      , location_city = COALESCE(excluded.location_city, media_metadata.location_city)
+      , location_state = COALESCE(excluded.location_state, media_metadata.location_state)
+      , location_country = COALESCE(excluded.location_country, media_metadata.location_country)
+      , video_codec = COALESCE(excluded.video_codec, media_metadata.video_codec)
+      , audio_codec = COALESCE(excluded.audio_codec, media_metadata.audio_codec)
+      , audio_channels = COALESCE(excluded.audio_channels, media_metadata.audio_channels)
+      , audio_sample_rate = COALESCE(excluded.audio_sample_rate, media_metadata.audio_sample_rate)
+      , keywords = COALESCE(excluded.keywords, media_metadata.keywords)
+      , duration_seconds = COALESCE(excluded.duration_seconds, media_metadata.duration_seconds)
+    "#;
+
     pub const UPDATE_THUMBNAIL: &str = r#"
     INSERT INTO media_metadata (thumbnail_path, media_id)
     VALUES (?, ?)
@@ -555,7 +1387,13 @@ pub mod albums {
         user_id
       , name
       , description
-    ) VALUES (?, ?, ?)
+      , position
+    ) VALUES (
+        ?1
+      , ?2
+      , ?3
+      , (SELECT COALESCE(MAX(position), -1) + 1 FROM albums WHERE user_id = ?1)
+    )
     "#;
 
     pub const SELECT_BY_ID: &str = r#"
@@ -576,12 +1414,43 @@ pub mod albums {
          , a.cover_media_id
          , COUNT(am.media_id) as media_count
          , a.created_at
+         , a.position
       FROM albums AS a
       JOIN album_access AS aa ON a.id = aa.album_id
       LEFT JOIN album_media AS am ON a.id = am.album_id
      WHERE aa.user_id = ?
      GROUP BY a.id
-     ORDER BY a.created_at DESC
+     ORDER BY a.position ASC, a.created_at DESC
+    "#;
+
+    pub const COUNT_FOR_USER: &str = r#"
+    SELECT COUNT(*)
+      FROM albums AS a
+      JOIN album_access AS aa ON a.id = aa.album_id
+     WHERE aa.user_id = ?
+    "#;
+
+    pub const SELECT_PAGINATED_FOR_USER: &str = r#"
+    SELECT a.id
+         , a.name
+         , a.description
+         , a.cover_media_id
+         , COUNT(am.media_id) as media_count
+         , a.created_at
+         , a.position
+      FROM albums AS a
+      JOIN album_access AS aa ON a.id = aa.album_id
+      LEFT JOIN album_media AS am ON a.id = am.album_id
+     WHERE aa.user_id = ?1
+       AND (
+            ?2 IS NULL
+         OR a.position > ?2
+         OR (a.position = ?2 AND a.created_at < ?3)
+         OR (a.position = ?2 AND a.created_at = ?3 AND a.id < ?4)
+       )
+     GROUP BY a.id
+     ORDER BY a.position ASC, a.created_at DESC, a.id DESC
+     LIMIT ?5
     "#;
 
     pub const CHECK_OWNERSHIP: &str = r#"
@@ -592,6 +1461,43 @@ pub mod albums {
        AND aa.user_id = ?
     "#;
 
+    /// The creator of an album the user has access to.
+    pub const SELECT_OWNER_FOR_USER: &str = r#"
+    SELECT a.user_id
+      FROM albums AS a
+      JOIN album_access AS aa ON a.id = aa.album_id
+     WHERE a.id = ?
+       AND aa.user_id = ?
+    "#;
+
+    pub fn build_count_owned(count: usize) -> String {
+        let placeholders = vec!["?"; count].join(", ");
+        format!(
+            r#"
+    SELECT COUNT(*)
+      FROM albums
+     WHERE user_id = ?
+       AND id IN ({placeholders})
+    "#
+        )
+    }
+
+    pub const UPDATE_ALBUM_POSITION: &str = r#"
+    UPDATE albums
+       SET position = ?
+     WHERE id = ?
+       AND user_id = ?
+    "#;
+
+    pub const SELECT_ID_BY_OWNER_AND_NAME: &str = r#"
+    SELECT id
+      FROM albums
+     WHERE user_id = ?
+       AND name = ?
+     ORDER BY id
+     LIMIT 1
+    "#;
+
     pub const DELETE: &str = r#"
     DELETE FROM albums
      WHERE id = ?
@@ -611,10 +1517,64 @@ pub mod albums {
     ) VALUES (?, ?, ?)
     "#;
 
-    pub const REMOVE_MEDIA: &str = r#"
+    pub const ADD_MEDIA_FROM_TAG: &str = r#"
+    INSERT OR IGNORE INTO album_media (
+        album_id
+      , media_id
+      , position
+    )
+    SELECT ?
+         , m.id
+         , ROW_NUMBER() OVER (ORDER BY mm.date_taken DESC, m.id DESC) - 1
+      FROM media_tags AS mt
+      JOIN media AS m ON mt.media_id = m.id
+      JOIN media_access AS ma ON m.id = ma.media_id
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE mt.tag_id = ?
+       AND ma.user_id = ?
+       AND ma.deleted_at IS NULL
+    "#;
+
+    /// Appends the source album's media to the target, keeping their order.
+    /// Parameters: target album id, first free position, source album id.
+    pub const ADD_MEDIA_FROM_ALBUM: &str = r#"
+    INSERT OR IGNORE INTO album_media (
+        album_id
+      , media_id
+      , position
+    )
+    SELECT ?1
+         , am.media_id
+         , ?2 + ROW_NUMBER() OVER (ORDER BY am.position, am.media_id) - 1
+      FROM album_media AS am
+     WHERE am.album_id = ?3
+    "#;
+
+    pub const SET_COVER_IF_MISSING: &str = r#"
+    UPDATE albums
+       SET cover_media_id = COALESCE(
+               (SELECT cover_media_id FROM albums WHERE id = ?1)
+             , (SELECT media_id FROM album_media WHERE album_id = ?2 ORDER BY position LIMIT 1)
+           )
+     WHERE id = ?2
+       AND cover_media_id IS NULL
+    "#;
+
+    pub const REMOVE_MEDIA: &str = r#"
+    DELETE FROM album_media
+     WHERE album_id = ?
+       AND media_id = ?
+    "#;
+
+    pub const REMOVE_ALL_MEDIA: &str = r#"
     DELETE FROM album_media
      WHERE album_id = ?
-       AND media_id = ?
+    "#;
+
+    pub const CLEAR_COVER: &str = r#"
+    UPDATE albums
+       SET cover_media_id = NULL
+     WHERE id = ?
     "#;
 
     pub const UPDATE_POSITION: &str = r#"
@@ -624,7 +1584,8 @@ pub mod albums {
        AND media_id = ?
     "#;
 
-    pub const SELECT_MEDIA: &str = r#"
+    pub const SELECT_MEDIA: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -651,13 +1612,37 @@ pub mod albums {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , COALESCE(ma.is_favorite, 0) AS is_favorite
       FROM media AS m
       JOIN album_media AS am ON m.id = am.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+      LEFT JOIN media_access AS ma ON m.id = ma.media_id AND ma.user_id = ?
+     WHERE am.album_id = ?
+     ORDER BY am.position
+    "#
+    );
+
+    /// Thumbnails of the first four items, used for the composite cover.
+    /// Items the album owner has moved to the trash are skipped.
+    pub const SELECT_COVER_THUMBNAILS: &str = r#"
+    SELECT mm.thumbnail_path
+      FROM album_media AS am
+      JOIN albums AS a ON a.id = am.album_id
+      JOIN media_metadata AS mm ON mm.media_id = am.media_id
+      JOIN media_access AS ma ON ma.media_id = am.media_id AND ma.user_id = a.user_id
      WHERE am.album_id = ?
+       AND mm.thumbnail_path IS NOT NULL
+       AND ma.deleted_at IS NULL
      ORDER BY am.position
+     LIMIT 4
     "#;
 
     pub const DELETE_ACCESS: &str = r#"
@@ -677,6 +1662,7 @@ pub mod albums {
          , a.cover_media_id
          , COUNT(am.media_id) as media_count
          , a.created_at
+         , a.position
       FROM albums AS a
       LEFT JOIN album_media AS am ON a.id = am.album_id
      WHERE a.id = ?
@@ -689,7 +1675,19 @@ pub mod map {
     pub const LONGITUDE_CLAUSE_ANTIMERIDIAN: &str =
         "(mm.gps_longitude >= ? OR mm.gps_longitude <= ?)";
 
+    pub const LENS_MODEL_CLAUSE: &str = "AND mm.lens_model = ?";
+
     pub fn build_clusters_query(precision: usize, longitude_clause: &str) -> String {
+        build_filtered_clusters_query(precision, longitude_clause, "")
+    }
+
+    /// Cluster query with an extra `AND ...` clause appended to the filter;
+    /// its parameters follow the bounding box parameters.
+    pub fn build_filtered_clusters_query(
+        precision: usize,
+        longitude_clause: &str,
+        filter_clause: &str,
+    ) -> String {
         format!(
             r#"
             WITH clustered AS (
@@ -706,6 +1704,7 @@ pub mod map {
                    AND mm.gps_latitude BETWEEN ? AND ?
                    AND {longitude_clause}
                    AND mm.geohash IS NOT NULL
+                   {filter_clause}
                   GROUP BY cell
             )
             SELECT c.cell
@@ -716,7 +1715,8 @@ pub mod map {
               FROM clustered AS c
             "#,
             precision = precision,
-            longitude_clause = longitude_clause
+            longitude_clause = longitude_clause,
+            filter_clause = filter_clause
         )
     }
 
@@ -759,9 +1759,14 @@ pub mod map {
                  , mm.location_state
                  , mm.location_country
                  , mm.video_codec
+                 , mm.audio_codec
+                 , mm.audio_channels
+                 , mm.audio_sample_rate
                  , mm.keywords
                  , m.content_hash
                  , m.created_at
+                 , {has_date_mismatch}
+                 , ma.is_favorite
               FROM media AS m
               JOIN media_access AS ma ON m.id = ma.media_id
               JOIN media_metadata AS mm ON m.id = mm.media_id
@@ -775,10 +1780,131 @@ pub mod map {
              ORDER BY COALESCE(mm.date_taken, m.created_at) DESC
                     , m.id DESC
             "#,
+            has_date_mismatch = date_mismatch_column!(),
             longitude_clause = longitude_clause,
             geohash_clause = geohash_clause
         )
     }
+
+    /// Geotagged media whose precise geohash starts with any of
+    /// `geohash_count` prefixes, roughly nearest first. Parameters are the
+    /// latitude and longitude of the point, the user id, one `LIKE` pattern
+    /// per prefix, the cosine of the latitude (twice) and the limit.
+    pub fn build_nearby_query(geohash_count: usize) -> String {
+        let conditions = (0..geohash_count)
+            .map(|_| "mm.geohash_precise LIKE ?")
+            .collect::<Vec<_>>()
+            .join(" OR ");
+
+        format!(
+            r#"
+            SELECT m.id
+                 , m.filename
+                 , m.original_filename
+                 , m.media_type
+                 , m.mime_type
+                 , mm.width
+                 , mm.height
+                 , m.file_size
+                 , mm.duration_seconds
+                 , mm.date_taken
+                 , mm.gps_latitude
+                 , mm.gps_longitude
+                 , mm.camera_make
+                 , mm.camera_model
+                 , mm.lens_make
+                 , mm.lens_model
+                 , mm.iso
+                 , mm.exposure_time
+                 , mm.f_number
+                 , mm.focal_length
+                 , mm.focal_length_35mm
+                 , mm.gps_altitude
+                 , mm.location_city
+                 , mm.location_state
+                 , mm.location_country
+                 , mm.video_codec
+                 , mm.audio_codec
+                 , mm.audio_channels
+                 , mm.audio_sample_rate
+                 , mm.keywords
+                 , m.content_hash
+                 , m.created_at
+                 , {has_date_mismatch}
+                 , ma.is_favorite
+                 , ABS(mm.gps_latitude - ?) AS lat_delta
+                 , ABS(mm.gps_longitude - ?) AS lon_delta
+              FROM media AS m
+              JOIN media_access AS ma ON m.id = ma.media_id
+              JOIN media_metadata AS mm ON m.id = mm.media_id
+             WHERE ma.user_id = ?
+               AND ma.deleted_at IS NULL
+               AND mm.gps_latitude IS NOT NULL
+               AND mm.gps_longitude IS NOT NULL
+               AND ({conditions})
+             ORDER BY lat_delta * lat_delta
+                    + MIN(lon_delta, 360 - lon_delta) * MIN(lon_delta, 360 - lon_delta) * ? * ?
+             LIMIT ?
+            "#,
+            has_date_mismatch = date_mismatch_column!(),
+            conditions = conditions
+        )
+    }
+
+    /// Geotagged media inside a geohash cell. `?2` is the `LIKE` pattern;
+    /// prefixes longer than the stored 7-character geohash only match the
+    /// precise column. `?3` caps the number of rows.
+    pub const SELECT_BY_GEOHASH_PREFIX: &str = concat!(
+        r#"
+    SELECT m.id
+         , m.filename
+         , m.original_filename
+         , m.media_type
+         , m.mime_type
+         , mm.width
+         , mm.height
+         , m.file_size
+         , mm.duration_seconds
+         , mm.date_taken
+         , mm.gps_latitude
+         , mm.gps_longitude
+         , mm.camera_make
+         , mm.camera_model
+         , mm.lens_make
+         , mm.lens_model
+         , mm.iso
+         , mm.exposure_time
+         , mm.f_number
+         , mm.focal_length
+         , mm.focal_length_35mm
+         , mm.gps_altitude
+         , mm.location_city
+         , mm.location_state
+         , mm.location_country
+         , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
+         , mm.keywords
+         , m.content_hash
+         , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
+         , ma.is_favorite
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+      JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE ma.user_id = ?1
+       AND ma.deleted_at IS NULL
+       AND mm.gps_latitude IS NOT NULL
+       AND mm.gps_longitude IS NOT NULL
+       AND (mm.geohash LIKE ?2 OR mm.geohash_precise LIKE ?2)
+     ORDER BY COALESCE(mm.date_taken, m.created_at) DESC
+            , m.id DESC
+     LIMIT ?3
+    "#
+    );
 }
 
 pub mod tags {
@@ -830,9 +1956,43 @@ pub mod tags {
      WHERE media_id = ?
        AND tag_id = ?
     "#;
+
+    pub const REASSIGN_MEDIA_TAGS: &str = r#"
+    UPDATE OR IGNORE media_tags
+       SET tag_id = ?
+     WHERE tag_id = ?
+    "#;
+
+    pub const DELETE_MEDIA_TAGS_BY_TAG: &str = r#"
+    DELETE FROM media_tags
+     WHERE tag_id = ?
+    "#;
+
+    pub fn build_count_media_for_tags(tag_count: usize) -> String {
+        let placeholders = vec!["?"; tag_count].join(", ");
+        format!(
+            r#"
+    SELECT COUNT(DISTINCT media_id)
+      FROM media_tags
+     WHERE tag_id IN ({placeholders})
+    "#
+        )
+    }
 }
 
 pub mod users {
+    /// Active media each user owns, including users with none.
+    pub const SELECT_OWNED_MEDIA_COUNTS: &str = r#"
+    SELECT u.id
+         , COUNT(ma.media_id)
+      FROM users AS u
+      LEFT JOIN media_access AS ma
+        ON ma.user_id = u.id
+       AND ma.access_level >= 2
+       AND ma.deleted_at IS NULL
+     GROUP BY u.id
+    "#;
+
     pub const SELECT_ID_BY_CREDENTIALS: &str = r#"
     SELECT id
       FROM users
@@ -885,6 +2045,16 @@ pub mod users {
      WHERE id = ?
     "#;
 
+    pub const DELETE_ALBUM_ACCESS: &str = r#"
+    DELETE FROM album_access
+     WHERE user_id = ?
+    "#;
+
+    pub const DELETE_SHARE_LINKS: &str = r#"
+    DELETE FROM share_links
+     WHERE user_id = ?
+    "#;
+
     pub const CHECK_ADMIN: &str = r#"
     SELECT id
       FROM users
@@ -901,6 +2071,13 @@ pub mod users {
       , must_change_password
     ) VALUES (?, ?, ?, 'admin', 1)
     "#;
+
+    pub const SET_TEMPORARY_PASSWORD: &str = r#"
+    UPDATE users
+       SET hashed_password = ?
+         , must_change_password = 1
+     WHERE id = ?
+    "#;
 }
 
 pub mod auth {
@@ -921,6 +2098,47 @@ pub mod auth {
      WHERE id = ?
     "#;
 
+    pub const SELECT_ACTIVE_USER_BY_EMAIL: &str = r#"
+    SELECT id
+         , username
+      FROM users
+     WHERE email = ?
+       AND is_active = 1
+    "#;
+
+    pub const INSERT_PASSWORD_RESET_TOKEN: &str = r#"
+    INSERT INTO password_reset_tokens (
+        user_id
+      , token_hash
+      , expires_at
+    ) VALUES (?, ?, ?)
+    "#;
+
+    pub const SELECT_VALID_PASSWORD_RESET_TOKEN: &str = r#"
+    SELECT prt.id
+         , prt.user_id
+      FROM password_reset_tokens AS prt
+      JOIN users AS u ON prt.user_id = u.id
+     WHERE prt.token_hash = ?
+       AND prt.used_at IS NULL
+       AND prt.expires_at > ?
+       AND u.is_active = 1
+    "#;
+
+    /// Claims the token; changes no row once another request has used it
+    pub const MARK_PASSWORD_RESET_TOKEN_USED: &str = r#"
+    UPDATE password_reset_tokens
+       SET used_at = ?
+     WHERE id = ?
+       AND used_at IS NULL
+    "#;
+
+    pub const DELETE_EXPIRED_PASSWORD_RESET_TOKENS: &str = r#"
+    DELETE FROM password_reset_tokens
+     WHERE expires_at < ?
+        OR used_at IS NOT NULL
+    "#;
+
     pub const UPDATE_PASSWORD_AND_RESET_FLAG: &str = r#"
     UPDATE users
        SET hashed_password = ?
@@ -933,7 +2151,9 @@ pub mod auth {
         token_hash
       , user_id
       , expires_at
-    ) VALUES (?, ?, ?)
+      , token_family
+      , device_name
+    ) VALUES (?, ?, ?, ?, ?)
     "#;
 
     pub const VALIDATE_REFRESH_TOKEN: &str = r#"
@@ -944,6 +2164,8 @@ pub mod auth {
          , u.username
          , u.role
          , u.is_active
+         , rt.token_family
+         , rt.device_name
       FROM refresh_tokens AS rt
       JOIN users AS u ON rt.user_id = u.id
      WHERE rt.token_hash = ?
@@ -967,10 +2189,58 @@ pub mod auth {
      WHERE user_id = ?
     "#;
 
-    pub const DELETE_REVOKED_TOKEN: &str = r#"
+    pub const COUNT_ACTIVE_FAMILY_TOKENS: &str = r#"
+    SELECT COUNT(*)
+      FROM refresh_tokens
+     WHERE token_family = ?
+       AND revoked = 0
+       AND expires_at > ?
+    "#;
+
+    /// Expired tokens, except those of a family that still has a live
+    /// token: they date the session and let reuse be detected.
+    pub const DELETE_EXPIRED_REFRESH_TOKENS: &str = r#"
     DELETE FROM refresh_tokens
-     WHERE revoked = 1
-       AND id = ?
+     WHERE expires_at <= ?1
+       AND (token_family IS NULL
+            OR token_family NOT IN (
+                SELECT token_family
+                  FROM refresh_tokens
+                 WHERE expires_at > ?1
+                   AND token_family IS NOT NULL))
+    "#;
+
+    pub const REVOKE_TOKEN_FAMILY: &str = r#"
+    UPDATE refresh_tokens
+       SET revoked = 1
+     WHERE token_family = ?
+    "#;
+
+    /// A session's start is the first token of its family, since every
+    /// refresh issues a new row.
+    pub const SELECT_ACTIVE_SESSIONS: &str = r#"
+    SELECT rt.id
+         , rt.device_name
+         , COALESCE(
+               (SELECT MIN(f.created_at)
+                  FROM refresh_tokens AS f
+                 WHERE f.token_family = rt.token_family)
+             , rt.created_at
+           ) AS created_at
+         , rt.expires_at
+      FROM refresh_tokens AS rt
+     WHERE rt.user_id = ?
+       AND rt.revoked = 0
+       AND rt.expires_at > ?
+     ORDER BY rt.id DESC
+    "#;
+
+    pub const SELECT_SESSION_FAMILY: &str = r#"
+    SELECT token_family
+      FROM refresh_tokens
+     WHERE id = ?
+       AND user_id = ?
+       AND revoked = 0
     "#;
 
     pub const SELECT_PASSWORD_HASH: &str = r#"
@@ -1017,7 +2287,8 @@ pub mod share {
       , token
       , password_hash
       , expires_at
-    ) VALUES (?, ?, ?, ?, ?, ?)
+      , permissions
+    ) VALUES (?, ?, ?, ?, ?, ?, ?)
     "#;
 
     pub const SELECT_BY_ID: &str = r#"
@@ -1029,6 +2300,7 @@ pub mod share {
          , expires_at
          , view_count
          , created_at
+         , permissions
       FROM share_links
      WHERE id = ?
     "#;
@@ -1042,6 +2314,7 @@ pub mod share {
          , expires_at
          , view_count
          , created_at
+         , permissions
       FROM share_links
      WHERE user_id = ?
      ORDER BY created_at DESC
@@ -1059,12 +2332,26 @@ pub mod share {
      WHERE id = ?
     "#;
 
+    pub const UPDATE_EXPIRES_AT: &str = r#"
+    UPDATE share_links
+       SET expires_at = ?
+     WHERE id = ?
+    "#;
+
+    pub const UPDATE_TOKEN: &str = r#"
+    UPDATE share_links
+       SET token = ?
+     WHERE id = ?
+    "#;
+
     pub const SELECT_BY_TOKEN: &str = r#"
     SELECT id
          , media_id
          , album_id
          , password_hash
          , expires_at
+         , permissions
+         , suspended_at
       FROM share_links
      WHERE token = ?
     "#;
@@ -1080,6 +2367,43 @@ pub mod share {
       FROM share_links
      WHERE token = ?
     "#;
+
+    pub const INSERT_REPORT: &str = r#"
+    INSERT INTO share_reports (token, ip_hash, reason)
+    VALUES (?, ?, ?)
+    "#;
+
+    pub const COUNT_RECENT_REPORTS_BY_CLIENT: &str = r#"
+    SELECT COUNT(*)
+      FROM share_reports
+     WHERE ip_hash = ?
+       AND created_at > datetime('now', ?)
+    "#;
+
+    pub const COUNT_REPORTERS: &str = r#"
+    SELECT COUNT(DISTINCT ip_hash)
+      FROM share_reports
+     WHERE token = ?
+    "#;
+
+    pub const SUSPEND_BY_TOKEN: &str = r#"
+    UPDATE share_links
+       SET suspended_at = ?
+     WHERE token = ?
+       AND suspended_at IS NULL
+    "#;
+
+    pub const SELECT_REPORTS: &str = r#"
+    SELECT r.id
+         , r.token
+         , r.reason
+         , r.created_at
+         , sl.id
+         , sl.suspended_at
+      FROM share_reports AS r
+      LEFT JOIN share_links AS sl ON sl.token = r.token
+     ORDER BY r.id DESC
+    "#;
 }
 
 pub mod public {
@@ -1091,7 +2415,8 @@ pub mod public {
      WHERE id = ?
     "#;
 
-    pub const SELECT_ALBUM_MEDIA: &str = r#"
+    pub const SELECT_ALBUM_MEDIA: &str = concat!(
+        r#"
     SELECT m.id
          , m.filename
          , m.original_filename
@@ -1118,14 +2443,21 @@ pub mod public {
          , mm.location_state
          , mm.location_country
          , mm.video_codec
+         , mm.audio_codec
+         , mm.audio_channels
+         , mm.audio_sample_rate
          , mm.keywords
          , m.created_at
+         , "#,
+        date_mismatch_column!(),
+        r#"
       FROM media AS m
       JOIN album_media AS am ON m.id = am.media_id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
      WHERE am.album_id = ?
      ORDER BY am.position
-    "#;
+    "#
+    );
 
     pub const CHECK_ALBUM_MEDIA: &str = r#"
     SELECT 1
@@ -1149,7 +2481,52 @@ pub mod public {
     "#;
 }
 
+pub mod storage {
+    pub const SELECT_USAGE_BREAKDOWN: &str = r#"
+    SELECT ma.user_id
+         , SUBSTR(mm.date_taken, 1, 7) AS month
+         , m.media_type
+         , COALESCE(SUM(m.file_size), 0) AS total_bytes
+         , COUNT(*) AS file_count
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+      LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+     WHERE ma.deleted_at IS NULL
+     GROUP BY ma.user_id, month, m.media_type
+     ORDER BY total_bytes DESC
+    "#;
+
+    pub const SELECT_TRASH_USAGE: &str = r#"
+    SELECT ma.user_id
+         , COALESCE(SUM(m.file_size), 0) AS trash_bytes
+      FROM media AS m
+      JOIN media_access AS ma ON m.id = ma.media_id
+     WHERE ma.deleted_at IS NOT NULL
+     GROUP BY ma.user_id
+     ORDER BY trash_bytes DESC
+    "#;
+
+    pub const SELECT_THUMBNAIL_PATHS: &str = r#"
+    SELECT thumbnail_path
+      FROM media_metadata
+     WHERE thumbnail_path IS NOT NULL
+    "#;
+
+    pub const SELECT_FILE_PATHS: &str = r#"
+    SELECT file_path
+      FROM media
+    "#;
+}
+
 pub mod trash {
+    pub const SELECT_TRASHED_PREVIEW_SOURCES: &str = r#"
+    SELECT ma.user_id
+         , m.file_path
+      FROM media_access AS ma
+      JOIN media AS m ON m.id = ma.media_id
+     WHERE ma.deleted_at IS NOT NULL
+    "#;
+
     pub const SELECT_DELETED: &str = r#"
     SELECT m.id
          , m.filename
@@ -1217,6 +2594,13 @@ pub mod trash {
        AND ma.deleted_at IS NOT NULL
     "#;
 
+    pub const TRASH_ALL_FOR_USER: &str = r#"
+    UPDATE media_access
+       SET deleted_at = ?
+     WHERE user_id = ?
+       AND deleted_at IS NULL
+    "#;
+
     pub const SELECT_OLD_DELETED: &str = r#"
     SELECT m.id
          , m.file_path
@@ -1224,9 +2608,10 @@ pub mod trash {
          , ma.user_id
       FROM media_access AS ma
       JOIN media AS m ON ma.media_id = m.id
+      JOIN users AS u ON ma.user_id = u.id
       LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
      WHERE ma.deleted_at IS NOT NULL
-       AND ma.deleted_at < ?
+       AND ma.deleted_at < CASE WHEN u.role = 'admin' THEN ?1 ELSE ?2 END
     "#;
 }
 
@@ -1252,6 +2637,14 @@ pub mod access {
     SELECT access_level FROM media_access WHERE media_id = ? AND user_id = ?
     "#;
 
+    pub const SET_FAVORITE: &str = r#"
+    UPDATE media_access
+       SET is_favorite = ?
+     WHERE media_id = ?
+       AND user_id = ?
+       AND deleted_at IS NULL
+    "#;
+
     pub const REMOVE_MEDIA_ACCESS: &str = r#"
     DELETE FROM media_access WHERE media_id = ? AND user_id = ?
     "#;
@@ -1260,7 +2653,189 @@ pub mod access {
     SELECT COUNT(*) FROM media_access WHERE media_id = ?
     "#;
 
+    /// Includes trashed rows, since those users can still restore the item.
+    pub const COUNT_OTHER_MEDIA_ACCESS: &str = r#"
+    SELECT COUNT(*) FROM media_access WHERE media_id = ? AND user_id != ?
+    "#;
+
+    pub const SELECT_ACTIVE_MEDIA_IDS_FOR_USER: &str = r#"
+    SELECT media_id
+      FROM media_access
+     WHERE user_id = ?
+       AND deleted_at IS NULL
+     ORDER BY media_id
+    "#;
+
+    /// Ignored when the recipient already has a row for the media; those
+    /// are folded in with `MERGE_TRANSFERRED_ACCESS` instead.
+    pub const TRANSFER_MEDIA_ACCESS: &str = r#"
+    UPDATE OR IGNORE media_access
+       SET user_id = ?1
+     WHERE media_id = ?2
+       AND user_id = ?3
+       AND deleted_at IS NULL
+    "#;
+
+    pub const MERGE_TRANSFERRED_ACCESS: &str = r#"
+    UPDATE media_access
+       SET access_level = MAX(access_level, (
+               SELECT s.access_level
+                 FROM media_access AS s
+                WHERE s.media_id = ?2
+                  AND s.user_id = ?3
+           ))
+         , deleted_at = NULL
+     WHERE media_id = ?2
+       AND user_id = ?1
+       AND EXISTS (
+               SELECT 1
+                 FROM media_access AS s
+                WHERE s.media_id = ?2
+                  AND s.user_id = ?3
+                  AND s.deleted_at IS NULL
+           )
+    "#;
+
     pub const DELETE_MEDIA_PERMANENTLY: &str = r#"
     DELETE FROM media WHERE id = ?
     "#;
 }
+
+pub mod edits {
+    pub const SELECT_EDITABLE_VALUES: &str = r#"
+    SELECT date_taken
+         , gps_latitude
+         , gps_longitude
+      FROM media_metadata
+     WHERE media_id = ?
+    "#;
+
+    pub const INSERT: &str = r#"
+    INSERT INTO media_edits (media_id, user_id, field_name, old_value, new_value)
+    VALUES (?, ?, ?, ?, ?)
+    "#;
+
+    pub const PRUNE_FOR_MEDIA: &str = r#"
+    DELETE FROM media_edits
+     WHERE media_id = ?1
+       AND id NOT IN (
+           SELECT id
+             FROM media_edits
+            WHERE media_id = ?1
+            ORDER BY id DESC
+            LIMIT ?2
+     )
+    "#;
+
+    pub const SELECT_FOR_MEDIA: &str = r#"
+    SELECT id
+         , media_id
+         , user_id
+         , field_name
+         , old_value
+         , new_value
+         , edited_at
+      FROM media_edits
+     WHERE media_id = ?
+     ORDER BY id DESC
+    "#;
+}
+
+pub mod search {
+    pub const UPDATE_SEARCH_TEXT: &str = r#"
+    UPDATE media
+       SET search_text = (
+           SELECT COALESCE(m.original_filename, '')
+               || ' ' || COALESCE(mm.keywords, '')
+               || ' ' || COALESCE(mm.camera_model, '')
+               || ' ' || COALESCE(mm.location_city, '')
+               || ' ' || COALESCE(mm.location_country, '')
+             FROM media AS m
+             LEFT JOIN media_metadata AS mm ON m.id = mm.media_id
+            WHERE m.id = media.id
+       )
+     WHERE id = ?
+    "#;
+}
+
+pub mod imports {
+    pub const UPSERT_JOB: &str = r#"
+    INSERT INTO import_jobs (
+        id
+      , job_type
+      , status
+      , total_files
+      , processed_files
+      , successful_imports
+      , new_imports
+      , duplicate_imports
+      , failed_imports
+      , started_at
+      , completed_at
+      , errors_json
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT(id) DO UPDATE SET
+        status = excluded.status
+      , total_files = excluded.total_files
+      , processed_files = excluded.processed_files
+      , successful_imports = excluded.successful_imports
+      , new_imports = excluded.new_imports
+      , duplicate_imports = excluded.duplicate_imports
+      , failed_imports = excluded.failed_imports
+      , completed_at = excluded.completed_at
+      , errors_json = excluded.errors_json
+    "#;
+
+    pub const SELECT_LATEST_JOB: &str = r#"
+    SELECT id
+         , status
+         , total_files
+         , processed_files
+         , successful_imports
+         , failed_imports
+         , started_at
+         , completed_at
+         , errors_json
+         , new_imports
+         , duplicate_imports
+      FROM import_jobs
+     ORDER BY id DESC
+     LIMIT 1
+    "#;
+
+    pub const SELECT_JOB_HISTORY: &str = r#"
+    SELECT id
+         , job_type
+         , status
+         , total_files
+         , successful_imports
+         , failed_imports
+         , started_at
+         , completed_at
+         , errors_json
+      FROM import_jobs
+     ORDER BY id DESC
+     LIMIT ? OFFSET ?
+    "#;
+
+    pub const COUNT_JOBS: &str = r#"
+    SELECT COUNT(*)
+      FROM import_jobs
+    "#;
+
+    pub const DELETE_FINISHED_JOBS_BEFORE: &str = r#"
+    DELETE FROM import_jobs
+     WHERE status != 'running'
+       AND COALESCE(completed_at, started_at) < ?
+    "#;
+
+    pub const PRUNE_JOBS: &str = r#"
+    DELETE FROM import_jobs
+     WHERE id NOT IN (
+           SELECT id
+             FROM import_jobs
+            ORDER BY id DESC
+            LIMIT ?
+     )
+    "#;
+}
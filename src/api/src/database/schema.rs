@@ -1,3 +1,4 @@
+use crate::database::migration::{run_migrations, run_migrations_up_to};
 use crate::database::queries;
 use crate::database::DbConn;
 use crate::error::AppResult;
@@ -6,6 +7,15 @@ const SCHEMA: &str = include_str!("schema.sql");
 
 pub mod sql {
     pub const PRAGMA_FOREIGN_KEYS_ON: &str = "PRAGMA foreign_keys = ON";
+
+    /// WAL lets readers proceed while a background job writes; the busy
+    /// timeout covers the remaining writer-writer contention.
+    pub const PRAGMA_CONNECTION_TUNING: &str = r#"
+    PRAGMA journal_mode = WAL;
+    PRAGMA synchronous = NORMAL;
+    PRAGMA cache_size = -32000;
+    PRAGMA busy_timeout = 5000;
+    "#;
 }
 
 fn table_exists(conn: &DbConn, table: &str) -> AppResult<bool> {
@@ -14,9 +24,20 @@ fn table_exists(conn: &DbConn, table: &str) -> AppResult<bool> {
 }
 
 pub fn init_database(conn: &DbConn) -> AppResult<()> {
-    if table_exists(conn, "media")? {
-        return Ok(());
+    create_base_schema(conn)?;
+    run_migrations(conn)
+}
+
+/// Like `init_database`, but stops at schema version `version`, so a later
+/// migration can be tested against the data it would find.
+pub fn init_database_at_version(conn: &DbConn, version: usize) -> AppResult<()> {
+    create_base_schema(conn)?;
+    run_migrations_up_to(conn, version)
+}
+
+fn create_base_schema(conn: &DbConn) -> AppResult<()> {
+    if !table_exists(conn, "media")? {
+        conn.execute_batch(SCHEMA)?;
     }
-    conn.execute_batch(SCHEMA)?;
     Ok(())
 }
@@ -3,8 +3,68 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::{IntoResponses, ToSchema};
+
+/// Machine-readable error code returned alongside the message so clients
+/// can branch on the kind of failure without parsing English text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    AuthFailed,
+    Forbidden,
+    MustChangePassword,
+    NotFound,
+    MediaNotFound,
+    ValidationFailed,
+    Conflict,
+    UnsupportedMedia,
+    BadRequest,
+    InternalError,
+    DatabaseError,
+    InvalidToken,
+    InvalidJson,
+    UpstreamFailed,
+    ServiceUnavailable,
+    TooManyRequests,
+}
+
+/// JSON body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+/// The error statuses shared by all API routes, for the OpenAPI spec.
+#[derive(IntoResponses)]
+pub enum ErrorResponses {
+    #[response(
+        status = 400,
+        description = "BAD_REQUEST, VALIDATION_FAILED or INVALID_JSON"
+    )]
+    BadRequest(ErrorBody),
+    #[response(status = 401, description = "AUTH_FAILED or INVALID_TOKEN")]
+    Unauthorized(ErrorBody),
+    #[response(status = 403, description = "FORBIDDEN or MUST_CHANGE_PASSWORD")]
+    Forbidden(ErrorBody),
+    #[response(status = 404, description = "NOT_FOUND or MEDIA_NOT_FOUND")]
+    NotFound(ErrorBody),
+    #[response(status = 409, description = "CONFLICT")]
+    Conflict(ErrorBody),
+    #[response(status = 422, description = "UNSUPPORTED_MEDIA")]
+    Unprocessable(ErrorBody),
+    #[response(status = 429, description = "TOO_MANY_REQUESTS")]
+    TooManyRequests(ErrorBody),
+    #[response(
+        status = 500,
+        description = "INTERNAL_ERROR, DATABASE_ERROR or UPSTREAM_FAILED"
+    )]
+    Internal(ErrorBody),
+    #[response(status = 503, description = "SERVICE_UNAVAILABLE")]
+    Unavailable(ErrorBody),
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -17,9 +77,15 @@ pub enum AppError {
     #[error("Forbidden: {0}")]
     Forbidden(String),
 
+    #[error("Password change required: {0}")]
+    PasswordChangeRequired(String),
+
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Media not found")]
+    MediaNotFound,
+
     #[error("Validation error: {0}")]
     Validation(String),
 
@@ -29,6 +95,15 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Unprocessable: {0}")]
+    Unprocessable(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    #[error("Service unavailable: {0}")]
+    Unavailable(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -51,16 +126,44 @@ pub enum AppError {
     Request(#[from] reqwest::Error),
 }
 
+impl AppError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Authentication(_) => ErrorCode::AuthFailed,
+            AppError::Authorization(_) | AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::PasswordChangeRequired(_) => ErrorCode::MustChangePassword,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::MediaNotFound => ErrorCode::MediaNotFound,
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Unprocessable(_) => ErrorCode::UnsupportedMedia,
+            AppError::TooManyRequests(_) => ErrorCode::TooManyRequests,
+            AppError::Unavailable(_) => ErrorCode::ServiceUnavailable,
+            AppError::Internal(_) | AppError::Io(_) => ErrorCode::InternalError,
+            AppError::Database(_) | AppError::Pool(_) => ErrorCode::DatabaseError,
+            AppError::Jwt(_) => ErrorCode::InvalidToken,
+            AppError::Json(_) => ErrorCode::InvalidJson,
+            AppError::Request(_) => ErrorCode::UpstreamFailed,
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, message) = match &self {
             AppError::Authentication(msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
             AppError::Authorization(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
+            AppError::PasswordChangeRequired(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            AppError::MediaNotFound => (StatusCode::NOT_FOUND, "Media not found".to_string()),
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, msg.clone()),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AppError::Unprocessable(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg.clone()),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg.clone()),
+            AppError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg.clone()),
             AppError::Internal(msg) => {
                 tracing::error!(
                     "Internal error: {}\nBacktrace: {:?}",
@@ -120,7 +223,10 @@ impl IntoResponse for AppError {
             }
         };
 
-        let body = Json(json!({ "detail": message }));
+        let body = Json(ErrorBody {
+            code: self.code(),
+            message,
+        });
         (status, body).into_response()
     }
 }
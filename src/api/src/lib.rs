@@ -1,10 +1,12 @@
 pub mod app;
 pub mod auth;
+pub mod cache;
 pub mod config;
 pub mod constants;
 pub mod database;
 pub mod error;
 pub mod logging;
+pub mod metrics;
 pub mod models;
 pub mod processor;
 pub mod routes;
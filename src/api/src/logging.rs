@@ -1,37 +1,160 @@
+use axum::body::HttpBody;
+use axum::extract::MatchedPath;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
 use axum::{body::Body, extract::Request, middleware::Next, response::Response};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+use opentelemetry_sdk::Resource;
+use std::fs::OpenOptions;
+use std::sync::Mutex;
 use std::time::Instant;
 use tracing::{error, info, warn};
-use tracing_subscriber::{fmt, EnvFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
 
-pub fn init_logging() {
+use crate::auth::peek_access_token_subject;
+use crate::config::{LogFormat, LoggingConfig, ObservabilityConfig};
+use crate::constants::LOGGED_REQUEST_BODY_MAX_BYTES;
+use crate::metrics::{record_http_request, UNMATCHED_PATH};
+
+/// Install the global subscriber built by `build_subscriber`. Calling this
+/// again after a subscriber is installed is a no-op.
+pub fn init_logging(config: &LoggingConfig, observability: &ObservabilityConfig) {
+    if let Err(e) = build_subscriber(config, observability).try_init() {
+        eprintln!("Logging already initialized: {}", e);
+    }
+}
+
+/// The subscriber for `config`. `RUST_LOG` takes precedence over the
+/// configured level. When an OTLP endpoint is configured, spans are also
+/// exported there.
+pub fn build_subscriber(
+    config: &LoggingConfig,
+    observability: &ObservabilityConfig,
+) -> impl tracing::Subscriber + Send + Sync + 'static {
     let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
         .unwrap_or_else(|_| EnvFilter::new("momento_api=info,tower_http=warn"));
 
-    fmt()
-        .with_env_filter(filter)
+    let file = config.file.as_ref().and_then(|path| {
+        match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Failed to open log file {}: {}", path.display(), e);
+                None
+            }
+        }
+    });
+
+    let layer = fmt::layer()
         .with_target(false)
         .with_thread_ids(false)
         .with_file(false)
-        .with_line_number(false)
-        .init();
+        .with_line_number(false);
+
+    let fmt_layer = match (config.format, file) {
+        (LogFormat::Plain, None) => layer.boxed(),
+        (LogFormat::Plain, Some(file)) => {
+            layer.with_ansi(false).with_writer(Mutex::new(file)).boxed()
+        }
+        (LogFormat::Json, None) => layer.json().boxed(),
+        (LogFormat::Json, Some(file)) => layer.json().with_writer(Mutex::new(file)).boxed(),
+    };
+
+    let otlp_layer = observability
+        .otlp_endpoint
+        .as_deref()
+        .and_then(create_otlp_tracer)
+        .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otlp_layer)
+        .with(filter)
+}
+
+fn create_otlp_tracer(endpoint: &str) -> Option<SdkTracer> {
+    let exporter = match SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("Failed to create OTLP exporter for {}: {}", endpoint, e);
+            return None;
+        }
+    };
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("momento-api").build())
+        .build();
+    let tracer = provider.tracer("momento-api");
+    opentelemetry::global::set_tracer_provider(provider);
+
+    Some(tracer)
 }
 
+/// Route patterns, as in `MatchedPath`, whose request bodies and
+/// `Authorization` headers carry credentials. Only status and latency are
+/// logged for these.
+const SENSITIVE_ROUTES: &[&str] = &[
+    "/api/v1/user/authenticate",
+    "/api/v1/user/refresh",
+    "/api/v1/user/logout",
+    "/api/v1/user/change-password",
+    "/api/v1/user/reset-password",
+    "/api/v1/user/create",
+    "/api/v1/user/update",
+    "/api/v1/admin/users/:id/reset-password",
+    "/api/v1/public/share/:token/verify",
+];
+
+/// Payload keys whose values are replaced before a body is logged,
+/// matched case-insensitively as substrings.
+const REDACTED_PAYLOAD_KEYS: &[&str] = &["password", "token", "secret"];
+
+/// Emits one event per request with `method`, `path`, `status_code`,
+/// `latency_ms` and, when a bearer token is present, `user_id`, and
+/// records the HTTP metrics under the matched route.
 pub async fn request_logger(mut request: Request<Body>, next: Next) -> Response {
     let method = request.method().clone();
     let uri = request.uri().clone();
     let path = uri.path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string());
 
     let is_static = path.starts_with("/assets/") || path.ends_with(".js") || path.ends_with(".css");
-    let payload = extract_compact_payload(&mut request).await;
+    let is_sensitive = SENSITIVE_ROUTES.contains(&route.as_deref().unwrap_or(path.as_str()));
+    let (payload, user_id) = if is_sensitive {
+        (None, None)
+    } else {
+        (
+            extract_compact_payload(&mut request).await,
+            request_user_id(&request),
+        )
+    };
 
     let start = Instant::now();
     let response = next.run(request).await;
     let duration = start.elapsed();
     let status = response.status();
 
+    record_http_request(
+        method.as_str(),
+        route.as_deref().unwrap_or(UNMATCHED_PATH),
+        status.as_u16(),
+        duration,
+    );
+
     if !is_static {
-        let duration_ms = duration.as_secs_f64() * 1000.0;
-        let duration_text = format!("{:05.2}", duration_ms);
+        let latency_ms = duration.as_secs_f64() * 1000.0;
+        let duration_text = format!("{:05.2}", latency_ms);
         let payload_text = payload.unwrap_or_else(|| "{}".to_string());
         let log_line = format!(
             "{} {} {} {}ms {}",
@@ -43,49 +166,118 @@ pub async fn request_logger(mut request: Request<Body>, next: Next) -> Response
         );
 
         let status_code = status.as_u16();
+
+        macro_rules! log_request {
+            ($level:ident) => {
+                $level!(
+                    method = %method,
+                    path = %path,
+                    status_code,
+                    latency_ms,
+                    user_id,
+                    "{}",
+                    log_line
+                )
+            };
+        }
+
         let is_missing_route = status_code == 404;
 
         if is_missing_route {
-            warn!("{}", log_line);
+            log_request!(warn);
             return response;
         }
 
         match status_code {
-            200..=299 => info!("{}", log_line),
-            400..=499 => warn!("{}", log_line),
-            500..=599 => error!("{}", log_line),
-            _ => info!("{}", log_line),
+            200..=299 => log_request!(info),
+            400..=499 => log_request!(warn),
+            500..=599 => log_request!(error),
+            _ => log_request!(info),
         }
     }
 
     response
 }
 
+/// Takes the bearer token from the header or `?token=` query the same way
+/// the auth extractor does, but only peeks at its claims.
+fn request_user_id(request: &Request<Body>) -> Option<i64> {
+    let header_token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    let token = header_token.or_else(|| {
+        let query = request.uri().query()?;
+        serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+            .ok()?
+            .into_iter()
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value)
+    })?;
+
+    peek_access_token_subject(&token)
+}
+
+/// Small JSON bodies only: anything else, such as a multipart upload, is
+/// passed through untouched rather than buffered into memory for the log.
 async fn extract_compact_payload(request: &mut Request<Body>) -> Option<String> {
     if request.method() != axum::http::Method::POST {
         return None;
     }
 
-    let body = std::mem::replace(request.body_mut(), Body::empty());
-    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
-        Ok(b) => b,
-        Err(_) => return None,
+    let headers = request.headers();
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|mime| {
+            let mime = mime.trim().to_ascii_lowercase();
+            mime == "application/json" || mime.ends_with("+json")
+        })
+        .unwrap_or(false);
+    // Exact for a request with a Content-Length; chunked bodies have none
+    let length = request
+        .body()
+        .size_hint()
+        .exact()
+        .and_then(|length| usize::try_from(length).ok());
+    let length = match length {
+        Some(length) if is_json && length <= LOGGED_REQUEST_BODY_MAX_BYTES => length,
+        _ => return None,
     };
 
-    let body_str = match String::from_utf8(bytes.to_vec()) {
-        Ok(s) => s,
+    let body = std::mem::replace(request.body_mut(), Body::empty());
+    let bytes = match axum::body::to_bytes(body, length).await {
+        Ok(b) => b,
         Err(_) => return None,
     };
 
-    let compact = match serde_json::from_str::<serde_json::Value>(&body_str) {
-        Ok(value) => value.to_string(),
-        Err(_) => body_str.trim().to_string(),
-    };
+    let parsed = serde_json::from_slice::<serde_json::Value>(&bytes);
+    *request.body_mut() = Body::from(bytes);
 
-    let restored = Body::from(bytes);
-    *request.body_mut() = restored;
+    let mut value = parsed.ok()?;
+    redact_payload(&mut value);
+    Some(value.to_string())
+}
 
-    Some(compact)
+fn redact_payload(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if REDACTED_PAYLOAD_KEYS.iter().any(|k| key.contains(k)) {
+                    *field = serde_json::Value::String("[redacted]".to_string());
+                } else {
+                    redact_payload(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_payload),
+        _ => {}
+    }
 }
 
 pub fn log_error(context: &str, error: &dyn std::error::Error) {
@@ -110,10 +302,18 @@ pub fn log_panic(info: &std::panic::PanicHookInfo) {
     error!("PANIC at {}: {}", location, payload);
 }
 
-pub fn install_panic_hook() {
+/// In JSON mode the default hook is skipped so that stderr stays
+/// machine-readable; the backtrace is attached to the log event instead.
+pub fn install_panic_hook(format: LogFormat) {
     let default_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
         log_panic(info);
-        default_hook(info);
+        match format {
+            LogFormat::Plain => default_hook(info),
+            LogFormat::Json => {
+                let backtrace = std::backtrace::Backtrace::capture();
+                error!(backtrace = %backtrace, "panic backtrace");
+            }
+        }
     }));
 }
@@ -1,16 +1,20 @@
-use momento_api::app::create_app;
+use momento_api::app::create_app_with_shutdown;
 use momento_api::auth::hash_password;
-use momento_api::config::{load_config, save_default_config};
+use momento_api::auth::ShutdownSignal;
+use momento_api::config::{load_config, save_default_config, try_load_config};
 use momento_api::constants::{
     CONFIG_PATH, DATA_DIR, IMPORTS_DIR, ORIGINALS_DIR, PREVIEWS_DIR, THUMBNAILS_DIR, WEBDAV_DIR,
 };
 use momento_api::database::{create_pool, init_database, queries};
 use momento_api::logging::{init_logging, install_panic_hook};
-use momento_api::processor::importer::start_webdav_import_job;
-use momento_api::processor::regenerator::generate_missing_metadata;
-use momento_api::routes::cleanup_expired_trash;
+use momento_api::processor::backup::start_backup_job;
+use momento_api::processor::importer::{start_sftp_import_job, start_webdav_import_job};
+use momento_api::processor::regenerator::{cancel_regeneration, generate_missing_metadata};
+use momento_api::processor::workers::init_image_workers;
+use momento_api::routes::{cleanup_expired_refresh_tokens, cleanup_expired_trash};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 fn init_directories() {
     for dir in [
@@ -25,6 +29,79 @@ fn init_directories() {
     }
 }
 
+fn find_on_path(program: &str) -> Option<std::path::PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+fn check_directory(dir: &std::path::Path) -> Result<(), String> {
+    let probe = dir.join(format!(".momento-check-{}", std::process::id()));
+    std::fs::create_dir_all(&probe).map_err(|e| e.to_string())?;
+    std::fs::remove_dir(&probe).map_err(|e| e.to_string())
+}
+
+/// Validate configuration and environment for `--check-config`.
+/// Prints one line per check and returns whether all of them passed.
+fn run_config_check() -> bool {
+    let mut ok = true;
+
+    println!("Config file: {}", CONFIG_PATH.display());
+    let config = match try_load_config(&CONFIG_PATH) {
+        Ok(config) => {
+            if CONFIG_PATH.exists() {
+                println!("  [ok] configuration parsed");
+            } else {
+                println!("  [ok] file not found, using defaults");
+            }
+            config
+        }
+        Err(e) => {
+            println!("  [fail] {}", e);
+            return false;
+        }
+    };
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
+    match std::net::TcpListener::bind(addr) {
+        Ok(_) => println!("  [ok] able to bind {}", addr),
+        Err(e) => {
+            println!("  [fail] cannot bind {}: {}", addr, e);
+            ok = false;
+        }
+    }
+
+    for program in ["exiftool", "ffprobe"] {
+        match find_on_path(program) {
+            Some(path) => println!("  [ok] {} found at {}", program, path.display()),
+            None => {
+                println!("  [fail] {} not found on PATH", program);
+                ok = false;
+            }
+        }
+    }
+
+    for dir in [
+        &*DATA_DIR,
+        &*ORIGINALS_DIR,
+        &*THUMBNAILS_DIR,
+        &*PREVIEWS_DIR,
+        &*IMPORTS_DIR,
+        &*WEBDAV_DIR,
+    ] {
+        match check_directory(dir) {
+            Ok(()) => println!("  [ok] directory writable: {}", dir.display()),
+            Err(e) => {
+                println!("  [fail] directory {} not writable: {}", dir.display(), e);
+                ok = false;
+            }
+        }
+    }
+
+    ok
+}
+
 fn ensure_backtrace_enabled() {
     let has_backtrace = std::env::var_os("RUST_LIB_BACKTRACE").is_some()
         || std::env::var_os("RUST_BACKTRACE").is_some();
@@ -75,7 +152,16 @@ fn start_background_tasks(
         generate_missing_metadata(&config_clone, &pool_clone).await;
 
         if let Ok(conn) = pool_clone.get() {
-            let _ = cleanup_expired_trash(&conn);
+            if let Err(e) = cleanup_expired_trash(&conn, &config_clone.trash) {
+                tracing::warn!("Failed to clean up expired trash: {}", e);
+            }
+            match cleanup_expired_refresh_tokens(&conn) {
+                Ok(removed) if removed > 0 => {
+                    tracing::info!("Removed {} expired refresh tokens", removed)
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to remove expired refresh tokens: {}", e),
+            }
         }
     });
 
@@ -86,6 +172,35 @@ fn start_background_tasks(
             start_webdav_import_job(webdav_config, webdav_pool).await;
         });
     }
+
+    if config.sftp.enabled {
+        let sftp_config = Arc::clone(&config);
+        let sftp_pool = pool.clone();
+        tokio::spawn(async move {
+            start_sftp_import_job(sftp_config, sftp_pool).await;
+        });
+    }
+
+    if config.backup.enabled {
+        let backup_config = Arc::clone(&config);
+        let backup_pool = pool.clone();
+        tokio::spawn(async move {
+            start_backup_job(backup_config, backup_pool).await;
+        });
+    }
+}
+
+/// Waits for CTRL+C, then marks the server as draining and asks a running
+/// regeneration job to stop after its current batch.
+async fn shutdown_signal(shutdown: ShutdownSignal) {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        tracing::error!("Failed to listen for shutdown signal: {}", e);
+        std::future::pending::<()>().await;
+    }
+
+    tracing::info!("Shutdown requested, draining in-flight requests");
+    shutdown.trigger();
+    cancel_regeneration();
 }
 
 #[tokio::main]
@@ -103,20 +218,30 @@ async fn main() {
         }
     }
 
-    ensure_backtrace_enabled();
+    if std::env::args().any(|arg| arg == "--check-config") {
+        let ok = run_config_check();
+        println!(
+            "Configuration check {}",
+            if ok { "passed" } else { "failed" }
+        );
+        std::process::exit(if ok { 0 } else { 1 });
+    }
 
-    // Initialize logging
-    init_logging();
-    install_panic_hook();
+    ensure_backtrace_enabled();
 
     // Load configuration
     let config = Arc::new(load_config(&CONFIG_PATH));
 
+    // Initialize logging
+    init_logging(&config.logging, &config.observability);
+    install_panic_hook(config.logging.format);
+
     // Initialize directories
     init_directories();
 
-    // Create database pool
-    let pool = create_pool().expect("Failed to create database pool");
+    // Create database pool, waiting for its idle connections to open
+    let pool = create_pool(&config.database)
+        .unwrap_or_else(|e| panic!("Database unavailable at startup: {}", e));
 
     // Initialize database schema
     {
@@ -127,11 +252,15 @@ async fn main() {
     // Create default admin if needed
     create_default_admin(&pool, &config);
 
+    // Size the image worker pool before background tasks first use it
+    init_image_workers(config.regenerate.num_cpus);
+
     // Start background tasks
     start_background_tasks(Arc::clone(&config), pool.clone());
 
     // Create the application
-    let app = create_app(Arc::clone(&config), pool);
+    let shutdown = ShutdownSignal::default();
+    let app = create_app_with_shutdown(Arc::clone(&config), pool, shutdown.clone());
 
     // Bind to address
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
@@ -142,5 +271,23 @@ async fn main() {
         .await
         .expect("Failed to bind");
 
-    axum::serve(listener, app).await.expect("Server failed");
+    let drain_timeout = Duration::from_secs(config.server.shutdown_timeout_seconds);
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(shutdown.clone()));
+
+    tokio::select! {
+        result = server => result.expect("Server failed"),
+        _ = async {
+            shutdown.triggered().await;
+            tokio::time::sleep(drain_timeout).await;
+        } => {
+            tracing::warn!(
+                "In-flight requests still running after {}s, exiting",
+                drain_timeout.as_secs()
+            );
+        }
+    }
 }
@@ -0,0 +1,126 @@
+use axum::extract::State;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use once_cell::sync::Lazy;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::auth::AppState;
+use crate::database::{fetch_all, queries};
+use crate::error::{AppError, AppResult};
+
+const HTTP_REQUESTS_TOTAL: &str = "momento_http_requests_total";
+const HTTP_REQUEST_DURATION_SECONDS: &str = "momento_http_request_duration_seconds";
+const MEDIA_TOTAL: &str = "momento_media_total";
+const IMPORT_JOBS_RUNNING: &str = "momento_import_jobs_running";
+const REGENERATION_JOBS_RUNNING: &str = "momento_regeneration_jobs_running";
+
+const HTTP_DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Label used for requests that matched no route, so scanners probing
+/// random paths cannot inflate the label set.
+pub const UNMATCHED_PATH: &str = "unmatched";
+
+/// The process-wide recorder. Installed on first use; every app built in
+/// the same process shares it.
+static HANDLE: Lazy<PrometheusHandle> = Lazy::new(|| {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(HTTP_REQUEST_DURATION_SECONDS.to_string()),
+            HTTP_DURATION_BUCKETS,
+        )
+        .expect("histogram buckets are not empty")
+        .install_recorder()
+        .expect("Failed to install the metrics recorder")
+});
+
+pub fn init_metrics() {
+    Lazy::force(&HANDLE);
+}
+
+/// `path` should be the matched route template (`/api/v1/media/:media_id`),
+/// never the raw URI.
+pub fn record_http_request(method: &str, path: &str, status: u16, duration: Duration) {
+    let labels = [
+        ("method", method.to_string()),
+        ("path", path.to_string()),
+        ("status", status.to_string()),
+    ];
+    metrics::counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION_SECONDS,
+        "method" => method.to_string(),
+        "path" => path.to_string(),
+    )
+    .record(duration.as_secs_f64());
+}
+
+pub fn import_job_started() {
+    metrics::gauge!(IMPORT_JOBS_RUNNING).increment(1.0);
+}
+
+pub fn import_job_finished() {
+    metrics::gauge!(IMPORT_JOBS_RUNNING).decrement(1.0);
+}
+
+pub fn set_regeneration_running(running: bool) {
+    metrics::gauge!(REGENERATION_JOBS_RUNNING).set(if running { 1.0 } else { 0.0 });
+}
+
+/// Prometheus text exposition. Unauthenticated unless
+/// `metrics.bearer_token` is configured. Per-user media counts are read
+/// at scrape time rather than tracked on every upload and delete.
+pub async fn metrics_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> AppResult<impl IntoResponse> {
+    if let Some(expected) = state.config.metrics.bearer_token.as_deref() {
+        let provided = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if !provided.is_some_and(|token| constant_time_eq(token, expected)) {
+            return Err(AppError::Authentication(
+                "Invalid metrics token".to_string(),
+            ));
+        }
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let counts: Vec<(i64, i64)> = fetch_all(
+        &conn,
+        queries::users::SELECT_OWNED_MEDIA_COUNTS,
+        &[],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let mut body = HANDLE.render();
+    render_media_totals(&mut body, &counts);
+
+    Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
+
+/// Appends the per-user media gauge. It is rendered from the current counts
+/// on every scrape instead of going through the recorder, which never drops
+/// a label set, so deleted users disappear from the output.
+fn render_media_totals(body: &mut String, counts: &[(i64, i64)]) {
+    if !body.is_empty() && !body.ends_with('\n') {
+        body.push('\n');
+    }
+    let _ = writeln!(body, "# TYPE {} gauge", MEDIA_TOTAL);
+    for (user_id, count) in counts {
+        let _ = writeln!(body, "{}{{user_id=\"{}\"}} {}", MEDIA_TOTAL, user_id, count);
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
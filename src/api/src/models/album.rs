@@ -1,7 +1,8 @@
 use crate::models::MediaResponse;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumResponse {
     pub id: i64,
@@ -10,9 +11,10 @@ pub struct AlbumResponse {
     pub cover_media_id: Option<i64>,
     pub media_count: i64,
     pub created_at: String,
+    pub position: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumDetailResponse {
     pub id: i64,
@@ -23,20 +25,28 @@ pub struct AlbumDetailResponse {
     pub created_at: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumGetRequest {
     pub album_id: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumCreateRequest {
     pub name: String,
     pub description: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumCreateFromTagRequest {
+    pub tag_name: String,
+    pub album_name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumUpdateRequest {
     pub album_id: i64,
@@ -45,35 +55,69 @@ pub struct AlbumUpdateRequest {
     pub cover_media_id: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumDeleteRequest {
     pub album_id: i64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumAddMediaRequest {
     pub album_id: i64,
     pub media_ids: Vec<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumRemoveMediaRequest {
     pub album_id: i64,
     pub media_ids: Vec<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumClearMediaResponse {
+    pub removed_count: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumReorderRequest {
     pub album_id: i64,
     pub media_ids: Vec<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumListReorderRequest {
+    pub album_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumMergeRequest {
+    pub source_album_id: i64,
+    pub target_album_id: i64,
+    #[serde(default)]
+    pub delete_source: bool,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumListRequest {
+    pub cursor: Option<String>,
+    pub limit: Option<i32>,
+    #[serde(default)]
+    pub include_total: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AlbumListResponse {
     pub albums: Vec<AlbumResponse>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<i64>,
 }
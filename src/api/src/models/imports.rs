@@ -1,26 +1,72 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ImportStatusResponse {
     pub status: String,
     pub total_files: i64,
     pub processed_files: i64,
     pub successful_imports: i64,
+    pub new_imports: i64,
+    pub duplicate_imports: i64,
     pub failed_imports: i64,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
     pub errors: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportHistoryEntry {
+    pub id: i64,
+    /// `local`, `webdav` or `sftp`
+    pub job_type: String,
+    pub status: String,
+    pub total_files: i64,
+    pub successful_imports: i64,
+    pub failed_imports: i64,
+    pub started_at: Option<String>,
+    pub completed_at: Option<String>,
+    /// The first few recorded errors
+    pub errors: Vec<String>,
+    pub error_count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportHistoryResponse {
+    pub jobs: Vec<ImportHistoryEntry>,
+    pub total_jobs: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportHistoryPurgeResponse {
+    pub deleted_count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ImportTriggerResponse {
     pub message: String,
     pub status: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalDirectoryImportRequest {
+    /// Must lie within one of `import.allowed_import_roots`
+    pub path: String,
+    /// Owner of the imported media
+    pub user_id: i64,
+    #[serde(default)]
+    pub recursive: bool,
+    #[serde(default)]
+    pub delete_after_import: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RegenerateRequest {
     #[serde(default = "default_missing_only")]
@@ -31,14 +77,14 @@ fn default_missing_only() -> bool {
     true
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RegenerateResponse {
     pub message: String,
     pub status: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RegenerationStatusResponse {
     pub status: String,
@@ -1,8 +1,9 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::MediaResponse;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct BoundingBox {
     pub north: f64,
@@ -11,14 +12,23 @@ pub struct BoundingBox {
     pub west: f64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MapClustersRequest {
     pub bounds: BoundingBox,
     pub zoom: u8,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MapLensFilterRequest {
+    pub lens_model: String,
+    pub bounds: BoundingBox,
+    /// Optional map zoom; estimated from the bounds when omitted.
+    pub zoom: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MapMediaRequest {
     pub bounds: BoundingBox,
@@ -26,7 +36,22 @@ pub struct MapMediaRequest {
     pub geohash_prefixes: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MapNearbyRequest {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_meters: f64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MapGeohashSearchRequest {
+    /// A geohash prefix of 2 to 9 characters, e.g. `dr5r`.
+    pub query: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Cluster {
     pub id: String,
@@ -36,15 +61,24 @@ pub struct Cluster {
     pub representative_id: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MapClustersResponse {
     pub clusters: Vec<Cluster>,
     pub total_count: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MapMediaListResponse {
     pub items: Vec<MediaResponse>,
 }
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MapGeohashSearchResponse {
+    pub geohash: String,
+    /// The cell covered by `geohash`, for fitting the map to the results.
+    pub bounds: BoundingBox,
+    pub items: Vec<MediaResponse>,
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaResponse {
     pub id: i64,
@@ -29,27 +30,89 @@ pub struct MediaResponse {
     pub location_state: Option<String>,
     pub location_country: Option<String>,
     pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_channels: Option<i32>,
+    pub audio_sample_rate: Option<i32>,
     pub keywords: Option<String>,
     pub content_hash: Option<String>,
     pub created_at: String,
+    /// `date_taken` and `created_at` are more than a year apart, which
+    /// usually means the camera clock was never set.
+    pub has_date_mismatch: bool,
+    pub is_favorite: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaListRequest {
     pub cursor: Option<String>,
     #[serde(default)]
     pub limit: Option<i32>,
     pub group_by: Option<String>,
+    pub iso_min: Option<i32>,
+    pub iso_max: Option<i32>,
+    pub focal_length_35mm_min: Option<f64>,
+    pub focal_length_35mm_max: Option<f64>,
+    pub lens_model: Option<String>,
+    /// Matched against `filename` and `original_filename`: a glob when it
+    /// contains `*` or `?`, otherwise a substring
+    pub filename_pattern: Option<String>,
+    /// Exact match; an empty string matches media with no recorded make
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub favorites_only: Option<bool>,
+    /// Bounds on when the media was added to the library, not when it was taken
+    pub uploaded_after: Option<String>,
+    pub uploaded_before: Option<String>,
+    /// Restricts the listing to an album the user can access, in album order
+    pub album_id: Option<i64>,
+    #[serde(default)]
+    pub include_total: bool,
+    /// Defaults to `dateTaken`; cannot be combined with `groupBy` or `albumId`
+    pub sort_by: Option<SortField>,
+    /// Defaults to `desc`. Media without a value sort last either way
+    pub sort_dir: Option<SortDir>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SortField {
+    #[default]
+    DateTaken,
+    FileSize,
+    Duration,
+    CameraModel,
+    OriginalFilename,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDir {
+    Asc,
+    #[default]
+    Desc,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaListByCameraRequest {
+    /// As returned by `/media/cameras`; empty when the make is unknown
+    #[serde(default)]
+    pub make: String,
+    pub model: String,
+    pub cursor: Option<String>,
+    pub limit: Option<i32>,
+    #[serde(default)]
+    pub include_total: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaBatchRequest {
     pub ids: Vec<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaListResponse {
     pub items: Vec<MediaResponse>,
@@ -57,15 +120,252 @@ pub struct MediaListResponse {
     pub has_more: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub groups: Option<Vec<TimelineGroup>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DateBucket {
+    pub date: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineDatesResponse {
+    pub buckets: Vec<DateBucket>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LensModelsResponse {
+    pub lens_models: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// Shared perceptual hash as 16 hex digits
+    pub phash: String,
+    pub media_ids: Vec<i64>,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicatesResponse {
+    pub groups: Vec<DuplicateGroup>,
+    pub total_wasted_bytes: i64,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartAlbumSuggestRequest {
+    /// Geohash prefix length items must share; 4 is roughly 40 km.
+    pub geohash_precision: Option<usize>,
+    pub window_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumSuggestion {
+    pub suggested_name: String,
+    pub media_ids: Vec<i64>,
+    pub center_lat: f64,
+    pub center_lon: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartAlbumSuggestResponse {
+    pub suggestions: Vec<AlbumSuggestion>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraCount {
+    pub camera: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraGroup {
+    pub make: String,
+    pub model: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationCount {
+    pub city: Option<String>,
+    pub country: Option<String>,
+    pub count: i64,
+}
+
+/// One bar of the 35mm-equivalent focal length histogram. `minMm` is
+/// inclusive and `maxMm` exclusive; the outer buckets are open-ended.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FocalLengthBucket {
+    pub label: String,
+    pub min_mm: Option<f64>,
+    pub max_mm: Option<f64>,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationFacet {
+    pub country: String,
+    pub state: Option<String>,
+    pub city: Option<String>,
+    pub count: i64,
+}
+
+/// A coarse geohash cell for heat maps, with its center point.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GeohashBucket {
+    pub geohash: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaLocationsResponse {
+    pub locations: Vec<LocationFacet>,
+    pub heatmap: Vec<GeohashBucket>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaStatsResponse {
+    pub total_media: i64,
+    pub total_photos: i64,
+    pub total_videos: i64,
+    pub total_size_bytes: i64,
+    pub oldest_date: Option<String>,
+    pub newest_date: Option<String>,
+    pub top_cameras: Vec<CameraCount>,
+    pub top_locations: Vec<LocationCount>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaBatchResponse {
     pub items: Vec<MediaResponse>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadError {
+    pub filename: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResponse {
+    pub uploaded: Vec<MediaResponse>,
+    pub failed: Vec<UploadError>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaImportUrlRequest {
+    pub url: String,
+    /// Overrides the name from `Content-Disposition` or the URL path.
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaReprocessRequest {
+    pub media_id: i64,
+    #[serde(default)]
+    pub regenerate_thumbnails: bool,
+    #[serde(default)]
+    pub regenerate_metadata: bool,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaExportMetadataRequest {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSimilarRequest {
+    pub media_id: i64,
+    /// Maximum Hamming distance between perceptual hashes
+    pub threshold: Option<u32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarMedia {
+    #[serde(flatten)]
+    pub media: MediaResponse,
+    pub distance: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSimilarResponse {
+    pub items: Vec<SimilarMedia>,
+}
+
+/// Sparse metadata edit: an omitted field is left unchanged and an explicit
+/// `null` clears it.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaMetadataPatch {
+    pub media_id: i64,
+    #[serde(default, deserialize_with = "present")]
+    pub camera_make: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub camera_model: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub lens_make: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub lens_model: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub iso: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub f_number: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub exposure_time: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub focal_length: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub keywords: Option<Option<String>>,
+}
+
+/// Distinguishes an explicit `null` (`Some(None)`) from a missing field,
+/// which `#[serde(default)]` leaves as `None`.
+fn present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaUpdateRequest {
     pub media_id: i64,
@@ -74,19 +374,188 @@ pub struct MediaUpdateRequest {
     pub gps_longitude: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+/// One field changed by a manual edit. Values are stored as text; `None`
+/// means the field was unset.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaEditRecord {
+    pub id: i64,
+    pub media_id: i64,
+    pub user_id: Option<i64>,
+    pub field_name: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub edited_at: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaManualGeocodeRequest {
+    pub media_id: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaStripLocationRequest {
+    pub media_id: i64,
+    #[serde(default)]
+    pub modify_original: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaRotateRequest {
+    pub media_id: i64,
+    pub degrees: i32,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDetectOrientationRequest {
+    pub media_id: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDetectOrientationResponse {
+    pub was_rotated: bool,
+    /// Clockwise rotation applied to the original; omitted when unchanged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub degrees_applied: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated_media: Option<MediaResponse>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaRegenerateThumbnailRequest {
+    pub media_id: i64,
+    pub offset_seconds: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDownloadTokenRequest {
+    pub media_id: i64,
+    pub expires_in_seconds: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDownloadTokenResponse {
+    pub token: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaDeleteRequest {
     pub media_id: i64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaCheckDuplicateRequest {
+    /// Lowercase hex SHA-256 of the file contents.
+    pub content_hash: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaCheckDuplicateResponse {
+    pub exists: bool,
+    pub media_id: Option<i64>,
+}
+
+/// Exactly one of `albumId` or `tag` selects the media.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSlideshowRequest {
+    pub album_id: Option<i64>,
+    /// Tag name, matched exactly
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub shuffle: bool,
+    /// Shuffle seed; a random one is used when omitted
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaSlideshowResponse {
+    /// Changes whenever the request or the resulting sequence does
+    pub sequence_id: String,
+    pub media_ids: Vec<i64>,
+    /// Seed the order was shuffled with, so a random order can be replayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaTransferRequest {
+    pub from_user_id: i64,
+    pub to_user_id: i64,
+    /// Transfers everything the source user has outside the trash when unset
+    pub media_ids: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaTransferResponse {
+    pub transferred_count: i64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDuplicateRequest {
+    pub media_id: i64,
+    pub target_user_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DeleteMediaResponse {
     pub message: String,
 }
 
-#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaBulkDeleteRequest {
+    pub media_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaFavoriteRequest {
+    pub media_id: i64,
+    pub is_favorite: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDeleteMediaResponse {
+    pub deleted_count: usize,
+    pub skipped_ids: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDateCorrectionRequest {
+    pub media_ids: Vec<i64>,
+    pub offset_seconds: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaDateCorrectionResponse {
+    pub affected_count: i64,
+    pub sample_new_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ThumbnailSize {
     #[default]
@@ -94,7 +563,7 @@ pub enum ThumbnailSize {
     Tiny,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ThumbnailBatchRequest {
     pub media_ids: Vec<i64>,
@@ -102,25 +571,25 @@ pub struct ThumbnailBatchRequest {
     pub size: ThumbnailSize,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ThumbnailBatchResponse {
     pub thumbnails: std::collections::HashMap<i64, Option<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PreviewBatchRequest {
     pub ids: Vec<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PreviewBatchResponse {
     pub previews: std::collections::HashMap<i64, Option<String>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TimelineGroup {
     pub date: String,
@@ -1,19 +1,23 @@
 mod album;
 mod auth;
+mod backup;
 mod imports;
 mod map;
 mod media;
 mod share;
+mod storage;
 mod tag;
 mod trash;
 mod user;
 
 pub use album::*;
 pub use auth::*;
+pub use backup::*;
 pub use imports::*;
 pub use map::*;
 pub use media::*;
 pub use share::*;
+pub use storage::*;
 pub use tag::*;
 pub use trash::*;
 pub use user::*;
@@ -1,6 +1,33 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Serialize)]
+/// What a public share link allows. View-only links serve metadata and
+/// thumbnails but refuse original file downloads.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SharePermissions {
+    ViewOnly,
+    #[default]
+    Download,
+}
+
+impl SharePermissions {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SharePermissions::ViewOnly => "view_only",
+            SharePermissions::Download => "download",
+        }
+    }
+
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "view_only" => SharePermissions::ViewOnly,
+            _ => SharePermissions::Download,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareLinkResponse {
     pub id: i64,
@@ -10,37 +37,56 @@ pub struct ShareLinkResponse {
     pub has_password: bool,
     pub expires_at: Option<String>,
     pub view_count: i64,
+    pub permissions: SharePermissions,
     pub created_at: String,
+    /// Absolute share link, present when `server.base_url` is configured
+    pub public_url: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareCreateRequest {
     pub media_id: Option<i64>,
     pub album_id: Option<i64>,
     pub password: Option<String>,
     pub expires_in_days: Option<i32>,
+    #[serde(default)]
+    pub permissions: SharePermissions,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareDeleteRequest {
     pub share_id: i64,
 }
 
-#[derive(Debug, Serialize)]
+/// Omitting `expires_in_days` makes the link non-expiring.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareExtendRequest {
+    pub share_id: i64,
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareRotateTokenRequest {
+    pub share_id: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareListResponse {
     pub shares: Vec<ShareLinkResponse>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareVerifyRequest {
     pub password: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareMediaRequest {
     pub media_id: i64,
@@ -48,10 +94,28 @@ pub struct ShareMediaRequest {
     pub access_level: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ShareAlbumRequest {
     pub album_id: i64,
     pub target_user_id: i64,
     pub access_level: i32,
 }
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareReportRequest {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareReportResponse {
+    pub id: i64,
+    pub token: String,
+    pub reason: String,
+    pub created_at: String,
+    /// `None` when the token no longer matches a share
+    pub share_id: Option<i64>,
+    pub share_suspended_at: Option<String>,
+}
@@ -0,0 +1,35 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsageEntry {
+    pub user_id: i64,
+    /// `YYYY-MM` of `date_taken`, or `None` for undated media
+    pub month: Option<String>,
+    pub media_type: String,
+    pub total_bytes: i64,
+    pub file_count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTrashUsage {
+    pub user_id: i64,
+    pub trash_bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanupResponse {
+    pub deleted_files: i64,
+    pub freed_bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageAnalysisResponse {
+    pub usage: Vec<StorageUsageEntry>,
+    pub trash: Vec<UserTrashUsage>,
+    pub generated_at: String,
+}
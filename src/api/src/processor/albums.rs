@@ -0,0 +1,36 @@
+use std::path::PathBuf;
+
+use crate::constants::ALBUM_COVERS_DIR;
+use crate::database::queries;
+use crate::error::AppResult;
+
+/// Creates an album owned by `user_id` and grants them owner access.
+pub fn insert_album(
+    conn: &rusqlite::Connection,
+    user_id: i64,
+    name: &str,
+    description: Option<&str>,
+) -> AppResult<i64> {
+    conn.execute(
+        queries::albums::INSERT,
+        rusqlite::params![user_id, name, description],
+    )?;
+    let album_id = conn.last_insert_rowid();
+
+    conn.execute(
+        queries::access::INSERT_ALBUM_ACCESS,
+        rusqlite::params![album_id, user_id, 2],
+    )?;
+
+    Ok(album_id)
+}
+
+pub fn album_cover_path(album_id: i64) -> PathBuf {
+    ALBUM_COVERS_DIR.join(format!("{}.jpg", album_id))
+}
+
+/// Drops the composite cover so the next request renders it again from
+/// the album's current first four items.
+pub fn invalidate_album_cover(album_id: i64) {
+    let _ = std::fs::remove_file(album_cover_path(album_id));
+}
@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use object_store::aws::AmazonS3Builder;
+use object_store::buffered::BufWriter;
+use object_store::path::Path as ObjectPath;
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info};
+
+use crate::config::{BackupConfig, BackupDestination, Config};
+use crate::database::{queries, DbPool};
+
+static BACKUP_RUNNING: AtomicBool = AtomicBool::new(false);
+
+pub fn is_backup_running() -> bool {
+    BACKUP_RUNNING.load(Ordering::SeqCst)
+}
+
+pub fn backup_file_name() -> String {
+    format!(
+        "momento_backup_{}.sqlite",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+/// Writes a consistent snapshot of the live database to `target`.
+async fn vacuum_into(pool: &DbPool, target: PathBuf) -> Result<(), String> {
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = pool
+            .get()
+            .map_err(|e| format!("Failed to get connection: {}", e))?;
+        conn.execute(
+            queries::schema::VACUUM_INTO,
+            [target.to_string_lossy().as_ref()],
+        )
+        .map_err(|e| format!("VACUUM INTO {} failed: {}", target.display(), e))?;
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Backup task panicked: {}", e))?
+}
+
+async fn upload_to_s3(
+    source: &Path,
+    endpoint: &str,
+    bucket: &str,
+    key: &str,
+    access_key: &str,
+    secret_key: &str,
+) -> Result<(), String> {
+    let store = AmazonS3Builder::new()
+        .with_endpoint(endpoint)
+        .with_bucket_name(bucket)
+        .with_access_key_id(access_key)
+        .with_secret_access_key(secret_key)
+        .with_allow_http(endpoint.starts_with("http://"))
+        .build()
+        .map_err(|e| format!("Invalid S3 configuration: {}", e))?;
+
+    let mut file = tokio::fs::File::open(source)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", source.display(), e))?;
+    let mut writer = BufWriter::new(Arc::new(store), ObjectPath::from(key));
+    tokio::io::copy(&mut file, &mut writer)
+        .await
+        .map_err(|e| format!("Failed to upload backup: {}", e))?;
+    writer
+        .shutdown()
+        .await
+        .map_err(|e| format!("Failed to finish backup upload: {}", e))?;
+
+    Ok(())
+}
+
+async fn write_backup(config: &BackupConfig, pool: &DbPool) -> Result<String, String> {
+    let file_name = backup_file_name();
+
+    match &config.destination {
+        BackupDestination::Local { path } => {
+            tokio::fs::create_dir_all(path)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+            let target = path.join(&file_name);
+            vacuum_into(pool, target.clone()).await?;
+            Ok(target.display().to_string())
+        }
+        BackupDestination::S3 {
+            endpoint,
+            bucket,
+            prefix,
+            access_key,
+            secret_key,
+        } => {
+            let staging = std::env::temp_dir().join(&file_name);
+            vacuum_into(pool, staging.clone()).await?;
+
+            let prefix = prefix.trim_matches('/');
+            let key = if prefix.is_empty() {
+                file_name
+            } else {
+                format!("{}/{}", prefix, file_name)
+            };
+            let result =
+                upload_to_s3(&staging, endpoint, bucket, &key, access_key, secret_key).await;
+            let _ = tokio::fs::remove_file(&staging).await;
+            result?;
+
+            Ok(format!("s3://{}/{}", bucket, key))
+        }
+    }
+}
+
+/// Creates one backup and returns where it was written.
+pub async fn run_backup(config: &BackupConfig, pool: &DbPool) -> Result<String, String> {
+    if BACKUP_RUNNING.swap(true, Ordering::SeqCst) {
+        return Err("Backup already in progress".to_string());
+    }
+
+    let result = write_backup(config, pool).await;
+    BACKUP_RUNNING.store(false, Ordering::SeqCst);
+
+    match &result {
+        Ok(location) => info!("Database backup written to {}", location),
+        Err(e) => error!("Database backup failed: {}", e),
+    }
+    result
+}
+
+pub async fn start_backup_job(config: Arc<Config>, pool: DbPool) {
+    let interval = Duration::from_secs(config.backup.interval_hours.max(1) * 3600);
+    info!(
+        "Scheduled database backups every {} hour(s)",
+        interval.as_secs() / 3600
+    );
+
+    loop {
+        tokio::time::sleep(interval).await;
+        let _ = run_backup(&config.backup, &pool).await;
+    }
+}
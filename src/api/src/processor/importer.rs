@@ -1,13 +1,24 @@
 use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use std::collections::HashMap;
 use std::fmt;
 use std::path::{Path, PathBuf};
-use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
 use tracing::{debug, error, info, warn};
 
-use crate::config::Config;
-use crate::constants::{IMPORTS_DIR, SUPPORTED_EXTENSIONS, WEBDAV_DIR};
-use crate::database::{fetch_one, DbPool};
-use crate::processor::media_processor::{process_media_file, MediaProcessingContext};
+use crate::config::{Config, SftpConfig};
+use crate::constants::{
+    IMPORT_JOB_HISTORY_LIMIT, SFTP_STAGING_DIR, SUPPORTED_EXTENSIONS, WEBDAV_DIR,
+};
+use crate::database::{execute_query, fetch_one, queries, DbPool};
+use crate::error::{AppError, AppResult};
+use crate::metrics::{import_job_finished, import_job_started};
+use crate::processor::albums::{insert_album, invalidate_album_cover};
+use crate::processor::media_processor::{
+    process_media_file, MediaProcessingContext, ProcessingResult,
+};
+use crate::utils::hash::calculate_quick_fingerprint;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ImportStatus {
@@ -28,12 +39,27 @@ impl fmt::Display for ImportStatus {
     }
 }
 
+impl ImportStatus {
+    fn from_db(value: &str) -> Self {
+        match value {
+            "running" => ImportStatus::Running,
+            "completed" => ImportStatus::Completed,
+            "failed" => ImportStatus::Failed,
+            _ => ImportStatus::Idle,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ImportJob {
+    pub id: Option<i64>,
     pub status: ImportStatus,
     pub total_files: i64,
     pub processed_files: i64,
+    /// `new_imports + duplicate_imports`
     pub successful_imports: i64,
+    pub new_imports: i64,
+    pub duplicate_imports: i64,
     pub failed_imports: i64,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
@@ -43,17 +69,61 @@ pub struct ImportJob {
 #[derive(Clone)]
 pub struct ImportSettings {
     pub processing: MediaProcessingContext,
+    pub source_dir: PathBuf,
+    /// Also import files from subdirectories of `source_dir`
+    pub recursive: bool,
     pub delete_after_import: bool,
     pub concurrency: usize,
+    pub recent_imports: Arc<RecentImportCache>,
+    pub cache_ttl_cycles: u8,
+}
+
+/// Quick fingerprints (see `calculate_quick_fingerprint`) of files imported
+/// by recent local import cycles, keyed by the user they were imported for
+/// and mapped to the cycle that imported them.
+#[derive(Default)]
+pub struct RecentImportCache {
+    cycle: AtomicU64,
+    fingerprints: DashMap<(i64, String), u64>,
+}
+
+impl RecentImportCache {
+    /// Starts a new cycle, evicting fingerprints older than `ttl_cycles`.
+    pub fn begin_cycle(&self, ttl_cycles: u8) -> u64 {
+        let cycle = self.cycle.fetch_add(1, Ordering::SeqCst) + 1;
+        self.fingerprints
+            .retain(|_, seen| cycle - *seen < u64::from(ttl_cycles));
+        cycle
+    }
+
+    pub fn contains(&self, user_id: i64, fingerprint: &str) -> bool {
+        self.fingerprints
+            .contains_key(&(user_id, fingerprint.to_string()))
+    }
+
+    pub fn insert(&self, user_id: i64, fingerprint: String, cycle: u64) {
+        self.fingerprints.insert((user_id, fingerprint), cycle);
+    }
+
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
 }
 
 impl Default for ImportJob {
     fn default() -> Self {
         Self {
+            id: None,
             status: ImportStatus::Idle,
             total_files: 0,
             processed_files: 0,
             successful_imports: 0,
+            new_imports: 0,
+            duplicate_imports: 0,
             failed_imports: 0,
             started_at: None,
             completed_at: None,
@@ -67,6 +137,8 @@ const MAX_JOB_ERRORS: usize = 100;
 
 lazy_static::lazy_static! {
     static ref CURRENT_JOB: RwLock<ImportJob> = RwLock::new(ImportJob::default());
+    /// Serializes folder album lookup and creation across concurrent WebDAV files
+    static ref FOLDER_ALBUM_LOCK: Mutex<()> = Mutex::new(());
 }
 
 fn push_job_error(errors: &mut Vec<String>, message: &str) {
@@ -77,6 +149,27 @@ fn push_job_error(errors: &mut Vec<String>, message: &str) {
     }
 }
 
+impl ImportJob {
+    fn record_result(&mut self, result: &ProcessingResult) {
+        self.processed_files += 1;
+        match result {
+            ProcessingResult::NewMedia { .. } => {
+                self.successful_imports += 1;
+                self.new_imports += 1;
+            }
+            ProcessingResult::DuplicateAccessGranted { .. }
+            | ProcessingResult::AlreadyOwned { .. } => {
+                self.successful_imports += 1;
+                self.duplicate_imports += 1;
+            }
+            ProcessingResult::Failed { reason } => {
+                self.failed_imports += 1;
+                push_job_error(&mut self.errors, reason);
+            }
+        }
+    }
+}
+
 pub fn get_import_status() -> ImportJob {
     CURRENT_JOB.read().unwrap().clone()
 }
@@ -85,30 +178,141 @@ pub fn is_import_running() -> bool {
     CURRENT_JOB.read().unwrap().status == ImportStatus::Running
 }
 
-fn start_import_job() {
-    let mut job = CURRENT_JOB.write().unwrap();
-    if job.status == ImportStatus::Running {
-        return;
-    }
-    *job = ImportJob {
-        status: ImportStatus::Running,
-        started_at: Some(Utc::now()),
-        ..Default::default()
+/// Upsert the job record and prune history beyond `IMPORT_JOB_HISTORY_LIMIT`.
+/// Returns the row id so later updates hit the same record.
+fn persist_job(pool: &DbPool, job_type: &str, job: &ImportJob) -> Option<i64> {
+    let conn = match pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("Failed to persist {} import job: {}", job_type, e);
+            return job.id;
+        }
     };
+
+    let errors_json = serde_json::to_string(&job.errors).unwrap_or_else(|_| "[]".to_string());
+    let result = execute_query(
+        &conn,
+        queries::imports::UPSERT_JOB,
+        &[
+            &job.id,
+            &job_type,
+            &job.status.to_string(),
+            &job.total_files,
+            &job.processed_files,
+            &job.successful_imports,
+            &job.new_imports,
+            &job.duplicate_imports,
+            &job.failed_imports,
+            &job.started_at.map(|dt| dt.to_rfc3339()),
+            &job.completed_at.map(|dt| dt.to_rfc3339()),
+            &errors_json,
+        ],
+    );
+
+    if let Err(e) = result {
+        warn!("Failed to persist {} import job: {}", job_type, e);
+        return job.id;
+    }
+
+    let id = job.id.unwrap_or_else(|| conn.last_insert_rowid());
+
+    if let Err(e) = execute_query(
+        &conn,
+        queries::imports::PRUNE_JOBS,
+        &[&IMPORT_JOB_HISTORY_LIMIT],
+    ) {
+        warn!("Failed to prune import job history: {}", e);
+    }
+
+    Some(id)
 }
 
-fn finalize_job_success() {
-    let mut job = CURRENT_JOB.write().unwrap();
-    job.status = ImportStatus::Completed;
-    job.completed_at = Some(Utc::now());
+fn persist_current_job(pool: &DbPool) {
+    let job = CURRENT_JOB.read().unwrap().clone();
+    let id = persist_job(pool, "local", &job);
+    CURRENT_JOB.write().unwrap().id = id;
+}
+
+/// Most recent persisted job, used when no job has run since startup.
+/// A record still marked running was interrupted by a restart and is
+/// reported as failed.
+pub fn get_last_persisted_job(pool: &DbPool) -> Option<ImportJob> {
+    let conn = pool.get().ok()?;
+    fetch_one(&conn, queries::imports::SELECT_LATEST_JOB, &[], |row| {
+        let status: String = row.get(1)?;
+        let started_at: Option<String> = row.get(6)?;
+        let completed_at: Option<String> = row.get(7)?;
+        let errors_json: String = row.get(8)?;
+
+        let status = match ImportStatus::from_db(&status) {
+            ImportStatus::Running => ImportStatus::Failed,
+            other => other,
+        };
+
+        Ok(ImportJob {
+            id: row.get(0)?,
+            status,
+            total_files: row.get(2)?,
+            processed_files: row.get(3)?,
+            successful_imports: row.get(4)?,
+            new_imports: row.get(9)?,
+            duplicate_imports: row.get(10)?,
+            failed_imports: row.get(5)?,
+            started_at: started_at.and_then(|s| parse_rfc3339(&s)),
+            completed_at: completed_at.and_then(|s| parse_rfc3339(&s)),
+            errors: serde_json::from_str(&errors_json).unwrap_or_default(),
+        })
+    })
+    .ok()
+    .flatten()
+}
+
+fn parse_rfc3339(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+fn start_import_job(pool: &DbPool) {
+    {
+        let mut job = CURRENT_JOB.write().unwrap();
+        if job.status == ImportStatus::Running {
+            return;
+        }
+        *job = ImportJob {
+            status: ImportStatus::Running,
+            started_at: Some(Utc::now()),
+            ..Default::default()
+        };
+    }
+    import_job_started();
+    persist_current_job(pool);
+}
+
+fn finalize_job_success(pool: &DbPool) {
+    {
+        let mut job = CURRENT_JOB.write().unwrap();
+        if job.status == ImportStatus::Running {
+            import_job_finished();
+        }
+        job.status = ImportStatus::Completed;
+        job.completed_at = Some(Utc::now());
+    }
+    persist_current_job(pool);
 }
 
 #[allow(dead_code)]
-fn finalize_job_failure(message: &str) {
-    let mut job = CURRENT_JOB.write().unwrap();
-    job.status = ImportStatus::Failed;
-    job.completed_at = Some(Utc::now());
-    push_job_error(&mut job.errors, message);
+fn finalize_job_failure(pool: &DbPool, message: &str) {
+    {
+        let mut job = CURRENT_JOB.write().unwrap();
+        if job.status == ImportStatus::Running {
+            import_job_finished();
+        }
+        job.status = ImportStatus::Failed;
+        job.completed_at = Some(Utc::now());
+        push_job_error(&mut job.errors, message);
+    }
+    persist_current_job(pool);
 }
 
 fn update_job_totals(total_files: i64) {
@@ -116,30 +320,24 @@ fn update_job_totals(total_files: i64) {
     job.total_files = total_files;
 }
 
-fn update_job_progress(success: bool, error_message: Option<&str>) {
-    let mut job = CURRENT_JOB.write().unwrap();
-    job.processed_files += 1;
-    if success {
-        job.successful_imports += 1;
-    } else {
-        job.failed_imports += 1;
-        if let Some(msg) = error_message {
-            push_job_error(&mut job.errors, msg);
-        }
-    }
+fn update_job_progress(result: &ProcessingResult) {
+    CURRENT_JOB.write().unwrap().record_result(result);
+}
+
+fn failed(reason: String) -> ProcessingResult {
+    ProcessingResult::Failed { reason }
 }
 
-fn collect_import_files(root: &Path) -> Vec<PathBuf> {
+fn collect_import_files(root: &Path, recursive: bool) -> Vec<PathBuf> {
     let mut files = Vec::new();
 
     for ext in SUPPORTED_EXTENSIONS.iter() {
         // Collect files with both cases
-        let patterns = vec![
-            format!("**/*{}", ext),
-            format!("**/*{}", ext.to_uppercase()),
-            format!("*{}", ext),
-            format!("*{}", ext.to_uppercase()),
-        ];
+        let mut patterns = vec![format!("*{}", ext), format!("*{}", ext.to_uppercase())];
+        if recursive {
+            patterns.push(format!("**/*{}", ext));
+            patterns.push(format!("**/*{}", ext.to_uppercase()));
+        }
 
         for pattern in patterns {
             let glob_pattern = root.join(&pattern);
@@ -162,9 +360,34 @@ use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 
 pub async fn run_local_import(settings: ImportSettings) {
-    start_import_job();
-
-    let files_to_import = collect_import_files(&IMPORTS_DIR);
+    let pool = settings.processing.pool.clone();
+    start_import_job(&pool);
+
+    let recent_imports = settings.recent_imports;
+    let user_id = settings.processing.user_id;
+    let cache_enabled = settings.cache_ttl_cycles > 0;
+    let cycle = recent_imports.begin_cycle(settings.cache_ttl_cycles);
+
+    let mut files_to_import = Vec::new();
+    let mut skipped = 0;
+    for file_path in collect_import_files(&settings.source_dir, settings.recursive) {
+        let fingerprint = if cache_enabled {
+            calculate_quick_fingerprint(&file_path).await.ok()
+        } else {
+            None
+        };
+        if fingerprint
+            .as_deref()
+            .is_some_and(|fingerprint| recent_imports.contains(user_id, fingerprint))
+        {
+            skipped += 1;
+            continue;
+        }
+        files_to_import.push((file_path, fingerprint));
+    }
+    if skipped > 0 {
+        info!("Skipping {} recently imported files", skipped);
+    }
     update_job_totals(files_to_import.len() as i64);
 
     let effective_concurrency = if settings.concurrency > 0 {
@@ -177,49 +400,53 @@ pub async fn run_local_import(settings: ImportSettings) {
     let processing = settings.processing;
 
     let mut stream = stream::iter(files_to_import)
-        .map(move |file_path| {
+        .map(move |(file_path, fingerprint)| {
             let semaphore = semaphore.clone();
             let processing = processing.clone();
+            let recent_imports = recent_imports.clone();
 
             async move {
                 let _permit = semaphore.acquire().await.unwrap();
 
                 if !file_path.exists() {
-                    update_job_progress(
-                        false,
-                        Some(&format!("Missing file: {}", file_path.display())),
-                    );
+                    update_job_progress(&failed(format!("Missing file: {}", file_path.display())));
                     return;
                 }
 
-                let media_id = process_media_file(&file_path, &processing).await;
+                let result = process_media_file(&file_path, &processing).await;
 
-                if media_id.is_none() {
-                    update_job_progress(
-                        false,
-                        Some(&format!("Failed to process: {}", file_path.display())),
-                    );
+                if let ProcessingResult::Failed { reason } = &result {
+                    update_job_progress(&failed(format!(
+                        "Failed to process {}: {}",
+                        file_path.display(),
+                        reason
+                    )));
                     return;
                 }
 
+                if let Some(fingerprint) = fingerprint {
+                    recent_imports.insert(user_id, fingerprint, cycle);
+                }
+
                 if delete_after_import {
                     if let Err(e) = tokio::fs::remove_file(&file_path).await {
-                        update_job_progress(
-                            false,
-                            Some(&format!("Failed to delete {}: {}", file_path.display(), e)),
-                        );
+                        update_job_progress(&failed(format!(
+                            "Failed to delete {}: {}",
+                            file_path.display(),
+                            e
+                        )));
                         return;
                     }
                 }
 
-                update_job_progress(true, None);
+                update_job_progress(&result);
             }
         })
         .buffer_unordered(effective_concurrency);
 
     while (stream.next().await).is_some() {}
 
-    finalize_job_success();
+    finalize_job_success(&pool);
 }
 
 pub async fn start_webdav_import_job(config: Arc<Config>, pool: DbPool) {
@@ -267,7 +494,7 @@ async fn run_webdav_import_cycle(config: &Config, pool: &DbPool) {
     let mut user_dir_count = 0usize;
     let mut skipped_user_dirs = 0usize;
     let mut queued_files = 0usize;
-    let mut tasks: JoinSet<()> = JoinSet::new();
+    let mut tasks: JoinSet<ProcessingResult> = JoinSet::new();
 
     for entry in entries.filter_map(|e| e.ok()) {
         let user_dir = entry.path();
@@ -319,12 +546,36 @@ async fn run_webdav_import_cycle(config: &Config, pool: &DbPool) {
             tasks.spawn(async move {
                 let _permit = semaphore.acquire().await.unwrap();
 
-                process_webdav_file(&file_path, user_id, &user_dir, &config, &pool).await;
+                process_webdav_file(&file_path, user_id, &user_dir, &config, &pool).await
             });
         }
     }
 
-    while tasks.join_next().await.is_some() {}
+    let mut webdav_job = (queued_files > 0).then(|| ImportJob {
+        status: ImportStatus::Running,
+        total_files: queued_files as i64,
+        started_at: Some(Utc::now()),
+        ..Default::default()
+    });
+    if let Some(job) = webdav_job.as_mut() {
+        job.id = persist_job(pool, "webdav", job);
+        import_job_started();
+    }
+
+    while let Some(result) = tasks.join_next().await {
+        let Some(job) = webdav_job.as_mut() else {
+            continue;
+        };
+        let result = result.unwrap_or_else(|e| failed(format!("WebDAV import task failed: {}", e)));
+        job.record_result(&result);
+    }
+
+    if let Some(mut job) = webdav_job {
+        import_job_finished();
+        job.status = ImportStatus::Completed;
+        job.completed_at = Some(Utc::now());
+        persist_job(pool, "webdav", &job);
+    }
 
     if user_dir_count == 0 {
         debug!(
@@ -346,7 +597,7 @@ async fn process_webdav_file(
     user_dir: &Path,
     config: &Config,
     pool: &DbPool,
-) {
+) -> ProcessingResult {
     let filename = file_path
         .file_name()
         .and_then(|n| n.to_str())
@@ -356,22 +607,24 @@ async fn process_webdav_file(
 
     let processing_dir = user_dir.join(".processing");
     if let Err(e) = std::fs::create_dir_all(&processing_dir) {
-        error!(
+        let message = format!(
             "Failed to create processing dir {}: {}",
             processing_dir.display(),
             e
         );
-        return;
+        error!("{}", message);
+        return failed(message);
     }
 
     let processing_path = processing_dir.join(filename);
     if let Err(e) = std::fs::rename(file_path, &processing_path) {
-        error!(
+        let message = format!(
             "Failed to move file to processing: {} ({})",
             file_path.display(),
             e
         );
-        return;
+        error!("{}", message);
+        return failed(message);
     }
 
     debug!(
@@ -383,16 +636,29 @@ async fn process_webdav_file(
         user_id,
         thumbnails: config.thumbnails.clone(),
         reverse_geocoding: Some(config.reverse_geocoding.clone()),
+        max_file_size_bytes: config.import.max_file_size_bytes,
         pool: pool.clone(),
+        post_processors: config.post_processors.clone(),
+        cluster_cache: None,
     };
     let result = process_media_file(&processing_path, &processing).await;
 
-    match result {
+    match result.media_id() {
         Some(media_id) => {
             info!(
                 "WebDAV import success: {} -> media_id={} (thumbnails + metadata generated)",
                 filename, media_id
             );
+            if config.webdav.processing.auto_create_albums {
+                if let Some(album_name) = folder_album_name(file_path, user_dir) {
+                    if let Err(e) = add_to_folder_album(pool, user_id, &album_name, media_id) {
+                        warn!(
+                            "Failed to add media {} to folder album '{}': {}",
+                            media_id, album_name, e
+                        );
+                    }
+                }
+            }
             match tokio::fs::remove_file(&processing_path).await {
                 Ok(()) => {
                     debug!(
@@ -404,14 +670,74 @@ async fn process_webdav_file(
                     warn!("Failed to cleanup processed file: {}", e);
                 }
             }
+            result
         }
         None => {
             error!("WebDAV import failed: {}", filename);
             move_to_failed(&processing_path, user_dir).await;
+            failed(format!("WebDAV import failed: {}", filename))
         }
     }
 }
 
+/// Name of the folder a WebDAV file sits in, or `None` when it was dropped
+/// directly into the user directory.
+pub fn folder_album_name(file_path: &Path, user_dir: &Path) -> Option<String> {
+    let parent = file_path.parent()?;
+    if parent == user_dir {
+        return None;
+    }
+    parent
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+}
+
+/// Appends the media to the user's album with this name, creating it first
+/// if they have none.
+pub fn add_to_folder_album(
+    pool: &DbPool,
+    user_id: i64,
+    album_name: &str,
+    media_id: i64,
+) -> AppResult<i64> {
+    let conn = pool.get().map_err(AppError::Pool)?;
+    let _guard = FOLDER_ALBUM_LOCK.lock().unwrap();
+
+    let album_id = match fetch_one(
+        &conn,
+        queries::albums::SELECT_ID_BY_OWNER_AND_NAME,
+        &[&user_id, &album_name],
+        |row| row.get::<_, i64>(0),
+    )? {
+        Some(id) => id,
+        None => {
+            info!(
+                "WebDAV import: creating album '{}' for user {}",
+                album_name, user_id
+            );
+            insert_album(&conn, user_id, album_name, None)?
+        }
+    };
+
+    let max_pos: i64 = fetch_one(
+        &conn,
+        queries::albums::SELECT_MAX_POSITION,
+        &[&album_id],
+        |row| row.get(0),
+    )?
+    .unwrap_or(-1);
+
+    execute_query(
+        &conn,
+        queries::albums::ADD_MEDIA,
+        &[&album_id, &media_id, &(max_pos + 1)],
+    )?;
+    invalidate_album_cover(album_id);
+
+    Ok(album_id)
+}
+
 async fn move_to_failed(processing_path: &Path, user_dir: &Path) {
     let failed_dir = user_dir.join(".failed");
     if let Err(e) = std::fs::create_dir_all(&failed_dir) {
@@ -534,3 +860,323 @@ fn is_supported_extension(path: &Path) -> bool {
         .map(|ext| SUPPORTED_EXTENSIONS.contains(ext.as_str()))
         .unwrap_or(false)
 }
+
+/// A supported media file found on the SFTP server.
+struct SftpRemoteFile {
+    remote_path: PathBuf,
+    username: String,
+    mtime: u64,
+}
+
+/// A remote file downloaded to the local staging directory.
+struct SftpStagedFile {
+    remote_path: PathBuf,
+    mtime: u64,
+    local_path: PathBuf,
+    user_id: i64,
+}
+
+pub async fn start_sftp_import_job(config: Arc<Config>, pool: DbPool) {
+    if !config.sftp.enabled {
+        info!("SFTP import job disabled");
+        return;
+    }
+
+    let poll_interval = std::time::Duration::from_secs(config.sftp.poll_interval_seconds);
+
+    info!(
+        "Starting SFTP import job: polling every {}s, remote={}@{}:{}",
+        config.sftp.poll_interval_seconds,
+        config.sftp.username,
+        config.sftp.host,
+        config.sftp.remote_path
+    );
+
+    // Remote files that were downloaded but failed to import, keyed by path
+    // with the mtime seen at the time. They are retried only once the remote
+    // file changes; download failures are retried on the next cycle.
+    let mut attempted: HashMap<PathBuf, u64> = HashMap::new();
+
+    loop {
+        run_sftp_import_cycle(&config, &pool, &mut attempted).await;
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+async fn run_sftp_import_cycle(
+    config: &Config,
+    pool: &DbPool,
+    attempted: &mut HashMap<PathBuf, u64>,
+) {
+    let sftp_config = config.sftp.clone();
+    let listing = tokio::task::spawn_blocking(move || -> Result<Vec<SftpRemoteFile>, String> {
+        let session = connect_sftp(&sftp_config)?;
+        let sftp = session
+            .sftp()
+            .map_err(|e| format!("Failed to start SFTP subsystem: {}", e))?;
+        list_sftp_files(&sftp, Path::new(&sftp_config.remote_path))
+    })
+    .await;
+
+    let remote_files = match listing {
+        Ok(Ok(files)) => files,
+        Ok(Err(e)) => {
+            error!("SFTP import: {}", e);
+            return;
+        }
+        Err(e) => {
+            error!("SFTP import listing task failed: {}", e);
+            return;
+        }
+    };
+
+    // Forget files that no longer exist remotely
+    let present: std::collections::HashSet<&PathBuf> =
+        remote_files.iter().map(|f| &f.remote_path).collect();
+    attempted.retain(|path, _| present.contains(path));
+
+    let mut candidates = Vec::new();
+    for file in remote_files {
+        if attempted.get(&file.remote_path) == Some(&file.mtime) {
+            continue;
+        }
+        let Some(user_id) = lookup_user_id(pool, &file.username) else {
+            warn!("SFTP directory for unknown user: {}", file.username);
+            continue;
+        };
+        candidates.push((file, user_id));
+    }
+
+    if candidates.is_empty() {
+        debug!("SFTP import: no new files");
+        return;
+    }
+
+    info!("SFTP import: downloading {} new files", candidates.len());
+
+    let mut sftp_job = ImportJob {
+        status: ImportStatus::Running,
+        total_files: candidates.len() as i64,
+        started_at: Some(Utc::now()),
+        ..Default::default()
+    };
+    sftp_job.id = persist_job(pool, "sftp", &sftp_job);
+    import_job_started();
+
+    let sftp_config = config.sftp.clone();
+    let download = tokio::task::spawn_blocking(move || {
+        download_sftp_files(&sftp_config, candidates, &SFTP_STAGING_DIR)
+    })
+    .await;
+
+    let (staged, download_errors) = match download {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => (Vec::new(), vec![e]),
+        Err(e) => (
+            Vec::new(),
+            vec![format!("SFTP download task failed: {}", e)],
+        ),
+    };
+    for message in &download_errors {
+        error!("SFTP import: {}", message);
+        sftp_job.record_result(&failed(message.clone()));
+    }
+
+    let mut imported = Vec::new();
+    for file in staged {
+        let processing = MediaProcessingContext {
+            user_id: file.user_id,
+            thumbnails: config.thumbnails.clone(),
+            reverse_geocoding: Some(config.reverse_geocoding.clone()),
+            max_file_size_bytes: config.import.max_file_size_bytes,
+            pool: pool.clone(),
+            post_processors: config.post_processors.clone(),
+            cluster_cache: None,
+        };
+        let result = process_media_file(&file.local_path, &processing).await;
+
+        attempted.insert(file.remote_path.clone(), file.mtime);
+        match result.media_id() {
+            Some(media_id) => {
+                info!(
+                    "SFTP import success: {} -> media_id={}",
+                    file.remote_path.display(),
+                    media_id
+                );
+                sftp_job.record_result(&result);
+                imported.push(file.remote_path);
+            }
+            None => {
+                let message = format!("SFTP import failed: {}", file.remote_path.display());
+                error!("{}", message);
+                sftp_job.record_result(&failed(message));
+            }
+        }
+
+        if let Some(staging_dir) = file.local_path.parent() {
+            let _ = tokio::fs::remove_dir_all(staging_dir).await;
+        }
+    }
+
+    if !imported.is_empty() {
+        let sftp_config = config.sftp.clone();
+        let removal = tokio::task::spawn_blocking(move || -> Result<Vec<String>, String> {
+            let session = connect_sftp(&sftp_config)?;
+            let sftp = session
+                .sftp()
+                .map_err(|e| format!("Failed to start SFTP subsystem: {}", e))?;
+            Ok(imported
+                .iter()
+                .filter_map(|path| {
+                    sftp.unlink(path)
+                        .err()
+                        .map(|e| format!("Failed to delete {}: {}", path.display(), e))
+                })
+                .collect())
+        })
+        .await;
+
+        match removal {
+            Ok(Ok(errors)) => {
+                for message in errors {
+                    warn!("SFTP import: {}", message);
+                }
+            }
+            Ok(Err(e)) => warn!("SFTP import: {}", e),
+            Err(e) => warn!("SFTP cleanup task failed: {}", e),
+        }
+    }
+
+    import_job_finished();
+    sftp_job.status = ImportStatus::Completed;
+    sftp_job.completed_at = Some(Utc::now());
+    persist_job(pool, "sftp", &sftp_job);
+}
+
+fn connect_sftp(config: &SftpConfig) -> Result<ssh2::Session, String> {
+    let tcp = std::net::TcpStream::connect((config.host.as_str(), config.port)).map_err(|e| {
+        format!(
+            "Failed to connect to {}:{}: {}",
+            config.host, config.port, e
+        )
+    })?;
+
+    let mut session =
+        ssh2::Session::new().map_err(|e| format!("Failed to create SSH session: {}", e))?;
+    session.set_tcp_stream(tcp);
+    session
+        .handshake()
+        .map_err(|e| format!("SSH handshake with {} failed: {}", config.host, e))?;
+    session
+        .userauth_pubkey_file(&config.username, None, &config.key_path, None)
+        .map_err(|e| format!("SSH authentication as {} failed: {}", config.username, e))?;
+
+    Ok(session)
+}
+
+fn list_sftp_files(sftp: &ssh2::Sftp, root: &Path) -> Result<Vec<SftpRemoteFile>, String> {
+    let entries = sftp
+        .readdir(root)
+        .map_err(|e| format!("Failed to list {}: {}", root.display(), e))?;
+
+    let mut files = Vec::new();
+    for (path, stat) in entries {
+        if !stat.is_dir() {
+            continue;
+        }
+        let username = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) if !name.starts_with('.') => name.to_string(),
+            _ => continue,
+        };
+        collect_sftp_files_recursive(sftp, &path, &username, &mut files);
+    }
+
+    Ok(files)
+}
+
+fn collect_sftp_files_recursive(
+    sftp: &ssh2::Sftp,
+    dir: &Path,
+    username: &str,
+    files: &mut Vec<SftpRemoteFile>,
+) {
+    let entries = match sftp.readdir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Failed to list {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    for (path, stat) in entries {
+        let hidden = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with('.'))
+            .unwrap_or(true);
+        if hidden {
+            continue;
+        }
+
+        if stat.is_dir() {
+            collect_sftp_files_recursive(sftp, &path, username, files);
+        } else if stat.is_file() && is_supported_extension(&path) {
+            files.push(SftpRemoteFile {
+                remote_path: path,
+                username: username.to_string(),
+                mtime: stat.mtime.unwrap_or(0),
+            });
+        }
+    }
+}
+
+/// Downloads each remote file into its own staging subdirectory so the
+/// original filename is preserved. Returns the staged files and a message
+/// for each file that could not be downloaded.
+fn download_sftp_files(
+    config: &SftpConfig,
+    candidates: Vec<(SftpRemoteFile, i64)>,
+    staging_root: &Path,
+) -> Result<(Vec<SftpStagedFile>, Vec<String>), String> {
+    let session = connect_sftp(config)?;
+    let sftp = session
+        .sftp()
+        .map_err(|e| format!("Failed to start SFTP subsystem: {}", e))?;
+
+    let mut staged = Vec::new();
+    let mut errors = Vec::new();
+
+    for (remote_file, user_id) in candidates {
+        let remote_path = remote_file.remote_path;
+        let filename = remote_path.file_name().unwrap_or_default();
+        let staging_dir = staging_root.join(uuid::Uuid::new_v4().to_string());
+        let local_path = staging_dir.join(filename);
+
+        let result = std::fs::create_dir_all(&staging_dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| {
+                let mut remote = sftp.open(&remote_path).map_err(|e| e.to_string())?;
+                let mut local = std::fs::File::create(&local_path).map_err(|e| e.to_string())?;
+                std::io::copy(&mut remote, &mut local).map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(_) => staged.push(SftpStagedFile {
+                remote_path,
+                mtime: remote_file.mtime,
+                local_path,
+                user_id,
+            }),
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&staging_dir);
+                errors.push(format!(
+                    "Failed to download {}: {}",
+                    remote_path.display(),
+                    e
+                ));
+            }
+        }
+    }
+
+    Ok((staged, errors))
+}
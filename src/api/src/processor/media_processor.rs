@@ -2,24 +2,60 @@ use chrono::{DateTime, Utc};
 use geohash::{encode, Coord};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::Instrument;
 use uuid::Uuid;
 
-use crate::config::{ReverseGeocodingConfig, ThumbnailConfig};
+use crate::cache::ClusterCache;
+use crate::config::{
+    PostProcessorConfig, PostProcessorScript, ReverseGeocodingConfig, ThumbnailConfig,
+    ThumbnailFormat,
+};
 use crate::constants::{
-    IMAGE_EXTENSIONS, ORIGINALS_DIR, THUMBNAILS_DIR, THUMBNAILS_TINY_DIR, VIDEO_EXTENSIONS,
+    GEOHASH_PRECISE_PRECISION, GEOHASH_PRECISION, IMAGE_EXTENSIONS, ORIGINALS_DIR, PREVIEWS_DIR,
+    PREVIEW_EXTENSIONS, THUMBNAILS_DIR, THUMBNAILS_TINY_DIR, VIDEO_EXTENSIONS,
 };
-use crate::database::{execute_query, fetch_one, insert_returning_id, queries, DbConn, DbPool};
+use crate::database::{execute_query, fetch_one, insert_returning_id, queries, DbPool};
 use crate::processor::metadata::{extract_image_metadata, extract_video_metadata, MediaMetadata};
 use crate::processor::thumbnails::{generate_image_thumbnail, generate_video_thumbnail};
-use crate::utils::hash::calculate_file_hash;
+use crate::processor::workers::{image_workers, run_image_task};
+use crate::utils::hash::{calculate_file_hash, calculate_perceptual_hash};
 
 #[derive(Clone)]
 pub struct MediaProcessingContext {
     pub user_id: i64,
     pub thumbnails: ThumbnailConfig,
     pub reverse_geocoding: Option<ReverseGeocodingConfig>,
+    pub max_file_size_bytes: Option<u64>,
     pub pool: DbPool,
+    pub post_processors: PostProcessorConfig,
+    /// Map clusters to invalidate when a geotagged item is added; `None`
+    /// for background importers, whose changes appear once the cache expires.
+    pub cluster_cache: Option<Arc<ClusterCache>>,
+}
+
+/// Outcome of `process_media_file`. Content-hash matches never create a
+/// second media row; they either grant the user access or find that the
+/// user already had it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessingResult {
+    NewMedia { media_id: i64 },
+    DuplicateAccessGranted { media_id: i64 },
+    AlreadyOwned { media_id: i64 },
+    Failed { reason: String },
+}
+
+impl ProcessingResult {
+    pub fn media_id(&self) -> Option<i64> {
+        match self {
+            ProcessingResult::NewMedia { media_id }
+            | ProcessingResult::DuplicateAccessGranted { media_id }
+            | ProcessingResult::AlreadyOwned { media_id } => Some(*media_id),
+            ProcessingResult::Failed { .. } => None,
+        }
+    }
 }
 
 pub fn get_media_type(file_path: &Path) -> Option<&'static str> {
@@ -80,20 +116,16 @@ fn save_original_file(
     Ok((dest_path, relative_path, new_filename))
 }
 
-pub async fn generate_thumbnails(
-    dest_path: &Path,
-    media_type: &str,
-    thumbnail_max_size: u32,
-    tiny_thumbnail_size: u32,
-    thumbnail_quality: u8,
-    video_frame_quality: u8,
-) -> (Option<String>, Option<String>) {
+/// Thumbnail location relative to the thumbnail directories: the original's
+/// parent directory name and file stem, with the format's extension.
+pub fn thumbnail_relative_path(dest_path: &Path, format: ThumbnailFormat) -> PathBuf {
     let thumbnail_filename = format!(
-        "{}.jpg",
+        "{}.{}",
         dest_path
             .file_stem()
             .and_then(|s| s.to_str())
-            .unwrap_or("thumb")
+            .unwrap_or("thumb"),
+        format.extension()
     );
 
     let parent_name = dest_path
@@ -102,7 +134,15 @@ pub async fn generate_thumbnails(
         .and_then(|n| n.to_str())
         .unwrap_or("unknown");
 
-    let thumbnail_relative = PathBuf::from(parent_name).join(&thumbnail_filename);
+    PathBuf::from(parent_name).join(thumbnail_filename)
+}
+
+pub async fn generate_thumbnails(
+    dest_path: &Path,
+    media_type: &str,
+    config: &ThumbnailConfig,
+) -> (Option<String>, Option<String>) {
+    let thumbnail_relative = thumbnail_relative_path(dest_path, config.thumbnail_format);
 
     let thumbnail_path = THUMBNAILS_DIR.join(&thumbnail_relative);
     if let Some(parent) = thumbnail_path.parent() {
@@ -113,17 +153,20 @@ pub async fn generate_thumbnails(
         generate_image_thumbnail(
             dest_path,
             &thumbnail_path,
-            thumbnail_max_size,
-            thumbnail_quality,
+            config.max_size,
+            config.quality,
+            config.thumbnail_format,
         )
         .await
     } else {
         generate_video_thumbnail(
             dest_path,
             &thumbnail_path,
-            thumbnail_max_size,
-            thumbnail_quality,
-            video_frame_quality,
+            config.max_size,
+            config.quality,
+            config.video_frame_quality,
+            config.thumbnail_format,
+            config.video_thumbnail_offset_seconds,
         )
         .await
     };
@@ -137,17 +180,20 @@ pub async fn generate_thumbnails(
         generate_image_thumbnail(
             dest_path,
             &tiny_thumbnail_path,
-            tiny_thumbnail_size,
-            thumbnail_quality,
+            config.tiny_size,
+            config.quality,
+            config.thumbnail_format,
         )
         .await
     } else {
         generate_video_thumbnail(
             dest_path,
             &tiny_thumbnail_path,
-            tiny_thumbnail_size,
-            thumbnail_quality,
-            video_frame_quality,
+            config.tiny_size,
+            config.quality,
+            config.video_frame_quality,
+            config.thumbnail_format,
+            config.video_thumbnail_offset_seconds,
         )
         .await
     };
@@ -251,18 +297,26 @@ pub async fn generate_complete_metadata(
         }
     }
 
-    if let Some(geo_config) = reverse_geo_config {
+    fill_reverse_geocoded_location(&mut metadata, reverse_geo_config).await;
+
+    metadata
+}
+
+/// Looks up city, state and country for coordinates whose place names the
+/// file did not carry, honouring the geocoder's rate limit.
+pub async fn fill_reverse_geocoded_location(
+    metadata: &mut MediaMetadata,
+    reverse_geo_config: Option<&ReverseGeocodingConfig>,
+) {
+    if let (Some(geo_config), Some(latitude), Some(longitude)) = (
+        reverse_geo_config,
+        metadata.gps_latitude,
+        metadata.gps_longitude,
+    ) {
         if geo_config.enabled
-            && metadata.gps_latitude.is_some()
-            && metadata.gps_longitude.is_some()
             && (metadata.location_state.is_none() || metadata.location_country.is_none())
         {
-            let (city, state, country) = reverse_geocode(
-                geo_config,
-                metadata.gps_latitude.unwrap(),
-                metadata.gps_longitude.unwrap(),
-            )
-            .await;
+            let (city, state, country) = reverse_geocode(geo_config, latitude, longitude).await;
             if city.is_some() {
                 metadata.location_city = city;
             }
@@ -279,14 +333,48 @@ pub async fn generate_complete_metadata(
             .await;
         }
     }
+}
 
-    metadata
+/// Returns the file size when it is over `max_bytes`. Shared by every
+/// ingestion path so imports and uploads enforce the same limit.
+pub fn exceeds_max_file_size(path: &Path, max_bytes: Option<u64>) -> Option<u64> {
+    let max_bytes = max_bytes?;
+    let file_size = path.metadata().ok()?.len();
+    (file_size > max_bytes).then_some(file_size)
+}
+
+/// Perceptual hash of an original, or of its thumbnail frame for videos.
+pub async fn compute_perceptual_hash(
+    original_path: &Path,
+    media_type: &str,
+    thumbnail_relative: Option<&str>,
+) -> Option<i64> {
+    let source = if media_type == "video" {
+        THUMBNAILS_DIR.join(thumbnail_relative?)
+    } else {
+        original_path.to_path_buf()
+    };
+
+    run_image_task(&image_workers(), move || calculate_perceptual_hash(&source))
+        .await
+        .flatten()
 }
 
+#[tracing::instrument(
+    skip_all,
+    fields(
+        path = %source_path.display(),
+        user_id = context.user_id,
+        media_type = tracing::field::Empty,
+        file_size_bytes = tracing::field::Empty,
+        media_id = tracing::field::Empty,
+    )
+)]
 pub async fn process_media_file(
     source_path: &Path,
     context: &MediaProcessingContext,
-) -> Option<i64> {
+) -> ProcessingResult {
+    let span = tracing::Span::current();
     let start_time = Instant::now();
     let user_id = context.user_id;
     tracing::info!(
@@ -294,9 +382,32 @@ pub async fn process_media_file(
         source_path.display(),
         user_id
     );
-    let media_type = get_media_type(source_path)?;
+    if let Some(file_size) = exceeds_max_file_size(source_path, context.max_file_size_bytes) {
+        tracing::warn!(
+            "Rejecting {}: {} bytes exceeds the {} byte import limit",
+            source_path.display(),
+            file_size,
+            context.max_file_size_bytes.unwrap_or_default()
+        );
+        return ProcessingResult::Failed {
+            reason: "File too large".to_string(),
+        };
+    }
+
+    let Some(media_type) = get_media_type(source_path) else {
+        return ProcessingResult::Failed {
+            reason: "unsupported file type".to_string(),
+        };
+    };
+    span.record("media_type", media_type);
+    if let Ok(file_metadata) = source_path.metadata() {
+        span.record("file_size_bytes", file_metadata.len());
+    }
 
-    let content_hash = match calculate_file_hash(source_path).await {
+    let content_hash = match calculate_file_hash(source_path)
+        .instrument(tracing::info_span!("hash_calculation"))
+        .await
+    {
         Ok(h) => h,
         Err(e) => {
             tracing::error!(
@@ -305,7 +416,9 @@ pub async fn process_media_file(
                 start_time.elapsed(),
                 e
             );
-            return None;
+            return ProcessingResult::Failed {
+                reason: format!("failed to hash file: {}", e),
+            };
         }
     };
 
@@ -344,12 +457,13 @@ pub async fn process_media_file(
                     &[&media_id, &user_id],
                 );
 
+                span.record("media_id", media_id);
                 tracing::info!(
                     "Media processing completed for {} in {:?}",
                     source_path.display(),
                     start_time.elapsed()
                 );
-                return Some(media_id);
+                return ProcessingResult::AlreadyOwned { media_id };
             }
 
             let _ = execute_query(
@@ -359,17 +473,22 @@ pub async fn process_media_file(
             );
 
             tracing::info!("Granted access to media {} for user {}", media_id, user_id);
+            if let Some(cache) = &context.cluster_cache {
+                cache.invalidate();
+            }
+            span.record("media_id", media_id);
             tracing::info!(
                 "Media processing completed for {} in {:?}",
                 source_path.display(),
                 start_time.elapsed()
             );
-            return Some(media_id);
+            return ProcessingResult::DuplicateAccessGranted { media_id };
         }
     }
 
     let metadata =
         generate_complete_metadata(source_path, media_type, context.reverse_geocoding.as_ref())
+            .instrument(tracing::info_span!("metadata_extraction"))
             .await;
     let date_taken = get_media_date(&metadata, source_path);
 
@@ -383,21 +502,23 @@ pub async fn process_media_file(
                 start_time.elapsed(),
                 e
             );
-            return None;
+            return ProcessingResult::Failed {
+                reason: format!("failed to save original file: {}", e),
+            };
         }
     };
 
-    let (thumbnail_relative, _tiny_thumbnail_relative) = generate_thumbnails(
-        &dest_path,
-        media_type,
-        context.thumbnails.max_size,
-        context.thumbnails.tiny_size,
-        context.thumbnails.quality,
-        context.thumbnails.video_frame_quality,
-    )
-    .await;
+    let (thumbnail_relative, _tiny_thumbnail_relative) =
+        generate_thumbnails(&dest_path, media_type, &context.thumbnails)
+            .instrument(tracing::info_span!("thumbnail_generation"))
+            .await;
+
+    let phash =
+        compute_perceptual_hash(&dest_path, media_type, thumbnail_relative.as_deref()).await;
 
     let file_size = dest_path.metadata().ok().map(|m| m.len() as i64);
+    let db_span = tracing::info_span!("db_insert");
+    let _db_guard = db_span.enter();
     let conn = match context.pool.get() {
         Ok(c) => c,
         Err(e) => {
@@ -407,13 +528,18 @@ pub async fn process_media_file(
                 start_time.elapsed(),
                 e
             );
-            return None;
+            return ProcessingResult::Failed {
+                reason: format!("failed to get DB connection: {}", e),
+            };
         }
     };
 
-    let geohash = match (metadata.gps_latitude, metadata.gps_longitude) {
-        (Some(lat), Some(lon)) => calculate_geohash(lat, lon),
-        _ => None,
+    let (geohash, geohash_precise) = match (metadata.gps_latitude, metadata.gps_longitude) {
+        (Some(lat), Some(lon)) => (
+            calculate_geohash(lat, lon, GEOHASH_PRECISION),
+            calculate_geohash(lat, lon, GEOHASH_PRECISE_PRECISION),
+        ),
+        _ => (None, None),
     };
 
     let media_id_result = insert_returning_id(
@@ -443,7 +569,9 @@ pub async fn process_media_file(
                 start_time.elapsed(),
                 e
             );
-            return None;
+            return ProcessingResult::Failed {
+                reason: format!("failed to insert media into DB: {}", e),
+            };
         }
     };
 
@@ -461,6 +589,7 @@ pub async fn process_media_file(
             &metadata.gps_longitude,
             &metadata.gps_altitude,
             &geohash,
+            &geohash_precise,
             &metadata.location_city,
             &metadata.location_state,
             &metadata.location_country,
@@ -474,6 +603,9 @@ pub async fn process_media_file(
             &metadata.focal_length,
             &metadata.focal_length_35mm,
             &metadata.video_codec,
+            &metadata.audio_codec,
+            &metadata.audio_channels,
+            &metadata.audio_sample_rate,
             &metadata.keywords,
         ],
     );
@@ -484,18 +616,138 @@ pub async fn process_media_file(
         &[&media_id, &user_id, &2],
     );
 
+    if phash.is_some() {
+        let _ = execute_query(&conn, queries::media::UPDATE_PHASH, &[&phash, &media_id]);
+    }
+
     if let (Some(lat), Some(lon)) = (metadata.gps_latitude, metadata.gps_longitude) {
         if let Err(e) = insert_into_rtree(&conn, media_id, lat, lon) {
             tracing::warn!("Failed to insert media {} into R-tree: {}", media_id, e);
         }
+        if let Some(cache) = &context.cluster_cache {
+            cache.invalidate();
+        }
     }
 
+    if let Err(e) = refresh_search_text(&conn, media_id) {
+        tracing::warn!("Failed to index search text for media {}: {}", media_id, e);
+    }
+
+    span.record("media_id", media_id);
     tracing::info!(
         "Media processing completed for {} in {:?}",
         source_path.display(),
         start_time.elapsed()
     );
-    Some(media_id)
+
+    drop(_db_guard);
+    drop(conn);
+    spawn_post_processors(dest_path, media_id, &context.post_processors);
+
+    ProcessingResult::NewMedia { media_id }
+}
+
+/// Starts the configured scripts for a newly imported item in the
+/// background, so slow scripts never hold up the upload or import that
+/// triggered them. `None` when no scripts are configured.
+pub fn spawn_post_processors(
+    file_path: PathBuf,
+    media_id: i64,
+    config: &PostProcessorConfig,
+) -> Option<tokio::task::JoinHandle<()>> {
+    if config.scripts.is_empty() {
+        return None;
+    }
+    let config = config.clone();
+    Some(tokio::spawn(async move {
+        run_post_processors(&file_path, media_id, &config).await;
+    }))
+}
+
+/// Runs each configured script for a newly imported item. Failures and
+/// timeouts are logged and never undo the import.
+pub async fn run_post_processors(file_path: &Path, media_id: i64, config: &PostProcessorConfig) {
+    for script in &config.scripts {
+        if let Err(e) = run_post_processor(script, file_path, media_id).await {
+            tracing::warn!(
+                "Post-processor {} failed for media {}: {}",
+                script.name,
+                media_id,
+                e
+            );
+        }
+    }
+}
+
+async fn run_post_processor(
+    script: &PostProcessorScript,
+    file_path: &Path,
+    media_id: i64,
+) -> Result<(), String> {
+    let child = tokio::process::Command::new(&script.command)
+        .args(&script.args)
+        .env("MOMENTO_MEDIA_PATH", file_path)
+        .env("MOMENTO_MEDIA_ID", media_id.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("failed to start {}: {}", script.command, e))?;
+
+    let timeout = Duration::from_secs(script.timeout_seconds);
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| format!("timed out after {}s", script.timeout_seconds))?
+        .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.trim().is_empty() {
+        tracing::debug!(
+            "Post-processor {} output for media {}: {}",
+            script.name,
+            media_id,
+            stdout.trim()
+        );
+    }
+
+    if !output.status.success() {
+        return Err(format!(
+            "exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Losslessly rotates a JPEG in place with `jpegtran`, keeping its metadata.
+pub async fn rotate_jpeg(file_path: &Path, degrees: i32) -> Result<(), String> {
+    let temp_path = file_path.with_extension("rotating");
+    rotate_jpeg_to(file_path, &temp_path, degrees).await?;
+    fs::rename(&temp_path, file_path).map_err(|e| format!("Failed to replace original: {}", e))
+}
+
+/// Writes a losslessly rotated copy of the JPEG at `source` to `dest`,
+/// leaving `source` untouched.
+pub async fn rotate_jpeg_to(source: &Path, dest: &Path, degrees: i32) -> Result<(), String> {
+    let output = tokio::process::Command::new("jpegtran")
+        .args(["-copy", "all", "-rotate", &degrees.to_string(), "-outfile"])
+        .arg(dest)
+        .arg(source)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run jpegtran: {}", e))?;
+
+    if !output.status.success() {
+        let _ = fs::remove_file(dest);
+        return Err(format!(
+            "jpegtran failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
 }
 
 pub fn delete_media_files(file_path: &str, thumbnail_path: Option<&str>) {
@@ -512,13 +764,36 @@ pub fn delete_media_files(file_path: &str, thumbnail_path: Option<&str>) {
     }
 }
 
-pub fn calculate_geohash(lat: f64, lon: f64) -> Option<String> {
+/// Removes every cached preview of the original at `file_path` generated
+/// for `user_id` (`PREVIEWS_DIR/<user_id>/<stem>_preview.<ext>`, for each
+/// of `PREVIEW_EXTENSIONS`), returning how many were deleted.
+pub fn delete_preview_files(user_id: i64, file_path: &str) -> usize {
+    let Some(stem) = Path::new(file_path).file_stem().and_then(|s| s.to_str()) else {
+        return 0;
+    };
+    let user_dir = PREVIEWS_DIR.join(user_id.to_string());
+
+    let mut deleted = 0;
+    for extension in PREVIEW_EXTENSIONS {
+        let path = user_dir.join(format!("{}_preview.{}", stem, extension));
+        match fs::remove_file(&path) {
+            Ok(()) => deleted += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to remove preview {}: {}", path.display(), e),
+        }
+    }
+    deleted
+}
+
+/// Precision 7 (about 153 m) backs clustering and prefix search; precision 9
+/// (about 5 m) backs point and radius queries.
+pub fn calculate_geohash(lat: f64, lon: f64, precision: usize) -> Option<String> {
     let coord = Coord { x: lon, y: lat };
-    encode(coord, 7).ok()
+    encode(coord, precision).ok()
 }
 
 pub fn insert_into_rtree(
-    conn: &DbConn,
+    conn: &rusqlite::Connection,
     media_id: i64,
     lat: f64,
     lon: f64,
@@ -530,10 +805,24 @@ pub fn insert_into_rtree(
     Ok(())
 }
 
-pub fn delete_from_rtree(conn: &DbConn, media_id: i64) -> Result<(), rusqlite::Error> {
+pub fn delete_from_rtree(
+    conn: &rusqlite::Connection,
+    media_id: i64,
+) -> Result<(), rusqlite::Error> {
     conn.execute(
         "DELETE FROM media_rtree WHERE media_id = ?",
         rusqlite::params![media_id],
     )?;
     Ok(())
 }
+
+/// Recomputes `media.search_text` from the current metadata. The triggers
+/// on `media` re-index the row in `fts_media`, and drop it again when the
+/// media row is deleted.
+pub fn refresh_search_text(
+    conn: &rusqlite::Connection,
+    media_id: i64,
+) -> Result<(), rusqlite::Error> {
+    conn.execute(queries::search::UPDATE_SEARCH_TEXT, [media_id])?;
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::Deserialize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::process::Command;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
 #[derive(Debug, Default, Clone)]
 pub struct MediaMetadata {
@@ -27,9 +27,52 @@ pub struct MediaMetadata {
     pub location_country: Option<String>,
     pub location_city: Option<String>,
     pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_channels: Option<i32>,
+    pub audio_sample_rate: Option<i32>,
     pub focal_length_35mm: Option<f64>,
 }
 
+impl MediaMetadata {
+    /// Takes each field still missing here from `other`. The MIME type is
+    /// left alone since a sidecar describes itself, not the media.
+    pub fn fill_missing_from(&mut self, other: MediaMetadata) {
+        macro_rules! fill {
+            ($($field:ident),*) => {
+                $(if self.$field.is_none() {
+                    self.$field = other.$field;
+                })*
+            };
+        }
+        fill!(
+            width,
+            height,
+            date_taken,
+            gps_latitude,
+            gps_longitude,
+            gps_altitude,
+            camera_make,
+            camera_model,
+            lens_make,
+            lens_model,
+            iso,
+            exposure_time,
+            f_number,
+            focal_length,
+            keywords,
+            duration_seconds,
+            location_state,
+            location_country,
+            location_city,
+            video_codec,
+            audio_codec,
+            audio_channels,
+            audio_sample_rate,
+            focal_length_35mm
+        );
+    }
+}
+
 fn fallback_to_mtime(file_path: &Path) -> Option<DateTime<Utc>> {
     file_path
         .metadata()
@@ -41,49 +84,20 @@ fn fallback_to_mtime(file_path: &Path) -> Option<DateTime<Utc>> {
 pub async fn extract_image_metadata(file_path: &Path) -> MediaMetadata {
     let mut metadata = MediaMetadata::default();
 
-    let output = Command::new("exiftool")
-        .args(["-json", "-n", file_path.to_str().unwrap_or("")])
-        .output()
-        .await;
+    if let Some(data) = read_exiftool_json(file_path).await {
+        apply_exif_data(&mut metadata, &data);
+    }
 
-    match output {
-        Ok(output) if output.status.success() => match String::from_utf8(output.stdout) {
-            Ok(json_str) => match serde_json::from_str::<Vec<serde_json::Value>>(&json_str) {
-                Ok(exif_data) => {
-                    if let Some(data) = exif_data.first() {
-                        apply_exif_data(&mut metadata, data);
-                    }
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to parse exiftool JSON for {:?}: {}",
-                        file_path.file_name().unwrap_or_default(),
-                        e
-                    );
-                }
-            },
-            Err(e) => {
-                warn!(
-                    "Failed to read exiftool output for {:?}: {}",
-                    file_path.file_name().unwrap_or_default(),
-                    e
-                );
-            }
-        },
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            warn!(
-                "exiftool failed for {:?}: {}",
-                file_path.file_name().unwrap_or_default(),
-                stderr
-            );
-        }
-        Err(e) => {
-            warn!(
-                "Failed to run exiftool for {:?}: {}",
+    if let Some(sidecar) = find_xmp_sidecar(file_path) {
+        if let Some(data) = read_exiftool_json(&sidecar).await {
+            debug!(
+                "Supplementing metadata for {:?} from XMP sidecar {:?}",
                 file_path.file_name().unwrap_or_default(),
-                e
+                sidecar.file_name().unwrap_or_default()
             );
+            let mut sidecar_metadata = MediaMetadata::default();
+            apply_exif_data(&mut sidecar_metadata, &data);
+            metadata.fill_missing_from(sidecar_metadata);
         }
     }
 
@@ -118,6 +132,76 @@ pub async fn extract_image_metadata(file_path: &Path) -> MediaMetadata {
     metadata
 }
 
+/// First object of `exiftool -json -n` for `file_path`; failures are
+/// logged and yield `None`.
+async fn read_exiftool_json(file_path: &Path) -> Option<serde_json::Value> {
+    run_exiftool_json(file_path)
+        .await
+        .map_err(|e| warn!("{} for {:?}", e, file_path.file_name().unwrap_or_default()))
+        .ok()
+}
+
+async fn run_exiftool_json(file_path: &Path) -> Result<serde_json::Value, String> {
+    let output = Command::new("exiftool")
+        .args(["-json", "-n", file_path.to_str().unwrap_or("")])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run exiftool: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "exiftool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let json_str = String::from_utf8(output.stdout)
+        .map_err(|e| format!("Failed to read exiftool output: {}", e))?;
+    serde_json::from_str::<Vec<serde_json::Value>>(&json_str)
+        .map_err(|e| format!("Failed to parse exiftool JSON: {}", e))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "exiftool printed no metadata".to_string())
+}
+
+/// Metadata actually recorded in the file: exiftool's output, plus the XMP
+/// sidecar for images and ffprobe's stream details for videos. Unlike the
+/// `extract_*_metadata` functions there is no mtime date fallback, and a
+/// file exiftool cannot read is an error rather than empty metadata.
+pub async fn extract_embedded_metadata(
+    file_path: &Path,
+    media_type: &str,
+) -> Result<MediaMetadata, String> {
+    let mut metadata = MediaMetadata::default();
+    apply_exif_data(&mut metadata, &run_exiftool_json(file_path).await?);
+
+    if media_type == "image" {
+        if let Some(sidecar) = find_xmp_sidecar(file_path) {
+            if let Some(data) = read_exiftool_json(&sidecar).await {
+                let mut sidecar_metadata = MediaMetadata::default();
+                apply_exif_data(&mut sidecar_metadata, &data);
+                metadata.fill_missing_from(sidecar_metadata);
+            }
+        }
+    } else if let Some(ffprobe_data) = run_ffprobe(file_path).await.and_then(parse_ffprobe_output) {
+        apply_ffprobe_output(&mut metadata, ffprobe_data);
+    }
+
+    Ok(metadata)
+}
+
+/// XMP sidecar beside `file_path`, either `IMG_1234.arw.xmp` (darktable
+/// style) or `IMG_1234.xmp` (Lightroom style), checked in that order.
+pub fn find_xmp_sidecar(file_path: &Path) -> Option<PathBuf> {
+    let file_name = file_path.file_name()?.to_str()?;
+    [
+        file_path.with_file_name(format!("{}.xmp", file_name)),
+        file_path.with_extension("xmp"),
+    ]
+    .into_iter()
+    .find(|candidate| candidate != file_path && candidate.is_file())
+}
+
 fn apply_exif_data(metadata: &mut MediaMetadata, data: &serde_json::Value) {
     fn get_str(data: &serde_json::Value, keys: &[&str]) -> Option<String> {
         for key in keys {
@@ -162,8 +246,14 @@ fn apply_exif_data(metadata: &mut MediaMetadata, data: &serde_json::Value) {
         metadata.date_taken = parse_exif_datetime(&date_str);
     }
 
-    metadata.gps_latitude = get_f64(data, &["GPSLatitude"]);
-    metadata.gps_longitude = get_f64(data, &["GPSLongitude"]);
+    metadata.gps_latitude = get_f64(data, &["GPSLatitude"]).or_else(|| {
+        let reference = get_str(data, &["GPSLatitudeRef"]).unwrap_or_default();
+        get_str(data, &["GPSLatitude"]).and_then(|s| parse_dms_coordinate(&s, &reference))
+    });
+    metadata.gps_longitude = get_f64(data, &["GPSLongitude"]).or_else(|| {
+        let reference = get_str(data, &["GPSLongitudeRef"]).unwrap_or_default();
+        get_str(data, &["GPSLongitude"]).and_then(|s| parse_dms_coordinate(&s, &reference))
+    });
     metadata.gps_altitude = get_f64(data, &["GPSAltitude"]);
 
     metadata.camera_make = get_str(data, &["Make"]);
@@ -184,7 +274,8 @@ fn apply_exif_data(metadata: &mut MediaMetadata, data: &serde_json::Value) {
         }
     }
 
-    if let Some(kw) = data.get("Keywords") {
+    // XMP (and so sidecars) carries keywords as `Subject`
+    if let Some(kw) = data.get("Keywords").or_else(|| data.get("Subject")) {
         metadata.keywords = match kw {
             serde_json::Value::String(s) => Some(s.clone()),
             serde_json::Value::Array(arr) => {
@@ -213,6 +304,114 @@ fn apply_exif_data(metadata: &mut MediaMetadata, data: &serde_json::Value) {
     }
 }
 
+/// Parse a GPS coordinate in the textual form exiftool emits without `-n`,
+/// e.g. `48 deg 51' 12.36" N`, `48 deg 51.206' N` or `48.8534`.
+/// South and west references (either in `reference` or trailing the value)
+/// produce a negative result.
+pub fn parse_dms_coordinate(s: &str, reference: &str) -> Option<f64> {
+    let trimmed = s.trim();
+    let negative_input = trimmed.starts_with('-');
+
+    let parts: Vec<f64> = trimmed
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    let value = match parts.as_slice() {
+        [deg] => *deg,
+        [deg, min] => deg + min / 60.0,
+        [deg, min, sec] => deg + min / 60.0 + sec / 3600.0,
+        _ => return None,
+    };
+
+    let direction = if reference.trim().is_empty() {
+        trimmed.chars().rev().find(|c| c.is_ascii_alphabetic())
+    } else {
+        reference.trim().chars().next()
+    };
+
+    let negative = match direction.map(|c| c.to_ascii_uppercase()) {
+        Some('S') | Some('W') => true,
+        Some('N') | Some('E') => false,
+        _ => negative_input,
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+/// Removes all GPS tags from the file in place.
+pub async fn strip_gps_metadata(file_path: &Path) -> Result<(), String> {
+    let output = Command::new("exiftool")
+        .args(["-overwrite_original", "-gps:all="])
+        .arg(file_path)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run exiftool: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "exiftool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reads the numeric EXIF `Orientation` tag, `None` when the file has none.
+/// A missing exiftool surfaces as an `ErrorKind::NotFound` error.
+pub async fn read_exif_orientation(file_path: &Path) -> std::io::Result<Option<i32>> {
+    let output = Command::new("exiftool")
+        .args(["-n", "-s3", "-Orientation"])
+        .arg(file_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "exiftool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+}
+
+/// Clockwise rotation that makes an image with EXIF `orientation` upright.
+/// `Some(0)` for the normal orientation, `None` for mirrored or unknown
+/// values, which a rotation alone cannot undo.
+pub fn orientation_rotation_degrees(orientation: i32) -> Option<i32> {
+    match orientation {
+        1 => Some(0),
+        3 => Some(180),
+        6 => Some(90),
+        8 => Some(270),
+        _ => None,
+    }
+}
+
+/// Removes the EXIF `Orientation` tag in place, once the pixels themselves
+/// have been rotated upright.
+pub async fn strip_exif_orientation(file_path: &Path) -> Result<(), String> {
+    let output = Command::new("exiftool")
+        .args(["-overwrite_original", "-Orientation="])
+        .arg(file_path)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run exiftool: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "exiftool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
 fn parse_exif_datetime(dt_str: &str) -> Option<DateTime<Utc>> {
     // Try common formats
     let formats = [
@@ -235,53 +434,71 @@ fn parse_exif_datetime(dt_str: &str) -> Option<DateTime<Utc>> {
 pub async fn extract_video_metadata(file_path: &Path) -> MediaMetadata {
     let mut metadata = MediaMetadata::default();
 
-    let exif_output = Command::new("exiftool")
-        .args(["-json", "-n", file_path.to_str().unwrap_or("")])
-        .output()
-        .await;
+    if let Some(data) = read_exiftool_json(file_path).await {
+        apply_exif_data(&mut metadata, &data);
+    }
 
-    match exif_output {
-        Ok(output) if output.status.success() => match String::from_utf8(output.stdout) {
-            Ok(json_str) => match serde_json::from_str::<Vec<serde_json::Value>>(&json_str) {
-                Ok(exif_data) => {
-                    if let Some(data) = exif_data.first() {
-                        apply_exif_data(&mut metadata, data);
-                    }
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to parse exiftool JSON for {:?}: {}",
-                        file_path.file_name().unwrap_or_default(),
-                        e
-                    );
-                }
-            },
-            Err(e) => {
-                warn!(
-                    "Failed to read exiftool output for {:?}: {}",
-                    file_path.file_name().unwrap_or_default(),
-                    e
-                );
-            }
-        },
-        Ok(output) => {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            warn!(
-                "exiftool failed for {:?}: {}",
-                file_path.file_name().unwrap_or_default(),
-                stderr
-            );
-        }
-        Err(e) => {
-            warn!(
-                "Failed to run exiftool for {:?}: {}",
-                file_path.file_name().unwrap_or_default(),
-                e
-            );
-        }
+    let Some(stdout) = run_ffprobe(file_path).await else {
+        metadata.date_taken = fallback_to_mtime(file_path);
+        metadata.duration_seconds = Some(0.0);
+        return metadata;
+    };
+    let Some(ffprobe_data) = parse_ffprobe_output(stdout) else {
+        metadata.date_taken = fallback_to_mtime(file_path);
+        return metadata;
+    };
+    apply_ffprobe_output(&mut metadata, ffprobe_data);
+
+    // Fallback date
+    if metadata.date_taken.is_none() {
+        metadata.date_taken = fallback_to_mtime(file_path);
     }
 
-    // Run ffprobe
+    // MIME type from extension
+    let ext = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    metadata.mime_type = Some(
+        match ext.as_str() {
+            "mp4" => "video/mp4",
+            "mov" => "video/quicktime",
+            "avi" => "video/x-msvideo",
+            "mkv" => "video/x-matroska",
+            "webm" => "video/webm",
+            "m4v" => "video/x-m4v",
+            _ => "video/mp4",
+        }
+        .to_string(),
+    );
+
+    log_extracted_metadata(file_path, &metadata);
+    metadata
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobeOutput {
+    streams: Option<Vec<FfprobeStream>>,
+    format: Option<FfprobeFormat>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<i32>,
+    height: Option<i32>,
+    // ffprobe reports these as strings, e.g. "48000"
+    sample_rate: Option<String>,
+    #[allow(dead_code)]
+    bit_rate: Option<String>,
+    channels: Option<i32>,
+}
+
+/// Raw `ffprobe` JSON for `file_path`, `None` when it could not run.
+async fn run_ffprobe(file_path: &Path) -> Option<Vec<u8>> {
     let output = Command::new("ffprobe")
         .args([
             "-v",
@@ -295,41 +512,21 @@ pub async fn extract_video_metadata(file_path: &Path) -> MediaMetadata {
         .output()
         .await;
 
-    let output = match output {
-        Ok(o) if o.status.success() => o,
-        _ => {
-            metadata.date_taken = fallback_to_mtime(file_path);
-            metadata.duration_seconds = Some(0.0);
-            return metadata;
-        }
-    };
-
-    let json_str = match String::from_utf8(output.stdout) {
-        Ok(s) => s,
-        Err(_) => {
-            metadata.date_taken = fallback_to_mtime(file_path);
-            return metadata;
-        }
-    };
+    match output {
+        Ok(o) if o.status.success() => Some(o.stdout),
+        _ => None,
+    }
+}
 
-    let ffprobe_data: FfprobeOutput = match serde_json::from_str(&json_str) {
-        Ok(d) => d,
-        Err(_) => {
-            metadata.date_taken = fallback_to_mtime(file_path);
-            return metadata;
-        }
-    };
+fn parse_ffprobe_output(stdout: Vec<u8>) -> Option<FfprobeOutput> {
+    let json_str = String::from_utf8(stdout).ok()?;
+    serde_json::from_str(&json_str).ok()
+}
 
-    // Extract video stream info
+fn apply_ffprobe_output(metadata: &mut MediaMetadata, ffprobe_data: FfprobeOutput) {
+    // Extract video and audio stream info
     if let Some(streams) = ffprobe_data.streams {
-        for stream in streams {
-            if stream.codec_type.as_deref() == Some("video") {
-                metadata.width = stream.width;
-                metadata.height = stream.height;
-                metadata.video_codec = stream.codec_name;
-                break;
-            }
-        }
+        apply_ffprobe_streams(metadata, streams);
     }
 
     // Extract format info
@@ -360,48 +557,31 @@ pub async fn extract_video_metadata(file_path: &Path) -> MediaMetadata {
             }
         }
     }
-
-    // Fallback date
-    if metadata.date_taken.is_none() {
-        metadata.date_taken = fallback_to_mtime(file_path);
-    }
-
-    // MIME type from extension
-    let ext = file_path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    metadata.mime_type = Some(
-        match ext.as_str() {
-            "mp4" => "video/mp4",
-            "mov" => "video/quicktime",
-            "avi" => "video/x-msvideo",
-            "mkv" => "video/x-matroska",
-            "webm" => "video/webm",
-            "m4v" => "video/x-m4v",
-            _ => "video/mp4",
-        }
-        .to_string(),
-    );
-
-    log_extracted_metadata(file_path, &metadata);
-    metadata
 }
 
-#[derive(Debug, Deserialize)]
-struct FfprobeOutput {
-    streams: Option<Vec<FfprobeStream>>,
-    format: Option<FfprobeFormat>,
-}
+/// Takes dimensions and codec from the first video stream and audio details
+/// from the first audio stream.
+fn apply_ffprobe_streams(metadata: &mut MediaMetadata, streams: Vec<FfprobeStream>) {
+    let mut seen_video = false;
+    let mut seen_audio = false;
 
-#[derive(Debug, Deserialize)]
-struct FfprobeStream {
-    codec_type: Option<String>,
-    codec_name: Option<String>,
-    width: Option<i32>,
-    height: Option<i32>,
+    for stream in streams {
+        match stream.codec_type.as_deref() {
+            Some("video") if !seen_video => {
+                seen_video = true;
+                metadata.width = stream.width;
+                metadata.height = stream.height;
+                metadata.video_codec = stream.codec_name;
+            }
+            Some("audio") if !seen_audio => {
+                seen_audio = true;
+                metadata.audio_codec = stream.codec_name;
+                metadata.audio_channels = stream.channels;
+                metadata.audio_sample_rate = stream.sample_rate.and_then(|r| r.parse().ok());
+            }
+            _ => {}
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -477,6 +657,15 @@ fn log_extracted_metadata(file_path: &Path, metadata: &MediaMetadata) {
     if let Some(ref codec) = metadata.video_codec {
         fields.push(format!("video_codec={}", codec));
     }
+    if let Some(ref codec) = metadata.audio_codec {
+        fields.push(format!("audio_codec={}", codec));
+    }
+    if let Some(channels) = metadata.audio_channels {
+        fields.push(format!("audio_channels={}", channels));
+    }
+    if let Some(rate) = metadata.audio_sample_rate {
+        fields.push(format!("audio_sample_rate={}", rate));
+    }
     if let Some(ref city) = metadata.location_city {
         fields.push(format!("location_city={}", city));
     }
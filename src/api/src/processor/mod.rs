@@ -1,5 +1,9 @@
+pub mod albums;
+pub mod backup;
 pub mod importer;
 pub mod media_processor;
 pub mod metadata;
+pub mod orphans;
 pub mod regenerator;
 pub mod thumbnails;
+pub mod workers;
@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::constants::ORPHAN_MIN_AGE_SECONDS;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OrphanCleanup {
+    pub deleted_files: i64,
+    pub freed_bytes: i64,
+}
+
+impl std::ops::AddAssign for OrphanCleanup {
+    fn add_assign(&mut self, other: Self) {
+        self.deleted_files += other.deleted_files;
+        self.freed_bytes += other.freed_bytes;
+    }
+}
+
+/// Deletes files under `dir`, outside the `excluded` subdirectories, whose
+/// `/`-separated relative path is rejected by `is_known`. Files younger than
+/// `ORPHAN_MIN_AGE_SECONDS` are kept, since imports write thumbnails before
+/// the media row exists. Blocks on file I/O, so run it off the runtime.
+pub fn remove_orphan_files(
+    dir: &Path,
+    excluded: &[&Path],
+    is_known: impl Fn(&str) -> bool,
+) -> OrphanCleanup {
+    let mut files = Vec::new();
+    collect_files_recursive(dir, excluded, &mut files);
+
+    let now = SystemTime::now();
+    let min_age = Duration::from_secs(ORPHAN_MIN_AGE_SECONDS);
+    let mut cleanup = OrphanCleanup::default();
+
+    for path in &files {
+        let Some(relative) = relative_key(dir, path) else {
+            continue;
+        };
+        if is_known(&relative) {
+            continue;
+        }
+
+        let Ok(metadata) = path.metadata() else {
+            continue;
+        };
+        let old_enough = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age >= min_age);
+        if !old_enough {
+            continue;
+        }
+
+        match std::fs::remove_file(path) {
+            Ok(()) => {
+                cleanup.deleted_files += 1;
+                cleanup.freed_bytes += metadata.len() as i64;
+            }
+            Err(e) => tracing::warn!("Failed to remove orphan {}: {}", path.display(), e),
+        }
+    }
+
+    cleanup
+}
+
+/// Previews are named `<user_id>/<original stem>_preview.<ext>`, so one is
+/// known while any original with that stem exists.
+pub fn is_known_preview(relative: &str, original_stems: &HashSet<String>) -> bool {
+    Path::new(relative)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_suffix("_preview"))
+        .is_some_and(|stem| original_stems.contains(stem))
+}
+
+fn relative_key(dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(dir).ok()?;
+    let parts: Vec<&str> = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+fn collect_files_recursive(dir: &Path, excluded: &[&Path], files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if !excluded.contains(&path.as_path()) {
+                collect_files_recursive(&path, excluded, files);
+            }
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+}
@@ -1,19 +1,27 @@
 use chrono::{DateTime, Utc};
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::RwLock;
 
-use crate::config::Config;
-use crate::constants::{ORIGINALS_DIR, THUMBNAILS_DIR, THUMBNAILS_TINY_DIR};
+use crate::config::{Config, ThumbnailFormat};
+use crate::constants::{
+    GEOHASH_PRECISE_PRECISION, GEOHASH_PRECISION, ORIGINALS_DIR, THUMBNAILS_DIR,
+    THUMBNAILS_TINY_DIR,
+};
 use crate::database::execute_query;
 use crate::database::{fetch_all, queries, DbPool};
+use crate::metrics::set_regeneration_running;
 use crate::processor::media_processor::{
-    calculate_geohash, delete_from_rtree, generate_complete_metadata, insert_into_rtree,
+    calculate_geohash, compute_perceptual_hash, delete_from_rtree, generate_complete_metadata,
+    insert_into_rtree, refresh_search_text,
 };
+use crate::processor::metadata::MediaMetadata;
 use crate::processor::thumbnails::{generate_image_thumbnail, generate_video_thumbnail};
 use crate::utils::hash::calculate_file_hash;
 use futures::stream::{self, StreamExt};
+use rusqlite::types::Value;
+use rusqlite::OptionalExtension;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 
@@ -111,16 +119,19 @@ fn start_job() {
         started_at: Some(Utc::now()),
         ..Default::default()
     };
+    set_regeneration_running(true);
 }
 
 fn finalize_job_success() {
     let mut job = CURRENT_JOB.write().unwrap();
+    set_regeneration_running(false);
     job.status = RegenerationStatus::Completed;
     job.completed_at = Some(Utc::now());
 }
 
 fn finalize_job_failure(message: &str) {
     let mut job = CURRENT_JOB.write().unwrap();
+    set_regeneration_running(false);
     job.status = RegenerationStatus::Failed;
     job.completed_at = Some(Utc::now());
     push_job_error(&mut job.errors, message);
@@ -136,6 +147,7 @@ fn push_job_error(errors: &mut Vec<String>, message: &str) {
 
 fn finalize_job_cancelled() {
     let mut job = CURRENT_JOB.write().unwrap();
+    set_regeneration_running(false);
     job.status = RegenerationStatus::Cancelled;
     job.completed_at = Some(Utc::now());
 }
@@ -206,6 +218,168 @@ fn merge_keyword_tags(conn: &rusqlite::Connection, media_id: i64, keywords: Opti
     inserted_count
 }
 
+/// Recomputes what is derived from stored metadata: geohashes, the
+/// R-tree entry and the search text. Failures are logged, not returned.
+fn refresh_derived_metadata(
+    conn: &rusqlite::Connection,
+    media_id: i64,
+    gps_latitude: Option<f64>,
+    gps_longitude: Option<f64>,
+) {
+    let (geohash, geohash_precise) = match (gps_latitude, gps_longitude) {
+        (Some(lat), Some(lon)) => (
+            calculate_geohash(lat, lon, GEOHASH_PRECISION),
+            calculate_geohash(lat, lon, GEOHASH_PRECISE_PRECISION),
+        ),
+        _ => (None, None),
+    };
+
+    if let Err(err) = conn.execute(
+        queries::media::UPDATE_GEOHASHES,
+        rusqlite::params![media_id, geohash, geohash_precise],
+    ) {
+        error!("Failed to update geohash for {}: {}", media_id, err);
+    }
+
+    if let Err(err) = delete_from_rtree(conn, media_id) {
+        error!("Failed to clear rtree for {}: {}", media_id, err);
+    }
+
+    if let (Some(lat), Some(lon)) = (gps_latitude, gps_longitude) {
+        if let Err(err) = insert_into_rtree(conn, media_id, lat, lon) {
+            error!("Failed to insert rtree for {}: {}", media_id, err);
+        }
+    }
+
+    if let Err(err) = refresh_search_text(conn, media_id) {
+        error!("Failed to refresh search text for {}: {}", media_id, err);
+    }
+}
+
+/// A `(field, old, new)` change to one metadata column, as recorded in the
+/// media edit history.
+pub type MetadataChange = (&'static str, Option<String>, Option<String>);
+
+/// Overwrites the stored metadata of one item with freshly extracted
+/// values, unlike `generate_missing_metadata` which only fills gaps. Fields
+/// the file did not yield keep their stored value, so a partial extraction
+/// cannot erase dimensions, GPS or manual edits. Returns the columns that
+/// changed.
+pub fn store_reprocessed_metadata(
+    conn: &rusqlite::Connection,
+    media_id: i64,
+    metadata: &MediaMetadata,
+) -> rusqlite::Result<Vec<MetadataChange>> {
+    let date_taken = metadata.date_taken.map(|dt| dt.to_rfc3339());
+    let extracted: [(&'static str, Option<String>); 24] = [
+        ("width", text(metadata.width)),
+        ("height", text(metadata.height)),
+        ("date_taken", date_taken.clone()),
+        ("gps_latitude", text(metadata.gps_latitude)),
+        ("gps_longitude", text(metadata.gps_longitude)),
+        ("gps_altitude", text(metadata.gps_altitude)),
+        ("camera_make", metadata.camera_make.clone()),
+        ("camera_model", metadata.camera_model.clone()),
+        ("lens_make", metadata.lens_make.clone()),
+        ("lens_model", metadata.lens_model.clone()),
+        ("iso", text(metadata.iso)),
+        ("exposure_time", metadata.exposure_time.clone()),
+        ("f_number", text(metadata.f_number)),
+        ("focal_length", text(metadata.focal_length)),
+        ("focal_length_35mm", text(metadata.focal_length_35mm)),
+        ("location_city", metadata.location_city.clone()),
+        ("location_state", metadata.location_state.clone()),
+        ("location_country", metadata.location_country.clone()),
+        ("video_codec", metadata.video_codec.clone()),
+        ("audio_codec", metadata.audio_codec.clone()),
+        ("audio_channels", text(metadata.audio_channels)),
+        ("audio_sample_rate", text(metadata.audio_sample_rate)),
+        ("keywords", metadata.keywords.clone()),
+        ("duration_seconds", text(metadata.duration_seconds)),
+    ];
+
+    let previous: Vec<Option<String>> = conn
+        .query_row(
+            queries::regenerator::SELECT_REPROCESSED_VALUES,
+            [media_id],
+            |row| {
+                (0..extracted.len())
+                    .map(|i| row.get::<_, Value>(i).map(value_text))
+                    .collect()
+            },
+        )
+        .optional()?
+        .unwrap_or_else(|| vec![None; extracted.len()]);
+
+    let changes: Vec<MetadataChange> = extracted
+        .into_iter()
+        .zip(previous)
+        .filter_map(|((field, new), old)| match new {
+            Some(new) if old.as_deref() != Some(new.as_str()) => Some((field, old, Some(new))),
+            _ => None,
+        })
+        .collect();
+    if changes.is_empty() {
+        return Ok(changes);
+    }
+
+    conn.execute(
+        queries::regenerator::UPDATE_REPROCESSED_METADATA,
+        rusqlite::params![
+            media_id,
+            metadata.width,
+            metadata.height,
+            date_taken,
+            metadata.gps_latitude,
+            metadata.gps_longitude,
+            metadata.gps_altitude,
+            metadata.camera_make,
+            metadata.camera_model,
+            metadata.lens_make,
+            metadata.lens_model,
+            metadata.iso,
+            metadata.exposure_time,
+            metadata.f_number,
+            metadata.focal_length,
+            metadata.focal_length_35mm,
+            metadata.location_city,
+            metadata.location_state,
+            metadata.location_country,
+            metadata.video_codec,
+            metadata.audio_codec,
+            metadata.audio_channels,
+            metadata.audio_sample_rate,
+            metadata.keywords,
+            metadata.duration_seconds
+        ],
+    )?;
+
+    let (gps_latitude, gps_longitude): (Option<f64>, Option<f64>) = conn.query_row(
+        queries::regenerator::SELECT_COORDINATES,
+        [media_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    refresh_derived_metadata(conn, media_id, gps_latitude, gps_longitude);
+    merge_keyword_tags(conn, media_id, metadata.keywords.as_deref());
+
+    Ok(changes)
+}
+
+fn text<T: ToString>(value: Option<T>) -> Option<String> {
+    value.map(|v| v.to_string())
+}
+
+/// Renders a stored column value the way the media edit history records it.
+pub fn value_text(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Integer(v) => Some(v.to_string()),
+        Value::Real(v) => Some(v.to_string()),
+        Value::Text(v) => Some(v),
+        Value::Blob(v) => Some(String::from_utf8_lossy(&v).into_owned()),
+    }
+}
+
 pub fn clear_all_metadata_and_thumbnails(pool: &DbPool) -> i64 {
     let conn = match pool.get() {
         Ok(c) => c,
@@ -261,11 +435,57 @@ struct MediaRow {
     location_state: Option<String>,
     location_country: Option<String>,
     video_codec: Option<String>,
+    audio_codec: Option<String>,
+    audio_channels: Option<i32>,
+    audio_sample_rate: Option<i32>,
     keywords: Option<String>,
 }
 
 use tracing::{error, info};
 
+/// Hashes media that have no perceptual hash yet. Items that cannot be
+/// hashed, e.g. undecodable files, are marked so later runs skip them.
+pub async fn backfill_perceptual_hashes(pool: &DbPool) {
+    let phash_rows: Vec<(i64, String, String, Option<String>)> = match pool.get() {
+        Ok(conn) => fetch_all(&conn, queries::media::SELECT_WITHOUT_PHASH, &[], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .unwrap_or_default(),
+        Err(_) => return,
+    };
+
+    if phash_rows.is_empty() {
+        return;
+    }
+    info!(
+        "Backfilling perceptual hashes for {} items",
+        phash_rows.len()
+    );
+
+    stream::iter(phash_rows)
+        .for_each_concurrent(
+            Some(num_cpus::get()),
+            |(id, path, media_type, thumbnail)| {
+                let pool = pool.clone();
+                async move {
+                    let full_path = ORIGINALS_DIR.join(&path);
+                    let phash =
+                        compute_perceptual_hash(&full_path, &media_type, thumbnail.as_deref())
+                            .await;
+                    if let Ok(c) = pool.get() {
+                        let _ = match phash {
+                            Some(phash) => {
+                                execute_query(&c, queries::media::UPDATE_PHASH, &[&phash, &id])
+                            }
+                            None => execute_query(&c, queries::media::MARK_PHASH_FAILED, &[&id]),
+                        };
+                    }
+                }
+            },
+        )
+        .await;
+}
+
 pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
     clear_cancel_request();
     start_job();
@@ -320,6 +540,35 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
             .await;
     }
 
+    backfill_perceptual_hashes(pool).await;
+
+    // Backfill precise geohashes for media geotagged before they existed
+    let geohash_rows: Vec<(i64, f64, f64)> = fetch_all(
+        &conn,
+        queries::media::SELECT_WITHOUT_PRECISE_GEOHASH,
+        &[],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .unwrap_or_default();
+
+    if !geohash_rows.is_empty() {
+        info!(
+            "Backfilling precise geohashes for {} items",
+            geohash_rows.len()
+        );
+        for (id, lat, lon) in geohash_rows {
+            let _ = execute_query(
+                &conn,
+                queries::media::UPDATE_GEOHASHES,
+                &[
+                    &id,
+                    &calculate_geohash(lat, lon, GEOHASH_PRECISION),
+                    &calculate_geohash(lat, lon, GEOHASH_PRECISE_PRECISION),
+                ],
+            );
+        }
+    }
+
     let rows: Vec<MediaRow> = match fetch_all(
         &conn,
         queries::regenerator::SELECT_MISSING_METADATA,
@@ -351,7 +600,10 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                 location_state: row.get(22)?,
                 location_country: row.get(23)?,
                 video_codec: row.get(24)?,
-                keywords: row.get(25)?,
+                audio_codec: row.get(25)?,
+                audio_channels: row.get(26)?,
+                audio_sample_rate: row.get(27)?,
+                keywords: row.get(28)?,
             })
         },
     ) {
@@ -460,6 +712,9 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                 let duration_seconds = choose(row.duration_seconds, metadata.duration_seconds);
                 let focal_length_35mm = choose(row.focal_length_35mm, metadata.focal_length_35mm);
                 let video_codec = choose(row.video_codec.clone(), metadata.video_codec);
+                let audio_codec = choose(row.audio_codec.clone(), metadata.audio_codec);
+                let audio_channels = choose(row.audio_channels, metadata.audio_channels);
+                let audio_sample_rate = choose(row.audio_sample_rate, metadata.audio_sample_rate);
 
                 let pool_clone = pool.clone();
                 let row_id = row.id;
@@ -490,32 +745,15 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                                 location_state,
                                 location_country,
                                 video_codec,
+                                audio_codec,
+                                audio_channels,
+                                audio_sample_rate,
                                 update_keywords,
                                 duration_seconds
                             ],
                         );
 
-                        let geohash = match (gps_latitude, gps_longitude) {
-                            (Some(lat), Some(lon)) => calculate_geohash(lat, lon),
-                            _ => None,
-                        };
-
-                        if let Err(err) = conn.execute(
-                            "INSERT INTO media_metadata (media_id, geohash) VALUES (?, ?) ON CONFLICT(media_id) DO UPDATE SET geohash = excluded.geohash",
-                            rusqlite::params![row_id, geohash],
-                        ) {
-                            error!("Failed to update geohash for {}: {}", row_id, err);
-                        }
-
-                        if let Err(err) = delete_from_rtree(&conn, row_id) {
-                            error!("Failed to clear rtree for {}: {}", row_id, err);
-                        }
-
-                        if let (Some(lat), Some(lon)) = (gps_latitude, gps_longitude) {
-                            if let Err(err) = insert_into_rtree(&conn, row_id, lat, lon) {
-                                error!("Failed to insert rtree for {}: {}", row_id, err);
-                            }
-                        }
+                        refresh_derived_metadata(&conn, row_id, gps_latitude, gps_longitude);
                     }
                 })
                 .await;
@@ -538,15 +776,18 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                     let thumbnail_relative = row.thumbnail_path.clone().unwrap_or_else(|| {
                         PathBuf::from(row.user_id.to_string())
                             .join(format!(
-                                "{}.jpg",
+                                "{}.{}",
                                 PathBuf::from(&row.file_path)
                                     .file_stem()
                                     .unwrap()
-                                    .to_string_lossy()
+                                    .to_string_lossy(),
+                                config.thumbnails.thumbnail_format.extension()
                             ))
                             .to_string_lossy()
                             .to_string()
                     });
+                    let thumbnail_format =
+                        ThumbnailFormat::from_path(Path::new(&thumbnail_relative));
 
                     let thumbnail_output = THUMBNAILS_DIR.join(&thumbnail_relative);
                     let tiny_thumbnail_output = THUMBNAILS_TINY_DIR.join(&thumbnail_relative);
@@ -561,6 +802,7 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                             &thumbnail_output,
                             config.thumbnails.max_size,
                             config.thumbnails.quality,
+                            thumbnail_format,
                         )
                         .await;
 
@@ -569,6 +811,7 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                             &tiny_thumbnail_output,
                             config.thumbnails.tiny_size,
                             config.thumbnails.quality,
+                            thumbnail_format,
                         )
                         .await;
 
@@ -580,6 +823,8 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                             config.thumbnails.max_size,
                             config.thumbnails.quality,
                             config.thumbnails.video_frame_quality,
+                            thumbnail_format,
+                            config.thumbnails.video_thumbnail_offset_seconds,
                         )
                         .await;
 
@@ -589,6 +834,8 @@ pub async fn generate_missing_metadata(config: &Config, pool: &DbPool) {
                             config.thumbnails.tiny_size,
                             config.thumbnails.quality,
                             config.thumbnails.video_frame_quality,
+                            thumbnail_format,
+                            config.thumbnails.video_thumbnail_offset_seconds,
                         )
                         .await;
 
@@ -1,7 +1,11 @@
-use std::path::Path;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
 use tokio::process::Command;
 use tracing::error;
 
+use crate::config::ThumbnailFormat;
+
 async fn run_command(cmd: &[&str], _timeout_secs: u64) -> bool {
     match Command::new(cmd[0]).args(&cmd[1..]).output().await {
         Ok(output) => {
@@ -27,6 +31,7 @@ pub async fn generate_image_thumbnail(
     output_path: &Path,
     max_size: u32,
     quality: u8,
+    format: ThumbnailFormat,
 ) -> bool {
     if let Some(parent) = output_path.parent() {
         if tokio::fs::create_dir_all(parent).await.is_err() {
@@ -34,7 +39,7 @@ pub async fn generate_image_thumbnail(
         }
     }
 
-    generate_montage_thumbnail(source_path, output_path, max_size, quality).await
+    generate_montage_thumbnail(source_path, output_path, max_size, quality, format).await
 }
 
 pub async fn generate_video_thumbnail(
@@ -43,6 +48,8 @@ pub async fn generate_video_thumbnail(
     max_size: u32,
     quality: u8,
     video_frame_quality: u8,
+    format: ThumbnailFormat,
+    offset_seconds: f64,
 ) -> bool {
     if let Some(parent) = output_path.parent() {
         if tokio::fs::create_dir_all(parent).await.is_err() {
@@ -51,7 +58,14 @@ pub async fn generate_video_thumbnail(
     }
 
     let temp_frame = output_path.with_extension("temp.jpg");
-    if !extract_video_frame(source_path, &temp_frame, video_frame_quality).await {
+    if !extract_video_frame(
+        source_path,
+        &temp_frame,
+        video_frame_quality,
+        offset_seconds,
+    )
+    .await
+    {
         error!(
             "Failed to extract video frame for thumbnail: {:?}",
             source_path
@@ -59,7 +73,8 @@ pub async fn generate_video_thumbnail(
         return false;
     }
 
-    let success = generate_montage_thumbnail(&temp_frame, output_path, max_size, quality).await;
+    let success =
+        generate_montage_thumbnail(&temp_frame, output_path, max_size, quality, format).await;
     if !success {
         error!("Failed to generate montage thumbnail: {:?}", output_path);
     }
@@ -101,9 +116,16 @@ async fn generate_montage_thumbnail(
     output_path: &Path,
     max_size: u32,
     quality: u8,
+    format: ThumbnailFormat,
 ) -> bool {
     let resized = format!("{}x{}", max_size, max_size);
     let source_input = format!("{}[0]", source_path.to_str().unwrap_or(""));
+    // Name the encoder explicitly so it does not depend on the extension.
+    let encoder = match format {
+        ThumbnailFormat::Jpeg => "jpeg",
+        ThumbnailFormat::Webp => "webp",
+    };
+    let output = format!("{}:{}", encoder, output_path.to_str().unwrap_or(""));
     let cmd = [
         "convert",
         source_input.as_str(),
@@ -116,32 +138,77 @@ async fn generate_montage_thumbnail(
         &resized,
         "-quality",
         &quality.to_string(),
-        output_path.to_str().unwrap_or(""),
+        output.as_str(),
     ];
 
     run_command(&cmd, 60).await && output_path.exists()
 }
 
+/// Grabs a single frame at `offset_seconds`. Seeking past the end of a
+/// short video yields no frame, so it retries at 1 s and then 0 s.
 async fn extract_video_frame(
     source_path: &Path,
     output_path: &Path,
     video_frame_quality: u8,
+    offset_seconds: f64,
 ) -> bool {
-    let seek_time = "00:00:00";
+    let mut offsets = vec![offset_seconds.max(0.0)];
+    for fallback in [1.0, 0.0] {
+        if offsets.last().is_some_and(|last| *last > fallback) {
+            offsets.push(fallback);
+        }
+    }
 
-    let cmd = [
-        "ffmpeg",
-        "-y",
-        "-ss",
-        seek_time,
-        "-i",
-        source_path.to_str().unwrap_or(""),
-        "-vframes",
-        "1",
-        "-q:v",
-        &video_frame_quality.to_string(),
-        output_path.to_str().unwrap_or(""),
-    ];
+    for offset in offsets {
+        let _ = tokio::fs::remove_file(output_path).await;
+        let seek_time = format!("{:.3}", offset);
+
+        let cmd = [
+            "ffmpeg",
+            "-y",
+            "-ss",
+            seek_time.as_str(),
+            "-i",
+            source_path.to_str().unwrap_or(""),
+            "-vframes",
+            "1",
+            "-q:v",
+            &video_frame_quality.to_string(),
+            output_path.to_str().unwrap_or(""),
+        ];
+
+        if run_command(&cmd, 60).await && output_path.exists() {
+            return true;
+        }
+    }
 
-    run_command(&cmd, 60).await && output_path.exists()
+    false
+}
+
+/// Lays out up to four thumbnails as a 2x2 grid of `tile_size` squares in
+/// reading order and writes it as a JPEG. Unused cells stay grey.
+pub fn composite_album_cover(
+    tiles: &[PathBuf],
+    output_path: &Path,
+    tile_size: u32,
+    quality: u8,
+) -> image::ImageResult<()> {
+    let mut cover =
+        image::RgbImage::from_pixel(tile_size * 2, tile_size * 2, image::Rgb([128, 128, 128]));
+
+    for (index, tile_path) in tiles.iter().take(4).enumerate() {
+        let tile = image::open(tile_path)?
+            .resize_to_fill(tile_size, tile_size, image::imageops::FilterType::Triangle)
+            .to_rgb8();
+        let x = (index as u32 % 2) * tile_size;
+        let y = (index as u32 / 2) * tile_size;
+        image::imageops::replace(&mut cover, &tile, x as i64, y as i64);
+    }
+
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let writer = BufWriter::new(File::create(output_path)?);
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(writer, quality);
+    cover.write_with_encoder(encoder)
 }
@@ -0,0 +1,46 @@
+use once_cell::sync::OnceCell;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+
+/// Dedicated pool for in-process image work (decoding, hashing,
+/// compositing), kept apart from Tokio's blocking pool so a large import
+/// cannot starve file I/O and database calls that use `spawn_blocking`.
+/// Thumbnails themselves are rendered by external processes and do not
+/// run here.
+static IMAGE_WORKERS: OnceCell<Arc<ThreadPool>> = OnceCell::new();
+
+/// Sizes the image pool; `0` means one thread per CPU. Only the first call
+/// (or first use through `image_workers`) decides the size.
+pub fn init_image_workers(num_threads: usize) -> Arc<ThreadPool> {
+    IMAGE_WORKERS
+        .get_or_init(|| {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .thread_name(|index| format!("image-worker-{}", index))
+                .build()
+                .expect("Failed to build image worker pool");
+            Arc::new(pool)
+        })
+        .clone()
+}
+
+pub fn image_workers() -> Arc<ThreadPool> {
+    init_image_workers(0)
+}
+
+/// Runs `task` on `pool` and waits for it without blocking the runtime.
+/// Returns `None` if the task panicked.
+pub async fn run_image_task<F, T>(pool: &ThreadPool, task: F) -> Option<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    pool.spawn(move || {
+        // An uncaught panic in a rayon task aborts the whole process
+        let result = std::panic::catch_unwind(AssertUnwindSafe(task));
+        let _ = sender.send(result.ok());
+    });
+    receiver.await.ok().flatten()
+}
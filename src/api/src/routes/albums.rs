@@ -1,24 +1,47 @@
-use axum::{extract::State, routing::post, Json, Router};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::header,
+    response::Response,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use std::collections::HashSet;
+use std::path::PathBuf;
 
 use crate::auth::{AppState, CurrentUser};
-use crate::database::{execute_query, fetch_all, fetch_one, insert_returning_id, queries};
-use crate::error::{AppError, AppResult};
+use crate::constants::{MAX_LIST_PAGE_SIZE, THUMBNAILS_DIR, THUMBNAILS_TINY_DIR};
+use crate::database::{execute_query, fetch_all, fetch_one, queries, DbConn};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    AlbumAddMediaRequest, AlbumCreateRequest, AlbumDeleteRequest, AlbumDetailResponse,
-    AlbumGetRequest, AlbumListResponse, AlbumRemoveMediaRequest, AlbumReorderRequest,
-    AlbumResponse, AlbumUpdateRequest, MediaResponse,
+    AlbumAddMediaRequest, AlbumClearMediaResponse, AlbumCreateFromTagRequest, AlbumCreateRequest,
+    AlbumDeleteRequest, AlbumDetailResponse, AlbumGetRequest, AlbumListReorderRequest,
+    AlbumListRequest, AlbumListResponse, AlbumMergeRequest, AlbumRemoveMediaRequest,
+    AlbumReorderRequest, AlbumResponse, AlbumUpdateRequest, MediaResponse,
 };
+use crate::processor::albums::{album_cover_path, insert_album, invalidate_album_cover};
+use crate::processor::thumbnails::composite_album_cover;
+use crate::processor::workers::run_image_task;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/album/create", post(create_album))
+        .route("/album/create-from-tag", post(create_album_from_tag))
         .route("/album/list", post(list_albums))
+        .route("/album/list/reorder", post(reorder_albums))
         .route("/album/get", post(get_album))
         .route("/album/update", post(update_album))
         .route("/album/delete", post(delete_album))
         .route("/album/add-media", post(add_media_to_album))
         .route("/album/remove-media", post(remove_media_from_album))
+        .route("/album/:album_id/media/all", delete(clear_album_media))
         .route("/album/reorder", post(reorder_album_media))
+        .route("/album/merge", post(merge_albums))
+        .route("/album/:album_id/cover", get(get_album_cover))
+        .route(
+            "/album/:album_id/cover/generate",
+            post(generate_album_cover),
+        )
 }
 
 fn map_album_row(row: &rusqlite::Row) -> rusqlite::Result<AlbumResponse> {
@@ -29,9 +52,18 @@ fn map_album_row(row: &rusqlite::Row) -> rusqlite::Result<AlbumResponse> {
         cover_media_id: row.get(3)?,
         media_count: row.get(4)?,
         created_at: row.get(5)?,
+        position: row.get(6)?,
     })
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/create",
+    tag = "albums",
+    request_body = AlbumCreateRequest,
+    responses((status = 200, description = "OK", body = AlbumDetailResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn create_album(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -39,18 +71,77 @@ async fn create_album(
 ) -> AppResult<Json<AlbumDetailResponse>> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
 
-    let album_id = insert_returning_id(
+    let album_id = insert_album(
         &conn,
-        queries::albums::INSERT,
-        &[&current_user.id, &request.name, &request.description],
+        current_user.id,
+        &request.name,
+        request.description.as_deref(),
     )?;
 
-    execute_query(
+    let album = fetch_one(&conn, queries::albums::SELECT_BY_ID, &[&album_id], |row| {
+        Ok(AlbumBasic {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            cover_media_id: row.get(3)?,
+            created_at: row.get(5)?,
+        })
+    })?
+    .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
+
+    let media = fetch_all(
         &conn,
-        queries::access::INSERT_ALBUM_ACCESS,
-        &[&album_id, &current_user.id, &2],
+        queries::albums::SELECT_MEDIA,
+        &[&current_user.id, &album_id],
+        map_media_row,
     )?;
 
+    Ok(Json(AlbumDetailResponse {
+        id: album.id,
+        name: album.name,
+        description: album.description,
+        cover_media_id: album.cover_media_id,
+        media,
+        created_at: album.created_at,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/album/create-from-tag",
+    tag = "albums",
+    request_body = AlbumCreateFromTagRequest,
+    responses((status = 200, description = "OK", body = AlbumDetailResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn create_album_from_tag(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<AlbumCreateFromTagRequest>,
+) -> AppResult<Json<AlbumDetailResponse>> {
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let tag_id: i64 = fetch_one(
+        &conn,
+        queries::tags::SELECT_ID_BY_NAME,
+        &[&request.tag_name],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::NotFound("Tag not found".to_string()))?;
+
+    let tx = conn.transaction()?;
+    let album_id = insert_album(
+        &tx,
+        current_user.id,
+        &request.album_name,
+        request.description.as_deref(),
+    )?;
+    tx.execute(
+        queries::albums::ADD_MEDIA_FROM_TAG,
+        rusqlite::params![album_id, tag_id, current_user.id],
+    )?;
+    tx.commit()?;
+
     let album = fetch_one(&conn, queries::albums::SELECT_BY_ID, &[&album_id], |row| {
         Ok(AlbumBasic {
             id: row.get(0)?,
@@ -65,7 +156,7 @@ async fn create_album(
     let media = fetch_all(
         &conn,
         queries::albums::SELECT_MEDIA,
-        &[&album_id],
+        &[&current_user.id, &album_id],
         map_media_row,
     )?;
 
@@ -115,12 +206,25 @@ fn map_media_row(row: &rusqlite::Row) -> rusqlite::Result<MediaResponse> {
         location_state: row.get(23)?,
         location_country: row.get(24)?,
         video_codec: row.get(25)?,
-        keywords: row.get(26)?,
-        created_at: row.get(27)?,
+        audio_codec: row.get(26)?,
+        audio_channels: row.get(27)?,
+        audio_sample_rate: row.get(28)?,
+        keywords: row.get(29)?,
+        created_at: row.get(30)?,
+        has_date_mismatch: row.get(31)?,
+        is_favorite: row.get(32)?,
         content_hash: None,
     })
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/update",
+    tag = "albums",
+    request_body = AlbumUpdateRequest,
+    responses((status = 200, description = "OK", body = AlbumResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn update_album(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -175,6 +279,14 @@ async fn update_album(
     Ok(Json(album))
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/delete",
+    tag = "albums",
+    request_body = AlbumDeleteRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn delete_album(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -204,6 +316,14 @@ async fn delete_album(
     ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/add-media",
+    tag = "albums",
+    request_body = AlbumAddMediaRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn add_media_to_album(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -251,9 +371,19 @@ async fn add_media_to_album(
         next_pos += 1;
     }
 
+    invalidate_album_cover(request.album_id);
+
     Ok(Json(serde_json::json!({"message": "Media added to album"})))
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/remove-media",
+    tag = "albums",
+    request_body = AlbumRemoveMediaRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn remove_media_from_album(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -279,27 +409,206 @@ async fn remove_media_from_album(
         )?;
     }
 
+    invalidate_album_cover(request.album_id);
+
     Ok(Json(
         serde_json::json!({"message": "Media removed from album"}),
     ))
 }
 
+/// Empties an album while keeping the album, its name and its sharing.
+#[utoipa::path(
+    delete,
+    path = "/album/{album_id}/media/all",
+    tag = "albums",
+    params(("album_id" = i64, Path)),
+    responses((status = 200, description = "OK", body = AlbumClearMediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn clear_album_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(album_id): Path<i64>,
+) -> AppResult<Json<AlbumClearMediaResponse>> {
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    check_album_owner(&conn, album_id, current_user.id)?;
+
+    let tx = conn.transaction()?;
+    let removed_count = tx.execute(queries::albums::REMOVE_ALL_MEDIA, [album_id])? as i64;
+    tx.execute(queries::albums::CLEAR_COVER, [album_id])?;
+    tx.commit()?;
+
+    invalidate_album_cover(album_id);
+
+    Ok(Json(AlbumClearMediaResponse { removed_count }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/album/list",
+    tag = "albums",
+    request_body = Option<AlbumListRequest>,
+    responses((status = 200, description = "OK", body = AlbumListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn list_albums(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    request: Option<Json<AlbumListRequest>>,
 ) -> AppResult<Json<AlbumListResponse>> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+    if let Some(limit) = request.limit {
+        if !(1..=MAX_LIST_PAGE_SIZE).contains(&limit) {
+            return Err(AppError::Validation(format!(
+                "limit must be between 1 and {}",
+                MAX_LIST_PAGE_SIZE
+            )));
+        }
+    }
+    let total_count = if request.include_total {
+        fetch_one(
+            &conn,
+            queries::albums::COUNT_FOR_USER,
+            &[&current_user.id],
+            |row| row.get(0),
+        )?
+    } else {
+        None
+    };
+
+    if request.limit.is_none() && request.cursor.is_none() {
+        let albums = fetch_all(
+            &conn,
+            queries::albums::SELECT_ALL_FOR_USER,
+            &[&current_user.id],
+            map_album_row,
+        )?;
+
+        return Ok(Json(AlbumListResponse {
+            albums,
+            next_cursor: None,
+            has_more: false,
+            total_count,
+        }));
+    }
+
+    let limit = request.limit.unwrap_or(100);
+    // The first page has no cursor, which the query reads as "from the start"
+    let (cursor_position, cursor_created_at, cursor_id) = match request.cursor.as_deref() {
+        Some(cursor) => {
+            let (position, created_at, id) = parse_album_cursor(cursor)
+                .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))?;
+            (Some(position), Some(created_at), Some(id))
+        }
+        None => (None, None, None),
+    };
 
-    let albums = fetch_all(
+    let rows = fetch_all(
         &conn,
-        queries::albums::SELECT_ALL_FOR_USER,
-        &[&current_user.id],
+        queries::albums::SELECT_PAGINATED_FOR_USER,
+        &[
+            &current_user.id,
+            &cursor_position,
+            &cursor_created_at,
+            &cursor_id,
+            &(limit + 1),
+        ],
         map_album_row,
     )?;
 
-    Ok(Json(AlbumListResponse { albums }))
+    let has_more = rows.len() > limit as usize;
+    let albums: Vec<AlbumResponse> = rows.into_iter().take(limit as usize).collect();
+
+    let next_cursor = if has_more {
+        albums
+            .last()
+            .map(|last| format!("{}_{}_{}", last.position, last.created_at, last.id))
+    } else {
+        None
+    };
+
+    Ok(Json(AlbumListResponse {
+        albums,
+        next_cursor,
+        has_more,
+        total_count,
+    }))
+}
+
+/// Splits a `{position}_{created_at}_{id}` list cursor.
+fn parse_album_cursor(cursor: &str) -> Option<(i64, String, i64)> {
+    let (position, rest) = cursor.split_once('_')?;
+    let (created_at, id) = rest.rsplit_once('_')?;
+    Some((
+        position.parse().ok()?,
+        created_at.to_string(),
+        id.parse().ok()?,
+    ))
+}
+
+/// Stores the caller's album order: each album's position becomes its
+/// index in `album_ids`. Every album must be owned by the caller.
+#[utoipa::path(
+    post,
+    path = "/album/list/reorder",
+    tag = "albums",
+    request_body = AlbumListReorderRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn reorder_albums(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<AlbumListReorderRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    let album_ids: Vec<i64> = {
+        let mut seen = HashSet::new();
+        request
+            .album_ids
+            .into_iter()
+            .filter(|id| seen.insert(*id))
+            .collect()
+    };
+    if album_ids.is_empty() {
+        return Err(AppError::BadRequest("No albums provided".to_string()));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    let tx = conn.transaction()?;
+
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&current_user.id];
+    params.extend(album_ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+    let owned: i64 = tx.query_row(
+        &queries::albums::build_count_owned(album_ids.len()),
+        params.as_slice(),
+        |row| row.get(0),
+    )?;
+    if owned != album_ids.len() as i64 {
+        return Err(AppError::NotFound("Album not found".to_string()));
+    }
+
+    for (i, album_id) in album_ids.iter().enumerate() {
+        tx.execute(
+            queries::albums::UPDATE_ALBUM_POSITION,
+            rusqlite::params![i as i64, album_id, current_user.id],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Albums reordered successfully"}),
+    ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/get",
+    tag = "albums",
+    request_body = AlbumGetRequest,
+    responses((status = 200, description = "OK", body = AlbumDetailResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_album(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -318,39 +627,129 @@ async fn get_album(
         return Err(AppError::NotFound("Album not found".to_string()));
     }
 
-    let album = fetch_one(
+    Ok(Json(load_album_detail(
         &conn,
-        queries::albums::SELECT_BY_ID,
-        &[&request.album_id],
-        |row| {
-            Ok(AlbumBasic {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                cover_media_id: row.get(3)?,
-                created_at: row.get(5)?,
-            })
-        },
+        request.album_id,
+        current_user.id,
+    )?))
+}
+
+/// Fails with 404 when the user cannot see the album and 403 when it was
+/// only shared with them.
+fn check_album_owner(conn: &DbConn, album_id: i64, user_id: i64) -> AppResult<()> {
+    let owner_id = fetch_one(
+        conn,
+        queries::albums::SELECT_OWNER_FOR_USER,
+        &[&album_id, &user_id],
+        |row| row.get::<_, i64>(0),
     )?
     .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
 
+    if owner_id != user_id {
+        return Err(AppError::Forbidden(
+            "Only the album owner can do this".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn load_album_detail(conn: &DbConn, album_id: i64, user_id: i64) -> AppResult<AlbumDetailResponse> {
+    let album = fetch_one(conn, queries::albums::SELECT_BY_ID, &[&album_id], |row| {
+        Ok(AlbumBasic {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            cover_media_id: row.get(3)?,
+            created_at: row.get(5)?,
+        })
+    })?
+    .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
+
     let media = fetch_all(
-        &conn,
+        conn,
         queries::albums::SELECT_MEDIA,
-        &[&request.album_id],
+        &[&user_id, &album_id],
         map_media_row,
     )?;
 
-    Ok(Json(AlbumDetailResponse {
+    Ok(AlbumDetailResponse {
         id: album.id,
         name: album.name,
         description: album.description,
         cover_media_id: album.cover_media_id,
         media,
         created_at: album.created_at,
-    }))
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/album/merge",
+    tag = "albums",
+    request_body = AlbumMergeRequest,
+    responses((status = 200, description = "OK", body = AlbumDetailResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn merge_albums(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<AlbumMergeRequest>,
+) -> AppResult<Json<AlbumDetailResponse>> {
+    if request.source_album_id == request.target_album_id {
+        return Err(AppError::BadRequest(
+            "Cannot merge an album into itself".to_string(),
+        ));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+
+    for album_id in [request.source_album_id, request.target_album_id] {
+        check_album_owner(&conn, album_id, current_user.id)?;
+    }
+
+    let tx = conn.transaction()?;
+    let max_position: i64 = tx.query_row(
+        queries::albums::SELECT_MAX_POSITION,
+        [request.target_album_id],
+        |row| row.get(0),
+    )?;
+    tx.execute(
+        queries::albums::ADD_MEDIA_FROM_ALBUM,
+        rusqlite::params![
+            request.target_album_id,
+            max_position + 1,
+            request.source_album_id
+        ],
+    )?;
+    tx.execute(
+        queries::albums::SET_COVER_IF_MISSING,
+        rusqlite::params![request.source_album_id, request.target_album_id],
+    )?;
+    if request.delete_source {
+        tx.execute(queries::albums::DELETE, [request.source_album_id])?;
+    }
+    tx.commit()?;
+
+    invalidate_album_cover(request.target_album_id);
+    if request.delete_source {
+        invalidate_album_cover(request.source_album_id);
+    }
+
+    Ok(Json(load_album_detail(
+        &conn,
+        request.target_album_id,
+        current_user.id,
+    )?))
 }
 
+#[utoipa::path(
+    post,
+    path = "/album/reorder",
+    tag = "albums",
+    request_body = AlbumReorderRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn reorder_album_media(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -376,7 +775,126 @@ async fn reorder_album_media(
         )?;
     }
 
+    invalidate_album_cover(request.album_id);
+
     Ok(Json(
         serde_json::json!({"message": "Album reordered successfully"}),
     ))
 }
+
+/// Renders the 2x2 composite cover from the tiny thumbnails of the first
+/// four items. Returns `false` when the album has nothing to show.
+async fn render_album_cover(state: &AppState, album_id: i64) -> AppResult<bool> {
+    let thumbnail_paths = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        fetch_all(
+            &conn,
+            queries::albums::SELECT_COVER_THUMBNAILS,
+            &[&album_id],
+            |row| row.get::<_, String>(0),
+        )?
+    };
+
+    if thumbnail_paths.is_empty() {
+        invalidate_album_cover(album_id);
+        return Ok(false);
+    }
+
+    let tiles: Vec<PathBuf> = thumbnail_paths
+        .iter()
+        .map(|relative| {
+            let tiny = THUMBNAILS_TINY_DIR.join(relative);
+            if tiny.exists() {
+                tiny
+            } else {
+                THUMBNAILS_DIR.join(relative)
+            }
+        })
+        .collect();
+
+    let output_path = album_cover_path(album_id);
+    let tile_size = state.config.thumbnails.tiny_size;
+    let quality = state.config.thumbnails.quality;
+    run_image_task(&state.image_workers, move || {
+        composite_album_cover(&tiles, &output_path, tile_size, quality)
+    })
+    .await
+    .ok_or_else(|| AppError::Internal("Album cover worker panicked".to_string()))?
+    .map_err(|e| AppError::Internal(format!("Failed to render album cover: {}", e)))?;
+
+    Ok(true)
+}
+
+#[utoipa::path(
+    post,
+    path = "/album/{album_id}/cover/generate",
+    tag = "albums",
+    params(("album_id" = i64, Path)),
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn generate_album_cover(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(album_id): Path<i64>,
+) -> AppResult<Json<serde_json::Value>> {
+    {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        fetch_one(
+            &conn,
+            queries::albums::CHECK_OWNERSHIP,
+            &[&album_id, &current_user.id],
+            |row| row.get::<_, i64>(0),
+        )?
+        .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
+    }
+
+    if !render_album_cover(&state, album_id).await? {
+        return Err(AppError::BadRequest(
+            "Album has no media to build a cover from".to_string(),
+        ));
+    }
+
+    Ok(Json(
+        serde_json::json!({"message": "Album cover generated"}),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/album/{album_id}/cover",
+    tag = "albums",
+    params(("album_id" = i64, Path)),
+    responses((status = 200, description = "Composite 2x2 cover", content_type = "image/jpeg"), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_album_cover(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(album_id): Path<i64>,
+) -> AppResult<Response> {
+    {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        fetch_one(
+            &conn,
+            queries::albums::CHECK_OWNERSHIP,
+            &[&album_id, &current_user.id],
+            |row| row.get::<_, i64>(0),
+        )?
+        .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
+    }
+
+    let cover_path = album_cover_path(album_id);
+    if !cover_path.exists() && !render_album_cover(&state, album_id).await? {
+        return Err(AppError::NotFound("Album cover not found".to_string()));
+    }
+
+    let data = tokio::fs::read(&cover_path).await?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "image/jpeg")
+        // Revalidate, since the cover changes whenever album media does
+        .header(header::CACHE_CONTROL, "private, no-cache")
+        .body(Body::from(data))
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
@@ -1,18 +1,25 @@
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::{header::AUTHORIZATION, HeaderMap},
-    routing::post,
+    routing::{delete, get, post},
     Json, Router,
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use uuid::Uuid;
 
 use crate::auth::{
-    create_access_token, create_refresh_token, hash_password, hash_refresh_token,
-    verify_and_migrate, AppState, CurrentUser,
+    create_access_token, create_password_reset_token, create_refresh_token, hash_password,
+    hash_refresh_token, verify_and_migrate, AllowPasswordChange, AppState, CurrentUser,
 };
-use crate::database::{execute_query, fetch_one, insert_returning_id, queries};
-use crate::error::{AppError, AppResult};
-use crate::models::{ChangePasswordRequest, LogoutRequest, RefreshTokenRequest, TokenResponse};
+use crate::constants::{MAX_DEVICE_NAME_LENGTH, PASSWORD_RESET_TOKEN_EXPIRE_MINUTES};
+use crate::database::{execute_query, fetch_all, fetch_one, insert_returning_id, queries, DbConn};
+use crate::error::{AppError, AppResult, ErrorResponses};
+use crate::models::{
+    ChangePasswordRequest, ForgotPasswordRequest, LogoutRequest, RefreshTokenRequest,
+    ResetPasswordRequest, SessionResponse, TokenResponse,
+};
+use crate::utils::mail::send_email;
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -20,8 +27,28 @@ pub fn router() -> Router<AppState> {
         .route("/user/refresh", post(refresh))
         .route("/user/logout", post(logout))
         .route("/user/change-password", post(change_password))
+        .route("/user/forgot-password", post(forgot_password))
+        .route("/user/reset-password", post(reset_password))
+        .route("/user/sessions", get(list_sessions))
+        .route("/user/sessions/:session_id", delete(revoke_session))
+}
+
+/// Optional client label from `X-Device-Name`, trimmed and capped.
+fn device_name(headers: &HeaderMap) -> Option<String> {
+    let name = headers
+        .get("x-device-name")
+        .and_then(|h| h.to_str().ok())?
+        .trim();
+    (!name.is_empty()).then(|| name.chars().take(MAX_DEVICE_NAME_LENGTH).collect())
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/authenticate",
+    tag = "auth",
+    responses((status = 200, description = "OK", body = TokenResponse), ErrorResponses),
+    security(("basic" = [])),
+)]
 async fn login(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -85,11 +112,18 @@ async fn login(
 
     let access_token = create_access_token(user.id, &user.username, &user.role, &state.config)?;
     let (raw_refresh, token_hash, expires_at) = create_refresh_token(user.id, &state.config);
+    let token_family = Uuid::new_v4().to_string();
 
     insert_returning_id(
         &conn,
         queries::auth::INSERT_REFRESH_TOKEN,
-        &[&token_hash, &user.id, &expires_at.to_rfc3339()],
+        &[
+            &token_hash,
+            &user.id,
+            &expires_at.to_rfc3339(),
+            &token_family,
+            &device_name(&headers),
+        ],
     )?;
 
     Ok(Json(TokenResponse::new(access_token, raw_refresh)))
@@ -103,6 +137,13 @@ struct UserAuthRow {
     is_active: i32,
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/refresh",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses((status = 200, description = "OK", body = TokenResponse), ErrorResponses),
+)]
 async fn refresh(
     State(state): State<AppState>,
     Json(request): Json<RefreshTokenRequest>,
@@ -122,12 +163,36 @@ async fn refresh(
                 username: row.get(4)?,
                 role: row.get(5)?,
                 is_active: row.get(6)?,
+                token_family: row.get(7)?,
+                device_name: row.get(8)?,
             })
         },
     )?
     .ok_or_else(|| AppError::Authentication("Invalid refresh token".to_string()))?;
 
     if token_row.revoked != 0 {
+        // A rotated-out token coming back while its family is still live means
+        // someone else holds a copy, so the whole family has to go
+        if let Some(family) = token_row.token_family.as_deref() {
+            let active: i64 = fetch_one(
+                &conn,
+                queries::auth::COUNT_ACTIVE_FAMILY_TOKENS,
+                &[&family, &chrono::Utc::now().to_rfc3339()],
+                |row| row.get(0),
+            )?
+            .unwrap_or(0);
+            if active > 0 {
+                execute_query(&conn, queries::auth::REVOKE_TOKEN_FAMILY, &[&family])?;
+                tracing::warn!(
+                    "Refresh token reuse detected for user {}; revoked token family",
+                    token_row.user_id
+                );
+                return Err(AppError::TooManyRequests(
+                    "Token reuse detected; this session has been revoked".to_string(),
+                ));
+            }
+        }
+
         return Err(AppError::Authentication(
             "Token has been revoked".to_string(),
         ));
@@ -137,9 +202,8 @@ async fn refresh(
         return Err(AppError::Authentication("User is inactive".to_string()));
     }
 
-    // Revoke old token
+    // Revoke old token, keeping the row so a replay of it can be detected
     execute_query(&conn, queries::auth::REVOKE_REFRESH_TOKEN, &[&token_row.id])?;
-    execute_query(&conn, queries::auth::DELETE_REVOKED_TOKEN, &[&token_row.id])?;
 
     // Create new tokens
     let access_token = create_access_token(
@@ -150,6 +214,10 @@ async fn refresh(
     )?;
     let (raw_refresh, new_token_hash, expires_at) =
         create_refresh_token(token_row.user_id, &state.config);
+    // Tokens issued before families existed start a new one on rotation
+    let token_family = token_row
+        .token_family
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
 
     insert_returning_id(
         &conn,
@@ -158,6 +226,8 @@ async fn refresh(
             &new_token_hash,
             &token_row.user_id,
             &expires_at.to_rfc3339(),
+            &token_family,
+            &token_row.device_name,
         ],
     )?;
 
@@ -171,8 +241,80 @@ struct RefreshTokenRow {
     username: String,
     role: String,
     is_active: i32,
+    token_family: Option<String>,
+    device_name: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/sessions",
+    tag = "auth",
+    responses((status = 200, description = "OK", body = Vec<SessionResponse>), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn list_sessions(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<Vec<SessionResponse>>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let sessions = fetch_all(
+        &conn,
+        queries::auth::SELECT_ACTIVE_SESSIONS,
+        &[&current_user.id, &Utc::now().to_rfc3339()],
+        |row| {
+            Ok(SessionResponse {
+                id: row.get(0)?,
+                device_name: row.get(1)?,
+                created_at: row.get(2)?,
+                expires_at: row.get(3)?,
+            })
+        },
+    )?;
+
+    Ok(Json(sessions))
+}
+
+/// Revokes one of the caller's sessions along with the rest of its token
+/// family, so an older token from the same login cannot revive it.
+#[utoipa::path(
+    delete,
+    path = "/user/sessions/{session_id}",
+    tag = "auth",
+    params(("session_id" = i64, Path)),
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn revoke_session(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(session_id): Path<i64>,
+) -> AppResult<Json<serde_json::Value>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let token_family: Option<String> = fetch_one(
+        &conn,
+        queries::auth::SELECT_SESSION_FAMILY,
+        &[&session_id, &current_user.id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+
+    execute_query(&conn, queries::auth::REVOKE_REFRESH_TOKEN, &[&session_id])?;
+    if let Some(family) = token_family {
+        execute_query(&conn, queries::auth::REVOKE_TOKEN_FAMILY, &[&family])?;
+    }
+
+    Ok(Json(serde_json::json!({"message": "Session revoked"})))
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/logout",
+    tag = "auth",
+    request_body = LogoutRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+)]
 async fn logout(
     State(state): State<AppState>,
     Json(request): Json<LogoutRequest>,
@@ -191,9 +333,17 @@ async fn logout(
     ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/change-password",
+    tag = "auth",
+    request_body = ChangePasswordRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn change_password(
     State(state): State<AppState>,
-    current_user: CurrentUser,
+    AllowPasswordChange(current_user): AllowPasswordChange,
     Json(request): Json<ChangePasswordRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
@@ -238,3 +388,133 @@ async fn change_password(
         serde_json::json!({"message": "Password changed successfully"}),
     ))
 }
+
+#[utoipa::path(
+    post,
+    path = "/user/forgot-password",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+)]
+async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    // Same response whether or not the email is known, to avoid enumeration
+    let response = Json(serde_json::json!({
+        "message": "If the email is registered, a reset link has been sent"
+    }));
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let now = chrono::Utc::now().to_rfc3339();
+    execute_query(
+        &conn,
+        queries::auth::DELETE_EXPIRED_PASSWORD_RESET_TOKENS,
+        &[&now],
+    )?;
+
+    let user = fetch_one(
+        &conn,
+        queries::auth::SELECT_ACTIVE_USER_BY_EMAIL,
+        &[&request.email],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+    )?;
+
+    let Some((user_id, username)) = user else {
+        return Ok(response);
+    };
+
+    let (raw_token, token_hash, expires_at) =
+        create_password_reset_token(PASSWORD_RESET_TOKEN_EXPIRE_MINUTES);
+
+    insert_returning_id(
+        &conn,
+        queries::auth::INSERT_PASSWORD_RESET_TOKEN,
+        &[&user_id, &token_hash, &expires_at.to_rfc3339()],
+    )?;
+
+    let smtp = state.config.smtp.clone();
+    let email = request.email;
+    tokio::spawn(async move {
+        let body = format!(
+            "Hello {},\n\nUse the following token to reset your Momento password:\n\n{}\n\nThis token expires in {} minutes. If you did not request a reset, you can ignore this email.\n",
+            username, raw_token, PASSWORD_RESET_TOKEN_EXPIRE_MINUTES
+        );
+        if let Err(e) = send_email(&smtp, &email, "Momento password reset", &body).await {
+            tracing::error!(
+                "Failed to send password reset email for user {}: {}",
+                user_id,
+                e
+            );
+        }
+    });
+
+    Ok(response)
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/reset-password",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+)]
+async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if request.new_password.len() < 8 {
+        return Err(AppError::BadRequest(
+            "Password must be at least 8 characters".to_string(),
+        ));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let token_hash = hash_refresh_token(&request.token);
+    let now = chrono::Utc::now().to_rfc3339();
+    let (token_id, user_id) = fetch_one(
+        &conn,
+        queries::auth::SELECT_VALID_PASSWORD_RESET_TOKEN,
+        &[&token_hash, &now],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+    )?
+    .ok_or_else(|| AppError::BadRequest("Invalid or expired reset token".to_string()))?;
+
+    let new_hash = hash_password(&request.new_password)
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
+
+    // Claiming the token first means two concurrent resets cannot both
+    // change the password with it
+    let tx = conn.transaction()?;
+    let claimed = tx.execute(
+        queries::auth::MARK_PASSWORD_RESET_TOKEN_USED,
+        rusqlite::params![now, token_id],
+    )?;
+    if claimed != 1 {
+        return Err(AppError::BadRequest(
+            "Invalid or expired reset token".to_string(),
+        ));
+    }
+    tx.execute(
+        queries::auth::UPDATE_PASSWORD_AND_RESET_FLAG,
+        rusqlite::params![new_hash, user_id],
+    )?;
+    tx.execute(queries::auth::REVOKE_ALL_USER_TOKENS, [user_id])?;
+    tx.commit()?;
+
+    Ok(Json(
+        serde_json::json!({"message": "Password reset successfully"}),
+    ))
+}
+
+/// Deletes expired refresh tokens whose whole family has expired, returning
+/// how many rows were removed.
+pub fn cleanup_expired_refresh_tokens(conn: &DbConn) -> AppResult<usize> {
+    execute_query(
+        conn,
+        queries::auth::DELETE_EXPIRED_REFRESH_TOKENS,
+        &[&Utc::now().to_rfc3339()],
+    )
+}
@@ -0,0 +1,35 @@
+use axum::{extract::State, routing::post, Json, Router};
+
+use crate::auth::{AppState, RequireAdmin};
+use crate::error::{AppError, AppResult, ErrorResponses};
+use crate::models::BackupTriggerResponse;
+use crate::processor::backup::{is_backup_running, run_backup};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/admin/backup/trigger", post(trigger_backup))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/backup/trigger",
+    tag = "backup",
+    responses((status = 200, description = "OK", body = BackupTriggerResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn trigger_backup(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+) -> AppResult<Json<BackupTriggerResponse>> {
+    if is_backup_running() {
+        return Err(AppError::Conflict("Backup already in progress".to_string()));
+    }
+
+    let location = run_backup(&state.config.backup, &state.pool)
+        .await
+        .map_err(AppError::Internal)?;
+
+    Ok(Json(BackupTriggerResponse {
+        message: "Backup completed".to_string(),
+        location,
+    }))
+}
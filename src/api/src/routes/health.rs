@@ -0,0 +1,81 @@
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde_json::json;
+
+use crate::auth::AppState;
+use crate::VERSION;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/health", get(liveness))
+        .route("/ready", get(readiness))
+}
+
+fn shutting_down() -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "status": "shutting_down"
+        })),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses((status = 200, description = "OK", body = serde_json::Value)),
+)]
+async fn liveness(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    if state.shutdown.is_triggered() {
+        return shutting_down();
+    }
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "status": "ok",
+            "version": VERSION
+        })),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "health",
+    responses((status = 200, description = "OK", body = serde_json::Value)),
+)]
+async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    if state.shutdown.is_triggered() {
+        return shutting_down();
+    }
+
+    let result = state
+        .pool
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| {
+            conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0))
+                .map_err(|e| e.to_string())
+        });
+
+    match result {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "ok",
+                "version": VERSION
+            })),
+        ),
+        Err(e) => {
+            tracing::warn!("Readiness check failed: {}", e);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(json!({
+                    "status": "degraded",
+                    "error": e
+                })),
+            )
+        }
+    }
+}
@@ -1,25 +1,49 @@
-use axum::{extract::State, routing::post, Json, Router};
+use axum::{
+    extract::{Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::path::PathBuf;
 use std::sync::Arc;
+use utoipa::IntoParams;
 
 use crate::auth::{AppState, RequireAdmin};
-use crate::error::{AppError, AppResult};
+use crate::constants::{
+    DEFAULT_IMPORT_HISTORY_PAGE_SIZE, IMPORTS_DIR, IMPORT_HISTORY_ERRORS_LIMIT,
+    MAX_IMPORT_HISTORY_PAGE_SIZE,
+};
+use crate::database::{execute_query, fetch_all, fetch_one, queries};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    ImportStatusResponse, ImportTriggerResponse, RegenerateRequest, RegenerateResponse,
+    ImportHistoryEntry, ImportHistoryPurgeResponse, ImportHistoryResponse, ImportStatusResponse,
+    ImportTriggerResponse, LocalDirectoryImportRequest, RegenerateRequest, RegenerateResponse,
     RegenerationStatusResponse,
 };
 use crate::processor::importer::{
-    get_import_status, is_import_running, run_local_import, ImportSettings,
+    get_import_status, get_last_persisted_job, is_import_running, run_local_import, ImportSettings,
+    ImportStatus,
 };
 use crate::processor::media_processor::MediaProcessingContext;
 use crate::processor::regenerator::{
     cancel_regeneration, clear_all_metadata_and_thumbnails, generate_missing_metadata,
     get_regeneration_status, is_regeneration_running,
 };
+use crate::utils::datetime::days_before;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/import/local", post(trigger_local_import))
+        .route(
+            "/import/local-directory",
+            post(trigger_local_directory_import),
+        )
         .route("/import/status", post(get_import_job_status))
+        .route(
+            "/import/history",
+            get(list_import_history).delete(purge_import_history),
+        )
         .route("/import/regenerate", post(trigger_regeneration))
         .route(
             "/import/regenerate/status",
@@ -29,9 +53,79 @@ pub fn router() -> Router<AppState> {
         .route("/import/reset", post(trigger_reset))
 }
 
+#[utoipa::path(
+    post,
+    path = "/import/local",
+    tag = "imports",
+    responses((status = 200, description = "OK", body = ImportTriggerResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn trigger_local_import(
     State(state): State<AppState>,
     RequireAdmin(admin): RequireAdmin,
+) -> AppResult<Json<ImportTriggerResponse>> {
+    start_local_import(&state, admin.id, IMPORTS_DIR.clone(), true, true)
+}
+
+/// Imports from a directory other than the imports folder, on behalf of
+/// `userId`. The path is resolved (following `..` and symlinks) before it
+/// is checked against `import.allowed_import_roots`.
+#[utoipa::path(
+    post,
+    path = "/import/local-directory",
+    tag = "imports",
+    request_body = LocalDirectoryImportRequest,
+    responses((status = 200, description = "OK", body = ImportTriggerResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn trigger_local_directory_import(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+    Json(request): Json<LocalDirectoryImportRequest>,
+) -> AppResult<Json<ImportTriggerResponse>> {
+    let source_dir = std::fs::canonicalize(&request.path)
+        .ok()
+        .filter(|path| path.is_dir())
+        .ok_or_else(|| AppError::BadRequest(format!("Not a directory: {}", request.path)))?;
+
+    let allowed = state
+        .config
+        .import
+        .allowed_import_roots
+        .iter()
+        .filter_map(|root| std::fs::canonicalize(root).ok())
+        .any(|root| source_dir.starts_with(root));
+    if !allowed {
+        return Err(AppError::Forbidden(
+            "Path is outside the allowed import roots".to_string(),
+        ));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    fetch_one(
+        &conn,
+        queries::users::SELECT_BY_ID,
+        &[&request.user_id],
+        |row| row.get::<_, i64>(0),
+    )?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    drop(conn);
+
+    start_local_import(
+        &state,
+        request.user_id,
+        source_dir,
+        request.recursive,
+        request.delete_after_import,
+    )
+}
+
+fn start_local_import(
+    state: &AppState,
+    user_id: i64,
+    source_dir: PathBuf,
+    recursive: bool,
+    delete_after_import: bool,
 ) -> AppResult<Json<ImportTriggerResponse>> {
     if is_import_running() {
         return Err(AppError::Conflict("Import already in progress".to_string()));
@@ -39,17 +133,23 @@ async fn trigger_local_import(
 
     let config = Arc::clone(&state.config);
     let pool = state.pool.clone();
-    let user_id = admin.id;
     let concurrency = config.regenerate.num_cpus;
     let settings = ImportSettings {
         processing: MediaProcessingContext {
             user_id,
             thumbnails: config.thumbnails.clone(),
             reverse_geocoding: Some(config.reverse_geocoding.clone()),
+            max_file_size_bytes: config.import.max_file_size_bytes,
             pool: pool.clone(),
+            post_processors: config.post_processors.clone(),
+            cluster_cache: Some(Arc::clone(&state.cluster_cache)),
         },
-        delete_after_import: true,
+        source_dir,
+        recursive,
+        delete_after_import,
         concurrency,
+        recent_imports: Arc::clone(&state.recently_imported_hashes),
+        cache_ttl_cycles: config.import.cache_ttl_cycles,
     };
 
     tokio::spawn(async move {
@@ -62,16 +162,31 @@ async fn trigger_local_import(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/import/status",
+    tag = "imports",
+    responses((status = 200, description = "OK", body = ImportStatusResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_import_job_status(
+    State(state): State<AppState>,
     RequireAdmin(_): RequireAdmin,
 ) -> AppResult<Json<ImportStatusResponse>> {
-    let job = get_import_status();
+    let mut job = get_import_status();
+    if job.status == ImportStatus::Idle {
+        if let Some(persisted) = get_last_persisted_job(&state.pool) {
+            job = persisted;
+        }
+    }
 
     Ok(Json(ImportStatusResponse {
         status: job.status.to_string(),
         total_files: job.total_files,
         processed_files: job.processed_files,
         successful_imports: job.successful_imports,
+        new_imports: job.new_imports,
+        duplicate_imports: job.duplicate_imports,
         failed_imports: job.failed_imports,
         started_at: job.started_at.map(|dt| dt.to_rfc3339()),
         completed_at: job.completed_at.map(|dt| dt.to_rfc3339()),
@@ -79,6 +194,125 @@ async fn get_import_job_status(
     }))
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImportHistoryQuery {
+    /// Defaults to 20, at most 100
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+/// Persisted local, WebDAV and SFTP import jobs, newest first. Only the
+/// most recent `IMPORT_JOB_HISTORY_LIMIT` jobs are kept.
+#[utoipa::path(
+    get,
+    path = "/import/history",
+    tag = "imports",
+    params(ImportHistoryQuery),
+    responses((status = 200, description = "OK", body = ImportHistoryResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn list_import_history(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+    Query(query): Query<ImportHistoryQuery>,
+) -> AppResult<Json<ImportHistoryResponse>> {
+    let limit = query.limit.unwrap_or(DEFAULT_IMPORT_HISTORY_PAGE_SIZE);
+    if !(1..=MAX_IMPORT_HISTORY_PAGE_SIZE).contains(&limit) {
+        return Err(AppError::Validation(format!(
+            "limit must be between 1 and {}",
+            MAX_IMPORT_HISTORY_PAGE_SIZE
+        )));
+    }
+    let offset = query.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(AppError::Validation(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let jobs = fetch_all(
+        &conn,
+        queries::imports::SELECT_JOB_HISTORY,
+        &[&limit, &offset],
+        |row| {
+            let errors_json: String = row.get(8)?;
+            let errors: Vec<String> = serde_json::from_str(&errors_json).unwrap_or_default();
+            Ok(ImportHistoryEntry {
+                id: row.get(0)?,
+                job_type: row.get(1)?,
+                status: row.get(2)?,
+                total_files: row.get(3)?,
+                successful_imports: row.get(4)?,
+                failed_imports: row.get(5)?,
+                started_at: row.get(6)?,
+                completed_at: row.get(7)?,
+                error_count: errors.len() as i64,
+                errors: errors
+                    .into_iter()
+                    .take(IMPORT_HISTORY_ERRORS_LIMIT)
+                    .collect(),
+            })
+        },
+    )?;
+    let total_jobs =
+        fetch_one(&conn, queries::imports::COUNT_JOBS, &[], |row| row.get(0))?.unwrap_or(0);
+
+    Ok(Json(ImportHistoryResponse { jobs, total_jobs }))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImportHistoryPurgeQuery {
+    /// Keep jobs that finished within this many days; omit to purge every
+    /// finished job
+    older_than_days: Option<i64>,
+}
+
+/// Deletes finished import jobs from the history. Jobs still marked
+/// running are kept.
+#[utoipa::path(
+    delete,
+    path = "/import/history",
+    tag = "imports",
+    params(ImportHistoryPurgeQuery),
+    responses((status = 200, description = "OK", body = ImportHistoryPurgeResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn purge_import_history(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+    Query(query): Query<ImportHistoryPurgeQuery>,
+) -> AppResult<Json<ImportHistoryPurgeResponse>> {
+    let older_than_days = query.older_than_days.unwrap_or(0);
+    if older_than_days < 0 {
+        return Err(AppError::Validation(
+            "older_than_days must not be negative".to_string(),
+        ));
+    }
+
+    let cutoff = days_before(Utc::now(), older_than_days)
+        .ok_or_else(|| AppError::Validation("older_than_days is out of range".to_string()))?
+        .to_rfc3339();
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let deleted_count = execute_query(
+        &conn,
+        queries::imports::DELETE_FINISHED_JOBS_BEFORE,
+        &[&cutoff],
+    )? as i64;
+
+    Ok(Json(ImportHistoryPurgeResponse { deleted_count }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/import/regenerate",
+    tag = "imports",
+    request_body = RegenerateRequest,
+    responses((status = 200, description = "OK", body = RegenerateResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn trigger_regeneration(
     State(state): State<AppState>,
     RequireAdmin(_): RequireAdmin,
@@ -103,6 +337,13 @@ async fn trigger_regeneration(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/import/regenerate/status",
+    tag = "imports",
+    responses((status = 200, description = "OK", body = RegenerationStatusResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_regeneration_job_status(
     RequireAdmin(_): RequireAdmin,
 ) -> AppResult<Json<RegenerationStatusResponse>> {
@@ -121,6 +362,13 @@ async fn get_regeneration_job_status(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/import/regenerate/cancel",
+    tag = "imports",
+    responses((status = 200, description = "OK", body = RegenerateResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn cancel_regeneration_job(
     RequireAdmin(_): RequireAdmin,
 ) -> AppResult<Json<RegenerateResponse>> {
@@ -137,6 +385,13 @@ async fn cancel_regeneration_job(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/import/reset",
+    tag = "imports",
+    responses((status = 200, description = "OK", body = RegenerateResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn trigger_reset(
     State(state): State<AppState>,
     RequireAdmin(_): RequireAdmin,
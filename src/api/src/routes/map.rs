@@ -1,17 +1,40 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
 use axum::{extract::State, routing::post, Json, Router};
 
 use crate::auth::{AppState, CurrentUser};
+use crate::constants::GEOHASH_PRECISE_PRECISION;
 use crate::database::{fetch_all, queries};
-use crate::error::{AppError, AppResult};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    Cluster, MapClustersRequest, MapClustersResponse, MapMediaListResponse, MapMediaRequest,
-    MediaResponse,
+    BoundingBox, Cluster, MapClustersRequest, MapClustersResponse, MapGeohashSearchRequest,
+    MapGeohashSearchResponse, MapLensFilterRequest, MapMediaListResponse, MapMediaRequest,
+    MapNearbyRequest, MediaResponse,
 };
 
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+const MAX_NEARBY_RADIUS_METERS: f64 = 100_000.0;
+const MAX_NEARBY_CELLS: usize = 32;
+const MAX_NEARBY_RESULTS: i64 = 500;
+const MIN_GEOHASH_SEARCH_LENGTH: usize = 2;
+const MAX_GEOHASH_SEARCH_RESULTS: i64 = 500;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/map/clusters", post(get_clusters))
         .route("/map/media", post(get_media))
+        .route("/map/lens-filter", post(get_lens_clusters))
+        .route("/map/nearby", post(get_nearby_media))
+        .route("/map/geohash-search", post(search_geohash))
+}
+
+fn bounds_hash(bounds: &BoundingBox) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for edge in [bounds.north, bounds.south, bounds.east, bounds.west] {
+        edge.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
 }
 
 fn zoom_to_geohash_precision(zoom: u8) -> usize {
@@ -26,6 +49,119 @@ fn zoom_to_geohash_precision(zoom: u8) -> usize {
     }
 }
 
+/// Approximate web-map zoom level for a viewport, from its longitude span.
+fn estimate_zoom_from_bounds(bounds: &BoundingBox) -> u8 {
+    let span = if bounds.west <= bounds.east {
+        bounds.east - bounds.west
+    } else {
+        360.0 - (bounds.west - bounds.east)
+    };
+
+    if span <= 0.0 {
+        return 18;
+    }
+
+    (360.0 / span).log2().floor().clamp(0.0, 18.0) as u8
+}
+
+/// Geohash base32: digits and lowercase letters except `a`, `i`, `l`, `o`.
+fn is_geohash_prefix(query: &str) -> bool {
+    (MIN_GEOHASH_SEARCH_LENGTH..=GEOHASH_PRECISE_PRECISION).contains(&query.len())
+        && query
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b.is_ascii_lowercase() && !b"ailo".contains(&b)))
+}
+
+fn haversine_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+}
+
+/// Width and height in degrees of a geohash cell at `precision`. Longitude
+/// takes the extra bit when the total bit count is odd.
+fn geohash_cell_size(precision: usize) -> (f64, f64) {
+    let bits = 5 * precision as i32;
+    let lon_bits = (bits + 1) / 2;
+    let lat_bits = bits / 2;
+    (360.0 / 2f64.powi(lon_bits), 180.0 / 2f64.powi(lat_bits))
+}
+
+/// Latitude/longitude boxes `(south, north, west, east)` enclosing the
+/// circle. A circle that reaches a pole spans every longitude, and one that
+/// crosses the antimeridian is split into two boxes.
+fn circle_bounding_boxes(lat: f64, lon: f64, radius: f64) -> Vec<(f64, f64, f64, f64)> {
+    let angular = radius / EARTH_RADIUS_METERS;
+    let d_lat = angular.to_degrees();
+    let south = (lat - d_lat).max(-90.0);
+    let north = (lat + d_lat).min(90.0);
+
+    let lon_ratio = angular.sin() / lat.to_radians().cos();
+    if south <= -90.0 || north >= 90.0 || lon_ratio >= 1.0 {
+        return vec![(south, north, -180.0, 180.0)];
+    }
+
+    let d_lon = lon_ratio.asin().to_degrees();
+    let (west, east) = (lon - d_lon, lon + d_lon);
+    if west < -180.0 {
+        vec![
+            (south, north, west + 360.0, 180.0),
+            (south, north, -180.0, east),
+        ]
+    } else if east > 180.0 {
+        vec![
+            (south, north, west, 180.0),
+            (south, north, -180.0, east - 360.0),
+        ]
+    } else {
+        vec![(south, north, west, east)]
+    }
+}
+
+/// Geohash cells at `precision` that intersect the boxes.
+fn covering_cells(boxes: &[(f64, f64, f64, f64)], precision: usize) -> Vec<(usize, usize)> {
+    let (width, height) = geohash_cell_size(precision);
+    let max_col = (360.0 / width) as usize - 1;
+    let max_row = (180.0 / height) as usize - 1;
+    let index = |value: f64, size: f64, max: usize| ((value / size) as usize).min(max);
+
+    let mut cells = Vec::new();
+    for &(south, north, west, east) in boxes {
+        for row in index(south + 90.0, height, max_row)..=index(north + 90.0, height, max_row) {
+            for col in index(west + 180.0, width, max_col)..=index(east + 180.0, width, max_col) {
+                cells.push((row, col));
+            }
+        }
+    }
+    cells.sort_unstable();
+    cells.dedup();
+    cells
+}
+
+/// Prefixes of the longest geohashes that cover the circle in at most
+/// `MAX_NEARBY_CELLS` cells.
+fn covering_geohash_prefixes(lat: f64, lon: f64, radius: f64) -> Vec<String> {
+    let boxes = circle_bounding_boxes(lat, lon, radius);
+    let precision = (1..=GEOHASH_PRECISE_PRECISION)
+        .rev()
+        .find(|&precision| covering_cells(&boxes, precision).len() <= MAX_NEARBY_CELLS)
+        .unwrap_or(1);
+
+    let (width, height) = geohash_cell_size(precision);
+    covering_cells(&boxes, precision)
+        .into_iter()
+        .filter_map(|(row, col)| {
+            let center = geohash::Coord {
+                x: -180.0 + (col as f64 + 0.5) * width,
+                y: -90.0 + (row as f64 + 0.5) * height,
+            };
+            geohash::encode(center, precision).ok()
+        })
+        .collect()
+}
+
 struct MediaRowData {
     id: i64,
     filename: String,
@@ -53,9 +189,14 @@ struct MediaRowData {
     location_state: Option<String>,
     location_country: Option<String>,
     video_codec: Option<String>,
+    audio_codec: Option<String>,
+    audio_channels: Option<i32>,
+    audio_sample_rate: Option<i32>,
     keywords: Option<String>,
     content_hash: Option<String>,
     created_at: String,
+    has_date_mismatch: bool,
+    is_favorite: bool,
 }
 
 fn map_media_row(row: &rusqlite::Row) -> rusqlite::Result<MediaResponse> {
@@ -86,9 +227,14 @@ fn map_media_row(row: &rusqlite::Row) -> rusqlite::Result<MediaResponse> {
         location_state: row.get(23)?,
         location_country: row.get(24)?,
         video_codec: row.get(25)?,
-        keywords: row.get(26)?,
-        content_hash: row.get(27)?,
-        created_at: row.get(28)?,
+        audio_codec: row.get(26)?,
+        audio_channels: row.get(27)?,
+        audio_sample_rate: row.get(28)?,
+        keywords: row.get(29)?,
+        content_hash: row.get(30)?,
+        created_at: row.get(31)?,
+        has_date_mismatch: row.get(32)?,
+        is_favorite: row.get(33)?,
     };
 
     Ok(MediaResponse {
@@ -118,17 +264,36 @@ fn map_media_row(row: &rusqlite::Row) -> rusqlite::Result<MediaResponse> {
         location_state: media_row.location_state,
         location_country: media_row.location_country,
         video_codec: media_row.video_codec,
+        audio_codec: media_row.audio_codec,
+        audio_channels: media_row.audio_channels,
+        audio_sample_rate: media_row.audio_sample_rate,
         keywords: media_row.keywords,
         content_hash: media_row.content_hash,
         created_at: media_row.created_at,
+        has_date_mismatch: media_row.has_date_mismatch,
+        is_favorite: media_row.is_favorite,
     })
 }
 
+#[utoipa::path(
+    post,
+    path = "/map/clusters",
+    tag = "map",
+    request_body = MapClustersRequest,
+    responses((status = 200, description = "OK", body = MapClustersResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_clusters(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Json(req): Json<MapClustersRequest>,
 ) -> AppResult<Json<MapClustersResponse>> {
+    let cache_key = (current_user.id, bounds_hash(&req.bounds), req.zoom);
+    let (version, cached) = state.cluster_cache.lookup(&cache_key);
+    if let Some(response) = cached {
+        return Ok(Json(response));
+    }
+
     let conn = state.pool.get().map_err(AppError::Pool)?;
     let precision = zoom_to_geohash_precision(req.zoom);
     let longitude_clause = if req.bounds.west <= req.bounds.east {
@@ -157,6 +322,75 @@ async fn get_clusters(
         })
     })?;
 
+    let total_count: i64 = clusters.iter().map(|c| c.count).sum();
+    let response = MapClustersResponse {
+        clusters,
+        total_count,
+    };
+    state
+        .cluster_cache
+        .store(cache_key, version, response.clone());
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/map/lens-filter",
+    tag = "map",
+    request_body = MapLensFilterRequest,
+    responses((status = 200, description = "OK", body = MapClustersResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_lens_clusters(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<MapLensFilterRequest>,
+) -> AppResult<Json<MapClustersResponse>> {
+    let lens_model = req.lens_model.trim();
+    if lens_model.is_empty() {
+        return Ok(Json(MapClustersResponse {
+            clusters: Vec::new(),
+            total_count: 0,
+        }));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let zoom = req
+        .zoom
+        .unwrap_or_else(|| estimate_zoom_from_bounds(&req.bounds));
+    let precision = zoom_to_geohash_precision(zoom);
+    let longitude_clause = if req.bounds.west <= req.bounds.east {
+        queries::map::LONGITUDE_CLAUSE_STANDARD
+    } else {
+        queries::map::LONGITUDE_CLAUSE_ANTIMERIDIAN
+    };
+
+    let query = queries::map::build_filtered_clusters_query(
+        precision,
+        longitude_clause,
+        queries::map::LENS_MODEL_CLAUSE,
+    );
+
+    let params: Vec<&dyn rusqlite::ToSql> = vec![
+        &current_user.id,
+        &req.bounds.south,
+        &req.bounds.north,
+        &req.bounds.west,
+        &req.bounds.east,
+        &lens_model,
+    ];
+
+    let clusters = fetch_all(&conn, &query, &params, |row| {
+        Ok(Cluster {
+            id: row.get(0)?,
+            count: row.get(1)?,
+            lat: row.get(2)?,
+            lng: row.get(3)?,
+            representative_id: row.get(4)?,
+        })
+    })?;
+
     let total_count: i64 = clusters.iter().map(|c| c.count).sum();
 
     Ok(Json(MapClustersResponse {
@@ -165,6 +399,14 @@ async fn get_clusters(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/map/media",
+    tag = "map",
+    request_body = MapMediaRequest,
+    responses((status = 200, description = "OK", body = MapMediaListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_media(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -195,3 +437,121 @@ async fn get_media(
 
     Ok(Json(MapMediaListResponse { items }))
 }
+
+/// Media within `radiusMeters` of a point, nearest first and at most
+/// `MAX_NEARBY_RESULTS` items. Candidates come from the precise geohash
+/// cells around the point and are then filtered by great-circle distance.
+#[utoipa::path(
+    post,
+    path = "/map/nearby",
+    tag = "map",
+    request_body = MapNearbyRequest,
+    responses((status = 200, description = "OK", body = MapMediaListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_nearby_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<MapNearbyRequest>,
+) -> AppResult<Json<MapMediaListResponse>> {
+    if !(-90.0..=90.0).contains(&req.latitude) || !(-180.0..=180.0).contains(&req.longitude) {
+        return Err(AppError::BadRequest("Invalid coordinates".to_string()));
+    }
+    if !(req.radius_meters > 0.0 && req.radius_meters <= MAX_NEARBY_RADIUS_METERS) {
+        return Err(AppError::BadRequest(format!(
+            "radiusMeters must be greater than 0 and at most {}",
+            MAX_NEARBY_RADIUS_METERS
+        )));
+    }
+
+    let prefixes = covering_geohash_prefixes(req.latitude, req.longitude, req.radius_meters);
+    if prefixes.is_empty() {
+        return Ok(Json(MapMediaListResponse { items: Vec::new() }));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let query = queries::map::build_nearby_query(prefixes.len());
+    let lon_scale = req.latitude.to_radians().cos();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![
+        Box::new(req.latitude),
+        Box::new(req.longitude),
+        Box::new(current_user.id),
+    ];
+    for prefix in &prefixes {
+        params.push(Box::new(format!("{}%", prefix)));
+    }
+    params.push(Box::new(lon_scale));
+    params.push(Box::new(lon_scale));
+    params.push(Box::new(MAX_NEARBY_RESULTS));
+
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    let mut items: Vec<(f64, MediaResponse)> =
+        fetch_all(&conn, &query, &param_refs, map_media_row)?
+            .into_iter()
+            .filter_map(|media| {
+                let distance = haversine_meters(
+                    req.latitude,
+                    req.longitude,
+                    media.gps_latitude?,
+                    media.gps_longitude?,
+                );
+                (distance <= req.radius_meters).then_some((distance, media))
+            })
+            .collect();
+    items.sort_by(|a, b| a.0.total_cmp(&b.0).then(b.1.id.cmp(&a.1.id)));
+
+    Ok(Json(MapMediaListResponse {
+        items: items.into_iter().map(|(_, media)| media).collect(),
+    }))
+}
+
+/// Media inside the cell of a geohash prefix, newest first and at most
+/// `MAX_GEOHASH_SEARCH_RESULTS` items, together with the cell's bounding box.
+#[utoipa::path(
+    post,
+    path = "/map/geohash-search",
+    tag = "map",
+    request_body = MapGeohashSearchRequest,
+    responses((status = 200, description = "OK", body = MapGeohashSearchResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn search_geohash(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(req): Json<MapGeohashSearchRequest>,
+) -> AppResult<Json<MapGeohashSearchResponse>> {
+    let geohash = req.query.trim().to_lowercase();
+    if !is_geohash_prefix(&geohash) {
+        return Err(AppError::BadRequest(format!(
+            "query must be a geohash of {} to {} characters",
+            MIN_GEOHASH_SEARCH_LENGTH, GEOHASH_PRECISE_PRECISION
+        )));
+    }
+
+    let cell = geohash::decode_bbox(&geohash)
+        .map_err(|e| AppError::BadRequest(format!("Invalid geohash: {}", e)))?;
+    let bounds = BoundingBox {
+        north: cell.max().y,
+        south: cell.min().y,
+        east: cell.max().x,
+        west: cell.min().x,
+    };
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let items = fetch_all(
+        &conn,
+        queries::map::SELECT_BY_GEOHASH_PREFIX,
+        &[
+            &current_user.id,
+            &format!("{}%", geohash),
+            &MAX_GEOHASH_SEARCH_RESULTS,
+        ],
+        map_media_row,
+    )?;
+
+    Ok(Json(MapGeohashSearchResponse {
+        geohash,
+        bounds,
+        items,
+    }))
+}
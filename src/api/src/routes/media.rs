@@ -1,48 +1,136 @@
 use axum::{
     body::Body,
-    extract::{Path, State},
-    http::{header, HeaderMap, StatusCode},
-    response::Response,
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
     Json, Router,
 };
-use chrono::{Datelike, NaiveDateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
 use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use rusqlite::OptionalExtension;
+use serde::Deserialize;
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
-use tokio_util::io::ReaderStream;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::{ReaderStream, SyncIoBridge};
+use utoipa::IntoParams;
 
-use crate::auth::{AppState, CurrentUser};
-use crate::constants::{ORIGINALS_DIR, PREVIEWS_DIR, THUMBNAILS_DIR, THUMBNAILS_TINY_DIR};
-use crate::database::{execute_query, fetch_all, fetch_one, queries};
-use crate::error::{AppError, AppResult};
+use crate::auth::{
+    create_download_token, create_slideshow_sequence_id, verify_download_token, AppState,
+    CurrentUser, RequireAdmin,
+};
+use crate::config::{ThumbnailConfig, ThumbnailFormat};
+use crate::constants::{
+    DEFAULT_SIMILAR_MEDIA_THRESHOLD, DEFAULT_SMART_ALBUM_GEOHASH_PRECISION,
+    DEFAULT_SMART_ALBUM_WINDOW_DAYS, EXPORT_STREAM_BUFFER_BYTES, FOCAL_LENGTH_BUCKET_EDGES_MM,
+    GEOHASH_PRECISE_PRECISION, GEOHASH_PRECISION, LOCATION_FACET_LIMIT,
+    LOCATION_HEATMAP_GEOHASH_PRECISION, MAX_DOWNLOAD_TOKEN_SECONDS, MAX_LIST_PAGE_SIZE,
+    MAX_MEDIA_BATCH_SIZE, MAX_SIMILAR_MEDIA_THRESHOLD, MEDIA_EDIT_HISTORY_LIMIT, ORIGINALS_DIR,
+    PREVIEWS_DIR, THUMBNAILS_DIR, THUMBNAILS_TINY_DIR, UPLOAD_STAGING_DIR,
+};
+use crate::database::{execute_query, fetch_all, fetch_one, queries, DbConn};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    DeleteMediaResponse, MediaBatchRequest, MediaBatchResponse, MediaDeleteRequest,
-    MediaListRequest, MediaListResponse, MediaResponse, MediaUpdateRequest, PreviewBatchRequest,
-    PreviewBatchResponse, ThumbnailBatchRequest, ThumbnailBatchResponse, ThumbnailSize,
+    AlbumSuggestion, BulkDeleteMediaResponse, CameraCount, CameraGroup, DateBucket,
+    DeleteMediaResponse, DuplicateGroup, DuplicatesResponse, ExportFormat, FocalLengthBucket,
+    GeohashBucket, LensModelsResponse, LocationCount, LocationFacet, MediaBatchRequest,
+    MediaBatchResponse, MediaBulkDeleteRequest, MediaCheckDuplicateRequest,
+    MediaCheckDuplicateResponse, MediaDateCorrectionRequest, MediaDateCorrectionResponse,
+    MediaDeleteRequest, MediaDetectOrientationRequest, MediaDetectOrientationResponse,
+    MediaDownloadTokenRequest, MediaDownloadTokenResponse, MediaDuplicateRequest, MediaEditRecord,
+    MediaExportMetadataRequest, MediaFavoriteRequest, MediaImportUrlRequest,
+    MediaListByCameraRequest, MediaListRequest, MediaListResponse, MediaLocationsResponse,
+    MediaManualGeocodeRequest, MediaMetadataPatch, MediaRegenerateThumbnailRequest,
+    MediaReprocessRequest, MediaResponse, MediaRotateRequest, MediaSimilarRequest,
+    MediaSimilarResponse, MediaSlideshowRequest, MediaSlideshowResponse, MediaStatsResponse,
+    MediaStripLocationRequest, MediaTransferRequest, MediaTransferResponse, MediaUpdateRequest,
+    PreviewBatchRequest, PreviewBatchResponse, SimilarMedia, SmartAlbumSuggestRequest,
+    SmartAlbumSuggestResponse, SortDir, SortField, ThumbnailBatchRequest, ThumbnailBatchResponse,
+    ThumbnailSize, TimelineDatesResponse, UploadError, UploadResponse,
+};
+use crate::processor::media_processor::{
+    calculate_geohash, delete_from_rtree, delete_preview_files, fill_reverse_geocoded_location,
+    generate_thumbnails, insert_into_rtree, process_media_file, refresh_search_text,
+    reverse_geocode, rotate_jpeg, rotate_jpeg_to, MediaProcessingContext, ProcessingResult,
+};
+use crate::processor::metadata::{
+    extract_embedded_metadata, orientation_rotation_degrees, read_exif_orientation,
+    strip_exif_orientation, strip_gps_metadata,
 };
-use crate::processor::media_processor::{calculate_geohash, delete_from_rtree, insert_into_rtree};
-use crate::processor::thumbnails::generate_image_preview;
+use crate::processor::regenerator::{store_reprocessed_metadata, value_text};
+use crate::processor::thumbnails::{generate_image_preview, generate_image_thumbnail};
+use crate::utils::datetime::{format_datetime, parse_datetime};
+use crate::utils::download::download_media;
+use crate::utils::hash::{calculate_file_hash, perceptual_hash_distance};
+use crate::utils::shuffle::seeded_shuffle;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn router() -> Router<AppState> {
     Router::new()
+        .route("/media/import-url", post(import_media_from_url))
         .route("/media/list", post(list_media))
+        .route("/media/list-by-camera", post(list_media_by_camera))
+        .route("/media/export-metadata", post(export_media_metadata))
         .route("/media/get-batch", post(get_media_batch))
+        .route("/media/similar", post(get_similar_media))
+        .route(
+            "/media/upload",
+            post(upload_media).layer(DefaultBodyLimit::disable()),
+        )
         .route("/media/update", post(update_media))
+        .route("/media/date-correction", post(correct_media_dates))
+        .route("/media/:media_id/edit-history", get(get_media_edit_history))
+        .route("/media/update-metadata", post(update_media_metadata))
+        .route("/media/manual-geocode", post(manual_geocode_media))
+        .route("/media/strip-location", post(strip_media_location))
+        .route("/media/rotate", post(rotate_media))
+        .route("/media/detect-orientation", post(detect_media_orientation))
+        .route(
+            "/media/regenerate-thumbnail",
+            post(regenerate_media_thumbnail),
+        )
+        .route("/media/reprocess", post(reprocess_media))
         .route("/media/delete", post(delete_media))
+        .route("/media/bulk-delete", post(bulk_delete_media))
+        .route("/media/favorite", post(set_media_favorite))
+        .route("/media/duplicate", post(duplicate_media))
+        .route("/admin/media/transfer", post(transfer_media))
+        .route("/admin/duplicates", post(list_duplicates))
         .route("/media/file/:media_id", get(get_media_file))
+        .route("/media/exif/:media_id", get(get_media_exif))
+        .route("/media/download-token", post(create_media_download_token))
+        .route(
+            "/media/file/:media_id/download/:token",
+            get(download_media_file),
+        )
+        .route("/media/lens-models", get(get_lens_models))
+        .route("/media/cameras", get(get_cameras))
+        .route("/media/focal-lengths", get(get_focal_lengths))
+        .route("/timeline/dates", get(get_timeline_dates))
+        .route("/media/stats", get(get_media_stats))
+        .route("/media/locations", get(get_media_locations))
+        .route("/media/date-mismatches", get(list_date_mismatches))
+        .route("/media/check-duplicate", post(check_duplicate))
+        .route("/media/slideshow", post(get_slideshow))
+        .route("/media/smart-album/suggest", post(suggest_smart_albums))
 }
 
 pub fn thumbnail_router() -> Router<AppState> {
-    Router::new().route("/thumbnail/get", post(get_media_thumbnail_batch))
+    Router::new()
+        .route("/thumbnail/get", post(get_media_thumbnail_batch))
+        .route("/thumbnail/:media_id", get(get_media_thumbnail))
 }
 
 pub fn preview_router() -> Router<AppState> {
-    Router::new().route("/preview/get", post(get_media_preview_batch))
+    Router::new()
+        .route("/preview/get", post(get_media_preview_batch))
+        .route("/preview/:media_id", delete(delete_media_preview))
 }
 
 struct MediaRowData {
@@ -72,8 +160,13 @@ struct MediaRowData {
     location_state: Option<String>,
     location_country: Option<String>,
     video_codec: Option<String>,
+    audio_codec: Option<String>,
+    audio_channels: Option<i32>,
+    audio_sample_rate: Option<i32>,
     keywords: Option<String>,
     created_at: String,
+    has_date_mismatch: bool,
+    is_favorite: bool,
 }
 
 impl MediaRowData {
@@ -105,8 +198,13 @@ impl MediaRowData {
             location_state: row.get(23)?,
             location_country: row.get(24)?,
             video_codec: row.get(25)?,
-            keywords: row.get(26)?,
-            created_at: row.get(27)?,
+            audio_codec: row.get(26)?,
+            audio_channels: row.get(27)?,
+            audio_sample_rate: row.get(28)?,
+            keywords: row.get(29)?,
+            created_at: row.get(30)?,
+            has_date_mismatch: row.get(31)?,
+            is_favorite: row.get(32)?,
         })
     }
 }
@@ -139,8 +237,13 @@ fn row_to_media_response(row: MediaRowData) -> MediaResponse {
         location_state,
         location_country,
         video_codec,
+        audio_codec,
+        audio_channels,
+        audio_sample_rate,
         keywords,
         created_at,
+        has_date_mismatch,
+        is_favorite,
     } = row;
     MediaResponse {
         id,
@@ -169,28 +272,153 @@ fn row_to_media_response(row: MediaRowData) -> MediaResponse {
         location_state,
         location_country,
         video_codec,
+        audio_codec,
+        audio_channels,
+        audio_sample_rate,
         keywords,
         created_at,
+        has_date_mismatch,
+        is_favorite,
         content_hash: None,
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/media/list",
+    tag = "media",
+    request_body = MediaListRequest,
+    responses((status = 200, description = "OK", body = MediaListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn list_media(
     State(state): State<AppState>,
     current_user: CurrentUser,
     Json(request): Json<MediaListRequest>,
 ) -> AppResult<Json<MediaListResponse>> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
+    let Json(mut response) = fetch_media_page(&conn, current_user.id, &request)?;
+    if request.include_total {
+        response.total_count = count_media(&conn, current_user.id, &request)?;
+    }
+
+    Ok(Json(response))
+}
+
+/// Number of media matching the request's album and filters, ignoring the
+/// cursor and limit.
+fn count_media(
+    conn: &crate::database::DbConn,
+    user_id: i64,
+    request: &MediaListRequest,
+) -> AppResult<Option<i64>> {
+    let (filter_clauses, filter_params) = build_list_filters(request);
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(user_id)];
+    let query = match request.album_id {
+        Some(album_id) => {
+            params.push(Box::new(album_id));
+            queries::media::build_count_album_filtered(&filter_clauses)
+        }
+        None => queries::media::build_count_filtered(&filter_clauses),
+    };
+    params.extend(filter_params);
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    fetch_one(conn, &query, &param_refs, |row| row.get(0))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/list-by-camera",
+    tag = "media",
+    request_body = MediaListByCameraRequest,
+    responses((status = 200, description = "OK", body = MediaListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn list_media_by_camera(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaListByCameraRequest>,
+) -> AppResult<Json<MediaListResponse>> {
+    if request.model.is_empty() {
+        return Err(AppError::Validation("model is required".to_string()));
+    }
+
+    let list_request = MediaListRequest {
+        cursor: request.cursor,
+        limit: request.limit,
+        camera_make: Some(request.make),
+        camera_model: Some(request.model),
+        ..Default::default()
+    };
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let Json(mut response) = fetch_media_page(&conn, current_user.id, &list_request)?;
+    if request.include_total {
+        response.total_count = count_media(&conn, current_user.id, &list_request)?;
+    }
+
+    Ok(Json(response))
+}
+
+fn fetch_media_page(
+    conn: &crate::database::DbConn,
+    user_id: i64,
+    request: &MediaListRequest,
+) -> AppResult<Json<MediaListResponse>> {
+    if let Some(limit) = request.limit {
+        if !(1..=MAX_LIST_PAGE_SIZE).contains(&limit) {
+            return Err(AppError::Validation(format!(
+                "limit must be between 1 and {}",
+                MAX_LIST_PAGE_SIZE
+            )));
+        }
+    }
+
+    if request.sort_by.is_some() || request.sort_dir.is_some() {
+        if request.group_by.is_some() || request.album_id.is_some() {
+            return Err(AppError::Validation(
+                "sortBy and sortDir cannot be combined with groupBy or albumId".to_string(),
+            ));
+        }
+        return list_sorted_media(conn, user_id, request);
+    }
+
+    if let Some(album_id) = request.album_id {
+        return list_album_media(conn, user_id, album_id, request);
+    }
 
     if let Some(group_by) = request.group_by.as_deref() {
         let limit = request.limit.unwrap_or(100);
-        let mut rows = fetch_timeline_rows(&conn, current_user.id, limit, request.cursor.as_deref())?;
+        let (filter_clauses, filter_params) = build_list_filters(request);
+        let mut rows = if filter_clauses.is_empty() {
+            fetch_timeline_rows(conn, user_id, limit, request.cursor.as_deref())?
+        } else {
+            let cursor = request
+                .cursor
+                .as_deref()
+                .map(parse_date_cursor)
+                .transpose()?;
+            fetch_filtered_rows(
+                conn,
+                user_id,
+                &filter_clauses,
+                filter_params,
+                cursor,
+                limit + 1,
+            )?
+            .into_iter()
+            .map(|media| {
+                let date_taken = media.date_taken.clone();
+                (media, date_taken)
+            })
+            .collect()
+        };
 
-        if rows.is_empty() && request.cursor.is_none() {
+        if rows.is_empty() && request.cursor.is_none() && filter_clauses.is_empty() {
             let fallback_items = fetch_all(
-                &conn,
+                conn,
                 queries::media::SELECT_ALL_FOR_USER,
-                &[&current_user.id],
+                &[&user_id],
                 map_media_row,
             )?;
             rows = fallback_items
@@ -228,14 +456,20 @@ async fn list_media(
             next_cursor,
             has_more,
             groups: Some(groups),
+            total_count: None,
         }));
     }
 
+    let (filter_clauses, filter_params) = build_list_filters(request);
+    if !filter_clauses.is_empty() {
+        return list_filtered_media(conn, user_id, request, &filter_clauses, filter_params);
+    }
+
     if request.limit.is_none() && request.cursor.is_none() {
         let items = fetch_all(
-            &conn,
+            conn,
             queries::media::SELECT_ALL_FOR_USER,
-            &[&current_user.id],
+            &[&user_id],
             map_media_row,
         )?;
 
@@ -244,32 +478,27 @@ async fn list_media(
             next_cursor: None,
             has_more: false,
             groups: None,
+            total_count: None,
         }));
     }
 
     let limit = request.limit.unwrap_or(100);
     let rows = if let Some(ref cursor) = request.cursor {
-        let parts: Vec<&str> = cursor.split('_').collect();
-        if parts.len() == 2 {
-            let cursor_date = parts[0];
-            let cursor_id: i64 = parts[1].parse().unwrap_or(0);
-            fetch_all(
-                &conn,
-                queries::media::SELECT_PAGINATED_FOR_USER,
-                &[
-                    &current_user.id,
-                    &cursor_date,
-                    &cursor_date,
-                    &cursor_id,
-                    &(limit + 1),
-                ],
-                map_media_row,
-            )?
-        } else {
-            fetch_default_media(&conn, current_user.id, limit)?
-        }
+        let (cursor_date, cursor_id) = parse_date_cursor(cursor)?;
+        fetch_all(
+            conn,
+            queries::media::SELECT_PAGINATED_FOR_USER,
+            &[
+                &user_id,
+                &cursor_date,
+                &cursor_date,
+                &cursor_id,
+                &(limit + 1),
+            ],
+            map_media_row,
+        )?
     } else {
-        fetch_default_media(&conn, current_user.id, limit)?
+        fetch_default_media(conn, user_id, limit)?
     };
 
     let has_more = rows.len() > limit as usize;
@@ -289,9 +518,413 @@ async fn list_media(
         next_cursor,
         has_more,
         groups: None,
+        total_count: None,
+    }))
+}
+
+fn push_range_filter<T>(
+    column: &str,
+    min: Option<T>,
+    max: Option<T>,
+    clauses: &mut Vec<String>,
+    params: &mut Vec<Box<dyn rusqlite::ToSql>>,
+) where
+    T: rusqlite::ToSql + 'static,
+{
+    match (min, max) {
+        (Some(min), Some(max)) => {
+            clauses.push(format!("AND {} BETWEEN ? AND ?", column));
+            params.push(Box::new(min));
+            params.push(Box::new(max));
+        }
+        (Some(min), None) => {
+            clauses.push(format!("AND {} >= ?", column));
+            params.push(Box::new(min));
+        }
+        (None, Some(max)) => {
+            clauses.push(format!("AND {} <= ?", column));
+            params.push(Box::new(max));
+        }
+        (None, None) => {}
+    }
+}
+
+fn build_list_filters(request: &MediaListRequest) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut clauses = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    push_range_filter(
+        "mm.iso",
+        request.iso_min,
+        request.iso_max,
+        &mut clauses,
+        &mut params,
+    );
+    push_range_filter(
+        "mm.focal_length_35mm",
+        request.focal_length_35mm_min,
+        request.focal_length_35mm_max,
+        &mut clauses,
+        &mut params,
+    );
+    push_range_filter(
+        "m.created_at",
+        request.uploaded_after.clone(),
+        request.uploaded_before.clone(),
+        &mut clauses,
+        &mut params,
+    );
+
+    if let Some(lens_model) = request.lens_model.as_deref().map(str::trim) {
+        if !lens_model.is_empty() {
+            clauses.push("AND LOWER(mm.lens_model) = LOWER(?)".to_string());
+            params.push(Box::new(lens_model.to_string()));
+        }
+    }
+
+    if let Some(pattern) = request.filename_pattern.as_deref().map(str::trim) {
+        if !pattern.is_empty() {
+            let (clause, value) = filename_pattern_filter(pattern);
+            clauses.push(clause.to_string());
+            params.push(Box::new(value.clone()));
+            params.push(Box::new(value));
+        }
+    }
+
+    if let Some(make) = &request.camera_make {
+        clauses.push("AND COALESCE(mm.camera_make, '') = ?".to_string());
+        params.push(Box::new(make.clone()));
+    }
+
+    if let Some(model) = &request.camera_model {
+        clauses.push("AND mm.camera_model = ?".to_string());
+        params.push(Box::new(model.clone()));
+    }
+
+    if request.favorites_only == Some(true) {
+        clauses.push("AND ma.is_favorite = 1".to_string());
+    }
+
+    (clauses.join("\n               "), params)
+}
+
+/// `IMG_2024*.jpg` style patterns use `GLOB`; anything else is a
+/// case-insensitive substring match with `LIKE` wildcards escaped.
+fn filename_pattern_filter(pattern: &str) -> (&'static str, String) {
+    if pattern.contains(['*', '?']) {
+        return (
+            "AND (m.filename GLOB ? OR m.original_filename GLOB ?)",
+            pattern.to_string(),
+        );
+    }
+
+    let escaped = pattern
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    (
+        "AND (m.filename LIKE ? ESCAPE '\\' OR m.original_filename LIKE ? ESCAPE '\\')",
+        format!("%{}%", escaped),
+    )
+}
+
+fn list_filtered_media(
+    conn: &crate::database::DbConn,
+    user_id: i64,
+    request: &MediaListRequest,
+    filter_clauses: &str,
+    filter_params: Vec<Box<dyn rusqlite::ToSql>>,
+) -> AppResult<Json<MediaListResponse>> {
+    let cursor = request
+        .cursor
+        .as_deref()
+        .map(parse_date_cursor)
+        .transpose()?;
+
+    // SQLite treats a negative LIMIT as unbounded
+    let limit = request.limit;
+    let query_limit = limit.map(|l| l + 1).unwrap_or(-1);
+    let rows = fetch_filtered_rows(
+        conn,
+        user_id,
+        filter_clauses,
+        filter_params,
+        cursor,
+        query_limit,
+    )?;
+
+    let (items, has_more) = match limit {
+        Some(limit) => {
+            let has_more = rows.len() > limit as usize;
+            (
+                rows.into_iter().take(limit as usize).collect::<Vec<_>>(),
+                has_more,
+            )
+        }
+        None => (rows, false),
+    };
+
+    let next_cursor = if has_more {
+        items.last().and_then(|last| {
+            last.date_taken
+                .as_ref()
+                .map(|dt| format!("{}_{}", dt, last.id))
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(MediaListResponse {
+        items,
+        next_cursor,
+        has_more,
+        groups: None,
+        total_count: None,
+    }))
+}
+
+/// Splits a `{date_taken}_{id}` list cursor.
+fn parse_date_cursor(cursor: &str) -> AppResult<(String, i64)> {
+    cursor
+        .rsplit_once('_')
+        .and_then(|(date, id)| Some((date.to_string(), id.parse().ok()?)))
+        .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+}
+
+/// Filtered library rows in `date_taken` order, after `cursor` when given.
+fn fetch_filtered_rows(
+    conn: &crate::database::DbConn,
+    user_id: i64,
+    filter_clauses: &str,
+    filter_params: Vec<Box<dyn rusqlite::ToSql>>,
+    cursor: Option<(String, i64)>,
+    query_limit: i32,
+) -> AppResult<Vec<MediaResponse>> {
+    let query = queries::media::build_select_filtered(filter_clauses, cursor.is_some());
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(filter_params.len() + 5);
+    params.push(Box::new(user_id));
+    params.extend(filter_params);
+    if let Some((cursor_date, cursor_id)) = cursor {
+        params.push(Box::new(cursor_date.clone()));
+        params.push(Box::new(cursor_date));
+        params.push(Box::new(cursor_id));
+    }
+    params.push(Box::new(query_limit));
+
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    fetch_all(conn, &query, &param_refs, map_media_row)
+}
+
+/// Expression a listing sorted by `field` orders on. Empty text counts as
+/// missing, so an empty cursor value can stand for "no value".
+fn sort_expression(field: SortField) -> &'static str {
+    match field {
+        SortField::DateTaken => "NULLIF(mm.date_taken, '')",
+        SortField::FileSize => "m.file_size",
+        SortField::Duration => "mm.duration_seconds",
+        SortField::CameraModel => "NULLIF(mm.camera_model, '')",
+        SortField::OriginalFilename => "NULLIF(m.original_filename, '')",
+    }
+}
+
+/// `media`'s value for `field` as written into a cursor, `None` when it
+/// has none.
+fn sort_cursor_value(field: SortField, media: &MediaResponse) -> Option<String> {
+    match field {
+        SortField::DateTaken => media.date_taken.clone(),
+        SortField::FileSize => media.file_size.map(|size| size.to_string()),
+        SortField::Duration => media.duration_seconds.map(|seconds| seconds.to_string()),
+        SortField::CameraModel => media.camera_model.clone(),
+        SortField::OriginalFilename => Some(media.original_filename.clone()),
+    }
+    .filter(|value| !value.is_empty())
+}
+
+/// Binds a cursor value with the column's type, since the sort expressions
+/// carry no affinity to convert text for numeric comparisons.
+fn sort_cursor_param(field: SortField, value: &str) -> Option<Box<dyn rusqlite::ToSql>> {
+    match field {
+        SortField::FileSize => value
+            .parse::<i64>()
+            .ok()
+            .map(|size| Box::new(size) as Box<dyn rusqlite::ToSql>),
+        SortField::Duration => value
+            .parse::<f64>()
+            .ok()
+            .map(|seconds| Box::new(seconds) as Box<dyn rusqlite::ToSql>),
+        _ => Some(Box::new(value.to_string())),
+    }
+}
+
+/// Listing in a caller-chosen order with the usual filters. The cursor is
+/// `{value}_{id}` of the last item, with an empty value for media that have
+/// none; those sort after everything else in either direction.
+fn list_sorted_media(
+    conn: &crate::database::DbConn,
+    user_id: i64,
+    request: &MediaListRequest,
+) -> AppResult<Json<MediaListResponse>> {
+    let field = request.sort_by.unwrap_or_default();
+    let (comparison, direction) = match request.sort_dir.unwrap_or_default() {
+        SortDir::Asc => (">", "ASC"),
+        SortDir::Desc => ("<", "DESC"),
+    };
+    let expr = sort_expression(field);
+
+    let cursor = request
+        .cursor
+        .as_deref()
+        .map(|cursor| {
+            let (value, id) = cursor.rsplit_once('_')?;
+            let id = id.parse::<i64>().ok()?;
+            if value.is_empty() {
+                return Some((None, id));
+            }
+            sort_cursor_param(field, value)?;
+            Some((Some(value), id))
+        })
+        .map(|cursor| cursor.ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string())))
+        .transpose()?;
+
+    let (filter_clauses, filter_params) = build_list_filters(request);
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(filter_params.len() + 5);
+    params.push(Box::new(user_id));
+    params.extend(filter_params);
+
+    let cursor_clause = match cursor {
+        Some((Some(value), id)) => {
+            params.extend(sort_cursor_param(field, value));
+            params.extend(sort_cursor_param(field, value));
+            params.push(Box::new(id));
+            format!(
+                "AND ({expr} {comparison} ? OR ({expr} = ? AND m.id {comparison} ?) OR {expr} IS NULL)"
+            )
+        }
+        Some((None, id)) => {
+            params.push(Box::new(id));
+            format!("AND {expr} IS NULL AND m.id {comparison} ?")
+        }
+        None => String::new(),
+    };
+    let order_by = format!("{expr} IS NULL, {expr} {direction}, m.id {direction}");
+
+    // SQLite treats a negative LIMIT as unbounded
+    let limit = request.limit;
+    params.push(Box::new(limit.map(|l| l + 1).unwrap_or(-1)));
+
+    let query =
+        queries::media::build_select_filtered_ordered(&filter_clauses, &cursor_clause, &order_by);
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    let rows = fetch_all(conn, &query, &param_refs, map_media_row)?;
+
+    let has_more = limit.is_some_and(|limit| rows.len() > limit as usize);
+    let items: Vec<MediaResponse> = match limit {
+        Some(limit) => rows.into_iter().take(limit as usize).collect(),
+        None => rows,
+    };
+
+    let next_cursor = if has_more {
+        items.last().map(|last| {
+            format!(
+                "{}_{}",
+                sort_cursor_value(field, last).unwrap_or_default(),
+                last.id
+            )
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(MediaListResponse {
+        items,
+        next_cursor,
+        has_more,
+        groups: None,
+        total_count: None,
+    }))
+}
+
+/// Album contents in album order. The cursor is `{position}_{id}` rather
+/// than the date-based cursor of the library listing.
+fn list_album_media(
+    conn: &crate::database::DbConn,
+    user_id: i64,
+    album_id: i64,
+    request: &MediaListRequest,
+) -> AppResult<Json<MediaListResponse>> {
+    fetch_one(
+        conn,
+        queries::albums::CHECK_OWNERSHIP,
+        &[&album_id, &user_id],
+        |row| row.get::<_, i64>(0),
+    )?
+    .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
+
+    let cursor = request
+        .cursor
+        .as_deref()
+        .map(|cursor| {
+            cursor
+                .split_once('_')
+                .and_then(|(position, id)| {
+                    Some((position.parse::<i64>().ok()?, id.parse::<i64>().ok()?))
+                })
+                .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+        })
+        .transpose()?;
+
+    // SQLite treats a negative LIMIT as unbounded
+    let limit = request.limit;
+    let query_limit = limit.map(|l| l + 1).unwrap_or(-1);
+
+    let (filter_clauses, filter_params) = build_list_filters(request);
+    let query = queries::media::build_select_album_filtered(&filter_clauses, cursor.is_some());
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(filter_params.len() + 6);
+    params.push(Box::new(user_id));
+    params.push(Box::new(album_id));
+    params.extend(filter_params);
+    if let Some((position, id)) = cursor {
+        params.push(Box::new(position));
+        params.push(Box::new(position));
+        params.push(Box::new(id));
+    }
+    params.push(Box::new(query_limit));
+
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    let rows = fetch_all(conn, &query, &param_refs, |row| {
+        Ok((map_media_row(row)?, row.get::<_, Option<i64>>(33)?))
+    })?;
+
+    let has_more = limit.is_some_and(|limit| rows.len() > limit as usize);
+    let rows: Vec<_> = match limit {
+        Some(limit) => rows.into_iter().take(limit as usize).collect(),
+        None => rows,
+    };
+
+    let next_cursor = if has_more {
+        rows.last()
+            .map(|(last, position)| format!("{}_{}", position.unwrap_or(0), last.id))
+    } else {
+        None
+    };
+
+    Ok(Json(MediaListResponse {
+        items: rows.into_iter().map(|(media, _)| media).collect(),
+        next_cursor,
+        has_more,
+        groups: None,
+        total_count: None,
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/media/get-batch",
+    tag = "media",
+    request_body = MediaBatchRequest,
+    responses((status = 200, description = "OK", body = MediaBatchResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_media_batch(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -300,6 +933,12 @@ async fn get_media_batch(
     if request.ids.is_empty() {
         return Ok(Json(MediaBatchResponse { items: Vec::new() }));
     }
+    if request.ids.len() > MAX_MEDIA_BATCH_SIZE {
+        return Err(AppError::BadRequest(format!(
+            "At most {} media ids can be fetched at once",
+            MAX_MEDIA_BATCH_SIZE
+        )));
+    }
 
     let conn = state.pool.get().map_err(AppError::Pool)?;
     let query = queries::media::build_select_by_ids(request.ids.len());
@@ -309,143 +948,2405 @@ async fn get_media_batch(
         params.push(Box::new(*media_id));
     }
 
-    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
-    let items = fetch_all(&conn, &query, &param_refs, map_media_row)?;
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    let items = fetch_all(&conn, &query, &param_refs, map_media_row)?;
+
+    let mut by_id = std::collections::HashMap::new();
+    for item in items {
+        by_id.insert(item.id, item);
+    }
+
+    let ordered_items = request
+        .ids
+        .iter()
+        .filter_map(|media_id| by_id.get(media_id).cloned())
+        .collect();
+
+    Ok(Json(MediaBatchResponse {
+        items: ordered_items,
+    }))
+}
+
+/// Streams each multipart file part to a staging file and imports it.
+/// A failed part is reported in `failed` without aborting the rest.
+#[utoipa::path(
+    post,
+    path = "/media/upload",
+    tag = "media",
+    request_body(content_type = "multipart/form-data", description = "One or more `file` parts"),
+    responses((status = 200, description = "OK", body = UploadResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn upload_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    mut multipart: Multipart,
+) -> AppResult<Json<UploadResponse>> {
+    let processing = MediaProcessingContext {
+        user_id: current_user.id,
+        thumbnails: state.config.thumbnails.clone(),
+        reverse_geocoding: Some(state.config.reverse_geocoding.clone()),
+        max_file_size_bytes: state.config.import.max_file_size_bytes,
+        pool: state.pool.clone(),
+        post_processors: state.config.post_processors.clone(),
+        cluster_cache: Some(Arc::clone(&state.cluster_cache)),
+    };
+
+    let mut uploaded = Vec::new();
+    let mut failed = Vec::new();
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
+    {
+        let Some(filename) = field
+            .file_name()
+            .and_then(|name| std::path::Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+
+        let staging_dir = UPLOAD_STAGING_DIR.join(uuid::Uuid::new_v4().to_string());
+        let staged_path = staging_dir.join(&filename);
+        let staged = stage_upload(
+            &mut field,
+            &staging_dir,
+            &staged_path,
+            processing.max_file_size_bytes,
+        )
+        .await;
+
+        let result = match staged {
+            Ok(()) => process_media_file(&staged_path, &processing).await,
+            Err(reason) => ProcessingResult::Failed { reason },
+        };
+        let _ = tokio::fs::remove_dir_all(&staging_dir).await;
+
+        let media_id = match result {
+            ProcessingResult::NewMedia { media_id }
+            | ProcessingResult::DuplicateAccessGranted { media_id }
+            | ProcessingResult::AlreadyOwned { media_id } => media_id,
+            ProcessingResult::Failed { reason } => {
+                failed.push(UploadError { filename, reason });
+                continue;
+            }
+        };
+
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        let media = fetch_one(
+            &conn,
+            queries::media::SELECT_BY_ID_AND_USER,
+            &[&media_id, &current_user.id],
+            map_media_row,
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?;
+        uploaded.push(media);
+    }
+
+    Ok(Json(UploadResponse { uploaded, failed }))
+}
+
+/// Streams one CSV row per media item the caller can see. Rows are written
+/// from a blocking task into a bounded pipe as the query yields them, so
+/// the library is never held in memory.
+#[utoipa::path(
+    post,
+    path = "/media/export-metadata",
+    tag = "media",
+    request_body = MediaExportMetadataRequest,
+    responses((status = 200, description = "CSV with one row per media item", content_type = "text/csv"), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn export_media_metadata(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaExportMetadataRequest>,
+) -> AppResult<Response> {
+    let ExportFormat::Csv = request.format;
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let (reader, writer) = tokio::io::duplex(EXPORT_STREAM_BUFFER_BYTES);
+    let output = SyncIoBridge::new(tokio::io::BufWriter::new(writer));
+    let user_id = current_user.id;
+
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = write_metadata_csv(&conn, user_id, output) {
+            tracing::warn!("Metadata export for user {} stopped: {}", user_id, e);
+        }
+    });
+
+    let filename = format!("momento_export_{}.csv", Utc::now().format("%Y-%m-%d"));
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/csv; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(Body::from_stream(ReaderStream::new(reader)))
+        .map_err(|e| AppError::Internal(e.to_string()))
+}
+
+fn write_metadata_csv(conn: &DbConn, user_id: i64, output: impl std::io::Write) -> AppResult<()> {
+    let mut writer = csv::Writer::from_writer(output);
+    let mut stmt = conn.prepare(queries::media::SELECT_ALL_FOR_USER)?;
+    let mut rows = stmt.query([user_id])?;
+
+    while let Some(row) = rows.next()? {
+        writer
+            .serialize(map_media_row(row)?)
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Downloads a public `https://` image or video and imports it like an
+/// upload.
+#[utoipa::path(
+    post,
+    path = "/media/import-url",
+    tag = "media",
+    request_body = MediaImportUrlRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn import_media_from_url(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaImportUrlRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    let processing = MediaProcessingContext {
+        user_id: current_user.id,
+        thumbnails: state.config.thumbnails.clone(),
+        reverse_geocoding: Some(state.config.reverse_geocoding.clone()),
+        max_file_size_bytes: state.config.import.max_file_size_bytes,
+        pool: state.pool.clone(),
+        post_processors: state.config.post_processors.clone(),
+        cluster_cache: Some(Arc::clone(&state.cluster_cache)),
+    };
+
+    let staging_dir = UPLOAD_STAGING_DIR.join(uuid::Uuid::new_v4().to_string());
+    let downloaded = download_media(
+        &request.url,
+        request.filename.as_deref(),
+        &staging_dir,
+        state.config.import.max_download_size_bytes,
+    )
+    .await;
+
+    let result = match downloaded {
+        Ok(path) => Ok(process_media_file(&path, &processing).await),
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_dir_all(&staging_dir).await;
+
+    let media_id = match result? {
+        ProcessingResult::NewMedia { media_id }
+        | ProcessingResult::DuplicateAccessGranted { media_id }
+        | ProcessingResult::AlreadyOwned { media_id } => media_id,
+        ProcessingResult::Failed { reason } => return Err(AppError::Unprocessable(reason)),
+    };
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+async fn stage_upload(
+    field: &mut axum::extract::multipart::Field<'_>,
+    staging_dir: &std::path::Path,
+    staged_path: &std::path::Path,
+    max_file_size_bytes: Option<u64>,
+) -> Result<(), String> {
+    tokio::fs::create_dir_all(staging_dir)
+        .await
+        .map_err(|e| format!("failed to create staging directory: {}", e))?;
+    let mut file = File::create(staged_path)
+        .await
+        .map_err(|e| format!("failed to create staging file: {}", e))?;
+
+    let mut written: u64 = 0;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| format!("failed to read upload: {}", e))?
+    {
+        written += chunk.len() as u64;
+        if max_file_size_bytes.is_some_and(|max| written > max) {
+            return Err("File too large".to_string());
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("failed to write upload: {}", e))?;
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| format!("failed to write upload: {}", e))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/similar",
+    tag = "media",
+    request_body = MediaSimilarRequest,
+    responses((status = 200, description = "OK", body = MediaSimilarResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_similar_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaSimilarRequest>,
+) -> AppResult<Json<MediaSimilarResponse>> {
+    let threshold = request.threshold.unwrap_or(DEFAULT_SIMILAR_MEDIA_THRESHOLD);
+    if threshold > MAX_SIMILAR_MEDIA_THRESHOLD {
+        return Err(AppError::Validation(format!(
+            "threshold must be between 0 and {}",
+            MAX_SIMILAR_MEDIA_THRESHOLD
+        )));
+    }
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let phash: Option<i64> = fetch_one(
+        &conn,
+        queries::media::SELECT_PHASH,
+        &[&request.media_id, &current_user.id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    let Some(phash) = phash else {
+        return Ok(Json(MediaSimilarResponse { items: Vec::new() }));
+    };
+
+    let candidates = fetch_all(
+        &conn,
+        queries::media::SELECT_PHASH_CANDIDATES,
+        &[&current_user.id, &request.media_id],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+    )?;
+
+    let mut matches: Vec<(i64, u32)> = candidates
+        .into_iter()
+        .map(|(media_id, other)| (media_id, perceptual_hash_distance(phash, other)))
+        .filter(|(_, distance)| *distance <= threshold)
+        .collect();
+    matches.sort_by_key(|(media_id, distance)| (*distance, *media_id));
+
+    if matches.is_empty() {
+        return Ok(Json(MediaSimilarResponse { items: Vec::new() }));
+    }
+
+    let query = queries::media::build_select_by_ids(matches.len());
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(matches.len() + 1);
+    params.push(Box::new(current_user.id));
+    for (media_id, _) in &matches {
+        params.push(Box::new(*media_id));
+    }
+
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|param| param.as_ref()).collect();
+    let mut by_id: std::collections::HashMap<i64, MediaResponse> =
+        fetch_all(&conn, &query, &param_refs, map_media_row)?
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+
+    let items = matches
+        .into_iter()
+        .filter_map(|(media_id, distance)| {
+            by_id
+                .remove(&media_id)
+                .map(|media| SimilarMedia { media, distance })
+        })
+        .collect();
+
+    Ok(Json(MediaSimilarResponse { items }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/favorite",
+    tag = "media",
+    request_body = MediaFavoriteRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn set_media_favorite(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaFavoriteRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let updated = execute_query(
+        &conn,
+        queries::access::SET_FAVORITE,
+        &[&request.is_favorite, &request.media_id, &current_user.id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::MediaNotFound);
+    }
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/update-metadata",
+    tag = "media",
+    request_body = MediaMetadataPatch,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn update_media_metadata(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(patch): Json<MediaMetadataPatch>,
+) -> AppResult<Json<MediaResponse>> {
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let exists = fetch_one(
+        &conn,
+        queries::media::CHECK_EXISTS,
+        &[&patch.media_id, &current_user.id],
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    if exists.is_none() {
+        return Err(AppError::MediaNotFound);
+    }
+
+    let mut columns: Vec<&str> = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(patch.media_id)];
+    let mut set = |column: &'static str, value: Option<Box<dyn rusqlite::ToSql>>| {
+        if let Some(value) = value {
+            columns.push(column);
+            params.push(value);
+        }
+    };
+    set("camera_make", boxed(patch.camera_make));
+    set("camera_model", boxed(patch.camera_model));
+    set("lens_make", boxed(patch.lens_make));
+    set("lens_model", boxed(patch.lens_model));
+    set("iso", boxed(patch.iso));
+    set("f_number", boxed(patch.f_number));
+    set("exposure_time", boxed(patch.exposure_time));
+    set("focal_length", boxed(patch.focal_length));
+    set("keywords", boxed(patch.keywords));
+
+    // GPS is not patchable here, so geohash and the R-tree stay valid
+    if !columns.is_empty() {
+        let param_refs: Vec<&dyn rusqlite::ToSql> =
+            params.iter().map(|param| param.as_ref()).collect();
+        let select_columns = queries::media::build_select_metadata_columns(&columns);
+        let read_columns = |conn: &rusqlite::Connection| {
+            conn.query_row(&select_columns, [patch.media_id], |row| {
+                (0..columns.len())
+                    .map(|i| row.get::<_, rusqlite::types::Value>(i).map(value_text))
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .optional()
+            .map(|values| values.unwrap_or_else(|| vec![None; columns.len()]))
+        };
+
+        let tx = conn.transaction()?;
+        let previous = read_columns(&tx)?;
+        tx.execute(
+            &queries::media::build_update_metadata_columns(&columns),
+            param_refs.as_slice(),
+        )?;
+        let current = read_columns(&tx)?;
+        refresh_search_text(&tx, patch.media_id).map_err(AppError::Database)?;
+
+        let edits: Vec<_> = columns
+            .iter()
+            .zip(previous.into_iter().zip(current))
+            .map(|(column, (old, new))| (*column, old, new))
+            .collect();
+        record_media_edits(&tx, patch.media_id, current_user.id, &edits)?;
+        tx.commit()?;
+    }
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&patch.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+fn boxed<T: rusqlite::ToSql + 'static>(
+    value: Option<Option<T>>,
+) -> Option<Box<dyn rusqlite::ToSql>> {
+    value.map(|value| Box::new(value) as Box<dyn rusqlite::ToSql>)
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/update",
+    tag = "media",
+    request_body = MediaUpdateRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn update_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaUpdateRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    let date_taken = match request.date_taken.as_deref() {
+        Some(value) => Some(format_datetime(&parse_datetime(value).ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Invalid dateTaken '{}': expected RFC 3339 (e.g. 2023-06-15T14:30:00+05:30) or YYYY:MM:DD HH:MM:SS",
+                value
+            ))
+        })?)),
+        None => None,
+    };
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let exists = fetch_one(
+        &conn,
+        queries::media::CHECK_EXISTS,
+        &[&request.media_id, &current_user.id],
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    if exists.is_none() {
+        return Err(AppError::MediaNotFound);
+    }
+
+    let gps_changed = request.gps_latitude.is_some() || request.gps_longitude.is_some();
+    let read_editable = |conn: &rusqlite::Connection| {
+        conn.query_row(
+            queries::edits::SELECT_EDITABLE_VALUES,
+            [request.media_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<f64>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                ))
+            },
+        )
+        .optional()
+        .map(Option::unwrap_or_default)
+    };
+
+    let tx = conn.transaction()?;
+    let (old_date_taken, old_latitude, old_longitude) = read_editable(&tx)?;
+
+    if date_taken.is_some() || gps_changed {
+        tx.execute(
+            r#"
+            INSERT INTO media_metadata (media_id, date_taken, gps_latitude, gps_longitude)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(media_id) DO UPDATE SET
+                date_taken = COALESCE(excluded.date_taken, media_metadata.date_taken),
+                gps_latitude = COALESCE(excluded.gps_latitude, media_metadata.gps_latitude),
+                gps_longitude = COALESCE(excluded.gps_longitude, media_metadata.gps_longitude)
+            "#,
+            rusqlite::params![
+                request.media_id,
+                date_taken,
+                request.gps_latitude,
+                request.gps_longitude,
+            ],
+        )?;
+    }
+    let (new_date_taken, new_latitude, new_longitude) = read_editable(&tx)?;
+
+    let coordinate = |value: Option<f64>| value.map(|v| v.to_string());
+    let mut edits = Vec::new();
+    if date_taken.is_some() {
+        edits.push(("date_taken", old_date_taken, new_date_taken));
+    }
+    if request.gps_latitude.is_some() {
+        edits.push((
+            "gps_latitude",
+            coordinate(old_latitude),
+            coordinate(new_latitude),
+        ));
+    }
+    if request.gps_longitude.is_some() {
+        edits.push((
+            "gps_longitude",
+            coordinate(old_longitude),
+            coordinate(new_longitude),
+        ));
+    }
+    record_media_edits(&tx, request.media_id, current_user.id, &edits)?;
+
+    if gps_changed {
+        let (geohash, geohash_precise) = match (new_latitude, new_longitude) {
+            (Some(lat), Some(lon)) => (
+                calculate_geohash(lat, lon, GEOHASH_PRECISION),
+                calculate_geohash(lat, lon, GEOHASH_PRECISE_PRECISION),
+            ),
+            _ => (None, None),
+        };
+
+        tx.execute(
+            queries::media::UPDATE_GEOHASHES,
+            rusqlite::params![request.media_id, geohash, geohash_precise],
+        )?;
+
+        delete_from_rtree(&tx, request.media_id).map_err(AppError::Database)?;
+
+        if let (Some(lat), Some(lon)) = (new_latitude, new_longitude) {
+            insert_into_rtree(&tx, request.media_id, lat, lon).map_err(AppError::Database)?;
+        }
+    }
+    tx.commit()?;
+
+    if gps_changed {
+        state.cluster_cache.invalidate();
+    }
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+/// Logs `(field, old, new)` changes that actually altered a value, then
+/// trims the media item's history to the newest `MEDIA_EDIT_HISTORY_LIMIT`.
+fn record_media_edits(
+    conn: &rusqlite::Connection,
+    media_id: i64,
+    user_id: i64,
+    edits: &[(&str, Option<String>, Option<String>)],
+) -> AppResult<()> {
+    let mut changed = false;
+    for (field_name, old_value, new_value) in edits {
+        if old_value == new_value {
+            continue;
+        }
+        conn.execute(
+            queries::edits::INSERT,
+            rusqlite::params![media_id, user_id, field_name, old_value, new_value],
+        )?;
+        changed = true;
+    }
+
+    if changed {
+        conn.execute(
+            queries::edits::PRUNE_FOR_MEDIA,
+            rusqlite::params![media_id, MEDIA_EDIT_HISTORY_LIMIT],
+        )?;
+    }
+    Ok(())
+}
+
+/// Shifts `date_taken` of a batch of owned media by a fixed offset, e.g. to
+/// fix a camera whose clock was set to the wrong time zone. Every id must be
+/// owned by the caller; media without a capture date are left untouched.
+#[utoipa::path(
+    post,
+    path = "/media/date-correction",
+    tag = "media",
+    request_body = MediaDateCorrectionRequest,
+    responses((status = 200, description = "OK", body = MediaDateCorrectionResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn correct_media_dates(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaDateCorrectionRequest>,
+) -> AppResult<Json<MediaDateCorrectionResponse>> {
+    let mut media_ids = request.media_ids;
+    media_ids.sort_unstable();
+    media_ids.dedup();
+
+    if media_ids.len() > MAX_MEDIA_BATCH_SIZE {
+        return Err(AppError::BadRequest(format!(
+            "At most {} media ids can be corrected at once",
+            MAX_MEDIA_BATCH_SIZE
+        )));
+    }
+    if request.offset_seconds == 0 {
+        return Err(AppError::BadRequest(
+            "offsetSeconds must not be zero".to_string(),
+        ));
+    }
+    let offset = chrono::Duration::try_seconds(request.offset_seconds)
+        .ok_or_else(|| AppError::BadRequest("offsetSeconds is out of range".to_string()))?;
+
+    if media_ids.is_empty() {
+        return Ok(Json(MediaDateCorrectionResponse {
+            affected_count: 0,
+            sample_new_date: None,
+        }));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    let tx = conn.transaction()?;
+
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&current_user.id];
+    params.extend(media_ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+    let owned: HashMap<i64, Option<String>> = {
+        let mut stmt = tx.prepare(&queries::media::build_select_owned_dates_taken(
+            media_ids.len(),
+        ))?;
+        let rows = stmt.query_map(params.as_slice(), |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_, _>>()?
+    };
+
+    let missing: Vec<String> = media_ids
+        .iter()
+        .filter(|id| !owned.contains_key(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::Forbidden(format!(
+            "Only the owner can correct dates; not owned: {}",
+            missing.join(", ")
+        )));
+    }
+
+    let mut new_dates = Vec::new();
+    for media_id in &media_ids {
+        let Some(old_date) = owned.get(media_id).cloned().flatten() else {
+            continue;
+        };
+        let Some(shifted) =
+            parse_datetime(&old_date).and_then(|date| date.checked_add_signed(offset))
+        else {
+            continue;
+        };
+        let new_date = format_datetime(&shifted);
+
+        tx.execute(
+            queries::media::UPDATE_DATE_TAKEN,
+            rusqlite::params![new_date, media_id],
+        )?;
+        record_media_edits(
+            &tx,
+            *media_id,
+            current_user.id,
+            &[("date_taken", Some(old_date), Some(new_date.clone()))],
+        )?;
+        new_dates.push((shifted, new_date));
+    }
+    tx.commit()?;
+
+    let earliest = new_dates.iter().min_by_key(|(date, _)| *date);
+    let latest = new_dates.iter().max_by_key(|(date, _)| *date);
+    if let (Some((_, earliest)), Some((_, latest))) = (earliest, latest) {
+        tracing::info!(
+            "Shifted date_taken of {} media items for user {} by {} seconds; new dates range from {} to {}",
+            new_dates.len(),
+            current_user.id,
+            request.offset_seconds,
+            earliest,
+            latest
+        );
+    }
+
+    Ok(Json(MediaDateCorrectionResponse {
+        affected_count: new_dates.len() as i64,
+        sample_new_date: new_dates.into_iter().next().map(|(_, date)| date),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/{media_id}/edit-history",
+    tag = "media",
+    params(("media_id" = i64, Path)),
+    responses((status = 200, description = "Newest edit first", body = Vec<MediaEditRecord>), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_media_edit_history(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(media_id): Path<i64>,
+) -> AppResult<Json<Vec<MediaEditRecord>>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let access_level: i32 = fetch_one(
+        &conn,
+        queries::access::CHECK_MEDIA_ACCESS,
+        &[&media_id, &current_user.id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    if access_level < 2 {
+        return Err(AppError::Forbidden(
+            "Only the owner can view edit history".to_string(),
+        ));
+    }
+
+    let history = fetch_all(
+        &conn,
+        queries::edits::SELECT_FOR_MEDIA,
+        &[&media_id],
+        |row| {
+            Ok(MediaEditRecord {
+                id: row.get(0)?,
+                media_id: row.get(1)?,
+                user_id: row.get(2)?,
+                field_name: row.get(3)?,
+                old_value: row.get(4)?,
+                new_value: row.get(5)?,
+                edited_at: row.get(6)?,
+            })
+        },
+    )?;
+
+    Ok(Json(history))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/manual-geocode",
+    tag = "media",
+    request_body = MediaManualGeocodeRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn manual_geocode_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaManualGeocodeRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    if !(-90.0..=90.0).contains(&request.latitude) || !(-180.0..=180.0).contains(&request.longitude)
+    {
+        return Err(AppError::BadRequest("Invalid coordinates".to_string()));
+    }
+
+    {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        let exists = fetch_one(
+            &conn,
+            queries::media::CHECK_EXISTS,
+            &[&request.media_id, &current_user.id],
+            |row| row.get::<_, i64>(0),
+        )?;
+
+        if exists.is_none() {
+            return Err(AppError::MediaNotFound);
+        }
+    }
+
+    let geo_config = &state.config.reverse_geocoding;
+    let (city, location_state, country) =
+        reverse_geocode(geo_config, request.latitude, request.longitude).await;
+    if geo_config.enabled {
+        tokio::time::sleep(std::time::Duration::from_secs_f64(
+            geo_config.rate_limit_seconds,
+        ))
+        .await;
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    let geohash = calculate_geohash(request.latitude, request.longitude, GEOHASH_PRECISION);
+    let geohash_precise = calculate_geohash(
+        request.latitude,
+        request.longitude,
+        GEOHASH_PRECISE_PRECISION,
+    );
+
+    let tx = conn.transaction()?;
+    let (old_latitude, old_longitude) = tx
+        .query_row(
+            queries::edits::SELECT_EDITABLE_VALUES,
+            [request.media_id],
+            |row| Ok((row.get::<_, Option<f64>>(1)?, row.get::<_, Option<f64>>(2)?)),
+        )
+        .optional()?
+        .unwrap_or_default();
+    tx.execute(
+        queries::media::UPDATE_LOCATION,
+        rusqlite::params![
+            request.media_id,
+            request.latitude,
+            request.longitude,
+            geohash,
+            geohash_precise,
+            city,
+            location_state,
+            country,
+        ],
+    )?;
+
+    delete_from_rtree(&tx, request.media_id).map_err(AppError::Database)?;
+    insert_into_rtree(&tx, request.media_id, request.latitude, request.longitude)
+        .map_err(AppError::Database)?;
+    refresh_search_text(&tx, request.media_id).map_err(AppError::Database)?;
+
+    let coordinate = |value: Option<f64>| value.map(|v| v.to_string());
+    record_media_edits(
+        &tx,
+        request.media_id,
+        current_user.id,
+        &[
+            (
+                "gps_latitude",
+                coordinate(old_latitude),
+                coordinate(Some(request.latitude)),
+            ),
+            (
+                "gps_longitude",
+                coordinate(old_longitude),
+                coordinate(Some(request.longitude)),
+            ),
+        ],
+    )?;
+    tx.commit()?;
+    state.cluster_cache.invalidate();
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/strip-location",
+    tag = "media",
+    request_body = MediaStripLocationRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn strip_media_location(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaStripLocationRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    let file_path: String = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+
+        let access_level: i32 = fetch_one(
+            &conn,
+            queries::access::CHECK_MEDIA_ACCESS,
+            &[&request.media_id, &current_user.id],
+            |row| row.get(0),
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?;
+
+        if access_level < 2 {
+            return Err(AppError::Forbidden(
+                "Only the owner can strip location data".to_string(),
+            ));
+        }
+
+        if request.modify_original {
+            let other_users: i64 = fetch_one(
+                &conn,
+                queries::access::COUNT_OTHER_MEDIA_ACCESS,
+                &[&request.media_id, &current_user.id],
+                |row| row.get(0),
+            )?
+            .unwrap_or(0);
+            if other_users > 0 {
+                return Err(AppError::Conflict(
+                    "Media is shared with other users and its original cannot be modified"
+                        .to_string(),
+                ));
+            }
+        }
+
+        fetch_one(
+            &conn,
+            queries::media::SELECT_FILE_INFO,
+            &[&request.media_id, &current_user.id],
+            |row| row.get(0),
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?
+    };
+
+    let mut file_update = None;
+    if request.modify_original {
+        let full_path = ORIGINALS_DIR.join(&file_path);
+        if !full_path.exists() {
+            return Err(AppError::NotFound("File not found".to_string()));
+        }
+
+        strip_gps_metadata(&full_path)
+            .await
+            .map_err(AppError::Internal)?;
+        let content_hash = calculate_file_hash(&full_path).await?;
+        let file_size = tokio::fs::metadata(&full_path).await?.len() as i64;
+        file_update = Some((content_hash, file_size));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    let tx = conn.transaction()?;
+    tx.execute(queries::media::CLEAR_LOCATION, [request.media_id])?;
+    delete_from_rtree(&tx, request.media_id).map_err(AppError::Database)?;
+    refresh_search_text(&tx, request.media_id).map_err(AppError::Database)?;
+
+    if let Some((content_hash, file_size)) = file_update {
+        tx.execute(
+            queries::media::UPDATE_FILE_HASH_AND_SIZE,
+            rusqlite::params![content_hash, file_size, request.media_id],
+        )?;
+    }
+    tx.commit()?;
+    state.cluster_cache.invalidate();
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+/// Removes the existing thumbnails of an item and renders new ones,
+/// returning the new relative thumbnail path.
+async fn regenerate_thumbnails(
+    original_path: &std::path::Path,
+    media_type: &str,
+    old_thumbnail_path: Option<&str>,
+    config: &ThumbnailConfig,
+) -> Option<String> {
+    if let Some(thumbnail_path) = old_thumbnail_path {
+        let _ = tokio::fs::remove_file(THUMBNAILS_DIR.join(thumbnail_path)).await;
+        let _ = tokio::fs::remove_file(THUMBNAILS_TINY_DIR.join(thumbnail_path)).await;
+    }
+
+    let (thumbnail_relative, _) = generate_thumbnails(original_path, media_type, config).await;
+    thumbnail_relative
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/regenerate-thumbnail",
+    tag = "media",
+    request_body = MediaRegenerateThumbnailRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn regenerate_media_thumbnail(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaRegenerateThumbnailRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    if let Some(offset) = request.offset_seconds {
+        if !offset.is_finite() || offset < 0.0 {
+            return Err(AppError::Validation(
+                "offsetSeconds must be a non-negative number".to_string(),
+            ));
+        }
+    }
+
+    let (file_path, media_type, thumbnail_path) = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+
+        let access_level: i32 = fetch_one(
+            &conn,
+            queries::access::CHECK_MEDIA_ACCESS,
+            &[&request.media_id, &current_user.id],
+            |row| row.get(0),
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?;
+
+        if access_level < 2 {
+            return Err(AppError::Forbidden(
+                "Only the owner can regenerate thumbnails".to_string(),
+            ));
+        }
+
+        fetch_one(
+            &conn,
+            queries::media::SELECT_THUMBNAIL_SOURCE,
+            &[&request.media_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?
+    };
+
+    let full_path = ORIGINALS_DIR.join(&file_path);
+    if !full_path.exists() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    let mut config = state.config.thumbnails.clone();
+    if let Some(offset) = request.offset_seconds {
+        config.video_thumbnail_offset_seconds = offset;
+    }
+    let thumbnail_relative =
+        regenerate_thumbnails(&full_path, &media_type, thumbnail_path.as_deref(), &config)
+            .await
+            .ok_or_else(|| AppError::Internal("Failed to generate thumbnail".to_string()))?;
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    execute_query(
+        &conn,
+        queries::regenerator::UPDATE_THUMBNAIL,
+        &[&thumbnail_relative, &request.media_id],
+    )?;
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+/// Re-extracts metadata and/or re-renders thumbnails for one item the
+/// caller owns, without running a library-wide regeneration.
+#[utoipa::path(
+    post,
+    path = "/media/reprocess",
+    tag = "media",
+    request_body = MediaReprocessRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn reprocess_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaReprocessRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    if !request.regenerate_thumbnails && !request.regenerate_metadata {
+        return Err(AppError::BadRequest(
+            "Nothing to reprocess: set regenerateThumbnails or regenerateMetadata".to_string(),
+        ));
+    }
+
+    let (file_path, media_type, thumbnail_path) = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+
+        let access_level: i32 = fetch_one(
+            &conn,
+            queries::access::CHECK_MEDIA_ACCESS,
+            &[&request.media_id, &current_user.id],
+            |row| row.get(0),
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?;
+
+        if access_level < 2 {
+            return Err(AppError::Forbidden(
+                "Only the owner can reprocess media".to_string(),
+            ));
+        }
+
+        fetch_one(
+            &conn,
+            queries::media::SELECT_THUMBNAIL_SOURCE,
+            &[&request.media_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?
+    };
+
+    let full_path = ORIGINALS_DIR.join(&file_path);
+    if !full_path.exists() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    if request.regenerate_metadata {
+        let mut metadata = extract_embedded_metadata(&full_path, &media_type)
+            .await
+            .map_err(|e| AppError::Unavailable(format!("Metadata extraction failed: {}", e)))?;
+        fill_reverse_geocoded_location(&mut metadata, Some(&state.config.reverse_geocoding)).await;
+
+        let mut conn = state.pool.get().map_err(AppError::Pool)?;
+        let tx = conn.transaction()?;
+        let changes = store_reprocessed_metadata(&tx, request.media_id, &metadata)?;
+        record_media_edits(&tx, request.media_id, current_user.id, &changes)?;
+        tx.commit()?;
+        state.cluster_cache.invalidate();
+    }
+
+    if request.regenerate_thumbnails {
+        let thumbnail_relative = regenerate_thumbnails(
+            &full_path,
+            &media_type,
+            thumbnail_path.as_deref(),
+            &state.config.thumbnails,
+        )
+        .await
+        .ok_or_else(|| AppError::Internal("Failed to generate thumbnail".to_string()))?;
+
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        execute_query(
+            &conn,
+            queries::regenerator::UPDATE_THUMBNAIL,
+            &[&thumbnail_relative, &request.media_id],
+        )?;
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &current_user.id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/rotate",
+    tag = "media",
+    request_body = MediaRotateRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn rotate_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaRotateRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    if ![90, 180, 270].contains(&request.degrees) {
+        return Err(AppError::Validation(
+            "Rotation must be 90, 180 or 270 degrees".to_string(),
+        ));
+    }
+
+    let (full_path, thumbnail_path) = owned_jpeg_paths(&state, current_user.id, request.media_id)?;
+
+    rotate_jpeg(&full_path, request.degrees)
+        .await
+        .map_err(AppError::Internal)?;
+
+    let media = store_rotated_jpeg(
+        &state,
+        current_user.id,
+        request.media_id,
+        &full_path,
+        thumbnail_path.as_deref(),
+        request.degrees,
+    )
+    .await?;
+
+    Ok(Json(media))
+}
+
+/// Bakes the EXIF `Orientation` of a JPEG into its pixels so viewers that
+/// ignore the tag still show it upright.
+#[utoipa::path(
+    post,
+    path = "/media/detect-orientation",
+    tag = "media",
+    request_body = MediaDetectOrientationRequest,
+    responses((status = 200, description = "OK", body = MediaDetectOrientationResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn detect_media_orientation(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaDetectOrientationRequest>,
+) -> AppResult<Json<MediaDetectOrientationResponse>> {
+    let (full_path, thumbnail_path) = owned_jpeg_paths(&state, current_user.id, request.media_id)?;
+
+    let orientation = read_exif_orientation(&full_path)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                AppError::Unavailable("exiftool is not installed".to_string())
+            }
+            _ => AppError::Internal(format!("Failed to read orientation: {}", e)),
+        })?;
+    let degrees = match orientation.map(orientation_rotation_degrees) {
+        None | Some(Some(0)) => {
+            return Ok(Json(MediaDetectOrientationResponse {
+                was_rotated: false,
+                degrees_applied: None,
+                updated_media: None,
+            }));
+        }
+        Some(Some(degrees)) => degrees,
+        Some(None) => {
+            return Err(AppError::Unprocessable(format!(
+                "EXIF orientation {} is mirrored and cannot be fixed by rotation",
+                orientation.unwrap_or_default()
+            )));
+        }
+    };
+
+    // The original is only replaced once it is both rotated and untagged,
+    // so a failure part way cannot leave it rotated twice when displayed
+    let temp_path = full_path.with_extension("orienting.jpg");
+    rotate_jpeg_to(&full_path, &temp_path, degrees)
+        .await
+        .map_err(AppError::Internal)?;
+    if let Err(e) = strip_exif_orientation(&temp_path).await {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(AppError::Internal(e));
+    }
+    if let Err(e) = tokio::fs::rename(&temp_path, &full_path).await {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return Err(AppError::Internal(format!(
+            "Failed to replace original: {}",
+            e
+        )));
+    }
+
+    let media = store_rotated_jpeg(
+        &state,
+        current_user.id,
+        request.media_id,
+        &full_path,
+        thumbnail_path.as_deref(),
+        degrees,
+    )
+    .await?;
+
+    Ok(Json(MediaDetectOrientationResponse {
+        was_rotated: true,
+        degrees_applied: Some(degrees),
+        updated_media: Some(media),
+    }))
+}
+
+/// Original path and thumbnail of a JPEG that `user_id` owns, for the
+/// lossless rotation routes. Media other users can also see is refused,
+/// since rotating the shared original would change it for them too.
+fn owned_jpeg_paths(
+    state: &AppState,
+    user_id: i64,
+    media_id: i64,
+) -> AppResult<(PathBuf, Option<String>)> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let access_level: i32 = fetch_one(
+        &conn,
+        queries::access::CHECK_MEDIA_ACCESS,
+        &[&media_id, &user_id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    if access_level < 2 {
+        return Err(AppError::Forbidden(
+            "Only the owner can rotate media".to_string(),
+        ));
+    }
+
+    let other_users: i64 = fetch_one(
+        &conn,
+        queries::access::COUNT_OTHER_MEDIA_ACCESS,
+        &[&media_id, &user_id],
+        |row| row.get(0),
+    )?
+    .unwrap_or(0);
+    if other_users > 0 {
+        return Err(AppError::Conflict(
+            "Media is shared with other users and cannot be rotated".to_string(),
+        ));
+    }
+
+    let (file_path, mime_type): (String, Option<String>) = fetch_one(
+        &conn,
+        queries::media::SELECT_FILE_INFO,
+        &[&media_id, &user_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    let thumbnail_path: Option<String> = fetch_one(
+        &conn,
+        queries::media::SELECT_THUMBNAIL_PATH,
+        &[&media_id],
+        |row| row.get(0),
+    )?
+    .flatten();
+
+    if mime_type.as_deref() != Some("image/jpeg") {
+        return Err(AppError::Unprocessable(
+            "Only JPEG images can be rotated losslessly".to_string(),
+        ));
+    }
+
+    let full_path = ORIGINALS_DIR.join(&file_path);
+    if !full_path.exists() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
+
+    Ok((full_path, thumbnail_path))
+}
+
+/// Records a rotation already applied to the original: new hash, size,
+/// thumbnails and, for quarter turns, swapped dimensions. Cached previews
+/// are dropped and regenerated on the next request.
+async fn store_rotated_jpeg(
+    state: &AppState,
+    user_id: i64,
+    media_id: i64,
+    full_path: &std::path::Path,
+    thumbnail_path: Option<&str>,
+    degrees: i32,
+) -> AppResult<MediaResponse> {
+    let content_hash = calculate_file_hash(full_path).await?;
+    let file_size = tokio::fs::metadata(full_path).await?.len() as i64;
+
+    let thumbnail_relative =
+        regenerate_thumbnails(full_path, "image", thumbnail_path, &state.config.thumbnails).await;
+    delete_preview_files(user_id, &full_path.to_string_lossy());
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    execute_query(
+        &conn,
+        queries::media::UPDATE_FILE_HASH_AND_SIZE,
+        &[&content_hash, &file_size, &media_id],
+    )?;
+    if degrees != 180 {
+        execute_query(&conn, queries::media::SWAP_DIMENSIONS, &[&media_id])?;
+    }
+    execute_query(
+        &conn,
+        queries::regenerator::UPDATE_THUMBNAIL,
+        &[&thumbnail_relative, &media_id],
+    )?;
+
+    fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&media_id, &user_id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/delete",
+    tag = "media",
+    request_body = MediaDeleteRequest,
+    responses((status = 200, description = "OK", body = DeleteMediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn delete_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaDeleteRequest>,
+) -> AppResult<Json<DeleteMediaResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let exists = fetch_one(
+        &conn,
+        queries::media::CHECK_EXISTS,
+        &[&request.media_id, &current_user.id],
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    if exists.is_none() {
+        return Err(AppError::MediaNotFound);
+    }
+
+    let deleted_at = Utc::now().to_rfc3339();
+    execute_query(
+        &conn,
+        queries::media::UPDATE_DELETED_AT,
+        &[&deleted_at, &request.media_id, &current_user.id],
+    )?;
+    state.cluster_cache.invalidate();
+
+    Ok(Json(DeleteMediaResponse {
+        message: "Media deleted".to_string(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/bulk-delete",
+    tag = "media",
+    request_body = MediaBulkDeleteRequest,
+    responses((status = 200, description = "OK", body = BulkDeleteMediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn bulk_delete_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaBulkDeleteRequest>,
+) -> AppResult<Json<BulkDeleteMediaResponse>> {
+    let mut media_ids = request.media_ids;
+    media_ids.sort_unstable();
+    media_ids.dedup();
+    if media_ids.len() > MAX_MEDIA_BATCH_SIZE {
+        return Err(AppError::BadRequest(format!(
+            "At most {} media ids can be deleted at once",
+            MAX_MEDIA_BATCH_SIZE
+        )));
+    }
+
+    if media_ids.is_empty() {
+        return Ok(Json(BulkDeleteMediaResponse {
+            deleted_count: 0,
+            skipped_ids: Vec::new(),
+        }));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    let tx = conn.transaction()?;
+
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&current_user.id];
+    params.extend(media_ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+    let valid_ids: HashSet<i64> = {
+        let mut stmt = tx.prepare(&queries::media::build_select_active_ids_for_user(
+            media_ids.len(),
+        ))?;
+        let rows = stmt.query_map(params.as_slice(), |row| row.get(0))?;
+        rows.collect::<Result<_, _>>()?
+    };
+
+    let (deleted_ids, skipped_ids): (Vec<i64>, Vec<i64>) =
+        media_ids.into_iter().partition(|id| valid_ids.contains(id));
+
+    if !deleted_ids.is_empty() {
+        let deleted_at = Utc::now().to_rfc3339();
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&deleted_at, &current_user.id];
+        params.extend(deleted_ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+        tx.execute(
+            &queries::media::build_bulk_update_deleted_at(deleted_ids.len()),
+            params.as_slice(),
+        )?;
+    }
+    tx.commit()?;
+    state.cluster_cache.invalidate();
+
+    Ok(Json(BulkDeleteMediaResponse {
+        deleted_count: deleted_ids.len(),
+        skipped_ids,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/duplicate",
+    tag = "media",
+    request_body = MediaDuplicateRequest,
+    responses((status = 200, description = "OK", body = MediaResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn duplicate_media(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaDuplicateRequest>,
+) -> AppResult<Json<MediaResponse>> {
+    let target_user_id = request.target_user_id.unwrap_or(current_user.id);
+    if target_user_id != current_user.id && current_user.role != "admin" {
+        return Err(AppError::Authorization("Admin access required".to_string()));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let access_level: i32 = fetch_one(
+        &conn,
+        queries::access::CHECK_MEDIA_ACCESS,
+        &[&request.media_id, &current_user.id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    if access_level < 2 {
+        return Err(AppError::Forbidden(
+            "Only the owner can duplicate media".to_string(),
+        ));
+    }
+
+    let user_exists = fetch_one(
+        &conn,
+        queries::users::CHECK_EXISTS,
+        &[&target_user_id],
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    if user_exists.is_none() {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    execute_query(
+        &conn,
+        queries::access::INSERT_MEDIA_ACCESS,
+        &[&request.media_id, &target_user_id, &2],
+    )?;
+    execute_query(
+        &conn,
+        queries::access::RESTORE_MEDIA_ACCESS,
+        &[&request.media_id, &target_user_id],
+    )?;
+    state.cluster_cache.invalidate();
+
+    let media = fetch_one(
+        &conn,
+        queries::media::SELECT_BY_ID_AND_USER,
+        &[&request.media_id, &target_user_id],
+        map_media_row,
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    Ok(Json(media))
+}
+
+/// Moves media access from one user to another, e.g. when consolidating
+/// accounts. Items the recipient can already see keep the recipient's row,
+/// raised to the sender's access level and taken out of the trash.
+#[utoipa::path(
+    post,
+    path = "/admin/media/transfer",
+    tag = "media",
+    request_body = MediaTransferRequest,
+    responses((status = 200, description = "OK", body = MediaTransferResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn transfer_media(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+    Json(request): Json<MediaTransferRequest>,
+) -> AppResult<Json<MediaTransferResponse>> {
+    if request.from_user_id == request.to_user_id {
+        return Err(AppError::BadRequest(
+            "fromUserId and toUserId must differ".to_string(),
+        ));
+    }
+
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+    for user_id in [request.from_user_id, request.to_user_id] {
+        fetch_one(&conn, queries::users::CHECK_EXISTS, &[&user_id], |row| {
+            row.get::<_, i64>(0)
+        })?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    }
+
+    let tx = conn.transaction()?;
+    let media_ids: Vec<i64> = match request.media_ids {
+        Some(ids) => ids
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect(),
+        None => {
+            let mut stmt = tx.prepare(queries::access::SELECT_ACTIVE_MEDIA_IDS_FOR_USER)?;
+            let ids = stmt
+                .query_map([request.from_user_id], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            ids
+        }
+    };
+
+    let mut transferred_count = 0;
+    for media_id in media_ids {
+        let params = rusqlite::params![request.to_user_id, media_id, request.from_user_id];
+        if tx.execute(queries::access::TRANSFER_MEDIA_ACCESS, params)? > 0 {
+            transferred_count += 1;
+        } else if tx.execute(queries::access::MERGE_TRANSFERRED_ACCESS, params)? > 0 {
+            tx.execute(
+                queries::access::REMOVE_MEDIA_ACCESS,
+                rusqlite::params![media_id, request.from_user_id],
+            )?;
+            transferred_count += 1;
+        }
+    }
+    tx.commit()?;
+    if transferred_count > 0 {
+        state.cluster_cache.invalidate();
+    }
+
+    tracing::info!(
+        "Transferred {} media items from user {} to user {}",
+        transferred_count,
+        request.from_user_id,
+        request.to_user_id
+    );
+
+    Ok(Json(MediaTransferResponse { transferred_count }))
+}
+
+fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Lets a client skip uploading a file whose hash is already among the
+/// media it can see.
+#[utoipa::path(
+    post,
+    path = "/media/check-duplicate",
+    tag = "media",
+    request_body = MediaCheckDuplicateRequest,
+    responses((status = 200, description = "OK", body = MediaCheckDuplicateResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn check_duplicate(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaCheckDuplicateRequest>,
+) -> AppResult<Json<MediaCheckDuplicateResponse>> {
+    if !is_sha256_hex(&request.content_hash) {
+        return Err(AppError::Validation(
+            "contentHash must be 64 lowercase hex characters".to_string(),
+        ));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let media_id = fetch_one(
+        &conn,
+        queries::media::SELECT_ACCESSIBLE_BY_CONTENT_HASH,
+        &[&request.content_hash, &current_user.id],
+        |row| row.get::<_, i64>(0),
+    )?;
+
+    Ok(Json(MediaCheckDuplicateResponse {
+        exists: media_id.is_some(),
+        media_id,
+    }))
+}
+
+/// Returns every media id of an album or tag in date order (or a seeded
+/// shuffle of it) so a client can run a slideshow without paging.
+#[utoipa::path(
+    post,
+    path = "/media/slideshow",
+    tag = "media",
+    request_body = MediaSlideshowRequest,
+    responses((status = 200, description = "OK", body = MediaSlideshowResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_slideshow(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<MediaSlideshowRequest>,
+) -> AppResult<Json<MediaSlideshowResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let (source, mut media_ids) = match (request.album_id, request.tag.as_deref()) {
+        (Some(album_id), None) => {
+            fetch_one(
+                &conn,
+                queries::albums::CHECK_OWNERSHIP,
+                &[&album_id, &current_user.id],
+                |row| row.get::<_, i64>(0),
+            )?
+            .ok_or_else(|| AppError::NotFound("Album not found".to_string()))?;
+            let media_ids = fetch_all(
+                &conn,
+                queries::media::SELECT_SLIDESHOW_IDS_FOR_ALBUM,
+                &[&album_id, &current_user.id],
+                |row| row.get(0),
+            )?;
+            (format!("album:{}", album_id), media_ids)
+        }
+        (None, Some(tag)) => {
+            let tag_id: i64 = fetch_one(&conn, queries::tags::SELECT_ID_BY_NAME, &[&tag], |row| {
+                row.get(0)
+            })?
+            .ok_or_else(|| AppError::NotFound("Tag not found".to_string()))?;
+            let media_ids = fetch_all(
+                &conn,
+                queries::media::SELECT_SLIDESHOW_IDS_FOR_TAG,
+                &[&tag_id, &current_user.id],
+                |row| row.get(0),
+            )?;
+            (format!("tag:{}", tag_id), media_ids)
+        }
+        _ => {
+            return Err(AppError::Validation(
+                "Exactly one of albumId or tag is required".to_string(),
+            ))
+        }
+    };
+
+    let seed = request
+        .shuffle
+        .then(|| request.seed.unwrap_or_else(rand::random));
+    let query = match seed {
+        Some(seed) => {
+            seeded_shuffle(&mut media_ids, seed);
+            format!("{}:shuffle:{}", source, seed)
+        }
+        None => source,
+    };
+
+    Ok(Json(MediaSlideshowResponse {
+        sequence_id: create_slideshow_sequence_id(&query, &media_ids, &state.config),
+        media_ids,
+        seed,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/lens-models",
+    tag = "media",
+    responses((status = 200, description = "OK", body = LensModelsResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_lens_models(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<LensModelsResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let lens_models = fetch_all(
+        &conn,
+        queries::media::SELECT_LENS_MODELS_FOR_USER,
+        &[&current_user.id],
+        |row| row.get(0),
+    )?;
+
+    Ok(Json(LensModelsResponse { lens_models }))
+}
+
+/// Counts media per 35mm-equivalent focal length range, returning every
+/// bucket (including empty ones) so a histogram keeps a stable x-axis.
+#[utoipa::path(
+    get,
+    path = "/media/focal-lengths",
+    tag = "media",
+    responses((status = 200, description = "OK", body = Vec<FocalLengthBucket>), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_focal_lengths(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<Vec<FocalLengthBucket>>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let counts: Vec<(f64, i64)> = fetch_all(
+        &conn,
+        queries::media::SELECT_FOCAL_LENGTH_COUNTS_FOR_USER,
+        &[&current_user.id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let edges = FOCAL_LENGTH_BUCKET_EDGES_MM;
+    let mut buckets: Vec<FocalLengthBucket> = (0..=edges.len())
+        .map(|i| {
+            let min_mm = i.checked_sub(1).map(|j| edges[j]);
+            let max_mm = edges.get(i).copied();
+            let label = match (min_mm, max_mm) {
+                (None, Some(max)) => format!("<{}mm", max),
+                (Some(min), Some(max)) => format!("{}-{}mm", min, max),
+                (Some(min), None) => format!(">{}mm", min),
+                (None, None) => unreachable!("at least one edge is defined"),
+            };
+            FocalLengthBucket {
+                label,
+                min_mm,
+                max_mm,
+                count: 0,
+            }
+        })
+        .collect();
+    for (focal_length, count) in counts {
+        let index = edges.partition_point(|edge| *edge <= focal_length);
+        buckets[index].count += count;
+    }
+
+    Ok(Json(buckets))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/cameras",
+    tag = "media",
+    responses((status = 200, description = "OK", body = Vec<CameraGroup>), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_cameras(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<Vec<CameraGroup>>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let cameras = fetch_all(
+        &conn,
+        queries::media::SELECT_CAMERAS_FOR_USER,
+        &[&current_user.id],
+        |row| {
+            Ok(CameraGroup {
+                make: row.get(0)?,
+                model: row.get(1)?,
+                count: row.get(2)?,
+            })
+        },
+    )?;
+
+    Ok(Json(cameras))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TimelineDatesQuery {
+    group_by: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/timeline/dates",
+    tag = "media",
+    params(TimelineDatesQuery),
+    responses((status = 200, description = "OK", body = TimelineDatesResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_timeline_dates(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Query(query): Query<TimelineDatesQuery>,
+) -> AppResult<Json<TimelineDatesResponse>> {
+    let prefix_len: i64 = match query.group_by.as_deref().unwrap_or("month") {
+        "year" => 4,
+        "month" => 7,
+        "day" => 10,
+        other => {
+            return Err(AppError::Validation(format!(
+                "group_by must be day, month or year, got '{}'",
+                other
+            )))
+        }
+    };
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let buckets = fetch_all(
+        &conn,
+        queries::media::SELECT_DATE_BUCKETS,
+        &[&prefix_len, &current_user.id],
+        |row| {
+            Ok(DateBucket {
+                date: row.get(0)?,
+                count: row.get(1)?,
+            })
+        },
+    )?;
+
+    Ok(Json(TimelineDatesResponse { buckets }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/stats",
+    tag = "media",
+    responses((status = 200, description = "OK", body = MediaStatsResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_media_stats(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<MediaStatsResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let top_cameras = fetch_all(
+        &conn,
+        queries::media::SELECT_TOP_CAMERAS_FOR_USER,
+        &[&current_user.id],
+        |row| {
+            Ok(CameraCount {
+                camera: row.get(0)?,
+                count: row.get(1)?,
+            })
+        },
+    )?;
+
+    let top_locations = fetch_all(
+        &conn,
+        queries::media::SELECT_TOP_LOCATIONS_FOR_USER,
+        &[&current_user.id],
+        |row| {
+            Ok(LocationCount {
+                city: row.get(0)?,
+                country: row.get(1)?,
+                count: row.get(2)?,
+            })
+        },
+    )?;
+
+    let stats = fetch_one(
+        &conn,
+        queries::media::SELECT_STATS_FOR_USER,
+        &[&current_user.id],
+        |row| {
+            Ok(MediaStatsResponse {
+                total_media: row.get(0)?,
+                total_photos: row.get(1)?,
+                total_videos: row.get(2)?,
+                total_size_bytes: row.get(3)?,
+                oldest_date: row.get(4)?,
+                newest_date: row.get(5)?,
+                top_cameras,
+                top_locations,
+            })
+        },
+    )?
+    .ok_or_else(|| AppError::Internal("Failed to compute media stats".to_string()))?;
+
+    Ok(Json(stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/locations",
+    tag = "media",
+    responses((status = 200, description = "OK", body = MediaLocationsResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_media_locations(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+) -> AppResult<Json<MediaLocationsResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let locations = fetch_all(
+        &conn,
+        queries::media::SELECT_LOCATION_FACETS_FOR_USER,
+        &[&current_user.id, &LOCATION_FACET_LIMIT],
+        |row| {
+            Ok(LocationFacet {
+                country: row.get(0)?,
+                state: row.get(1)?,
+                city: row.get(2)?,
+                count: row.get(3)?,
+            })
+        },
+    )?;
+
+    let cells: Vec<(String, i64)> = fetch_all(
+        &conn,
+        queries::media::SELECT_GEOHASH_BUCKETS_FOR_USER,
+        &[
+            &(LOCATION_HEATMAP_GEOHASH_PRECISION as i64),
+            &current_user.id,
+        ],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let heatmap = cells
+        .into_iter()
+        .filter_map(|(cell, count)| {
+            let (center, _, _) = geohash::decode(&cell).ok()?;
+            Some(GeohashBucket {
+                geohash: cell,
+                latitude: center.y,
+                longitude: center.x,
+                count,
+            })
+        })
+        .collect();
+
+    Ok(Json(MediaLocationsResponse { locations, heatmap }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/date-mismatches",
+    tag = "media",
+    responses((status = 200, description = "OK", body = MediaBatchResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn list_date_mismatches(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+) -> AppResult<Json<MediaBatchResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let items = fetch_all(
+        &conn,
+        queries::media::SELECT_DATE_MISMATCHES,
+        &[],
+        map_media_row,
+    )?;
+
+    Ok(Json(MediaBatchResponse { items }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/duplicates",
+    tag = "media",
+    responses((status = 200, description = "OK", body = DuplicatesResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn list_duplicates(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+) -> AppResult<Json<DuplicatesResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let rows = fetch_all(&conn, queries::media::SELECT_DUPLICATE_GROUPS, &[], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let mut groups = Vec::with_capacity(rows.len());
+    let mut total_wasted_bytes = 0;
+    for (phash, media_ids, count, wasted_bytes) in rows {
+        let media_ids: Vec<i64> = serde_json::from_str(&media_ids)
+            .map_err(|e| AppError::Internal(format!("Invalid media id list: {}", e)))?;
+        total_wasted_bytes += wasted_bytes;
+        groups.push(DuplicateGroup {
+            phash: format!("{:016x}", phash as u64),
+            media_ids,
+            count,
+        });
+    }
+
+    Ok(Json(DuplicatesResponse {
+        groups,
+        total_wasted_bytes,
+    }))
+}
 
-    let mut by_id = std::collections::HashMap::new();
+struct GeotaggedMedia {
+    id: i64,
+    geohash: String,
+    date_taken: DateTime<Utc>,
+    latitude: f64,
+    longitude: f64,
+    place: Option<String>,
+}
+
+/// Groups items sharing a geohash prefix whose dates all fall within
+/// `window` of the group's first item. Expects `items` sorted by date.
+fn group_smart_album_candidates(
+    items: Vec<GeotaggedMedia>,
+    precision: usize,
+    window: chrono::Duration,
+) -> Vec<Vec<GeotaggedMedia>> {
+    let mut by_prefix: IndexMap<String, Vec<GeotaggedMedia>> = IndexMap::new();
     for item in items {
-        by_id.insert(item.id, item);
+        let prefix: String = item.geohash.chars().take(precision).collect();
+        by_prefix.entry(prefix).or_default().push(item);
     }
 
-    let ordered_items = request
-        .ids
-        .iter()
-        .filter_map(|media_id| by_id.get(media_id).cloned())
-        .collect();
+    let mut groups = Vec::new();
+    for (_, items) in by_prefix {
+        let mut current: Vec<GeotaggedMedia> = Vec::new();
+        for item in items {
+            let starts_new_group = current
+                .first()
+                .is_some_and(|first| item.date_taken - first.date_taken > window);
+            if starts_new_group {
+                groups.push(std::mem::take(&mut current));
+            }
+            current.push(item);
+        }
+        groups.push(current);
+    }
 
-    Ok(Json(MediaBatchResponse {
-        items: ordered_items,
-    }))
+    groups.retain(|group| group.len() >= 2);
+    groups.sort_by_key(|group| std::cmp::Reverse(group[0].date_taken));
+    groups
 }
 
-async fn update_media(
+fn build_album_suggestion(group: Vec<GeotaggedMedia>) -> AlbumSuggestion {
+    let count = group.len() as f64;
+    let center_lat = group.iter().map(|item| item.latitude).sum::<f64>() / count;
+    let center_lon = group.iter().map(|item| item.longitude).sum::<f64>() / count;
+
+    let mut place_counts: IndexMap<&str, usize> = IndexMap::new();
+    for place in group.iter().filter_map(|item| item.place.as_deref()) {
+        *place_counts.entry(place).or_default() += 1;
+    }
+    let place = place_counts
+        .iter()
+        .max_by_key(|(_, count)| **count)
+        .map(|(place, _)| place.to_string())
+        .unwrap_or_else(|| "Trip".to_string());
+
+    AlbumSuggestion {
+        suggested_name: format!("{}, {}", place, group[0].date_taken.format("%B %Y")),
+        media_ids: group.iter().map(|item| item.id).collect(),
+        center_lat,
+        center_lon,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/media/smart-album/suggest",
+    tag = "media",
+    request_body = Option<SmartAlbumSuggestRequest>,
+    responses((status = 200, description = "OK", body = SmartAlbumSuggestResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn suggest_smart_albums(
     State(state): State<AppState>,
     current_user: CurrentUser,
-    Json(request): Json<MediaUpdateRequest>,
-) -> AppResult<Json<MediaResponse>> {
-    let conn = state.pool.get().map_err(AppError::Pool)?;
+    request: Option<Json<SmartAlbumSuggestRequest>>,
+) -> AppResult<Json<SmartAlbumSuggestResponse>> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let precision = request
+        .geohash_precision
+        .unwrap_or(DEFAULT_SMART_ALBUM_GEOHASH_PRECISION);
+    if !(1..=9).contains(&precision) {
+        return Err(AppError::Validation(
+            "geohashPrecision must be between 1 and 9".to_string(),
+        ));
+    }
+    let window_days = request
+        .window_days
+        .unwrap_or(DEFAULT_SMART_ALBUM_WINDOW_DAYS);
+    if window_days < 0 {
+        return Err(AppError::Validation(
+            "windowDays must not be negative".to_string(),
+        ));
+    }
+    let window = chrono::Duration::try_days(window_days)
+        .ok_or_else(|| AppError::Validation("windowDays is out of range".to_string()))?;
 
-    let exists = fetch_one(
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let rows = fetch_all(
         &conn,
-        queries::media::CHECK_EXISTS,
-        &[&request.media_id, &current_user.id],
-        |row| row.get::<_, i64>(0),
+        queries::media::SELECT_GEOTAGGED_FOR_USER,
+        &[&current_user.id],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        },
     )?;
 
-    if exists.is_none() {
-        return Err(AppError::NotFound("Media not found".to_string()));
-    }
+    let mut items: Vec<GeotaggedMedia> = rows
+        .into_iter()
+        .filter_map(
+            |(id, geohash, date_taken, latitude, longitude, city, country)| {
+                Some(GeotaggedMedia {
+                    id,
+                    geohash,
+                    date_taken: parse_datetime(&date_taken)?,
+                    latitude,
+                    longitude,
+                    place: city.or(country),
+                })
+            },
+        )
+        .collect();
+    items.sort_by_key(|item| (item.date_taken, item.id));
 
-    if request.date_taken.is_some()
-        || request.gps_latitude.is_some()
-        || request.gps_longitude.is_some()
-    {
-        execute_query(
-            &conn,
-            r#"
-            INSERT INTO media_metadata (media_id, date_taken, gps_latitude, gps_longitude)
-            VALUES (?, ?, ?, ?)
-            ON CONFLICT(media_id) DO UPDATE SET
-                date_taken = COALESCE(excluded.date_taken, media_metadata.date_taken),
-                gps_latitude = COALESCE(excluded.gps_latitude, media_metadata.gps_latitude),
-                gps_longitude = COALESCE(excluded.gps_longitude, media_metadata.gps_longitude)
-            "#,
-            &[
-                &request.media_id,
-                &request.date_taken,
-                &request.gps_latitude,
-                &request.gps_longitude,
-            ],
-        )?;
-    }
+    let suggestions = group_smart_album_candidates(items, precision, window)
+        .into_iter()
+        .map(build_album_suggestion)
+        .collect();
+
+    Ok(Json(SmartAlbumSuggestResponse { suggestions }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/media/file/{media_id}",
+    tag = "media",
+    params(("media_id" = i64, Path)),
+    responses((status = 200, description = "Original file", content_type = "application/octet-stream"), (status = 206, description = "Requested byte range", content_type = "application/octet-stream"), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_media_file(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(media_id): Path<i64>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
 
     let media = fetch_one(
         &conn,
-        queries::media::SELECT_BY_ID_AND_USER,
-        &[&request.media_id, &current_user.id],
-        map_media_row,
+        queries::media::SELECT_FILE_INFO,
+        &[&media_id, &current_user.id],
+        |row| {
+            Ok(FileInfo {
+                file_path: row.get(0)?,
+                mime_type: row.get(1)?,
+                original_filename: row.get(2)?,
+            })
+        },
     )?
-    .ok_or_else(|| AppError::NotFound("Media not found".to_string()))?;
+    .ok_or_else(|| AppError::MediaNotFound)?;
 
-    if request.gps_latitude.is_some() || request.gps_longitude.is_some() {
-        let geohash = match (media.gps_latitude, media.gps_longitude) {
-            (Some(lat), Some(lon)) => calculate_geohash(lat, lon),
-            _ => None,
-        };
+    let full_path = ORIGINALS_DIR.join(&media.file_path);
+    if !full_path.exists() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
 
-        execute_query(
+    serve_file_with_range(
+        full_path,
+        &media
+            .mime_type
+            .unwrap_or_else(|| "application/octet-stream".to_string()),
+        &headers,
+        Some(&media.original_filename),
+    )
+    .await
+}
+
+/// Full exiftool output for the original, for users who need more than the
+/// indexed subset of fields.
+#[utoipa::path(
+    get,
+    path = "/media/exif/{media_id}",
+    tag = "media",
+    params(("media_id" = i64, Path)),
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_media_exif(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(media_id): Path<i64>,
+) -> AppResult<Response> {
+    let media = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        fetch_one(
             &conn,
-            r#"
-            INSERT INTO media_metadata (media_id, geohash)
-            VALUES (?, ?)
-            ON CONFLICT(media_id) DO UPDATE SET
-                geohash = excluded.geohash
-            "#,
-            &[&media.id, &geohash],
-        )?;
+            queries::media::SELECT_FILE_INFO,
+            &[&media_id, &current_user.id],
+            |row| {
+                Ok(FileInfo {
+                    file_path: row.get(0)?,
+                    mime_type: row.get(1)?,
+                    original_filename: row.get(2)?,
+                })
+            },
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?
+    };
 
-        delete_from_rtree(&conn, media.id).map_err(AppError::Database)?;
+    let full_path = ORIGINALS_DIR.join(&media.file_path);
+    if !full_path.exists() {
+        return Err(AppError::NotFound("File not found".to_string()));
+    }
 
-        if let (Some(lat), Some(lon)) = (media.gps_latitude, media.gps_longitude) {
-            insert_into_rtree(&conn, media.id, lat, lon).map_err(AppError::Database)?;
-        }
+    let output = tokio::process::Command::new("exiftool")
+        .arg("-json")
+        .arg(&full_path)
+        .output()
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                AppError::Unavailable("exiftool is not installed".to_string())
+            }
+            _ => AppError::Internal(format!("Failed to run exiftool: {}", e)),
+        })?;
+
+    if !output.status.success() {
+        return Err(AppError::Internal(format!(
+            "exiftool failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
     }
 
-    Ok(Json(media))
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(output.stdout))
+        .map_err(|e| AppError::Internal(e.to_string()))
 }
 
-async fn delete_media(
+#[utoipa::path(
+    post,
+    path = "/media/download-token",
+    tag = "media",
+    request_body = MediaDownloadTokenRequest,
+    responses((status = 200, description = "OK", body = MediaDownloadTokenResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn create_media_download_token(
     State(state): State<AppState>,
     current_user: CurrentUser,
-    Json(request): Json<MediaDeleteRequest>,
-) -> AppResult<Json<DeleteMediaResponse>> {
-    let conn = state.pool.get().map_err(AppError::Pool)?;
+    Json(request): Json<MediaDownloadTokenRequest>,
+) -> AppResult<Json<MediaDownloadTokenResponse>> {
+    if request.expires_in_seconds == 0 || request.expires_in_seconds > MAX_DOWNLOAD_TOKEN_SECONDS {
+        return Err(AppError::Validation(format!(
+            "expiresInSeconds must be between 1 and {}",
+            MAX_DOWNLOAD_TOKEN_SECONDS
+        )));
+    }
 
+    let conn = state.pool.get().map_err(AppError::Pool)?;
     let exists = fetch_one(
         &conn,
         queries::media::CHECK_EXISTS,
         &[&request.media_id, &current_user.id],
         |row| row.get::<_, i64>(0),
     )?;
-
     if exists.is_none() {
-        return Err(AppError::NotFound("Media not found".to_string()));
+        return Err(AppError::MediaNotFound);
     }
 
-    let deleted_at = Utc::now().to_rfc3339();
-    execute_query(
-        &conn,
-        queries::media::UPDATE_DELETED_AT,
-        &[&deleted_at, &request.media_id, &current_user.id],
-    )?;
+    let expires_at = Utc::now().timestamp() + request.expires_in_seconds as i64;
+    let token = create_download_token(request.media_id, current_user.id, expires_at, &state.config);
+    let url = format!("/api/v1/media/file/{}/download/{}", request.media_id, token);
 
-    Ok(Json(DeleteMediaResponse {
-        message: "Media deleted".to_string(),
-    }))
+    Ok(Json(MediaDownloadTokenResponse { token, url }))
 }
 
-async fn get_media_file(
+#[utoipa::path(
+    get,
+    path = "/media/file/{media_id}/download/{token}",
+    tag = "media",
+    params(("media_id" = i64, Path), ("token" = String, Path)),
+    responses((status = 200, description = "Original file", content_type = "application/octet-stream"), (status = 206, description = "Requested byte range", content_type = "application/octet-stream"), ErrorResponses),
+)]
+async fn download_media_file(
     State(state): State<AppState>,
-    current_user: CurrentUser,
-    Path(media_id): Path<i64>,
+    Path((media_id, token)): Path<(i64, String)>,
     headers: HeaderMap,
 ) -> AppResult<Response> {
-    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let user_id = verify_download_token(media_id, &token, &state.config)
+        .ok_or_else(|| AppError::Authentication("Invalid or expired download token".to_string()))?;
 
+    // The issuer may have lost access or trashed the item since.
+    let conn = state.pool.get().map_err(AppError::Pool)?;
     let media = fetch_one(
         &conn,
-        queries::media::SELECT_FILE_INFO,
-        &[&media_id, &current_user.id],
+        queries::media::SELECT_ACTIVE_FILE_INFO,
+        &[&media_id, &user_id],
         |row| {
             Ok(FileInfo {
                 file_path: row.get(0)?,
@@ -454,7 +3355,7 @@ async fn get_media_file(
             })
         },
     )?
-    .ok_or_else(|| AppError::NotFound("Media not found".to_string()))?;
+    .ok_or_else(|| AppError::MediaNotFound)?;
 
     let full_path = ORIGINALS_DIR.join(&media.file_path);
     if !full_path.exists() {
@@ -551,23 +3452,19 @@ fn fetch_timeline_rows(
     cursor: Option<&str>,
 ) -> AppResult<Vec<(MediaResponse, Option<String>)>> {
     if let Some(cursor) = cursor {
-        let parts: Vec<&str> = cursor.split('_').collect();
-        if parts.len() == 2 {
-            let cursor_date = parts[0];
-            let cursor_id: i64 = parts[1].parse().unwrap_or(0);
-            return fetch_all(
-                conn,
-                queries::timeline::SELECT_PAGINATED,
-                &[
-                    &user_id,
-                    &cursor_date,
-                    &cursor_date,
-                    &cursor_id,
-                    &(limit + 1),
-                ],
-                map_timeline_row,
-            );
-        }
+        let (cursor_date, cursor_id) = parse_date_cursor(cursor)?;
+        return fetch_all(
+            conn,
+            queries::timeline::SELECT_PAGINATED,
+            &[
+                &user_id,
+                &cursor_date,
+                &cursor_date,
+                &cursor_id,
+                &(limit + 1),
+            ],
+            map_timeline_row,
+        );
     }
 
     fetch_default_timeline(conn, user_id, limit)
@@ -600,16 +3497,26 @@ struct FileInfo {
     original_filename: String,
 }
 
+#[utoipa::path(
+    post,
+    path = "/thumbnail/get",
+    tag = "media",
+    request_body = ThumbnailBatchRequest,
+    responses((status = 200, description = "OK", body = ThumbnailBatchResponse), (status = 304, description = "Unchanged since the ETag in If-None-Match"), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_media_thumbnail_batch(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    headers: HeaderMap,
     Json(request): Json<ThumbnailBatchRequest>,
-) -> AppResult<Json<ThumbnailBatchResponse>> {
+) -> AppResult<Response> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
     if request.media_ids.is_empty() {
         return Ok(Json(ThumbnailBatchResponse {
             thumbnails: HashMap::new(),
-        }));
+        })
+        .into_response());
     }
 
     let thumbnail_base_dir = match request.size {
@@ -638,7 +3545,7 @@ async fn get_media_thumbnail_batch(
         .filter(|(id, _, _, _, _)| requested_ids.contains(id))
         .collect::<Vec<_>>();
 
-    let mut thumbnails: HashMap<i64, Option<String>> = HashMap::new();
+    let mut resolved: Vec<(i64, Option<(PathBuf, &'static str)>)> = Vec::new();
 
     for (media_id, thumbnail_path, file_path, _media_type, _user_id) in rows {
         let stem = PathBuf::from(&file_path)
@@ -654,38 +3561,190 @@ async fn get_media_thumbnail_batch(
                 .and_then(|n| n.to_str())
                 .unwrap_or("unknown")
                 .to_string();
-            format!("{}/{}.jpg", parent, stem)
+            format!(
+                "{}/{}.{}",
+                parent,
+                stem,
+                state.config.thumbnails.thumbnail_format.extension()
+            )
         });
 
         let full_path = thumbnail_base_dir.join(&thumbnail_relative);
+        let format = ThumbnailFormat::from_path(&full_path);
+
+        if request.size == ThumbnailSize::Tiny && !full_path.exists() {
+            let normal_path = THUMBNAILS_DIR.join(&thumbnail_relative);
+            if normal_path.exists() {
+                let generated = generate_image_thumbnail(
+                    &normal_path,
+                    &full_path,
+                    state.config.thumbnails.tiny_size,
+                    state.config.thumbnails.quality,
+                    format,
+                )
+                .await;
+                if !generated {
+                    tracing::warn!("Failed to generate tiny thumbnail for media {}", media_id);
+                }
+            }
+        }
+
+        let source = full_path.exists().then(|| (full_path, format.mime_type()));
+        resolved.push((media_id, source));
+    }
+
+    let etag = batch_etag(&resolved);
+    if matches_if_none_match(&headers, &etag) {
+        return Ok(with_etag(StatusCode::NOT_MODIFIED.into_response(), &etag));
+    }
+
+    let mut thumbnails: HashMap<i64, Option<String>> = HashMap::new();
+    for (media_id, source) in resolved {
+        thumbnails.insert(media_id, read_data_url(source).await);
+    }
+
+    Ok(with_etag(
+        Json(ThumbnailBatchResponse { thumbnails }).into_response(),
+        &etag,
+    ))
+}
+
+/// Serves a single thumbnail, generating it first if it is missing.
+/// Clients can point an `<img>` at this without tracking missing thumbnails.
+#[utoipa::path(
+    get,
+    path = "/thumbnail/{media_id}",
+    tag = "media",
+    params(("media_id" = i64, Path)),
+    responses((status = 200, description = "Thumbnail image", content_type = "image/*"), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn get_media_thumbnail(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(media_id): Path<i64>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let (file_path, media_type, thumbnail_path) = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+
+        fetch_one(
+            &conn,
+            queries::media::CHECK_EXISTS,
+            &[&media_id, &current_user.id],
+            |row| row.get::<_, i64>(0),
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?;
+
+        fetch_one(
+            &conn,
+            queries::media::SELECT_THUMBNAIL_SOURCE,
+            &[&media_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            },
+        )?
+        .ok_or_else(|| AppError::MediaNotFound)?
+    };
 
-        if full_path.exists() {
-            if let Ok(data) = tokio::fs::read(&full_path).await {
-                let encoded = STANDARD.encode(data);
-                thumbnails.insert(
-                    media_id,
-                    Some(format!("data:image/jpeg;base64,{}", encoded)),
+    let existing = thumbnail_path
+        .map(|relative| THUMBNAILS_DIR.join(relative))
+        .filter(|path| path.exists());
+
+    let thumbnail = match existing {
+        Some(path) => Some(path),
+        None => {
+            let original_path = ORIGINALS_DIR.join(&file_path);
+            let generated = if original_path.exists() {
+                regenerate_thumbnails(&original_path, &media_type, None, &state.config.thumbnails)
+                    .await
+            } else {
+                None
+            };
+
+            if let Some(relative) = &generated {
+                let conn = state.pool.get().map_err(AppError::Pool)?;
+                execute_query(
+                    &conn,
+                    queries::regenerator::UPDATE_THUMBNAIL,
+                    &[relative, &media_id],
+                )?;
+            } else {
+                tracing::warn!(
+                    "On-demand thumbnail generation failed for media {}",
+                    media_id
                 );
-                continue;
             }
+            generated.map(|relative| THUMBNAILS_DIR.join(relative))
         }
+    };
 
-        thumbnails.insert(media_id, None);
+    let last_modified = thumbnail.as_deref().and_then(http_mtime);
+    if let Some(last_modified) = last_modified {
+        if is_not_modified(&headers, last_modified) {
+            return Ok(not_modified_response(last_modified));
+        }
     }
 
-    Ok(Json(ThumbnailBatchResponse { thumbnails }))
+    let data = match &thumbnail {
+        Some(path) => tokio::fs::read(path).await.ok(),
+        None => None,
+    };
+
+    let response = match (thumbnail, data) {
+        (Some(path), Some(data)) => Response::builder()
+            .header(
+                header::CONTENT_TYPE,
+                ThumbnailFormat::from_path(&path).mime_type(),
+            )
+            .header(header::CACHE_CONTROL, "private, max-age=86400")
+            .body(Body::from(data))
+            .map(|response| with_last_modified(response, last_modified)),
+        // Not cached, so the real thumbnail is picked up once it exists
+        _ => Response::builder()
+            .header(header::CONTENT_TYPE, "image/jpeg")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Body::from(PLACEHOLDER_THUMBNAIL.clone())),
+    };
+
+    response.map_err(|e| AppError::Internal(e.to_string()))
 }
 
+/// 1x1 grey JPEG served when a thumbnail cannot be generated.
+static PLACEHOLDER_THUMBNAIL: Lazy<Vec<u8>> = Lazy::new(|| {
+    let mut bytes = Vec::new();
+    let pixel = image::RgbImage::from_pixel(1, 1, image::Rgb([128, 128, 128]));
+    let _ = pixel.write_to(
+        &mut std::io::Cursor::new(&mut bytes),
+        image::ImageFormat::Jpeg,
+    );
+    bytes
+});
+
+#[utoipa::path(
+    post,
+    path = "/preview/get",
+    tag = "media",
+    request_body = PreviewBatchRequest,
+    responses((status = 200, description = "OK", body = PreviewBatchResponse), (status = 304, description = "Unchanged since the ETag in If-None-Match"), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_media_preview_batch(
     State(state): State<AppState>,
     current_user: CurrentUser,
+    headers: HeaderMap,
     Json(request): Json<PreviewBatchRequest>,
-) -> AppResult<Json<PreviewBatchResponse>> {
+) -> AppResult<Response> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
     if request.ids.is_empty() {
         return Ok(Json(PreviewBatchResponse {
             previews: HashMap::new(),
-        }));
+        })
+        .into_response());
     }
 
     let rows: Vec<(i64, String, String, Option<String>)> = fetch_all(
@@ -701,34 +3760,46 @@ async fn get_media_preview_batch(
         .filter(|(id, _, _, _)| requested_ids.contains(id))
         .collect::<Vec<_>>();
 
-    let mut previews: HashMap<i64, Option<String>> = HashMap::new();
+    let webp_preferred = state.config.thumbnails.preview_webp_enabled && accepts_webp(&headers);
+    let webp_threshold = state.config.thumbnails.preview_webp_threshold_bytes;
+
+    let mut resolved: Vec<(i64, Option<(PathBuf, String)>)> = Vec::new();
 
     for (media_id, file_path, media_type, mime_type) in rows {
         let original_path = ORIGINALS_DIR.join(&file_path);
         if !original_path.exists() {
-            previews.insert(media_id, None);
+            resolved.push((media_id, None));
             continue;
         }
 
         if media_type == "video" {
-            previews.insert(media_id, None);
+            resolved.push((media_id, None));
             continue;
         }
 
+        let original_size = tokio::fs::metadata(&original_path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let use_webp = webp_preferred && original_size > webp_threshold;
+
         let web_compatible = ["image/jpeg", "image/png", "image/webp", "image/gif"];
-        if let Some(ref mime) = mime_type {
-            if web_compatible.contains(&mime.as_str()) {
-                if let Ok(data) = tokio::fs::read(&original_path).await {
-                    let encoded = STANDARD.encode(data);
-                    previews.insert(media_id, Some(format!("data:{};base64,{}", mime, encoded)));
-                    continue;
-                }
+        if let Some(mime) = mime_type {
+            if !use_webp && web_compatible.contains(&mime.as_str()) {
+                resolved.push((media_id, Some((original_path, mime))));
+                continue;
             }
         }
 
+        let (extension, preview_mime) = if use_webp {
+            ("webp", "image/webp")
+        } else {
+            ("jpg", "image/jpeg")
+        };
         let preview_filename = format!(
-            "{}_preview.jpg",
-            original_path.file_stem().unwrap().to_string_lossy()
+            "{}_preview.{}",
+            original_path.file_stem().unwrap().to_string_lossy(),
+            extension
         );
         let preview_path = PREVIEWS_DIR
             .join(current_user.id.to_string())
@@ -741,21 +3812,168 @@ async fn get_media_preview_batch(
             generate_image_preview(&original_path, &preview_path, 2048, 90).await;
         }
 
-        if preview_path.exists() {
-            if let Ok(data) = tokio::fs::read(&preview_path).await {
-                let encoded = STANDARD.encode(data);
-                previews.insert(
-                    media_id,
-                    Some(format!("data:image/jpeg;base64,{}", encoded)),
-                );
-                continue;
+        let source = preview_path
+            .exists()
+            .then(|| (preview_path, preview_mime.to_string()));
+        resolved.push((media_id, source));
+    }
+
+    let etag = batch_etag(&resolved);
+    if matches_if_none_match(&headers, &etag) {
+        return Ok(with_etag(StatusCode::NOT_MODIFIED.into_response(), &etag));
+    }
+
+    let mut previews: HashMap<i64, Option<String>> = HashMap::new();
+    for (media_id, source) in resolved {
+        previews.insert(media_id, read_data_url(source).await);
+    }
+
+    Ok(with_etag(
+        Json(PreviewBatchResponse { previews }).into_response(),
+        &etag,
+    ))
+}
+
+/// Deletes the caller's cached previews of a media item they own so the
+/// next `/preview/get` regenerates them.
+#[utoipa::path(
+    delete,
+    path = "/preview/{media_id}",
+    tag = "media",
+    params(("media_id" = i64, Path)),
+    responses((status = 204, description = "Previews removed"), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn delete_media_preview(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Path(media_id): Path<i64>,
+) -> AppResult<StatusCode> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let access_level: i32 = fetch_one(
+        &conn,
+        queries::access::CHECK_MEDIA_ACCESS,
+        &[&media_id, &current_user.id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    if access_level < 2 {
+        return Err(AppError::Forbidden(
+            "Only the owner can purge previews".to_string(),
+        ));
+    }
+
+    let file_path: String = fetch_one(
+        &conn,
+        queries::media::SELECT_FILE_INFO,
+        &[&media_id, &current_user.id],
+        |row| row.get(0),
+    )?
+    .ok_or_else(|| AppError::MediaNotFound)?;
+
+    delete_preview_files(current_user.id, &file_path);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Strong validator for a batch response: an xxh3 hash over the sorted
+/// requested ids, each with the size and modification time of the file
+/// served for it, so asking for a different set of ids never revalidates.
+fn batch_etag<M>(resolved: &[(i64, Option<(PathBuf, M)>)]) -> String {
+    let mut entries: Vec<(i64, Option<(u64, u128)>)> = resolved
+        .iter()
+        .map(|(media_id, source)| {
+            let stamp = source.as_ref().and_then(|(path, _)| {
+                let metadata = std::fs::metadata(path).ok()?;
+                let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+                Some((metadata.len(), mtime.as_nanos()))
+            });
+            (*media_id, stamp)
+        })
+        .collect();
+    entries.sort_unstable();
+
+    let mut hasher = xxhash_rust::xxh3::Xxh3::new();
+    for (media_id, stamp) in entries {
+        hasher.update(&media_id.to_le_bytes());
+        match stamp {
+            Some((size, mtime)) => {
+                hasher.update(&[1]);
+                hasher.update(&size.to_le_bytes());
+                hasher.update(&mtime.to_le_bytes());
             }
+            None => hasher.update(&[0]),
         }
+    }
+    format!("\"{:016x}\"", hasher.digest())
+}
+
+fn matches_if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|candidate| candidate.trim().trim_start_matches("W/"))
+        .any(|candidate| candidate == etag || candidate == "*")
+}
+
+fn with_etag(mut response: Response, etag: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+/// Modification time of `path` truncated to whole seconds, as sent in
+/// `Last-Modified`.
+fn http_mtime(path: &std::path::Path) -> Option<SystemTime> {
+    let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(SystemTime::from(httpdate::HttpDate::from(mtime)))
+}
+
+fn is_not_modified(headers: &HeaderMap, last_modified: SystemTime) -> bool {
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok())
+        .is_some_and(|since| last_modified <= since)
+}
 
-        previews.insert(media_id, None);
+fn not_modified_response(last_modified: SystemTime) -> Response {
+    with_last_modified(
+        StatusCode::NOT_MODIFIED.into_response(),
+        Some(last_modified),
+    )
+}
+
+fn with_last_modified(mut response: Response, last_modified: Option<SystemTime>) -> Response {
+    if let Some(value) =
+        last_modified.and_then(|mtime| HeaderValue::from_str(&httpdate::fmt_http_date(mtime)).ok())
+    {
+        response.headers_mut().insert(header::LAST_MODIFIED, value);
     }
+    response
+}
+
+async fn read_data_url<M: AsRef<str>>(source: Option<(PathBuf, M)>) -> Option<String> {
+    let (path, mime) = source?;
+    let data = tokio::fs::read(&path).await.ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        mime.as_ref(),
+        STANDARD.encode(data)
+    ))
+}
 
-    Ok(Json(PreviewBatchResponse { previews }))
+fn accepts_webp(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|accept| accept.contains("image/webp"))
+        .unwrap_or(false)
 }
 
 async fn serve_file_with_range(
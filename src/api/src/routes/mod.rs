@@ -1,21 +1,30 @@
 mod albums;
 mod auth;
+mod backup;
+mod health;
 mod imports;
 mod map;
 mod media;
 mod public;
 mod share;
+mod storage;
 mod tags;
 mod trash;
 mod users;
 
 use crate::auth::AppState;
+use crate::error::{ErrorBody, ErrorCode};
 use axum::Router;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 
+pub use auth::cleanup_expired_refresh_tokens;
+pub use storage::StorageReportCache;
 pub use trash::cleanup_expired_trash;
 
 pub fn api_router() -> Router<AppState> {
     Router::new()
+        .merge(health::router())
         .merge(auth::router())
         .merge(users::router())
         .merge(media::router())
@@ -28,4 +37,167 @@ pub fn api_router() -> Router<AppState> {
         .merge(public::router())
         .merge(imports::router())
         .merge(trash::router())
+        .merge(backup::router())
+        .merge(storage::router())
+}
+
+/// OpenAPI description of everything under `/api/v1`, served at
+/// `/api/openapi.json`. WebDAV is not covered.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Momento API"),
+    servers((url = "/api/v1")),
+    paths(
+        albums::create_album,
+        albums::create_album_from_tag,
+        albums::list_albums,
+        albums::reorder_albums,
+        albums::get_album,
+        albums::update_album,
+        albums::delete_album,
+        albums::add_media_to_album,
+        albums::remove_media_from_album,
+        albums::clear_album_media,
+        albums::reorder_album_media,
+        albums::merge_albums,
+        albums::get_album_cover,
+        albums::generate_album_cover,
+        auth::login,
+        auth::refresh,
+        auth::logout,
+        auth::change_password,
+        auth::forgot_password,
+        auth::reset_password,
+        auth::list_sessions,
+        auth::revoke_session,
+        backup::trigger_backup,
+        health::liveness,
+        health::readiness,
+        imports::trigger_local_import,
+        imports::trigger_local_directory_import,
+        imports::get_import_job_status,
+        imports::list_import_history,
+        imports::purge_import_history,
+        imports::trigger_regeneration,
+        imports::get_regeneration_job_status,
+        imports::cancel_regeneration_job,
+        imports::trigger_reset,
+        map::get_clusters,
+        map::get_media,
+        map::get_lens_clusters,
+        map::get_nearby_media,
+        map::search_geohash,
+        media::import_media_from_url,
+        media::list_media,
+        media::list_media_by_camera,
+        media::export_media_metadata,
+        media::get_media_batch,
+        media::get_similar_media,
+        media::upload_media,
+        media::update_media,
+        media::correct_media_dates,
+        media::get_media_edit_history,
+        media::update_media_metadata,
+        media::manual_geocode_media,
+        media::strip_media_location,
+        media::rotate_media,
+        media::detect_media_orientation,
+        media::regenerate_media_thumbnail,
+        media::reprocess_media,
+        media::delete_media,
+        media::bulk_delete_media,
+        media::set_media_favorite,
+        media::duplicate_media,
+        media::check_duplicate,
+        media::transfer_media,
+        media::get_media_file,
+        media::get_media_exif,
+        media::create_media_download_token,
+        media::download_media_file,
+        media::get_lens_models,
+        media::get_cameras,
+        media::get_focal_lengths,
+        media::get_slideshow,
+        media::get_timeline_dates,
+        media::get_media_stats,
+        media::get_media_locations,
+        media::list_date_mismatches,
+        media::list_duplicates,
+        media::suggest_smart_albums,
+        media::get_media_thumbnail_batch,
+        media::get_media_thumbnail,
+        media::get_media_preview_batch,
+        media::delete_media_preview,
+        public::get_shared_content,
+        public::verify_share_password,
+        public::report_share,
+        public::get_shared_media_file,
+        public::get_shared_thumbnail,
+        share::create_share_link,
+        share::list_share_links,
+        share::delete_share_link,
+        share::extend_share_link,
+        share::rotate_share_token,
+        share::share_media_with_user,
+        share::share_album_with_user,
+        share::list_share_reports,
+        storage::analyze_storage,
+        storage::cleanup_orphans,
+        tags::list_tags,
+        tags::create_tag,
+        tags::delete_tag,
+        tags::add_tag_to_media,
+        tags::remove_tag_from_media,
+        tags::merge_tags,
+        trash::list_trash,
+        trash::restore_from_trash,
+        trash::permanently_delete,
+        trash::empty_trash,
+        users::create_user,
+        users::list_users,
+        users::get_user,
+        users::update_user,
+        users::delete_user,
+        users::reset_user_password,
+        users::delete_account,
+    ),
+    components(schemas(ErrorBody, ErrorCode)),
+    tags(
+        (name = "albums", description = "Albums, ordering and covers"),
+        (name = "auth", description = "Login, token refresh and sessions"),
+        (name = "backup", description = "Database backups"),
+        (name = "health", description = "Liveness and readiness probes"),
+        (name = "imports", description = "Local imports and regeneration jobs"),
+        (name = "map", description = "Geographic clustering and search"),
+        (name = "media", description = "Media items, files, thumbnails and previews"),
+        (name = "public", description = "Public share links"),
+        (name = "share", description = "Managing share links and user shares"),
+        (name = "storage", description = "Storage analysis and cleanup"),
+        (name = "tags", description = "Tags"),
+        (name = "trash", description = "Soft-deleted media"),
+        (name = "users", description = "User administration and accounts"),
+    ),
+    modifiers(&SecuritySchemes)
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Basic).build()),
+        );
+    }
 }
@@ -1,26 +1,35 @@
 use axum::{
     body::Body,
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
     response::Response,
     routing::{get, post},
     Json, Router,
 };
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use std::net::SocketAddr;
 use tokio::fs::File;
 use tokio_util::io::ReaderStream;
+use utoipa::IntoParams;
 
-use crate::auth::{verify_password, AppState};
-use crate::constants::{ORIGINALS_DIR, THUMBNAILS_DIR};
+use crate::auth::{hash_client_ip, verify_password, AppState};
+use crate::config::ThumbnailFormat;
+use crate::constants::{
+    ORIGINALS_DIR, SHARE_REPORT_MAX_REASON_LENGTH, SHARE_REPORT_MIN_REASON_LENGTH,
+    SHARE_REPORT_RATE_LIMIT, SHARE_REPORT_RATE_WINDOW_MINUTES, THUMBNAILS_DIR,
+};
 use crate::database::{execute_query, fetch_all, fetch_one, queries, DbConn};
-use crate::error::{AppError, AppResult};
-use crate::models::{MediaResponse, ShareVerifyRequest};
+use crate::error::{AppError, AppResult, ErrorResponses};
+use crate::models::{MediaResponse, SharePermissions, ShareReportRequest, ShareVerifyRequest};
+use crate::utils::network::resolve_client_ip;
+use crate::utils::validation::validate_share_token_format;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/public/share/:token", get(get_shared_content))
         .route("/public/share/:token/verify", post(verify_share_password))
+        .route("/public/share/:token/report", post(report_share))
         .route(
             "/public/share/:token/media/:media_id",
             get(get_shared_media_file),
@@ -31,7 +40,8 @@ pub fn router() -> Router<AppState> {
         )
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct PasswordQuery {
     password: Option<String>,
 }
@@ -42,9 +52,15 @@ struct ShareRow {
     album_id: Option<i64>,
     password_hash: Option<String>,
     expires_at: Option<String>,
+    permissions: SharePermissions,
+    suspended_at: Option<String>,
 }
 
 fn validate_share_token(conn: &DbConn, token: &str, password: Option<&str>) -> AppResult<ShareRow> {
+    if !validate_share_token_format(token) {
+        return Err(AppError::NotFound("Share link not found".to_string()));
+    }
+
     let share = fetch_one(conn, queries::share::SELECT_BY_TOKEN, &[&token], |row| {
         Ok(ShareRow {
             id: row.get(0)?,
@@ -52,10 +68,16 @@ fn validate_share_token(conn: &DbConn, token: &str, password: Option<&str>) -> A
             album_id: row.get(2)?,
             password_hash: row.get(3)?,
             expires_at: row.get(4)?,
+            permissions: SharePermissions::from_db(&row.get::<_, String>(5)?),
+            suspended_at: row.get(6)?,
         })
     })?
     .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
 
+    if share.suspended_at.is_some() {
+        return Err(AppError::NotFound("Share link suspended".to_string()));
+    }
+
     // Check expiration
     if let Some(expires_at) = &share.expires_at {
         if let Ok(dt) = DateTime::parse_from_rfc3339(expires_at) {
@@ -66,9 +88,9 @@ fn validate_share_token(conn: &DbConn, token: &str, password: Option<&str>) -> A
     }
 
     // Check password
-    if share.password_hash.is_some() {
+    if let Some(password_hash) = &share.password_hash {
         if let Some(pwd) = password {
-            if !verify_password(pwd, share.password_hash.as_ref().unwrap()) {
+            if !verify_password(pwd, password_hash) {
                 return Err(AppError::Authentication("Invalid password".to_string()));
             }
         } else {
@@ -113,12 +135,24 @@ fn map_public_media_row(row: &rusqlite::Row) -> rusqlite::Result<MediaResponse>
         location_state: row.get(23)?,
         location_country: row.get(24)?,
         video_codec: row.get(25)?,
-        keywords: row.get(26)?,
-        created_at: row.get(27)?,
+        audio_codec: row.get(26)?,
+        audio_channels: row.get(27)?,
+        audio_sample_rate: row.get(28)?,
+        keywords: row.get(29)?,
+        created_at: row.get(30)?,
+        has_date_mismatch: row.get(31)?,
+        is_favorite: false,
         content_hash: None,
     })
 }
 
+#[utoipa::path(
+    get,
+    path = "/public/share/{token}",
+    tag = "public",
+    params(("token" = String, Path), PasswordQuery),
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+)]
 async fn get_shared_content(
     State(state): State<AppState>,
     Path(token): Path<String>,
@@ -138,7 +172,7 @@ async fn get_shared_content(
             &[&media_id],
             map_public_media_row,
         )?
-        .ok_or_else(|| AppError::NotFound("Media not found".to_string()))?;
+        .ok_or_else(|| AppError::MediaNotFound)?;
 
         return Ok(Json(serde_json::json!({
             "type": "media",
@@ -182,11 +216,23 @@ async fn get_shared_content(
     Err(AppError::Internal("Invalid share link".to_string()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/public/share/{token}/verify",
+    tag = "public",
+    request_body = ShareVerifyRequest,
+    params(("token" = String, Path)),
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+)]
 async fn verify_share_password(
     State(state): State<AppState>,
     Path(token): Path<String>,
     Json(request): Json<ShareVerifyRequest>,
 ) -> AppResult<Json<serde_json::Value>> {
+    if !validate_share_token_format(&token) {
+        return Err(AppError::NotFound("Share link not found".to_string()));
+    }
+
     let conn = state.pool.get().map_err(AppError::Pool)?;
 
     let share = fetch_one(
@@ -218,6 +264,13 @@ async fn verify_share_password(
     })))
 }
 
+#[utoipa::path(
+    get,
+    path = "/public/share/{token}/media/{media_id}",
+    tag = "public",
+    params(("token" = String, Path), ("media_id" = i64, Path), PasswordQuery),
+    responses((status = 200, description = "Original file", content_type = "application/octet-stream"), ErrorResponses),
+)]
 async fn get_shared_media_file(
     State(state): State<AppState>,
     Path((token, media_id)): Path<(String, i64)>,
@@ -226,6 +279,12 @@ async fn get_shared_media_file(
     let conn = state.pool.get().map_err(AppError::Pool)?;
     let share = validate_share_token(&conn, &token, query.password.as_deref())?;
 
+    if share.permissions == SharePermissions::ViewOnly {
+        return Err(AppError::Forbidden(
+            "Downloads are disabled for this share link".to_string(),
+        ));
+    }
+
     // Verify media is in share
     if let Some(share_media_id) = share.media_id {
         if share_media_id != media_id {
@@ -260,7 +319,7 @@ async fn get_shared_media_file(
             })
         },
     )?
-    .ok_or_else(|| AppError::NotFound("Media not found".to_string()))?;
+    .ok_or_else(|| AppError::MediaNotFound)?;
 
     let full_path = ORIGINALS_DIR.join(&media.file_path);
     if !full_path.exists() {
@@ -283,6 +342,13 @@ struct FileInfo {
     original_filename: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/public/share/{token}/thumbnail/{media_id}",
+    tag = "public",
+    params(("token" = String, Path), ("media_id" = i64, Path), PasswordQuery),
+    responses((status = 200, description = "Thumbnail image", content_type = "image/*"), ErrorResponses),
+)]
 async fn get_shared_thumbnail(
     State(state): State<AppState>,
     Path((token, media_id)): Path<(String, i64)>,
@@ -322,7 +388,7 @@ async fn get_shared_thumbnail(
         &[&media_id],
         |row| row.get(0),
     )?
-    .ok_or_else(|| AppError::NotFound("Media not found".to_string()))?;
+    .ok_or_else(|| AppError::MediaNotFound)?;
 
     let thumbnail_path =
         thumbnail_path.ok_or_else(|| AppError::NotFound("Thumbnail not available".to_string()))?;
@@ -332,7 +398,8 @@ async fn get_shared_thumbnail(
         return Err(AppError::NotFound("Thumbnail file not found".to_string()));
     }
 
-    serve_file(full_path, "image/jpeg", None).await
+    let mime_type = ThumbnailFormat::from_path(&full_path).mime_type();
+    serve_file(full_path, mime_type, None).await
 }
 
 async fn serve_file(
@@ -359,3 +426,83 @@ async fn serve_file(
         .body(body)
         .map_err(|e| AppError::Internal(e.to_string()))
 }
+
+/// Lets anyone flag a public share. The share itself is never looked up,
+/// so the response is the same whether or not the token exists. Once more
+/// than `share.report_suspend_threshold` distinct clients have reported a
+/// token, its share is suspended until an admin reviews it. Clients are
+/// told apart by their connection address, or by the forwarded address
+/// when the connection comes from one of `server.trusted_proxies`.
+#[utoipa::path(
+    post,
+    path = "/public/share/{token}/report",
+    tag = "public",
+    params(("token" = String, Path)),
+    request_body = ShareReportRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+)]
+async fn report_share(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<ShareReportRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !validate_share_token_format(&token) {
+        return Err(AppError::Validation("Invalid share token".to_string()));
+    }
+    let reason = request.reason.trim();
+    let reason_length = reason.chars().count();
+    if reason_length < SHARE_REPORT_MIN_REASON_LENGTH {
+        return Err(AppError::Validation(format!(
+            "Reason must be at least {} characters",
+            SHARE_REPORT_MIN_REASON_LENGTH
+        )));
+    }
+    if reason_length > SHARE_REPORT_MAX_REASON_LENGTH {
+        return Err(AppError::Validation(format!(
+            "Reason must be at most {} characters",
+            SHARE_REPORT_MAX_REASON_LENGTH
+        )));
+    }
+
+    let client_ip = resolve_client_ip(&headers, peer.ip(), &state.config.server.trusted_proxies);
+    let ip_hash = hash_client_ip(&client_ip.to_string(), &state.config);
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let recent_reports: i64 = fetch_one(
+        &conn,
+        queries::share::COUNT_RECENT_REPORTS_BY_CLIENT,
+        &[
+            &ip_hash,
+            &format!("-{} minutes", SHARE_REPORT_RATE_WINDOW_MINUTES),
+        ],
+        |row| row.get(0),
+    )?
+    .unwrap_or(0);
+    if recent_reports >= SHARE_REPORT_RATE_LIMIT {
+        return Err(AppError::TooManyRequests(
+            "Too many reports; try again later".to_string(),
+        ));
+    }
+
+    execute_query(
+        &conn,
+        queries::share::INSERT_REPORT,
+        &[&token, &ip_hash, &reason],
+    )?;
+
+    let reporters: i64 = fetch_one(&conn, queries::share::COUNT_REPORTERS, &[&token], |row| {
+        row.get(0)
+    })?
+    .unwrap_or(0);
+    if reporters > state.config.share.report_suspend_threshold {
+        execute_query(
+            &conn,
+            queries::share::SUSPEND_BY_TOKEN,
+            &[&Utc::now().to_rfc3339(), &token],
+        )?;
+    }
+
+    Ok(Json(serde_json::json!({"message": "Report received"})))
+}
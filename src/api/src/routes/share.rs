@@ -1,22 +1,33 @@
-use axum::{extract::State, routing::post, Json, Router};
-use chrono::{Duration, Utc};
+use axum::{
+    extract::State,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
 use rand::Rng;
 
-use crate::auth::{hash_password, AppState, CurrentUser};
-use crate::database::{execute_query, fetch_all, fetch_one, insert_returning_id, queries};
-use crate::error::{AppError, AppResult};
+use crate::auth::{hash_password, AppState, CurrentUser, RequireAdmin};
+use crate::config::ServerConfig;
+use crate::constants::SHARE_TOKEN_LENGTH;
+use crate::database::{execute_query, fetch_all, fetch_one, insert_returning_id, queries, DbConn};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    ShareAlbumRequest, ShareCreateRequest, ShareDeleteRequest, ShareLinkResponse,
-    ShareListResponse, ShareMediaRequest,
+    ShareAlbumRequest, ShareCreateRequest, ShareDeleteRequest, ShareExtendRequest,
+    ShareLinkResponse, ShareListResponse, ShareMediaRequest, SharePermissions, ShareReportResponse,
+    ShareRotateTokenRequest,
 };
+use crate::utils::datetime::days_after;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/share/create", post(create_share_link))
         .route("/share/list", post(list_share_links))
         .route("/share/delete", post(delete_share_link))
+        .route("/share/extend", post(extend_share_link))
+        .route("/share/rotate-token", post(rotate_share_token))
         .route("/share/media", post(share_media_with_user))
         .route("/share/album", post(share_album_with_user))
+        .route("/admin/reports", get(list_share_reports))
 }
 
 fn map_share_row(row: &rusqlite::Row) -> rusqlite::Result<ShareLinkResponse> {
@@ -29,10 +40,66 @@ fn map_share_row(row: &rusqlite::Row) -> rusqlite::Result<ShareLinkResponse> {
         has_password: password_hash.is_some(),
         expires_at: row.get(5)?,
         view_count: row.get(6)?,
+        permissions: SharePermissions::from_db(&row.get::<_, String>(8)?),
         created_at: row.get(7)?,
+        public_url: None,
     })
 }
 
+fn with_public_url(mut share: ShareLinkResponse, server: &ServerConfig) -> ShareLinkResponse {
+    share.public_url = server.public_share_url(&share.token);
+    share
+}
+
+fn generate_share_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(SHARE_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Expiry timestamp `days` from now.
+fn share_expiry(days: i64) -> AppResult<String> {
+    days_after(Utc::now(), days)
+        .map(|expires_at| expires_at.to_rfc3339())
+        .ok_or_else(|| AppError::BadRequest("expiresInDays is out of range".to_string()))
+}
+
+fn check_share_ownership(conn: &DbConn, share_id: i64, user_id: i64) -> AppResult<()> {
+    fetch_one(
+        conn,
+        queries::share::CHECK_OWNERSHIP,
+        &[&share_id, &user_id],
+        |row| row.get::<_, i64>(0),
+    )?
+    .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))?;
+    Ok(())
+}
+
+fn load_share_link(
+    conn: &DbConn,
+    server: &ServerConfig,
+    share_id: i64,
+) -> AppResult<ShareLinkResponse> {
+    fetch_one(
+        conn,
+        queries::share::SELECT_BY_ID,
+        &[&share_id],
+        map_share_row,
+    )?
+    .map(|share| with_public_url(share, server))
+    .ok_or_else(|| AppError::NotFound("Share link not found".to_string()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/share/create",
+    tag = "share",
+    request_body = ShareCreateRequest,
+    responses((status = 200, description = "OK", body = ShareLinkResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn create_share_link(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -61,7 +128,7 @@ async fn create_share_link(
         )?;
 
         if exists.is_none() {
-            return Err(AppError::NotFound("Media not found".to_string()));
+            return Err(AppError::MediaNotFound);
         }
     }
 
@@ -78,11 +145,7 @@ async fn create_share_link(
         }
     }
 
-    let token: String = rand::thread_rng()
-        .sample_iter(&rand::distributions::Alphanumeric)
-        .take(22)
-        .map(char::from)
-        .collect();
+    let token = generate_share_token();
 
     let password_hash = request
         .password
@@ -93,7 +156,8 @@ async fn create_share_link(
 
     let expires_at = request
         .expires_in_days
-        .map(|days| (Utc::now() + Duration::days(days as i64)).to_rfc3339());
+        .map(|days| share_expiry(days.into()))
+        .transpose()?;
 
     let share_id = insert_returning_id(
         &conn,
@@ -105,6 +169,7 @@ async fn create_share_link(
             &token,
             &password_hash,
             &expires_at,
+            &request.permissions.as_str(),
         ],
     )?;
 
@@ -116,9 +181,16 @@ async fn create_share_link(
     )?
     .ok_or_else(|| AppError::Internal("Failed to create share link".to_string()))?;
 
-    Ok(Json(share))
+    Ok(Json(with_public_url(share, &state.config.server)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/share/list",
+    tag = "share",
+    responses((status = 200, description = "OK", body = ShareListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn list_share_links(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -132,9 +204,22 @@ async fn list_share_links(
         map_share_row,
     )?;
 
+    let shares = shares
+        .into_iter()
+        .map(|share| with_public_url(share, &state.config.server))
+        .collect();
+
     Ok(Json(ShareListResponse { shares }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/share/delete",
+    tag = "share",
+    request_body = ShareDeleteRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn delete_share_link(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -160,6 +245,76 @@ async fn delete_share_link(
     ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/share/extend",
+    tag = "share",
+    request_body = ShareExtendRequest,
+    responses((status = 200, description = "OK", body = ShareLinkResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn extend_share_link(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<ShareExtendRequest>,
+) -> AppResult<Json<ShareLinkResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    check_share_ownership(&conn, request.share_id, current_user.id)?;
+
+    let expires_at = request
+        .expires_in_days
+        .map(|days| share_expiry(days.into()))
+        .transpose()?;
+    execute_query(
+        &conn,
+        queries::share::UPDATE_EXPIRES_AT,
+        &[&expires_at, &request.share_id],
+    )?;
+
+    Ok(Json(load_share_link(
+        &conn,
+        &state.config.server,
+        request.share_id,
+    )?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/share/rotate-token",
+    tag = "share",
+    request_body = ShareRotateTokenRequest,
+    responses((status = 200, description = "OK", body = ShareLinkResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn rotate_share_token(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<ShareRotateTokenRequest>,
+) -> AppResult<Json<ShareLinkResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    check_share_ownership(&conn, request.share_id, current_user.id)?;
+
+    execute_query(
+        &conn,
+        queries::share::UPDATE_TOKEN,
+        &[&generate_share_token(), &request.share_id],
+    )?;
+
+    Ok(Json(load_share_link(
+        &conn,
+        &state.config.server,
+        request.share_id,
+    )?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/share/media",
+    tag = "share",
+    request_body = ShareMediaRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn share_media_with_user(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -173,7 +328,7 @@ async fn share_media_with_user(
         &[&request.media_id, &current_user.id],
         |row| row.get(0),
     )?
-    .ok_or_else(|| AppError::NotFound("Media not found".to_string()))?;
+    .ok_or_else(|| AppError::MediaNotFound)?;
 
     if access_level < 2 {
         return Err(AppError::Forbidden(
@@ -196,6 +351,14 @@ async fn share_media_with_user(
     ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/share/album",
+    tag = "share",
+    request_body = ShareAlbumRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn share_album_with_user(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -228,3 +391,30 @@ async fn share_album_with_user(
         serde_json::json!({"message": "Album shared successfully"}),
     ))
 }
+
+/// Abuse reports filed through `/public/share/{token}/report`, newest first.
+#[utoipa::path(
+    get,
+    path = "/admin/reports",
+    tag = "share",
+    responses((status = 200, description = "OK", body = Vec<ShareReportResponse>), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn list_share_reports(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+) -> AppResult<Json<Vec<ShareReportResponse>>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+    let reports = fetch_all(&conn, queries::share::SELECT_REPORTS, &[], |row| {
+        Ok(ShareReportResponse {
+            id: row.get(0)?,
+            token: row.get(1)?,
+            reason: row.get(2)?,
+            created_at: row.get(3)?,
+            share_id: row.get(4)?,
+            share_suspended_at: row.get(5)?,
+        })
+    })?;
+
+    Ok(Json(reports))
+}
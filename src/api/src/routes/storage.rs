@@ -0,0 +1,170 @@
+use axum::{extract::State, routing::post, Json, Router};
+use chrono::Utc;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::auth::{AppState, RequireAdmin};
+use crate::constants::{
+    ALBUM_COVERS_DIR, PREVIEWS_DIR, STORAGE_REPORT_CACHE_SECONDS, THUMBNAILS_DIR,
+    THUMBNAILS_TINY_DIR,
+};
+use crate::database::{fetch_all, queries, DbPool};
+use crate::error::{AppError, AppResult, ErrorResponses};
+use crate::models::{
+    OrphanCleanupResponse, StorageAnalysisResponse, StorageUsageEntry, UserTrashUsage,
+};
+use crate::processor::orphans::{is_known_preview, remove_orphan_files, OrphanCleanup};
+
+/// Last `/admin/storage/analyze` report and when it was computed.
+#[derive(Default)]
+pub struct StorageReportCache {
+    report: Mutex<Option<(Instant, serde_json::Value)>>,
+}
+
+impl StorageReportCache {
+    /// The cached report, unless it is older than
+    /// `STORAGE_REPORT_CACHE_SECONDS`.
+    fn fresh(&self) -> Option<serde_json::Value> {
+        let report = self.report.lock().unwrap();
+        report
+            .as_ref()
+            .filter(|(computed_at, _)| {
+                computed_at.elapsed() < Duration::from_secs(STORAGE_REPORT_CACHE_SECONDS)
+            })
+            .map(|(_, report)| report.clone())
+    }
+
+    fn store(&self, report: serde_json::Value) {
+        *self.report.lock().unwrap() = Some((Instant::now(), report));
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/storage/analyze", post(analyze_storage))
+        .route("/admin/storage/cleanup-orphans", post(cleanup_orphans))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/storage/cleanup-orphans",
+    tag = "storage",
+    responses((status = 200, description = "OK", body = OrphanCleanupResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn cleanup_orphans(
+    State(state): State<AppState>,
+    RequireAdmin(admin): RequireAdmin,
+) -> AppResult<Json<OrphanCleanupResponse>> {
+    let (thumbnails, original_stems) = {
+        let conn = state.pool.get().map_err(AppError::Pool)?;
+        let thumbnails: HashSet<String> = fetch_all(
+            &conn,
+            queries::storage::SELECT_THUMBNAIL_PATHS,
+            &[],
+            |row| row.get(0),
+        )?
+        .into_iter()
+        .collect();
+        let original_stems: HashSet<String> =
+            fetch_all(&conn, queries::storage::SELECT_FILE_PATHS, &[], |row| {
+                row.get::<_, String>(0)
+            })?
+            .into_iter()
+            .filter_map(|file_path| {
+                Path::new(&file_path)
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(str::to_string)
+            })
+            .collect();
+        (thumbnails, original_stems)
+    };
+
+    let cleanup = tokio::task::spawn_blocking(move || {
+        // Album covers are tracked by album id rather than in the database
+        let excluded = [ALBUM_COVERS_DIR.as_path()];
+        let mut cleanup = OrphanCleanup::default();
+        for dir in [&*THUMBNAILS_DIR, &*THUMBNAILS_TINY_DIR] {
+            cleanup +=
+                remove_orphan_files(dir, &excluded, |relative| thumbnails.contains(relative));
+        }
+        cleanup += remove_orphan_files(&PREVIEWS_DIR, &[], |relative| {
+            is_known_preview(relative, &original_stems)
+        });
+        cleanup
+    })
+    .await
+    .map_err(|e| AppError::Internal(format!("Orphan cleanup failed: {}", e)))?;
+
+    tracing::info!(
+        "Orphan cleanup by user {} removed {} files ({} bytes)",
+        admin.id,
+        cleanup.deleted_files,
+        cleanup.freed_bytes
+    );
+
+    Ok(Json(OrphanCleanupResponse {
+        deleted_files: cleanup.deleted_files,
+        freed_bytes: cleanup.freed_bytes,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/storage/analyze",
+    tag = "storage",
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn analyze_storage(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+) -> AppResult<Json<serde_json::Value>> {
+    if let Some(report) = state.storage_report_cache.fresh() {
+        return Ok(Json(report));
+    }
+
+    // Computed without holding the cache, so a slow analysis never blocks
+    // requests that could be served the previous report
+    let pool = state.pool.clone();
+    let report = tokio::task::spawn_blocking(move || build_storage_report(&pool))
+        .await
+        .map_err(|e| AppError::Internal(format!("Storage analysis failed: {}", e)))??;
+    state.storage_report_cache.store(report.clone());
+
+    Ok(Json(report))
+}
+
+fn build_storage_report(pool: &DbPool) -> AppResult<serde_json::Value> {
+    let conn = pool.get().map_err(AppError::Pool)?;
+    let usage = fetch_all(
+        &conn,
+        queries::storage::SELECT_USAGE_BREAKDOWN,
+        &[],
+        |row| {
+            Ok(StorageUsageEntry {
+                user_id: row.get(0)?,
+                month: row.get(1)?,
+                media_type: row.get(2)?,
+                total_bytes: row.get(3)?,
+                file_count: row.get(4)?,
+            })
+        },
+    )?;
+    let trash = fetch_all(&conn, queries::storage::SELECT_TRASH_USAGE, &[], |row| {
+        Ok(UserTrashUsage {
+            user_id: row.get(0)?,
+            trash_bytes: row.get(1)?,
+        })
+    })?;
+
+    serde_json::to_value(StorageAnalysisResponse {
+        usage,
+        trash,
+        generated_at: Utc::now().to_rfc3339(),
+    })
+    .map_err(|e| AppError::Internal(e.to_string()))
+}
@@ -2,10 +2,10 @@ use axum::{extract::State, routing::post, Json, Router};
 
 use crate::auth::{AppState, CurrentUser, RequireAdmin};
 use crate::database::{execute_query, fetch_all, fetch_one, insert_returning_id, queries};
-use crate::error::{AppError, AppResult};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    TagAddToMediaRequest, TagCreateRequest, TagDeleteRequest, TagListResponse,
-    TagRemoveFromMediaRequest, TagResponse,
+    TagAddToMediaRequest, TagCreateRequest, TagDeleteRequest, TagListResponse, TagMergeRequest,
+    TagMergeResponse, TagRemoveFromMediaRequest, TagResponse,
 };
 
 pub fn router() -> Router<AppState> {
@@ -15,6 +15,7 @@ pub fn router() -> Router<AppState> {
         .route("/tag/delete", post(delete_tag))
         .route("/tag/add-to-media", post(add_tag_to_media))
         .route("/tag/remove-from-media", post(remove_tag_from_media))
+        .route("/tag/merge", post(merge_tags))
 }
 
 fn map_tag_row(row: &rusqlite::Row) -> rusqlite::Result<TagResponse> {
@@ -25,6 +26,13 @@ fn map_tag_row(row: &rusqlite::Row) -> rusqlite::Result<TagResponse> {
     })
 }
 
+#[utoipa::path(
+    post,
+    path = "/tag/list",
+    tag = "tags",
+    responses((status = 200, description = "OK", body = TagListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn list_tags(
     State(state): State<AppState>,
     _current_user: CurrentUser,
@@ -36,6 +44,14 @@ async fn list_tags(
     Ok(Json(TagListResponse { tags }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tag/create",
+    tag = "tags",
+    request_body = TagCreateRequest,
+    responses((status = 200, description = "OK", body = TagResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn create_tag(
     State(state): State<AppState>,
     _current_user: CurrentUser,
@@ -63,6 +79,14 @@ async fn create_tag(
     Ok(Json(tag))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tag/delete",
+    tag = "tags",
+    request_body = TagDeleteRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn delete_tag(
     State(state): State<AppState>,
     RequireAdmin(_): RequireAdmin,
@@ -88,6 +112,14 @@ async fn delete_tag(
     ))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tag/add-to-media",
+    tag = "tags",
+    request_body = TagAddToMediaRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn add_tag_to_media(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -128,6 +160,14 @@ async fn add_tag_to_media(
     Ok(Json(serde_json::json!({"message": "Tag added to media"})))
 }
 
+#[utoipa::path(
+    post,
+    path = "/tag/remove-from-media",
+    tag = "tags",
+    request_body = TagRemoveFromMediaRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn remove_tag_from_media(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -156,3 +196,71 @@ async fn remove_tag_from_media(
         serde_json::json!({"message": "Tag removed from media"}),
     ))
 }
+
+#[utoipa::path(
+    post,
+    path = "/tag/merge",
+    tag = "tags",
+    request_body = TagMergeRequest,
+    responses((status = 200, description = "OK", body = TagMergeResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn merge_tags(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+    Json(request): Json<TagMergeRequest>,
+) -> AppResult<Json<TagMergeResponse>> {
+    let mut conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let mut source_ids = request.source_tag_ids.clone();
+    source_ids.sort_unstable();
+    source_ids.dedup();
+
+    if source_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "At least one source tag is required".to_string(),
+        ));
+    }
+    if source_ids.contains(&request.target_tag_id) {
+        return Err(AppError::BadRequest(
+            "Target tag cannot also be a source tag".to_string(),
+        ));
+    }
+
+    for tag_id in source_ids
+        .iter()
+        .chain(std::iter::once(&request.target_tag_id))
+    {
+        let exists = fetch_one(&conn, queries::tags::CHECK_EXISTS, &[tag_id], |row| {
+            row.get::<_, i64>(0)
+        })?;
+        if exists.is_none() {
+            return Err(AppError::NotFound(format!("Tag {} not found", tag_id)));
+        }
+    }
+
+    let count_query = queries::tags::build_count_media_for_tags(source_ids.len());
+    let params: Vec<&dyn rusqlite::ToSql> = source_ids
+        .iter()
+        .map(|id| id as &dyn rusqlite::ToSql)
+        .collect();
+    let affected_media =
+        fetch_one(&conn, &count_query, &params, |row| row.get::<_, i64>(0))?.unwrap_or(0);
+
+    let tx = conn.transaction()?;
+    for source_id in &source_ids {
+        tx.execute(
+            queries::tags::REASSIGN_MEDIA_TAGS,
+            rusqlite::params![request.target_tag_id, source_id],
+        )?;
+        // Rows left behind already had the target tag
+        tx.execute(queries::tags::DELETE_MEDIA_TAGS_BY_TAG, [source_id])?;
+        tx.execute(queries::tags::DELETE, [source_id])?;
+    }
+    tx.commit()?;
+
+    Ok(Json(TagMergeResponse {
+        merged_count: source_ids.len() as i64,
+        affected_media,
+    }))
+}
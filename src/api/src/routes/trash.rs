@@ -1,14 +1,17 @@
 use axum::{extract::State, routing::post, Json, Router};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 
 use crate::auth::{AppState, CurrentUser};
-use crate::constants::TRASH_RETENTION_DAYS;
-use crate::database::{execute_query, fetch_all, fetch_one, queries};
-use crate::error::{AppError, AppResult};
+use crate::config::TrashConfig;
+use crate::database::{execute_query, fetch_all, fetch_one, queries, DbConn};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
     TrashDeleteRequest, TrashListResponse, TrashMediaResponse, TrashResponse, TrashRestoreRequest,
 };
-use crate::processor::media_processor::{delete_from_rtree, delete_media_files};
+use crate::processor::media_processor::{
+    delete_from_rtree, delete_media_files, delete_preview_files,
+};
+use crate::utils::datetime::days_before;
 
 pub fn router() -> Router<AppState> {
     Router::new()
@@ -35,6 +38,13 @@ fn map_trash_row(row: &rusqlite::Row) -> rusqlite::Result<TrashMediaResponse> {
     })
 }
 
+#[utoipa::path(
+    post,
+    path = "/trash/list",
+    tag = "trash",
+    responses((status = 200, description = "OK", body = TrashListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn list_trash(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -53,6 +63,14 @@ async fn list_trash(
     Ok(Json(TrashListResponse { items, total_count }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/trash/restore",
+    tag = "trash",
+    request_body = TrashRestoreRequest,
+    responses((status = 200, description = "OK", body = TrashResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn restore_from_trash(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -84,6 +102,7 @@ async fn restore_from_trash(
     let sql = queries::trash::RESTORE_MEDIA.replace("{}", &placeholders);
     let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
     execute_query(&conn, &sql, &param_refs)?;
+    state.cluster_cache.invalidate();
 
     Ok(Json(TrashResponse {
         message: "Media restored successfully".to_string(),
@@ -91,6 +110,14 @@ async fn restore_from_trash(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/trash/delete",
+    tag = "trash",
+    request_body = TrashDeleteRequest,
+    responses((status = 200, description = "OK", body = TrashResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn permanently_delete(
     State(state): State<AppState>,
     current_user: CurrentUser,
@@ -159,22 +186,42 @@ async fn permanently_delete(
     }))
 }
 
-struct MediaFileInfo {
+pub(super) struct MediaFileInfo {
     id: i64,
     file_path: String,
     thumbnail_path: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/trash/empty",
+    tag = "trash",
+    responses((status = 200, description = "OK", body = TrashResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn empty_trash(
     State(state): State<AppState>,
     current_user: CurrentUser,
 ) -> AppResult<Json<TrashResponse>> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
 
+    let (deleted_count, orphaned) = purge_user_trash(&conn, current_user.id)?;
+    delete_orphaned_files(&orphaned);
+
+    Ok(Json(TrashResponse {
+        message: "Trash emptied".to_string(),
+        affected_count: deleted_count,
+    }))
+}
+
+/// Drops `user_id`'s access to everything in their trash and deletes the
+/// rows of media nobody else can access. Returns the number of trashed items
+/// and the deleted media, whose files the caller removes.
+fn purge_user_trash(conn: &DbConn, user_id: i64) -> AppResult<(i64, Vec<MediaFileInfo>)> {
     let rows: Vec<MediaFileInfo> = fetch_all(
-        &conn,
+        conn,
         queries::trash::SELECT_ALL_DELETED,
-        &[&current_user.id],
+        &[&user_id],
         |row| {
             Ok(MediaFileInfo {
                 id: row.get(0)?,
@@ -185,41 +232,86 @@ async fn empty_trash(
     )?;
 
     let mut deleted_count = 0;
+    let mut orphaned = Vec::new();
     for row in rows {
-        execute_query(
-            &conn,
-            queries::trash::DELETE_ACCESS,
-            &[&row.id, &current_user.id],
-        )?;
+        execute_query(conn, queries::trash::DELETE_ACCESS, &[&row.id, &user_id])?;
 
         let access_count: i64 =
-            fetch_one(&conn, queries::trash::CHECK_ACCESS_COUNT, &[&row.id], |r| {
+            fetch_one(conn, queries::trash::CHECK_ACCESS_COUNT, &[&row.id], |r| {
                 r.get(0)
             })?
             .unwrap_or(0);
 
         if access_count == 0 {
-            let _ = delete_from_rtree(&conn, row.id);
-            delete_media_files(&row.file_path, row.thumbnail_path.as_deref());
-            execute_query(&conn, queries::trash::DELETE_PERMANENTLY, &[&row.id])?;
+            let _ = delete_from_rtree(conn, row.id);
+            execute_query(conn, queries::trash::DELETE_PERMANENTLY, &[&row.id])?;
+            orphaned.push(row);
         }
 
         deleted_count += 1;
     }
 
-    Ok(Json(TrashResponse {
-        message: "Trash emptied".to_string(),
-        affected_count: deleted_count,
-    }))
+    Ok((deleted_count, orphaned))
 }
 
-pub fn cleanup_expired_trash(conn: &crate::database::DbConn) -> AppResult<i64> {
-    let cutoff_date = (Utc::now() - Duration::days(TRASH_RETENTION_DAYS)).to_rfc3339();
+pub(super) fn delete_orphaned_files(orphaned: &[MediaFileInfo]) {
+    for row in orphaned {
+        delete_media_files(&row.file_path, row.thumbnail_path.as_deref());
+    }
+}
+
+/// Trashes everything `user_id` can access and purges it at once rather
+/// than after the configured retention. Files are left on disk so a caller
+/// inside a transaction can commit before `delete_orphaned_files`.
+pub(super) fn purge_all_user_media(conn: &DbConn, user_id: i64) -> AppResult<Vec<MediaFileInfo>> {
+    let deleted_at = Utc::now().to_rfc3339();
+    execute_query(
+        conn,
+        queries::trash::TRASH_ALL_FOR_USER,
+        &[&deleted_at, &user_id],
+    )?;
+
+    let (_, orphaned) = purge_user_trash(conn, user_id)?;
+    Ok(orphaned)
+}
+
+/// Deletes the cached previews of everything currently in a trash; they
+/// are regenerated on demand if the media is restored.
+fn cleanup_trashed_previews(conn: &DbConn) -> AppResult<usize> {
+    let sources: Vec<(i64, String)> = fetch_all(
+        conn,
+        queries::trash::SELECT_TRASHED_PREVIEW_SOURCES,
+        &[],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    Ok(sources
+        .iter()
+        .map(|(user_id, file_path)| delete_preview_files(*user_id, file_path))
+        .sum())
+}
+
+/// Purges trash older than the retention for the role of the user who
+/// trashed it, after dropping the previews of everything in a trash.
+pub fn cleanup_expired_trash(conn: &DbConn, config: &TrashConfig) -> AppResult<i64> {
+    let removed_previews = cleanup_trashed_previews(conn)?;
+    if removed_previews > 0 {
+        tracing::info!("Removed {} previews of trashed media", removed_previews);
+    }
+
+    let now = Utc::now();
+    let cutoff = |days: i64, setting: &str| {
+        days_before(now, days)
+            .map(|cutoff| cutoff.to_rfc3339())
+            .ok_or_else(|| AppError::Validation(format!("trash.{} is out of range", setting)))
+    };
+    let admin_cutoff = cutoff(config.retention_days_admin, "retention_days_admin")?;
+    let user_cutoff = cutoff(config.retention_days_user, "retention_days_user")?;
 
     let rows: Vec<MediaFileInfoWithUser> = fetch_all(
         conn,
         queries::trash::SELECT_OLD_DELETED,
-        &[&cutoff_date],
+        &[&admin_cutoff, &user_cutoff],
         |row| {
             Ok(MediaFileInfoWithUser {
                 id: row.get(0)?,
@@ -1,17 +1,25 @@
 use axum::{
-    extract::{Query, State},
-    routing::post,
+    extract::{Path, Query, State},
+    routing::{delete, post},
     Json, Router,
 };
+use rand::Rng;
 use serde::Deserialize;
+use utoipa::IntoParams;
 
-use crate::auth::{hash_password, AppState, CurrentUser, RequireAdmin};
+use super::trash::{delete_orphaned_files, purge_all_user_media};
+use crate::auth::{
+    hash_password, verify_and_migrate, AllowPasswordChange, AppState, CurrentUser, RequireAdmin,
+};
 use crate::database::{execute_query, fetch_all, fetch_one, insert_returning_id, queries};
-use crate::error::{AppError, AppResult};
+use crate::error::{AppError, AppResult, ErrorResponses};
 use crate::models::{
-    UserCreateRequest, UserDeleteRequest, UserListResponse, UserResponse, UserUpdateRequest,
+    AccountDeleteRequest, TemporaryPasswordResponse, UserCreateRequest, UserDeleteRequest,
+    UserListResponse, UserResponse, UserUpdateRequest,
 };
 
+const TEMPORARY_PASSWORD_LENGTH: usize = 16;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/user/create", post(create_user))
@@ -19,6 +27,8 @@ pub fn router() -> Router<AppState> {
         .route("/user/get", post(get_user))
         .route("/user/update", post(update_user))
         .route("/user/delete", post(delete_user))
+        .route("/admin/users/:id/reset-password", post(reset_user_password))
+        .route("/user/account", delete(delete_account))
 }
 
 fn row_to_user_response(
@@ -41,6 +51,14 @@ fn row_to_user_response(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/create",
+    tag = "users",
+    request_body = UserCreateRequest,
+    responses((status = 200, description = "OK", body = UserResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn create_user(
     State(state): State<AppState>,
     RequireAdmin(_): RequireAdmin,
@@ -93,6 +111,13 @@ async fn create_user(
     Ok(Json(user))
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/list",
+    tag = "users",
+    responses((status = 200, description = "OK", body = UserListResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn list_users(
     State(state): State<AppState>,
     RequireAdmin(_): RequireAdmin,
@@ -114,9 +139,17 @@ async fn list_users(
     Ok(Json(UserListResponse { users }))
 }
 
+// Reachable while a password change is pending so clients can find out
+#[utoipa::path(
+    post,
+    path = "/user/get",
+    tag = "users",
+    responses((status = 200, description = "OK", body = UserResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn get_user(
     State(state): State<AppState>,
-    current_user: CurrentUser,
+    AllowPasswordChange(current_user): AllowPasswordChange,
 ) -> AppResult<Json<UserResponse>> {
     let conn = state.pool.get().map_err(AppError::Pool)?;
 
@@ -141,11 +174,21 @@ async fn get_user(
     Ok(Json(user))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct UserIdQuery {
     user_id: i64,
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/update",
+    tag = "users",
+    request_body = UserUpdateRequest,
+    params(UserIdQuery),
+    responses((status = 200, description = "OK", body = UserResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn update_user(
     State(state): State<AppState>,
     RequireAdmin(admin): RequireAdmin,
@@ -209,6 +252,14 @@ async fn update_user(
     Ok(Json(user))
 }
 
+#[utoipa::path(
+    post,
+    path = "/user/delete",
+    tag = "users",
+    request_body = UserDeleteRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
 async fn delete_user(
     State(state): State<AppState>,
     RequireAdmin(admin): RequireAdmin,
@@ -237,3 +288,112 @@ async fn delete_user(
         serde_json::json!({"message": "User deleted successfully"}),
     ))
 }
+
+/// Self-service deletion of the caller's account together with their
+/// media, albums and share links. Admins have to be removed by another
+/// admin through `/user/delete`.
+#[utoipa::path(
+    delete,
+    path = "/user/account",
+    tag = "users",
+    request_body = AccountDeleteRequest,
+    responses((status = 200, description = "OK", body = serde_json::Value), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn delete_account(
+    State(state): State<AppState>,
+    current_user: CurrentUser,
+    Json(request): Json<AccountDeleteRequest>,
+) -> AppResult<Json<serde_json::Value>> {
+    if !request.confirm {
+        return Err(AppError::BadRequest(
+            "Account deletion must be confirmed".to_string(),
+        ));
+    }
+
+    if current_user.role == "admin" {
+        return Err(AppError::Forbidden(
+            "Admins cannot delete their own account; use /user/delete".to_string(),
+        ));
+    }
+
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let password_hash = fetch_one(
+        &conn,
+        queries::auth::SELECT_PASSWORD_HASH,
+        &[&current_user.id],
+        |row| row.get::<_, String>(0),
+    )?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let (valid, _) = verify_and_migrate(&request.password, &password_hash);
+    if !valid {
+        return Err(AppError::BadRequest("Password is incorrect".to_string()));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let orphaned = purge_all_user_media(&conn, current_user.id)?;
+    execute_query(
+        &conn,
+        queries::users::DELETE_ALBUM_ACCESS,
+        &[&current_user.id],
+    )?;
+    execute_query(
+        &conn,
+        queries::users::DELETE_SHARE_LINKS,
+        &[&current_user.id],
+    )?;
+    execute_query(&conn, queries::users::DELETE, &[&current_user.id])?;
+    tx.commit()?;
+
+    delete_orphaned_files(&orphaned);
+
+    Ok(Json(
+        serde_json::json!({"message": "Account deleted successfully"}),
+    ))
+}
+
+/// Replaces the user's password with a random one that is returned only in
+/// this response. The user has to change it before using anything else.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/reset-password",
+    tag = "users",
+    params(("id" = i64, Path)),
+    responses((status = 200, description = "OK", body = TemporaryPasswordResponse), ErrorResponses),
+    security(("bearer" = [])),
+)]
+async fn reset_user_password(
+    State(state): State<AppState>,
+    RequireAdmin(_): RequireAdmin,
+    Path(user_id): Path<i64>,
+) -> AppResult<Json<TemporaryPasswordResponse>> {
+    let conn = state.pool.get().map_err(AppError::Pool)?;
+
+    let exists = fetch_one(&conn, queries::users::CHECK_EXISTS, &[&user_id], |row| {
+        row.get::<_, i64>(0)
+    })?;
+
+    if exists.is_none() {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let temporary_password: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(TEMPORARY_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect();
+
+    let hashed = hash_password(&temporary_password)
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
+
+    execute_query(
+        &conn,
+        queries::users::SET_TEMPORARY_PASSWORD,
+        &[&hashed, &user_id],
+    )?;
+    execute_query(&conn, queries::auth::REVOKE_ALL_USER_TOKENS, &[&user_id])?;
+
+    Ok(Json(TemporaryPasswordResponse { temporary_password }))
+}
@@ -1,6 +1,8 @@
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 
 pub fn parse_datetime(dt_str: &str) -> Option<DateTime<Utc>> {
+    let dt_str = dt_str.trim();
+
     // Try ISO 8601 format first
     if let Ok(dt) = DateTime::parse_from_rfc3339(dt_str) {
         return Some(dt.with_timezone(&Utc));
@@ -9,9 +11,9 @@ pub fn parse_datetime(dt_str: &str) -> Option<DateTime<Utc>> {
     // Try common formats
     let formats = [
         "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S%.f",
         "%Y-%m-%d %H:%M:%S",
         "%Y:%m:%d %H:%M:%S",
-        "%Y-%m-%d",
     ];
 
     let clean_str = dt_str.replace("Z", "");
@@ -21,9 +23,29 @@ pub fn parse_datetime(dt_str: &str) -> Option<DateTime<Utc>> {
         }
     }
 
+    // Date-only values are treated as midnight UTC
+    for fmt in ["%Y-%m-%d", "%Y:%m:%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(&clean_str, fmt) {
+            let naive = date.and_hms_opt(0, 0, 0)?;
+            return Some(DateTime::from_naive_utc_and_offset(naive, Utc));
+        }
+    }
+
     None
 }
 
 pub fn format_datetime(dt: &DateTime<Utc>) -> String {
     dt.to_rfc3339()
 }
+
+/// `now` minus `days` whole days, or `None` when the result would be out of
+/// range for chrono.
+pub fn days_before(now: DateTime<Utc>, days: i64) -> Option<DateTime<Utc>> {
+    chrono::Duration::try_days(days).and_then(|days| now.checked_sub_signed(days))
+}
+
+/// `now` plus `days` whole days, or `None` when the result would be out of
+/// range for chrono.
+pub fn days_after(now: DateTime<Utc>, days: i64) -> Option<DateTime<Utc>> {
+    chrono::Duration::try_days(days).and_then(|days| now.checked_add_signed(days))
+}
@@ -0,0 +1,237 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use reqwest::{redirect, Url};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+
+use crate::constants::{
+    IMAGE_EXTENSIONS, IMPORT_URL_MAX_REDIRECTS, IMPORT_URL_TIMEOUT_SECONDS, VIDEO_EXTENSIONS,
+};
+use crate::error::{AppError, AppResult};
+
+/// RFC 1918 ranges plus loopback, link-local, CGNAT, benchmarking,
+/// multicast, reserved and the IPv6 equivalents including NAT64: nothing a
+/// public media URL should resolve to.
+pub fn is_blocked_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [first, second, ..] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || first == 0
+                || first >= 240
+                || (first == 100 && (second & 0xc0) == 64)
+                || (first == 198 && (second & 0xfe) == 18)
+        }
+        IpAddr::V6(v6) => {
+            let segments = v6.segments();
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                || v6
+                    .to_ipv4_mapped()
+                    .is_some_and(|v4| is_blocked_address(IpAddr::V4(v4)))
+        }
+    }
+}
+
+fn parse_https_url(url: &str) -> AppResult<Url> {
+    let parsed =
+        Url::parse(url).map_err(|e| AppError::BadRequest(format!("Invalid URL: {}", e)))?;
+    if parsed.scheme() != "https" {
+        return Err(AppError::BadRequest(
+            "Only https:// URLs can be imported".to_string(),
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Resolves the URL's host and rejects it when any address is private, so
+/// a hostname cannot smuggle in an internal target. Every redirect target
+/// goes through this again.
+pub async fn resolve_public_address(url: &Url) -> AppResult<SocketAddr> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| AppError::BadRequest("URL has no host".to_string()))?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|_| AppError::BadRequest(format!("Could not resolve host {}", host)))?
+        .collect();
+
+    if addresses.iter().any(|addr| is_blocked_address(addr.ip())) {
+        return Err(AppError::BadRequest(
+            "URL resolves to a private address".to_string(),
+        ));
+    }
+
+    addresses
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::BadRequest(format!("Could not resolve host {}", host)))
+}
+
+/// Extracts `filename` from a `Content-Disposition` header value.
+pub fn content_disposition_filename(value: &str) -> Option<String> {
+    value.split(';').find_map(|part| {
+        let (key, value) = part.trim().split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("filename") {
+            return None;
+        }
+        let name = value.trim().trim_matches('"');
+        (!name.is_empty()).then(|| name.to_string())
+    })
+}
+
+fn sanitize_filename(name: &str) -> Option<String> {
+    Path::new(name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+fn is_media_extension(extension: &str) -> bool {
+    let dotted = format!(".{}", extension.to_lowercase());
+    IMAGE_EXTENSIONS.contains(dotted.as_str()) || VIDEO_EXTENSIONS.contains(dotted.as_str())
+}
+
+/// Picks the explicit name, then `Content-Disposition`, then the last URL
+/// path segment, appending an extension from the MIME type when the name
+/// has no recognised one.
+fn choose_filename(
+    requested: Option<&str>,
+    headers: &HeaderMap,
+    url: &Url,
+    content_type: &str,
+) -> String {
+    let filename = requested
+        .and_then(sanitize_filename)
+        .or_else(|| {
+            headers
+                .get(CONTENT_DISPOSITION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(content_disposition_filename)
+                .and_then(|name| sanitize_filename(&name))
+        })
+        .or_else(|| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(sanitize_filename)
+        })
+        .unwrap_or_else(|| "download".to_string());
+
+    let has_media_extension = Path::new(&filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(is_media_extension);
+    if has_media_extension {
+        return filename;
+    }
+
+    let extension = mime_guess::get_mime_extensions_str(content_type)
+        .and_then(|extensions| extensions.iter().find(|ext| is_media_extension(ext)));
+    match extension {
+        Some(extension) => format!("{}.{}", filename, extension),
+        None => filename,
+    }
+}
+
+/// Downloads an image or video from a public `https://` URL into
+/// `dest_dir`, following redirects only to other public `https://` URLs.
+/// Returns the path of the downloaded file.
+pub async fn download_media(
+    url: &str,
+    filename: Option<&str>,
+    dest_dir: &Path,
+    max_bytes: u64,
+) -> AppResult<PathBuf> {
+    let mut url = parse_https_url(url)?;
+
+    for _ in 0..=IMPORT_URL_MAX_REDIRECTS {
+        let address = resolve_public_address(&url).await?;
+        let mut builder = reqwest::Client::builder()
+            .redirect(redirect::Policy::none())
+            .timeout(Duration::from_secs(IMPORT_URL_TIMEOUT_SECONDS));
+        // Pin the checked address so a second DNS lookup cannot differ
+        if let Some(domain) = url.domain() {
+            builder = builder.resolve(domain, address);
+        }
+        let client = builder.build()?;
+
+        let mut response = client.get(url.clone()).send().await?;
+
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| AppError::BadRequest("Redirect without location".to_string()))?;
+            let next = url
+                .join(location)
+                .map_err(|e| AppError::BadRequest(format!("Invalid redirect: {}", e)))?;
+            url = parse_https_url(next.as_str())?;
+            continue;
+        }
+
+        if !response.status().is_success() {
+            return Err(AppError::BadRequest(format!(
+                "Download failed with status {}",
+                response.status()
+            )));
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_lowercase())
+            .unwrap_or_default();
+        if !content_type.starts_with("image/") && !content_type.starts_with("video/") {
+            return Err(AppError::Unprocessable(format!(
+                "Unsupported content type: {}",
+                content_type
+            )));
+        }
+
+        let declared_length = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if declared_length.is_some_and(|length| length > max_bytes) {
+            return Err(AppError::BadRequest("File too large".to_string()));
+        }
+
+        let filename = choose_filename(filename, response.headers(), &url, &content_type);
+        tokio::fs::create_dir_all(dest_dir).await?;
+        let path = dest_dir.join(filename);
+        let mut file = File::create(&path).await?;
+
+        let mut written: u64 = 0;
+        while let Some(chunk) = response.chunk().await? {
+            written += chunk.len() as u64;
+            if written > max_bytes {
+                return Err(AppError::BadRequest("File too large".to_string()));
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        return Ok(path);
+    }
+
+    Err(AppError::BadRequest("Too many redirects".to_string()))
+}
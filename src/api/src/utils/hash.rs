@@ -2,6 +2,9 @@ use sha2::{Digest, Sha256};
 use std::path::Path;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::constants::QUICK_FINGERPRINT_BYTES;
 
 /// Calculate SHA256 hash of a file
 /// Uses buffered reading for memory efficiency with large media files
@@ -21,6 +24,20 @@ pub async fn calculate_file_hash(path: &Path) -> std::io::Result<String> {
     Ok(hex_encode(hasher.finalize()))
 }
 
+/// Cheap pre-check fingerprint: the file size plus an xxh3 hash of the
+/// first `QUICK_FINGERPRINT_BYTES`. Equal fingerprints only suggest equal
+/// content; `calculate_file_hash` is the authority.
+pub async fn calculate_quick_fingerprint(path: &Path) -> std::io::Result<String> {
+    let file = File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let mut head = Vec::with_capacity(QUICK_FINGERPRINT_BYTES);
+    file.take(QUICK_FINGERPRINT_BYTES as u64)
+        .read_to_end(&mut head)
+        .await?;
+
+    Ok(format!("{}:{:016x}", size, xxh3_64(&head)))
+}
+
 fn hex_encode(bytes: impl AsRef<[u8]>) -> String {
     bytes
         .as_ref()
@@ -28,3 +45,81 @@ fn hex_encode(bytes: impl AsRef<[u8]>) -> String {
         .map(|b| format!("{:02x}", b))
         .collect()
 }
+
+const PHASH_SAMPLE_SIZE: usize = 32;
+const PHASH_BLOCK_SIZE: usize = 8;
+
+/// 64-bit DCT perceptual hash of an image file.
+/// The image is reduced to 32x32 grayscale and each bit records the sign of
+/// one low-frequency DCT coefficient, so the hash survives resizing and
+/// recompression. Returns `None` if the file cannot be decoded.
+pub fn calculate_perceptual_hash(path: &Path) -> Option<i64> {
+    let image = image::open(path).ok()?;
+    Some(perceptual_hash_of(&image))
+}
+
+pub fn perceptual_hash_of(image: &image::DynamicImage) -> i64 {
+    let size = PHASH_SAMPLE_SIZE as u32;
+    let pixels = image
+        .resize_exact(size, size, image::imageops::FilterType::Triangle)
+        .to_luma8();
+
+    let cosines: Vec<Vec<f64>> = (0..PHASH_BLOCK_SIZE)
+        .map(|k| {
+            (0..PHASH_SAMPLE_SIZE)
+                .map(|n| {
+                    ((2 * n + 1) as f64 * k as f64 * std::f64::consts::PI
+                        / (2 * PHASH_SAMPLE_SIZE) as f64)
+                        .cos()
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut hash = 0u64;
+    for v in 0..PHASH_BLOCK_SIZE {
+        for u in 0..PHASH_BLOCK_SIZE {
+            let mut coefficient = 0.0;
+            for (x, y, pixel) in pixels.enumerate_pixels() {
+                // Centre on zero so the DC term carries no bias into the AC signs
+                let value = pixel.0[0] as f64 - 128.0;
+                coefficient += value * cosines[u][x as usize] * cosines[v][y as usize];
+            }
+            if coefficient > 0.0 {
+                hash |= 1 << (v * PHASH_BLOCK_SIZE + u);
+            }
+        }
+    }
+
+    hash as i64
+}
+
+/// Hash of the same image turned by 90 degrees. Transposing the pixels
+/// transposes the DCT block and mirroring one axis negates its odd
+/// frequencies, so this needs only the hash bits.
+fn rotate_perceptual_hash(hash: i64) -> i64 {
+    let hash = hash as u64;
+    let mut rotated = 0u64;
+    for v in 0..PHASH_BLOCK_SIZE {
+        for u in 0..PHASH_BLOCK_SIZE {
+            let mut bit = (hash >> (u * PHASH_BLOCK_SIZE + v)) & 1;
+            if u % 2 == 1 {
+                bit ^= 1;
+            }
+            rotated |= bit << (v * PHASH_BLOCK_SIZE + u);
+        }
+    }
+    rotated as i64
+}
+
+/// Hamming distance between two perceptual hashes, taking the closest of
+/// the four quarter-turn orientations so rotated copies still match.
+pub fn perceptual_hash_distance(a: i64, b: i64) -> u32 {
+    let mut rotated = b;
+    let mut best = u32::MAX;
+    for _ in 0..4 {
+        best = best.min((a ^ rotated).count_ones());
+        rotated = rotate_perceptual_hash(rotated);
+    }
+    best
+}
@@ -0,0 +1,48 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use crate::config::SmtpConfig;
+
+pub async fn send_email(
+    config: &SmtpConfig,
+    to: &str,
+    subject: &str,
+    body: &str,
+) -> Result<(), String> {
+    if config.host.is_empty() {
+        return Err("SMTP host is not configured".to_string());
+    }
+
+    let from: Mailbox = config
+        .from_address
+        .parse()
+        .map_err(|e| format!("Invalid from address: {}", e))?;
+    let to: Mailbox = to
+        .parse()
+        .map_err(|e| format!("Invalid recipient address: {}", e))?;
+
+    let message = Message::builder()
+        .from(from)
+        .to(to)
+        .subject(subject)
+        .body(body.to_string())
+        .map_err(|e| format!("Failed to build email: {}", e))?;
+
+    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+        .map_err(|e| format!("Failed to configure SMTP transport: {}", e))?
+        .port(config.port);
+    if !config.username.is_empty() {
+        builder = builder.credentials(Credentials::new(
+            config.username.clone(),
+            config.password.clone(),
+        ));
+    }
+
+    builder
+        .build()
+        .send(message)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to send email: {}", e))
+}
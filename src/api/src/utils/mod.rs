@@ -1,3 +1,8 @@
 pub mod datetime;
+pub mod download;
 pub mod geocoding;
 pub mod hash;
+pub mod mail;
+pub mod network;
+pub mod shuffle;
+pub mod validation;
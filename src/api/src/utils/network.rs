@@ -0,0 +1,92 @@
+use axum::http::HeaderMap;
+use std::net::IpAddr;
+
+/// The first `X-Forwarded-For` hop, then `X-Real-IP`, else `"unknown"`.
+/// Both headers are client-controlled unless a proxy overwrites them.
+pub fn client_ip(headers: &HeaderMap) -> String {
+    if let Some(value) = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+    {
+        if let Some(ip) = value.split(',').next() {
+            let trimmed = ip.trim();
+            if !trimmed.is_empty() {
+                return trimmed.to_string();
+            }
+        }
+    }
+
+    if let Some(value) = headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+    {
+        let trimmed = value.trim();
+        if !trimmed.is_empty() {
+            return trimmed.to_string();
+        }
+    }
+
+    "unknown".to_string()
+}
+
+/// The address a request really came from: the TCP `peer`, unless the peer
+/// is one of `trusted_proxies`, in which case it is the nearest
+/// `X-Forwarded-For` hop the proxies did not add themselves, then
+/// `X-Real-IP`. Headers from untrusted peers are ignored, as anyone can
+/// send them.
+pub fn resolve_client_ip(headers: &HeaderMap, peer: IpAddr, trusted_proxies: &[String]) -> IpAddr {
+    if !is_trusted_proxy(peer, trusted_proxies) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| hop.trim().parse().ok())
+        .collect();
+    if let Some(first) = forwarded.first() {
+        return forwarded
+            .iter()
+            .rev()
+            .find(|hop| !is_trusted_proxy(**hop, trusted_proxies))
+            .unwrap_or(first)
+            .to_owned();
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+/// Whether `ip` matches one of `trusted_proxies`, each a single address or
+/// a CIDR range such as `10.0.0.0/8`.
+fn is_trusted_proxy(ip: IpAddr, trusted_proxies: &[String]) -> bool {
+    trusted_proxies.iter().any(|entry| {
+        let (address, prefix) = match entry.split_once('/') {
+            Some((address, prefix)) => (address, prefix.parse::<u32>().ok()),
+            None => (entry.as_str(), None),
+        };
+        match (ip, address.trim().parse::<IpAddr>()) {
+            (IpAddr::V4(ip), Ok(IpAddr::V4(network))) => {
+                prefix_matches(u32::from(ip).into(), u32::from(network).into(), 32, prefix)
+            }
+            (IpAddr::V6(ip), Ok(IpAddr::V6(network))) => {
+                prefix_matches(u128::from(ip), u128::from(network), 128, prefix)
+            }
+            _ => false,
+        }
+    })
+}
+
+fn prefix_matches(ip: u128, network: u128, bits: u32, prefix: Option<u32>) -> bool {
+    let prefix = prefix.unwrap_or(bits);
+    if prefix > bits {
+        return false;
+    }
+    let shift = bits - prefix;
+    shift == bits || ip >> shift == network >> shift
+}
@@ -0,0 +1,38 @@
+/// Xorshift64 generator. Not cryptographic; only used where a shuffle has
+/// to be reproducible from a client-supplied seed.
+struct Xorshift64 {
+    state: u64,
+}
+
+impl Xorshift64 {
+    fn new(seed: u64) -> Self {
+        // An all-zero state would only ever produce zeros
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Self {
+            state: if state == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                state
+            },
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+}
+
+/// Fisher-Yates shuffle driven by `Xorshift64`; the same seed and input
+/// always give the same order.
+pub fn seeded_shuffle<T>(items: &mut [T], seed: u64) {
+    let mut rng = Xorshift64::new(seed);
+    for i in (1..items.len()).rev() {
+        let j = (rng.next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
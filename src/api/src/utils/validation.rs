@@ -0,0 +1,7 @@
+use crate::constants::SHARE_TOKEN_LENGTH;
+
+/// Share tokens are `SHARE_TOKEN_LENGTH` ASCII alphanumerics, as generated
+/// in `routes/share.rs`. Anything else cannot match a stored token.
+pub fn validate_share_token_format(token: &str) -> bool {
+    token.len() == SHARE_TOKEN_LENGTH && token.bytes().all(|b| b.is_ascii_alphanumeric())
+}
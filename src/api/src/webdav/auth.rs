@@ -1,15 +1,17 @@
 use axum::{
     body::Body,
     extract::State,
-    http::{header, HeaderMap, Request, StatusCode},
+    http::{header, Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use base64::Engine;
-use tracing::{error, warn};
+use tracing::{error, info, warn};
 
 use crate::auth::{verify_password, AppState};
+use crate::constants::{WEBDAV_DIR, WEBDAV_WELCOME_FILENAME, WEBDAV_WELCOME_TEXT};
 use crate::database::{fetch_one, queries};
+use crate::utils::network::client_ip;
 
 #[derive(Clone)]
 pub struct WebDAVUser {
@@ -114,6 +116,8 @@ pub async fn basic_auth_middleware(
         return unauthorized_response(&state.config.webdav.realm);
     }
 
+    ensure_user_root(&db_username).await;
+
     request.extensions_mut().insert(WebDAVUser {
         id: user_id,
         username: db_username,
@@ -138,6 +142,31 @@ pub async fn path_guard_middleware(request: Request<Body>, next: Next) -> Respon
     }
 }
 
+/// Creates `WEBDAV_DIR/<username>` with a welcome note the first time the
+/// user connects, so the import job has a directory to watch.
+async fn ensure_user_root(username: &str) {
+    let user_root = WEBDAV_DIR.join(username);
+    let result = tokio::task::spawn_blocking(move || -> std::io::Result<bool> {
+        if user_root.exists() {
+            return Ok(false);
+        }
+        std::fs::create_dir_all(&user_root)?;
+        std::fs::write(user_root.join(WEBDAV_WELCOME_FILENAME), WEBDAV_WELCOME_TEXT)?;
+        Ok(true)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(true)) => info!("Created WebDAV directory for user {}", username),
+        Ok(Ok(false)) => {}
+        Ok(Err(e)) => error!(
+            "Failed to create WebDAV directory for user {}: {}",
+            username, e
+        ),
+        Err(e) => error!("WebDAV directory task failed for user {}: {}", username, e),
+    }
+}
+
 fn unauthorized_response(realm: &str) -> Response {
     (
         StatusCode::UNAUTHORIZED,
@@ -149,29 +178,3 @@ fn unauthorized_response(realm: &str) -> Response {
     )
         .into_response()
 }
-
-fn client_ip(headers: &HeaderMap) -> String {
-    if let Some(value) = headers
-        .get("x-forwarded-for")
-        .and_then(|value| value.to_str().ok())
-    {
-        if let Some(ip) = value.split(',').next() {
-            let trimmed = ip.trim();
-            if !trimmed.is_empty() {
-                return trimmed.to_string();
-            }
-        }
-    }
-
-    if let Some(value) = headers
-        .get("x-real-ip")
-        .and_then(|value| value.to_str().ok())
-    {
-        let trimmed = value.trim();
-        if !trimmed.is_empty() {
-            return trimmed.to_string();
-        }
-    }
-
-    "unknown".to_string()
-}
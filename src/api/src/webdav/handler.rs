@@ -1,7 +1,7 @@
 use axum::{
     body::{Body, Bytes},
     extract::Request,
-    http::{header, Method, StatusCode},
+    http::{header, HeaderValue, Method, StatusCode},
     response::Response,
 };
 use dav_server::{fakels::FakeLs, localfs::LocalFs, DavHandler};
@@ -9,18 +9,59 @@ use http_body_util::BodyExt;
 use std::path::Path;
 use tracing::{debug, error, info, trace};
 
-pub fn create_dav_handler(webdav_root: &Path) -> DavHandler {
-    std::fs::create_dir_all(webdav_root).ok();
-
+pub fn create_dav_handler(webdav_root: &Path, prefix: &str) -> DavHandler {
     DavHandler::builder()
+        .strip_prefix(prefix)
         .filesystem(LocalFs::new(webdav_root, false, false, false))
         .locksystem(FakeLs::new())
         .autoindex(true)
         .build_handler()
 }
 
+const PROPFIND_FINITE_DEPTH_ERROR: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:error xmlns:D="DAV:"><D:propfind-finite-depth/></D:error>
+"#;
+
+/// Refuses `Depth: infinity` PROPFIND requests (RFC 4918 §9.1) so a single
+/// request cannot walk an entire tree. A missing header would otherwise be
+/// treated as infinity, so it is pinned to `1` instead.
+fn limit_propfind_depth(parts: &mut axum::http::request::Parts) -> Option<Response> {
+    if parts.method.as_str() != "PROPFIND" {
+        return None;
+    }
+
+    match parts
+        .headers
+        .get("depth")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(depth) if depth.trim().eq_ignore_ascii_case("infinity") => {
+            info!(
+                "WebDAV PROPFIND with infinite depth rejected: {}",
+                parts.uri.path()
+            );
+            Some(
+                Response::builder()
+                    .status(StatusCode::FORBIDDEN)
+                    .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+                    .body(Body::from(PROPFIND_FINITE_DEPTH_ERROR))
+                    .unwrap(),
+            )
+        }
+        Some(_) => None,
+        None => {
+            parts.headers.insert("depth", HeaderValue::from_static("1"));
+            None
+        }
+    }
+}
+
 pub async fn handle_webdav_request(dav_handler: DavHandler, request: Request) -> Response {
-    let (parts, body) = request.into_parts();
+    let (mut parts, body) = request.into_parts();
+    if let Some(response) = limit_propfind_depth(&mut parts) {
+        return response;
+    }
+
     let method = parts.method.clone();
     let path = parts.uri.path().to_string();
     let content_length = parts
@@ -3,8 +3,13 @@ mod handler;
 
 use axum::http::{uri::PathAndQuery, StatusCode, Uri};
 use axum::{
-    body::Body, extract::Request, middleware, response::IntoResponse, response::Response,
-    routing::any, Router,
+    body::Body,
+    extract::{Request, State},
+    middleware,
+    response::IntoResponse,
+    response::Response,
+    routing::any,
+    Router,
 };
 
 use crate::auth::AppState;
@@ -14,7 +19,7 @@ pub use auth::WebDAVUser;
 use auth::{basic_auth_middleware, path_guard_middleware};
 use handler::{create_dav_handler, handle_webdav_request};
 
-async fn webdav_handler(request: Request<Body>) -> Response {
+async fn webdav_handler(State(state): State<AppState>, request: Request<Body>) -> Response {
     let (mut parts, body) = request.into_parts();
     let user = parts.extensions.get::<WebDAVUser>().cloned();
     let Some(user) = user else {
@@ -24,10 +29,13 @@ async fn webdav_handler(request: Request<Body>) -> Response {
     let path = parts.uri.path();
     let stripped = path.strip_prefix("/webdav").unwrap_or(path);
     let stripped = if stripped.is_empty() { "/" } else { stripped };
+    // dav_server strips this prefix again and adds it to PROPFIND hrefs,
+    // so listings point at the public `/webdav` location
+    let prefix = format!("{}/webdav", state.config.server.base_path());
     let normalized_path = if stripped.starts_with('/') {
-        stripped.to_string()
+        format!("{}{}", prefix, stripped)
     } else {
-        format!("/{}", stripped)
+        format!("{}/{}", prefix, stripped)
     };
     let new_path = match parts.uri.query() {
         Some(query) => format!("{}?{}", normalized_path, query),
@@ -54,7 +62,7 @@ async fn webdav_handler(request: Request<Body>) -> Response {
     let request = Request::from_parts(parts, body);
 
     let user_root = WEBDAV_DIR.join(&user.username);
-    let dav_handler = create_dav_handler(&user_root);
+    let dav_handler = create_dav_handler(&user_root, &prefix);
 
     handle_webdav_request(dav_handler, request).await
 }
@@ -0,0 +1,18 @@
+use momento_api::config::try_load_config;
+use std::io::Write;
+
+fn load_yaml(yaml: &str) -> Result<momento_api::config::Config, String> {
+    let mut file = tempfile::NamedTempFile::new().expect("Failed to create config file");
+    file.write_all(yaml.as_bytes())
+        .expect("Failed to write config file");
+    try_load_config(file.path())
+}
+
+#[test]
+fn test_compression_min_size_rejects_values_above_u16() {
+    let config = load_yaml("server:\n  compression_min_size_bytes: 65535\n").unwrap();
+    assert_eq!(config.server.compression_min_size_bytes, 65535);
+
+    let error = load_yaml("server:\n  compression_min_size_bytes: 65536\n").unwrap_err();
+    assert!(error.contains("compression_min_size_bytes"), "{}", error);
+}
@@ -0,0 +1,2 @@
+mod pool;
+mod search;
@@ -0,0 +1,59 @@
+use momento_api::database::DbConn;
+use momento_api::processor::media_processor::refresh_search_text;
+
+use crate::test_utils::{create_test_db, create_test_media};
+
+fn fts_matches(conn: &DbConn, term: &str) -> Vec<i64> {
+    let mut stmt = conn
+        .prepare("SELECT rowid FROM fts_media WHERE fts_media MATCH ? ORDER BY rowid")
+        .unwrap();
+    stmt.query_map([term], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_fts_index_follows_search_text_writes() {
+    let pool = create_test_db();
+    let conn = pool.get().expect("Failed to get connection");
+    let media_id = create_test_media(&pool, "harbour.jpg");
+    conn.execute(
+        "UPDATE media_metadata SET keywords = 'sailboat' WHERE media_id = ?",
+        [media_id],
+    )
+    .unwrap();
+
+    refresh_search_text(&conn, media_id).unwrap();
+    assert_eq!(fts_matches(&conn, "sailboat"), vec![media_id]);
+
+    conn.execute(
+        "UPDATE media_metadata SET keywords = 'lighthouse' WHERE media_id = ?",
+        [media_id],
+    )
+    .unwrap();
+    refresh_search_text(&conn, media_id).unwrap();
+    assert!(fts_matches(&conn, "sailboat").is_empty());
+    assert_eq!(fts_matches(&conn, "lighthouse"), vec![media_id]);
+
+    conn.execute(
+        "INSERT INTO media (filename, original_filename, file_path, media_type, mime_type, file_size, content_hash, created_at, search_text) \
+         VALUES ('pier.jpg', 'pier.jpg', '/tmp/pier.jpg', 'image', 'image/jpeg', 1, 'hash_pier', datetime('now'), 'pier lighthouse')",
+        [],
+    )
+    .unwrap();
+    let inserted_id = conn.last_insert_rowid();
+    assert_eq!(
+        fts_matches(&conn, "lighthouse"),
+        vec![media_id, inserted_id]
+    );
+
+    conn.execute("DELETE FROM media WHERE id = ?", [media_id])
+        .unwrap();
+    assert_eq!(fts_matches(&conn, "lighthouse"), vec![inserted_id]);
+    conn.execute(
+        "INSERT INTO fts_media (fts_media, rank) VALUES ('integrity-check', 1)",
+        [],
+    )
+    .expect("FTS index should match the media table");
+}
@@ -0,0 +1,356 @@
+//! Route tests that drive exiftool and jpegtran through shell stand-ins.
+//! They run in their own binary because the stand-ins are put on `PATH`
+//! for the whole process, which would change what other tests see.
+
+#[allow(dead_code)]
+mod test_utils;
+
+use axum_test::TestServer;
+use momento_api::constants::PREVIEWS_DIR;
+use momento_api::database::DbPool;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::test_utils::{
+    create_test_app, create_test_media, create_test_token, create_test_user, grant_media_access,
+};
+
+/// Keeps metadata for FILE in FILE.json and the EXIF orientation in a
+/// `#orientation=N` trailer appended to FILE itself, so the tag travels with
+/// the bytes through temp files and renames. A `#strip-fails` marker makes
+/// removing the tag fail.
+const FAKE_EXIFTOOL: &str = r#"#!/bin/sh
+for file; do :; done
+case " $* " in
+*" -Orientation= "*)
+    if grep -aq '#strip-fails' "$file"; then
+        echo "Error: fake write failure - $file" >&2
+        exit 1
+    fi
+    offset=$(grep -abo '#orientation=' "$file" | head -n 1 | cut -d: -f1)
+    if [ -n "$offset" ]; then
+        head -c "$offset" "$file" > "$file.strip" && mv "$file.strip" "$file"
+    fi
+    ;;
+*" -Orientation "*)
+    grep -ao '#orientation=[0-9]*' "$file" | head -n 1 | cut -d= -f2
+    ;;
+*" -json "*)
+    if [ ! -f "$file.json" ]; then
+        echo "Error: File format error - $file" >&2
+        exit 1
+    fi
+    cat "$file.json"
+    ;;
+esac
+"#;
+
+/// Called as `jpegtran -copy all -rotate D -outfile OUT IN`: copies IN to
+/// OUT unrotated and appends D to IN.rotations.
+const FAKE_JPEGTRAN: &str = r#"#!/bin/sh
+echo "$4" >> "$7.rotations"
+cat "$7" > "$6"
+"#;
+
+fn install_fake_tools() {
+    static TOOLS_DIR: OnceLock<tempfile::TempDir> = OnceLock::new();
+    TOOLS_DIR.get_or_init(|| {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().expect("Failed to create tools dir");
+        for (name, script) in [("exiftool", FAKE_EXIFTOOL), ("jpegtran", FAKE_JPEGTRAN)] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, script).expect("Failed to write fake tool");
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .expect("Failed to make fake tool executable");
+        }
+
+        let mut paths = vec![dir.path().to_path_buf()];
+        paths.extend(std::env::split_paths(
+            &std::env::var_os("PATH").unwrap_or_default(),
+        ));
+        std::env::set_var("PATH", std::env::join_paths(paths).unwrap());
+        dir
+    });
+}
+
+fn grant_owner_access(pool: &DbPool, media_id: i64, user_id: i64) {
+    pool.get()
+        .unwrap()
+        .execute(
+            "INSERT INTO media_access (media_id, user_id, access_level) VALUES (?, ?, 2)",
+            rusqlite::params![media_id, user_id],
+        )
+        .expect("Failed to grant owner access");
+}
+
+/// Writes a `width` x `height` JPEG to `dir` and points `media_id` at it.
+fn attach_jpeg(pool: &DbPool, media_id: i64, dir: &Path, width: u32, height: u32) -> PathBuf {
+    let path = dir.join(format!("fixture_{}.jpg", media_id));
+    image::RgbImage::from_pixel(width, height, image::Rgb([90, 160, 30]))
+        .save(&path)
+        .expect("Failed to write fixture JPEG");
+    let file_size = std::fs::metadata(&path).unwrap().len() as i64;
+    let conn = pool.get().unwrap();
+    conn.execute(
+        "UPDATE media SET file_path = ?, file_size = ? WHERE id = ?",
+        rusqlite::params![path.to_string_lossy(), file_size, media_id],
+    )
+    .expect("Failed to set file path");
+    conn.execute(
+        "UPDATE media_metadata SET width = ?, height = ? WHERE media_id = ?",
+        rusqlite::params![width, height, media_id],
+    )
+    .expect("Failed to set dimensions");
+    path
+}
+
+#[tokio::test]
+async fn test_reprocess_media_stores_extracted_fields_and_records_edits() {
+    install_fake_tools();
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "reprocess_fake", "reprocess_fake@example.com");
+    let media_id = create_test_media(&pool, "reprocess_fake.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = attach_jpeg(&pool, media_id, temp_dir.path(), 8, 8);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_metadata SET width = 1, height = 1, camera_model = 'Stale Camera', \
+             gps_latitude = 48.5, gps_longitude = 2.25 WHERE media_id = ?",
+            [media_id],
+        )
+        .unwrap();
+    }
+    std::fs::write(
+        path.with_extension("jpg.json"),
+        json!([{
+            "ImageWidth": 8,
+            "ImageHeight": 8,
+            "Model": "Fake Camera",
+            "DateTimeOriginal": "2023:05:06 07:08:09",
+        }])
+        .to_string(),
+    )
+    .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "reprocess_fake", "user");
+    let response = server
+        .post("/api/v1/media/reprocess")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaId": media_id, "regenerateMetadata": true}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["width"], 8);
+    assert_eq!(body["cameraModel"], "Fake Camera");
+    assert_eq!(body["dateTaken"], "2023-05-06T07:08:09+00:00");
+    // GPS the file does not carry is kept, e.g. a manual geocode
+    assert_eq!(body["gpsLatitude"], 48.5);
+    assert_eq!(body["gpsLongitude"], 2.25);
+
+    let history: serde_json::Value = server
+        .get(&format!("/api/v1/media/{}/edit-history", media_id))
+        .authorization_bearer(token.clone())
+        .await
+        .json();
+    let camera_edit = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|edit| edit["fieldName"] == "camera_model")
+        .expect("camera_model change is recorded");
+    assert_eq!(camera_edit["oldValue"], "Stale Camera");
+    assert_eq!(camera_edit["newValue"], "Fake Camera");
+
+    // Without readable metadata nothing is overwritten
+    std::fs::remove_file(path.with_extension("jpg.json")).unwrap();
+    server
+        .post("/api/v1/media/reprocess")
+        .authorization_bearer(token)
+        .json(&json!({"mediaId": media_id, "regenerateMetadata": true}))
+        .await
+        .assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let camera_model: String = pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT camera_model FROM media_metadata WHERE media_id = ?",
+            [media_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(camera_model, "Fake Camera");
+}
+
+fn append_to_file(path: &Path, bytes: &[u8]) {
+    use std::io::Write;
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(bytes))
+        .expect("Failed to append to fixture");
+}
+
+fn stored_file_size_and_dimensions(pool: &DbPool, media_id: i64) -> (i64, i64, i64) {
+    pool.get()
+        .unwrap()
+        .query_row(
+            "SELECT m.file_size, mm.width, mm.height FROM media AS m \
+             JOIN media_metadata AS mm ON mm.media_id = m.id WHERE m.id = ?",
+            [media_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_detect_orientation_rotates_and_untags_the_original() {
+    install_fake_tools();
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "orient_fake", "orient_fake@example.com");
+    let media_id = create_test_media(&pool, "orient_fake.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = attach_jpeg(&pool, media_id, temp_dir.path(), 8, 4);
+    append_to_file(&path, b"#orientation=6");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/detect-orientation")
+        .authorization_bearer(create_test_token(user_id, "orient_fake", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["wasRotated"], true);
+    assert_eq!(body["degreesApplied"], 90);
+    assert_eq!(body["updatedMedia"]["width"], 4);
+    assert_eq!(body["updatedMedia"]["height"], 8);
+
+    let rotations = std::fs::read_to_string(path.with_extension("jpg.rotations")).unwrap();
+    assert_eq!(rotations.trim(), "90");
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(!String::from_utf8_lossy(&bytes).contains("#orientation="));
+    assert_eq!(
+        stored_file_size_and_dimensions(&pool, media_id),
+        (bytes.len() as i64, 4, 8)
+    );
+    // No temp copy is left next to the original
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+}
+
+#[tokio::test]
+async fn test_detect_orientation_leaves_original_alone_when_untagging_fails() {
+    install_fake_tools();
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "orient_fail", "orient_fail@example.com");
+    let media_id = create_test_media(&pool, "orient_fail.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = attach_jpeg(&pool, media_id, temp_dir.path(), 8, 4);
+    append_to_file(&path, b"#strip-fails#orientation=6");
+    let before = std::fs::read(&path).unwrap();
+    let stored_before = stored_file_size_and_dimensions(&pool, media_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/detect-orientation")
+        .authorization_bearer(create_test_token(user_id, "orient_fail", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .assert_status(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    assert_eq!(
+        stored_file_size_and_dimensions(&pool, media_id),
+        stored_before
+    );
+    // Only the original and the stand-in's rotation log remain
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 2);
+}
+
+#[tokio::test]
+async fn test_rotate_media_swaps_dimensions_and_drops_previews() {
+    install_fake_tools();
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "rotate_fake", "rotate_fake@example.com");
+    let media_id = create_test_media(&pool, "rotate_fake.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = attach_jpeg(&pool, media_id, temp_dir.path(), 8, 4);
+    let user_previews = PREVIEWS_DIR.join(user_id.to_string());
+    std::fs::create_dir_all(&user_previews).unwrap();
+    let preview = user_previews.join(format!("fixture_{}_preview.jpg", media_id));
+    std::fs::write(&preview, b"stale preview").unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "rotate_fake", "user");
+    let rotate = |degrees: i32| {
+        server
+            .post("/api/v1/media/rotate")
+            .authorization_bearer(token.clone())
+            .json(&json!({"mediaId": media_id, "degrees": degrees}))
+    };
+
+    let rotated: serde_json::Value = rotate(90).await.json();
+    assert_eq!(
+        (&rotated["width"], &rotated["height"]),
+        (&json!(4), &json!(8))
+    );
+    assert!(!preview.exists());
+
+    let flipped: serde_json::Value = rotate(180).await.json();
+    assert_eq!(
+        (&flipped["width"], &flipped["height"]),
+        (&json!(4), &json!(8))
+    );
+
+    // A full turn brings back the stored dimensions and file size
+    let restored: serde_json::Value = rotate(90).await.json();
+    assert_eq!(
+        (&restored["width"], &restored["height"]),
+        (&json!(8), &json!(4))
+    );
+    assert_eq!(
+        stored_file_size_and_dimensions(&pool, media_id),
+        (std::fs::metadata(&path).unwrap().len() as i64, 8, 4)
+    );
+
+    let rotations = std::fs::read_to_string(path.with_extension("jpg.rotations")).unwrap();
+    assert_eq!(rotations.lines().collect::<Vec<_>>(), ["90", "180", "90"]);
+}
+
+#[tokio::test]
+async fn test_rotate_media_refuses_media_other_users_can_see() {
+    install_fake_tools();
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "rotate_shared", "rotate_shared@example.com");
+    let other_id = create_test_user(&pool, "rotate_viewer", "rotate_viewer@example.com");
+    let media_id = create_test_media(&pool, "rotate_shared.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+    grant_media_access(&pool, media_id, other_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let path = attach_jpeg(&pool, media_id, temp_dir.path(), 8, 4);
+    let before = std::fs::read(&path).unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/rotate")
+        .authorization_bearer(create_test_token(user_id, "rotate_shared", "user"))
+        .json(&json!({"mediaId": media_id, "degrees": 90}))
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+
+    assert_eq!(std::fs::read(&path).unwrap(), before);
+    assert!(!path.with_extension("jpg.rotations").exists());
+    assert_eq!(stored_file_size_and_dimensions(&pool, media_id).1, 8);
+}
@@ -0,0 +1,202 @@
+use axum::http::{header::AUTHORIZATION, HeaderValue, StatusCode};
+use axum_test::TestServer;
+use momento_api::config::{LogFormat, LoggingConfig, ObservabilityConfig};
+use momento_api::logging::build_subscriber;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+use crate::test_utils::{create_test_app, create_test_token, create_test_user};
+
+#[test]
+fn test_build_subscriber_writes_json_to_log_file() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let log_path = dir.path().join("momento.log");
+
+    let json = build_subscriber(
+        &LoggingConfig {
+            format: LogFormat::Json,
+            level: "info".to_string(),
+            file: Some(log_path.clone()),
+        },
+        &ObservabilityConfig::default(),
+    );
+    tracing::subscriber::with_default(json, || tracing::info!("json logging initialized"));
+
+    let plain = build_subscriber(
+        &LoggingConfig {
+            format: LogFormat::Plain,
+            ..Default::default()
+        },
+        &ObservabilityConfig::default(),
+    );
+    tracing::subscriber::with_default(plain, || tracing::info!("plain logging initialized"));
+
+    let contents = std::fs::read_to_string(&log_path).expect("Log file should exist");
+    let lines: Vec<serde_json::Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Log line should be JSON"))
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["fields"]["message"], "json logging initialized");
+}
+
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLogs {
+    fn events(&self) -> Vec<serde_json::Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("Log line should be JSON"))
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn test_request_logger_records_structured_fields() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "log_user", "log_user@example.com");
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(user_id, "log_user", "user"))
+        .json(&json!({}))
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/v1/user/authenticate")
+        .add_header(
+            AUTHORIZATION,
+            HeaderValue::from_static("Basic bG9nX3VzZXI6c2VjcmV0"),
+        )
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let events = logs.events();
+    let list_event = events
+        .iter()
+        .find(|event| event["fields"]["path"] == "/api/v1/media/list")
+        .expect("media list request should be logged");
+    assert_eq!(list_event["fields"]["method"], "POST");
+    assert_eq!(list_event["fields"]["status_code"], 200);
+    assert_eq!(list_event["fields"]["user_id"], user_id);
+    assert!(list_event["fields"]["latency_ms"].is_number());
+
+    let auth_event = events
+        .iter()
+        .find(|event| event["fields"]["path"] == "/api/v1/user/authenticate")
+        .expect("authenticate request should be logged");
+    assert_eq!(auth_event["fields"]["status_code"], 401);
+    assert!(auth_event["fields"]["latency_ms"].is_number());
+    assert!(!events
+        .iter()
+        .any(|event| event.to_string().contains("bG9nX3VzZXI6c2VjcmV0")));
+}
+
+#[tokio::test]
+async fn test_request_logger_only_records_small_json_bodies() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "body_log_user", "body_log_user@example.com");
+    let token = create_test_token(user_id, "body_log_user", "user");
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"note": "small-body-marker"}))
+        .await
+        .assert_status_ok();
+
+    let padding = "large-body-marker ".repeat(2048);
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"note": padding}))
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .text("plain-body-marker")
+        .await;
+
+    let messages: Vec<String> = logs
+        .events()
+        .iter()
+        .filter(|event| event["fields"]["path"] == "/api/v1/media/list")
+        .map(|event| event["fields"]["message"].to_string())
+        .collect();
+    assert_eq!(messages.len(), 3);
+    assert!(messages[0].contains("small-body-marker"));
+    assert!(!messages[1].contains("large-body-marker"));
+    assert!(!messages[2].contains("plain-body-marker"));
+}
+
+#[tokio::test]
+async fn test_request_logger_keeps_secrets_out_of_logged_payloads() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "secret_log_user", "secret_log_user@example.com");
+    let token = create_test_token(user_id, "secret_log_user", "user");
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/public/share/unknown-share/verify")
+        .json(&json!({"password": "share-password-marker"}))
+        .await;
+    server
+        .post("/api/v1/user/update")
+        .authorization_bearer(token.clone())
+        .json(&json!({"password": "update-password-marker"}))
+        .await;
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"note": {"apiToken": "nested-token-marker", "label": "kept-marker"}}))
+        .await
+        .assert_status_ok();
+
+    let logged = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(!logged.contains("share-password-marker"));
+    assert!(!logged.contains("update-password-marker"));
+    assert!(!logged.contains("nested-token-marker"));
+    assert!(logged.contains("kept-marker"));
+}
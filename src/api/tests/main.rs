@@ -1,3 +1,7 @@
+mod config;
+mod database;
+mod logging;
 mod processor;
 mod routes;
 mod test_utils;
+mod utils;
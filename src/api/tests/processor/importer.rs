@@ -0,0 +1,99 @@
+use momento_api::processor::importer::{add_to_folder_album, folder_album_name, RecentImportCache};
+use std::path::Path;
+
+use crate::test_utils::{create_test_db, create_test_media, create_test_user};
+
+#[test]
+fn test_recent_import_cache_evicts_after_ttl_cycles() {
+    let cache = RecentImportCache::default();
+
+    let first = cache.begin_cycle(2);
+    cache.insert(1, "100:aaaa".to_string(), first);
+    assert!(cache.contains(1, "100:aaaa"));
+
+    let second = cache.begin_cycle(2);
+    cache.insert(1, "200:bbbb".to_string(), second);
+    assert!(cache.contains(1, "100:aaaa"));
+
+    cache.begin_cycle(2);
+    assert!(!cache.contains(1, "100:aaaa"));
+    assert!(cache.contains(1, "200:bbbb"));
+    assert_eq!(cache.len(), 1);
+
+    cache.begin_cycle(0);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_recent_import_cache_keeps_users_apart() {
+    let cache = RecentImportCache::default();
+
+    // The same file imported for one user must still be imported for another
+    let cycle = cache.begin_cycle(2);
+    cache.insert(1, "100:aaaa".to_string(), cycle);
+    assert!(cache.contains(1, "100:aaaa"));
+    assert!(!cache.contains(2, "100:aaaa"));
+
+    cache.insert(2, "100:aaaa".to_string(), cycle);
+    assert!(cache.contains(2, "100:aaaa"));
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn test_folder_album_name_uses_the_innermost_folder() {
+    let user_dir = Path::new("/data/webdav/alice");
+
+    assert_eq!(
+        folder_album_name(&user_dir.join("photo.jpg"), user_dir),
+        None
+    );
+    assert_eq!(
+        folder_album_name(&user_dir.join("Holidays/photo.jpg"), user_dir),
+        Some("Holidays".to_string())
+    );
+    assert_eq!(
+        folder_album_name(&user_dir.join("2024/Holidays/Rome/photo.jpg"), user_dir),
+        Some("Rome".to_string())
+    );
+}
+
+#[test]
+fn test_add_to_folder_album_reuses_the_users_album() {
+    let pool = create_test_db();
+    let user_id = create_test_user(&pool, "folder_album", "folder_album@example.com");
+    let other_id = create_test_user(
+        &pool,
+        "folder_album_other",
+        "folder_album_other@example.com",
+    );
+    let first = create_test_media(&pool, "folder_first.jpg");
+    let second = create_test_media(&pool, "folder_second.jpg");
+    let third = create_test_media(&pool, "folder_third.jpg");
+
+    let album_id = add_to_folder_album(&pool, user_id, "Rome", first).unwrap();
+    assert_eq!(
+        add_to_folder_album(&pool, user_id, "Rome", second).unwrap(),
+        album_id
+    );
+    // Another user's folder of the same name gets its own album
+    let other_album = add_to_folder_album(&pool, other_id, "Rome", third).unwrap();
+    assert_ne!(other_album, album_id);
+
+    let conn = pool.get().unwrap();
+    let albums: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM albums WHERE user_id = ? AND name = 'Rome'",
+            [user_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(albums, 1);
+    let members: Vec<(i64, i64)> = conn
+        .prepare("SELECT media_id, position FROM album_media WHERE album_id = ? ORDER BY position")
+        .unwrap()
+        .query_map([album_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(members, vec![(first, 0), (second, 1)]);
+}
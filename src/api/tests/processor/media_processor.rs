@@ -1,8 +1,15 @@
 use crate::test_utils::create_test_db;
+use momento_api::config::{
+    PostProcessorConfig, PostProcessorScript, ThumbnailConfig, ThumbnailFormat,
+};
+use momento_api::constants::{GEOHASH_PRECISE_PRECISION, GEOHASH_PRECISION};
 use momento_api::database::DbConn;
 use momento_api::processor::media_processor::{
-    calculate_geohash, delete_from_rtree, insert_into_rtree,
+    calculate_geohash, delete_from_rtree, insert_into_rtree, process_media_file,
+    run_post_processors, spawn_post_processors, thumbnail_relative_path, MediaProcessingContext,
+    ProcessingResult,
 };
+use std::path::{Path, PathBuf};
 
 fn insert_test_media(conn: &DbConn, id: i64, filename: &str) {
     conn.execute(
@@ -21,7 +28,7 @@ fn insert_test_media(conn: &DbConn, id: i64, filename: &str) {
 
 #[test]
 fn test_calculate_geohash_new_york() {
-    let geohash = calculate_geohash(40.7128, -74.0060);
+    let geohash = calculate_geohash(40.7128, -74.0060, GEOHASH_PRECISION);
     assert!(geohash.is_some());
 
     let hash = geohash.unwrap();
@@ -31,7 +38,7 @@ fn test_calculate_geohash_new_york() {
 
 #[test]
 fn test_calculate_geohash_london() {
-    let geohash = calculate_geohash(51.5074, -0.1278);
+    let geohash = calculate_geohash(51.5074, -0.1278, GEOHASH_PRECISION);
     assert!(geohash.is_some());
 
     let hash = geohash.unwrap();
@@ -41,7 +48,7 @@ fn test_calculate_geohash_london() {
 
 #[test]
 fn test_calculate_geohash_tokyo() {
-    let geohash = calculate_geohash(35.6762, 139.6503);
+    let geohash = calculate_geohash(35.6762, 139.6503, GEOHASH_PRECISION);
     assert!(geohash.is_some());
 
     let hash = geohash.unwrap();
@@ -49,6 +56,15 @@ fn test_calculate_geohash_tokyo() {
     assert!(hash.starts_with("xn7"));
 }
 
+#[test]
+fn test_calculate_geohash_precise_extends_standard() {
+    let standard = calculate_geohash(48.85837, 2.29448, GEOHASH_PRECISION).unwrap();
+    let precise = calculate_geohash(48.85837, 2.29448, GEOHASH_PRECISE_PRECISION).unwrap();
+
+    assert_eq!(precise.len(), 9);
+    assert!(precise.starts_with(&standard));
+}
+
 #[test]
 fn test_rtree_insert_and_query() {
     let pool = create_test_db();
@@ -158,7 +174,8 @@ fn test_new_media_populates_geohash_and_rtree() {
     let media_id = 1;
     let latitude = 40.7128;
     let longitude = -74.0060;
-    let geohash = calculate_geohash(latitude, longitude).expect("Geohash should be calculated");
+    let geohash = calculate_geohash(latitude, longitude, GEOHASH_PRECISION)
+        .expect("Geohash should be calculated");
 
     conn.execute(
         "INSERT INTO media (id, filename, original_filename, file_path, media_type, content_hash) VALUES (?, ?, ?, ?, ?, ?)",
@@ -206,3 +223,124 @@ fn test_new_media_populates_geohash_and_rtree() {
 
     assert_eq!(rtree_count, 1);
 }
+
+#[test]
+fn test_thumbnail_relative_path_uses_jpeg_extension_by_default() {
+    let path = thumbnail_relative_path(
+        Path::new("/data/originals/2024/IMG_0001.HEIC"),
+        ThumbnailFormat::default(),
+    );
+    assert_eq!(path, PathBuf::from("2024/IMG_0001.jpg"));
+}
+
+#[test]
+fn test_thumbnail_relative_path_uses_webp_extension() {
+    let path = thumbnail_relative_path(
+        Path::new("/data/originals/2024/IMG_0001.HEIC"),
+        ThumbnailFormat::Webp,
+    );
+    assert_eq!(path, PathBuf::from("2024/IMG_0001.webp"));
+    assert_eq!(ThumbnailFormat::from_path(&path), ThumbnailFormat::Webp);
+}
+
+#[tokio::test]
+async fn test_process_media_file_rejects_files_over_size_limit() {
+    let pool = create_test_db();
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let source_path = temp_dir.path().join("oversized.jpg");
+    std::fs::write(&source_path, vec![0u8; 2048]).expect("Failed to write file");
+
+    let context = MediaProcessingContext {
+        user_id: 1,
+        thumbnails: ThumbnailConfig::default(),
+        reverse_geocoding: None,
+        max_file_size_bytes: Some(1024),
+        pool: pool.clone(),
+        post_processors: PostProcessorConfig::default(),
+        cluster_cache: None,
+    };
+    let result = process_media_file(&source_path, &context).await;
+
+    assert_eq!(
+        result,
+        ProcessingResult::Failed {
+            reason: "File too large".to_string()
+        }
+    );
+    let conn = pool.get().expect("Failed to get connection");
+    let count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM media", [], |row| row.get(0))
+        .expect("Failed to count media");
+    assert_eq!(count, 0);
+    assert!(source_path.exists());
+}
+
+fn shell_script(name: &str, script: &str, timeout_seconds: u64) -> PostProcessorScript {
+    PostProcessorScript {
+        name: name.to_string(),
+        command: "sh".to_string(),
+        args: vec!["-c".to_string(), script.to_string()],
+        timeout_seconds,
+    }
+}
+
+#[tokio::test]
+async fn test_run_post_processors_continues_past_failures_and_timeouts() {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let marker = temp_dir.path().join("marker.txt");
+    let config = PostProcessorConfig {
+        scripts: vec![
+            shell_script("failing", "exit 3", 5),
+            shell_script("slow", "sleep 10", 1),
+            PostProcessorScript {
+                name: "missing".to_string(),
+                command: "momento-no-such-command".to_string(),
+                args: Vec::new(),
+                timeout_seconds: 5,
+            },
+            shell_script(
+                "marker",
+                &format!(
+                    "echo \"$MOMENTO_MEDIA_ID $MOMENTO_MEDIA_PATH\" > {}",
+                    marker.display()
+                ),
+                5,
+            ),
+        ],
+    };
+
+    let started = std::time::Instant::now();
+    run_post_processors(Path::new("/data/originals/2024/a.jpg"), 42, &config).await;
+
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    let written = std::fs::read_to_string(&marker).expect("Marker script did not run");
+    assert_eq!(written.trim(), "42 /data/originals/2024/a.jpg");
+}
+
+#[tokio::test]
+async fn test_spawn_post_processors_does_not_wait_for_scripts() {
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let marker = temp_dir.path().join("marker.txt");
+    let config = PostProcessorConfig {
+        scripts: vec![shell_script(
+            "slow",
+            &format!("sleep 1 && echo done > {}", marker.display()),
+            5,
+        )],
+    };
+
+    let started = std::time::Instant::now();
+    let handle = spawn_post_processors(PathBuf::from("/data/originals/2024/a.jpg"), 42, &config)
+        .expect("Scripts are configured");
+    assert!(started.elapsed() < std::time::Duration::from_millis(500));
+    assert!(!marker.exists());
+
+    handle.await.expect("Post-processors panicked");
+    assert!(marker.exists());
+    assert!(spawn_post_processors(
+        PathBuf::from("/data/originals/2024/a.jpg"),
+        42,
+        &PostProcessorConfig::default()
+    )
+    .is_none());
+}
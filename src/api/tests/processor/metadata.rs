@@ -0,0 +1,119 @@
+use momento_api::processor::metadata::{
+    find_xmp_sidecar, orientation_rotation_degrees, parse_dms_coordinate, MediaMetadata,
+};
+use tempfile::TempDir;
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("Expected a parsed coordinate");
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn test_parse_dms_coordinate_north_is_positive() {
+    assert_close(
+        parse_dms_coordinate("48 deg 51' 12.36\" N", "North"),
+        48.0 + 51.0 / 60.0 + 12.36 / 3600.0,
+    );
+}
+
+#[test]
+fn test_parse_dms_coordinate_south_is_negative() {
+    assert_close(
+        parse_dms_coordinate("33 deg 52' 4.00\"", "S"),
+        -(33.0 + 52.0 / 60.0 + 4.0 / 3600.0),
+    );
+}
+
+#[test]
+fn test_parse_dms_coordinate_east_is_positive() {
+    assert_close(
+        parse_dms_coordinate("2 deg 20' 55.20\" E", "East"),
+        2.0 + 20.0 / 60.0 + 55.2 / 3600.0,
+    );
+}
+
+#[test]
+fn test_parse_dms_coordinate_west_is_negative() {
+    assert_close(
+        parse_dms_coordinate("73 deg 59' 8.50\" W", ""),
+        -(73.0 + 59.0 / 60.0 + 8.5 / 3600.0),
+    );
+}
+
+#[test]
+fn test_parse_dms_coordinate_degrees_minutes() {
+    assert_close(
+        parse_dms_coordinate("48 deg 51.206'", "N"),
+        48.0 + 51.206 / 60.0,
+    );
+}
+
+#[test]
+fn test_parse_dms_coordinate_decimal() {
+    assert_close(parse_dms_coordinate("151.2093", "E"), 151.2093);
+    assert_close(parse_dms_coordinate("-151.2093", ""), -151.2093);
+}
+
+#[test]
+fn test_parse_dms_coordinate_invalid() {
+    assert!(parse_dms_coordinate("", "N").is_none());
+    assert!(parse_dms_coordinate("unknown", "N").is_none());
+    assert!(parse_dms_coordinate("1 2 3 4", "N").is_none());
+}
+
+#[test]
+fn test_orientation_rotation_degrees() {
+    assert_eq!(orientation_rotation_degrees(1), Some(0));
+    assert_eq!(orientation_rotation_degrees(3), Some(180));
+    assert_eq!(orientation_rotation_degrees(6), Some(90));
+    assert_eq!(orientation_rotation_degrees(8), Some(270));
+    assert_eq!(orientation_rotation_degrees(2), None);
+    assert_eq!(orientation_rotation_degrees(5), None);
+    assert_eq!(orientation_rotation_degrees(9), None);
+}
+
+#[test]
+fn test_find_xmp_sidecar_prefers_full_name() {
+    let dir = TempDir::new().unwrap();
+    let raw = dir.path().join("IMG_1234.arw");
+    std::fs::write(&raw, b"raw").unwrap();
+    assert_eq!(find_xmp_sidecar(&raw), None);
+
+    std::fs::write(dir.path().join("IMG_1234.xmp"), b"xmp").unwrap();
+    assert_eq!(
+        find_xmp_sidecar(&raw),
+        Some(dir.path().join("IMG_1234.xmp"))
+    );
+
+    std::fs::write(dir.path().join("IMG_1234.arw.xmp"), b"xmp").unwrap();
+    assert_eq!(
+        find_xmp_sidecar(&raw),
+        Some(dir.path().join("IMG_1234.arw.xmp"))
+    );
+}
+
+#[test]
+fn test_fill_missing_from_keeps_existing_values() {
+    let mut metadata = MediaMetadata {
+        camera_model: Some("ILCE-7M4".to_string()),
+        mime_type: Some("image/x-sony-arw".to_string()),
+        ..Default::default()
+    };
+    metadata.fill_missing_from(MediaMetadata {
+        camera_model: Some("Edited".to_string()),
+        keywords: Some("beach,sunset".to_string()),
+        gps_latitude: Some(36.5),
+        mime_type: Some("application/rdf+xml".to_string()),
+        ..Default::default()
+    });
+
+    assert_eq!(metadata.camera_model.as_deref(), Some("ILCE-7M4"));
+    assert_eq!(metadata.keywords.as_deref(), Some("beach,sunset"));
+    assert_eq!(metadata.gps_latitude, Some(36.5));
+    assert_eq!(metadata.mime_type.as_deref(), Some("image/x-sony-arw"));
+}
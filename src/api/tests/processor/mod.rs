@@ -1 +1,7 @@
+mod importer;
 mod media_processor;
+mod metadata;
+mod orphans;
+mod regenerator;
+mod thumbnails;
+mod workers;
@@ -0,0 +1,64 @@
+use momento_api::processor::orphans::{is_known_preview, remove_orphan_files, OrphanCleanup};
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+fn write_file(path: &Path, len: usize, age: Duration) {
+    std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create dir");
+    std::fs::write(path, vec![0u8; len]).expect("Failed to write file");
+    let file = std::fs::File::options()
+        .write(true)
+        .open(path)
+        .expect("Failed to open file");
+    file.set_modified(SystemTime::now() - age)
+        .expect("Failed to set mtime");
+}
+
+#[test]
+fn test_remove_orphan_files_keeps_known_and_recent_files() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let old = Duration::from_secs(2 * 60 * 60);
+    write_file(&dir.path().join("2024-01/kept.jpg"), 10, old);
+    write_file(&dir.path().join("2024-01/orphan.jpg"), 30, old);
+    write_file(&dir.path().join("2023-12/orphan.webp"), 12, old);
+    write_file(&dir.path().join("2024-02/fresh.jpg"), 7, Duration::ZERO);
+    write_file(&dir.path().join("covers/1.jpg"), 5, old);
+
+    let known: HashSet<String> = ["2024-01/kept.jpg".to_string()].into_iter().collect();
+    let excluded = dir.path().join("covers");
+    let cleanup = remove_orphan_files(dir.path(), &[excluded.as_path()], |relative| {
+        known.contains(relative)
+    });
+
+    assert_eq!(
+        cleanup,
+        OrphanCleanup {
+            deleted_files: 2,
+            freed_bytes: 42
+        }
+    );
+    assert!(dir.path().join("2024-01/kept.jpg").exists());
+    assert!(dir.path().join("2024-02/fresh.jpg").exists());
+    assert!(dir.path().join("covers/1.jpg").exists());
+    assert!(!dir.path().join("2024-01/orphan.jpg").exists());
+}
+
+#[test]
+fn test_is_known_preview_matches_original_stem() {
+    let stems: HashSet<String> = ["20240115_103000_abc123def456".to_string()]
+        .into_iter()
+        .collect();
+
+    assert!(is_known_preview(
+        "3/20240115_103000_abc123def456_preview.jpg",
+        &stems
+    ));
+    assert!(!is_known_preview(
+        "3/20240115_103000_zzz_preview.webp",
+        &stems
+    ));
+    assert!(!is_known_preview(
+        "3/20240115_103000_abc123def456.jpg",
+        &stems
+    ));
+}
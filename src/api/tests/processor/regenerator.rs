@@ -0,0 +1,52 @@
+use momento_api::database::DbPool;
+use momento_api::processor::regenerator::backfill_perceptual_hashes;
+
+use crate::test_utils::{create_test_db, create_test_media};
+
+fn phash_state(pool: &DbPool, media_id: i64) -> (Option<i64>, Option<String>) {
+    pool.get()
+        .unwrap()
+        .query_row(
+            "SELECT phash, phash_failed_at FROM media WHERE id = ?",
+            [media_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_backfill_perceptual_hashes_marks_undecodable_files() {
+    let pool = create_test_db();
+    let dir = tempfile::tempdir().unwrap();
+    let image_path = dir.path().join("gradient.png");
+    image::RgbImage::from_fn(32, 32, |x, y| image::Rgb([(x * 8) as u8, (y * 8) as u8, 0]))
+        .save(&image_path)
+        .unwrap();
+    let broken_path = dir.path().join("broken.jpg");
+    std::fs::write(&broken_path, b"not an image").unwrap();
+
+    let decodable = create_test_media(&pool, "gradient.png");
+    let broken = create_test_media(&pool, "broken.jpg");
+    for (media_id, path) in [(decodable, &image_path), (broken, &broken_path)] {
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE media SET file_path = ? WHERE id = ?",
+                rusqlite::params![path.to_string_lossy(), media_id],
+            )
+            .unwrap();
+    }
+
+    backfill_perceptual_hashes(&pool).await;
+
+    let (phash, failed_at) = phash_state(&pool, decodable);
+    assert!(phash.is_some());
+    assert!(failed_at.is_none());
+    let (phash, failed_at) = phash_state(&pool, broken);
+    assert!(phash.is_none());
+    let failed_at = failed_at.expect("undecodable file should be marked");
+
+    // A second pass leaves the marked item alone
+    backfill_perceptual_hashes(&pool).await;
+    assert_eq!(phash_state(&pool, broken), (None, Some(failed_at)));
+}
@@ -0,0 +1,41 @@
+use momento_api::processor::thumbnails::composite_album_cover;
+
+fn write_solid_image(path: &std::path::Path, color: [u8; 3]) {
+    image::RgbImage::from_pixel(20, 10, image::Rgb(color))
+        .save(path)
+        .expect("Failed to write test image");
+}
+
+#[test]
+fn test_composite_album_cover_places_tiles_in_reading_order() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255]];
+    let tiles: Vec<_> = colors
+        .iter()
+        .enumerate()
+        .map(|(i, color)| {
+            let path = dir.path().join(format!("tile{}.png", i));
+            write_solid_image(&path, *color);
+            path
+        })
+        .collect();
+    let output = dir.path().join("covers").join("1.jpg");
+
+    composite_album_cover(&tiles, &output, 16, 95).expect("Failed to composite cover");
+
+    let cover = image::open(&output)
+        .expect("Cover should be readable")
+        .to_rgb8();
+    assert_eq!(cover.dimensions(), (32, 32));
+
+    let dominant = |x: u32, y: u32| {
+        let pixel = cover.get_pixel(x, y).0;
+        (0..3).max_by_key(|&c| pixel[c]).unwrap()
+    };
+    assert_eq!(dominant(8, 8), 0);
+    assert_eq!(dominant(24, 8), 1);
+    assert_eq!(dominant(8, 24), 2);
+
+    let empty = cover.get_pixel(24, 24).0;
+    assert!(empty.iter().all(|&c| (118..=138).contains(&c)));
+}
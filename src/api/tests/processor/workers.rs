@@ -0,0 +1,17 @@
+use momento_api::processor::workers::{image_workers, run_image_task};
+
+#[tokio::test]
+async fn test_run_image_task_returns_result_and_survives_panics() {
+    let pool = image_workers();
+
+    let sum = run_image_task(&pool, || (1..=10).sum::<i32>()).await;
+    assert_eq!(sum, Some(55));
+
+    let panicked: Option<()> = run_image_task(&pool, || panic!("decoder blew up")).await;
+    assert_eq!(panicked, None);
+
+    let thread_name = run_image_task(&pool, || std::thread::current().name().map(str::to_string))
+        .await
+        .flatten();
+    assert!(thread_name.is_some_and(|name| name.starts_with("image-worker-")));
+}
@@ -0,0 +1,630 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use momento_api::database::DbPool;
+use serde_json::json;
+
+use crate::test_utils::{
+    create_test_app, create_test_media, create_test_token, create_test_user, grant_media_access,
+};
+
+fn create_tag(pool: &DbPool, name: &str) -> i64 {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute("INSERT INTO tags (name) VALUES (?)", [name])
+        .expect("Failed to insert tag");
+    conn.last_insert_rowid()
+}
+
+fn tag_media(pool: &DbPool, media_id: i64, tag_id: i64, date_taken: &str) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO media_tags (media_id, tag_id) VALUES (?, ?)",
+        rusqlite::params![media_id, tag_id],
+    )
+    .expect("Failed to tag media");
+    conn.execute(
+        "UPDATE media_metadata SET date_taken = ? WHERE media_id = ?",
+        rusqlite::params![date_taken, media_id],
+    )
+    .expect("Failed to set date taken");
+}
+
+#[tokio::test]
+async fn test_create_album_from_tag_includes_all_tagged_media() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "tag_album_user", "tag_album_user@example.com");
+    let other_id = create_test_user(&pool, "tag_album_other", "tag_album_other@example.com");
+    let tag_id = create_tag(&pool, "vacation");
+
+    let oldest = create_test_media(&pool, "oldest.jpg");
+    let newest = create_test_media(&pool, "newest.jpg");
+    let middle = create_test_media(&pool, "middle.jpg");
+    let untagged = create_test_media(&pool, "untagged.jpg");
+    let foreign = create_test_media(&pool, "foreign.jpg");
+    for media_id in [oldest, newest, middle, untagged] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    grant_media_access(&pool, foreign, other_id);
+    tag_media(&pool, oldest, tag_id, "2020-01-01T00:00:00");
+    tag_media(&pool, newest, tag_id, "2024-01-01T00:00:00");
+    tag_media(&pool, middle, tag_id, "2022-01-01T00:00:00");
+    tag_media(&pool, foreign, tag_id, "2023-01-01T00:00:00");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/album/create-from-tag")
+        .authorization_bearer(create_test_token(user_id, "tag_album_user", "user"))
+        .json(&json!({"tagName": "vacation", "albumName": "Vacation 2020-2024"}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["name"], "Vacation 2020-2024");
+    let ids: Vec<i64> = body["media"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![newest, middle, oldest]);
+}
+
+#[tokio::test]
+async fn test_create_album_from_unknown_tag_returns_404() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "tag_album_missing", "tag_album_missing@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/album/create-from-tag")
+        .authorization_bearer(create_test_token(user_id, "tag_album_missing", "user"))
+        .json(&json!({"tagName": "does-not-exist", "albumName": "Nothing"}))
+        .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "NOT_FOUND");
+    assert_eq!(body["message"], "Tag not found");
+    let conn = pool.get().unwrap();
+    let albums: i64 = conn
+        .query_row("SELECT COUNT(*) FROM albums", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(albums, 0);
+}
+
+#[tokio::test]
+async fn test_list_albums_paginates_with_cursor() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "album_pager", "album_pager@example.com");
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "album_pager", "user");
+
+    for index in 0..5 {
+        server
+            .post("/api/v1/album/create")
+            .authorization_bearer(token.clone())
+            .json(&json!({"name": format!("Album {}", index)}))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .post("/api/v1/album/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"limit": 2, "includeTotal": true}))
+        .await;
+    response.assert_status_ok();
+    let first: serde_json::Value = response.json();
+    assert_eq!(first["albums"].as_array().unwrap().len(), 2);
+    assert_eq!(first["hasMore"], true);
+    assert_eq!(first["totalCount"], 5);
+    let cursor = first["nextCursor"].as_str().unwrap().to_string();
+
+    let response = server
+        .post("/api/v1/album/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"limit": 10, "cursor": cursor}))
+        .await;
+    response.assert_status_ok();
+    let rest: serde_json::Value = response.json();
+    assert_eq!(rest["albums"].as_array().unwrap().len(), 3);
+    assert_eq!(rest["hasMore"], false);
+    assert!(rest["nextCursor"].is_null());
+    assert!(rest.get("totalCount").is_none());
+
+    let mut names: Vec<String> = first["albums"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .chain(rest["albums"].as_array().unwrap())
+        .map(|album| album["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), 5);
+
+    for body in [
+        json!({"limit": 0}),
+        json!({"limit": i32::MAX}),
+        json!({"limit": 2, "cursor": "garbage"}),
+    ] {
+        server
+            .post("/api/v1/album/list")
+            .authorization_bearer(token.clone())
+            .json(&body)
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_list_albums_without_body_returns_all() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "album_all", "album_all@example.com");
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "album_all", "user");
+
+    for index in 0..3 {
+        server
+            .post("/api/v1/album/create")
+            .authorization_bearer(token.clone())
+            .json(&json!({"name": format!("Album {}", index)}))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .post("/api/v1/album/list")
+        .authorization_bearer(token)
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["albums"].as_array().unwrap().len(), 3);
+    assert_eq!(body["hasMore"], false);
+}
+
+fn create_album_with_media(pool: &DbPool, user_id: i64, name: &str, media_ids: &[i64]) -> i64 {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO albums (user_id, name) VALUES (?, ?)",
+        rusqlite::params![user_id, name],
+    )
+    .expect("Failed to insert album");
+    let album_id = conn.last_insert_rowid();
+    conn.execute(
+        "INSERT INTO album_access (album_id, user_id, access_level) VALUES (?, ?, 2)",
+        rusqlite::params![album_id, user_id],
+    )
+    .expect("Failed to grant album access");
+    for (position, media_id) in media_ids.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO album_media (album_id, media_id, position) VALUES (?, ?, ?)",
+            rusqlite::params![album_id, media_id, position as i64],
+        )
+        .expect("Failed to add album media");
+    }
+    album_id
+}
+
+fn share_album(pool: &DbPool, album_id: i64, user_id: i64, access_level: i64) {
+    pool.get()
+        .expect("Failed to get connection")
+        .execute(
+            "INSERT INTO album_access (album_id, user_id, access_level) VALUES (?, ?, ?)",
+            rusqlite::params![album_id, user_id, access_level],
+        )
+        .expect("Failed to share album");
+}
+
+#[tokio::test]
+async fn test_merge_albums_appends_source_in_order() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "album_merger", "album_merger@example.com");
+
+    let media: Vec<i64> = (0..5)
+        .map(|index| create_test_media(&pool, &format!("merge_{}.jpg", index)))
+        .collect();
+    for media_id in &media {
+        grant_media_access(&pool, *media_id, user_id);
+    }
+    let target = create_album_with_media(&pool, user_id, "Target", &[media[0], media[1]]);
+    let source = create_album_with_media(&pool, user_id, "Source", &[media[4], media[2], media[3]]);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/album/merge")
+        .authorization_bearer(create_test_token(user_id, "album_merger", "user"))
+        .json(&json!({
+            "sourceAlbumId": source,
+            "targetAlbumId": target,
+            "deleteSource": true
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["id"], target);
+    assert_eq!(body["coverMediaId"], media[0]);
+    let ids: Vec<i64> = body["media"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![media[0], media[1], media[4], media[2], media[3]]);
+
+    let conn = pool.get().unwrap();
+    let remaining: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM albums WHERE id = ?",
+            [source],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[tokio::test]
+async fn test_merge_albums_rejects_foreign_album() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "merge_owner", "merge_owner@example.com");
+    let other_id = create_test_user(&pool, "merge_other", "merge_other@example.com");
+    let target = create_album_with_media(&pool, user_id, "Mine", &[]);
+    let source = create_album_with_media(&pool, other_id, "Theirs", &[]);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/album/merge")
+        .authorization_bearer(create_test_token(user_id, "merge_owner", "user"))
+        .json(&json!({"sourceAlbumId": source, "targetAlbumId": target}))
+        .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_merge_albums_refuses_to_delete_a_shared_album() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "merge_sharee", "merge_sharee@example.com");
+    let owner_id = create_test_user(&pool, "merge_sharer", "merge_sharer@example.com");
+    let media_id = create_test_media(&pool, "merge_shared.jpg");
+    let target = create_album_with_media(&pool, user_id, "Mine", &[]);
+    let source = create_album_with_media(&pool, owner_id, "Shared", &[media_id]);
+    share_album(&pool, source, user_id, 2);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/album/merge")
+        .authorization_bearer(create_test_token(user_id, "merge_sharee", "user"))
+        .json(&json!({"sourceAlbumId": source, "targetAlbumId": target, "deleteSource": true}))
+        .await;
+
+    response.assert_status(StatusCode::FORBIDDEN);
+    let conn = pool.get().unwrap();
+    let remaining: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM album_media WHERE album_id = ?",
+            [source],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(remaining, 1);
+    let target_items: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM album_media WHERE album_id = ?",
+            [target],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(target_items, 0);
+}
+
+#[tokio::test]
+async fn test_media_list_album_filter_pages_in_album_order() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "album_lister", "album_lister@example.com");
+    let stranger_id = create_test_user(&pool, "album_stranger", "album_stranger@example.com");
+    let first = create_test_media(&pool, "album_order_a.jpg");
+    let second = create_test_media(&pool, "album_order_b.jpg");
+    let third = create_test_media(&pool, "album_order_c.jpg");
+    let outside = create_test_media(&pool, "album_order_outside.jpg");
+    for media_id in [first, second, third, outside] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    let album_id = create_album_with_media(&pool, user_id, "Ordered", &[third, first, second]);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "album_lister", "user");
+    let ids = |body: &serde_json::Value| -> Vec<i64> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_i64().unwrap())
+            .collect()
+    };
+
+    let page: serde_json::Value = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"albumId": album_id, "limit": 2, "includeTotal": true}))
+        .await
+        .json();
+    assert_eq!(ids(&page), vec![third, first]);
+    assert_eq!(page["hasMore"], true);
+    assert_eq!(page["totalCount"], 3);
+    assert_eq!(page["nextCursor"], format!("1_{}", first));
+
+    let next: serde_json::Value = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"albumId": album_id, "limit": 2, "cursor": page["nextCursor"]}))
+        .await
+        .json();
+    assert_eq!(ids(&next), vec![second]);
+    assert_eq!(next["hasMore"], false);
+
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"albumId": album_id, "limit": 2, "cursor": "2024-01-15T10:30:00_1"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(stranger_id, "album_stranger", "user"))
+        .json(&json!({"albumId": album_id}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_album_cover_requires_access_and_media() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "cover_owner", "cover_owner@example.com");
+    let other_id = create_test_user(&pool, "cover_other", "cover_other@example.com");
+    let empty = create_album_with_media(&pool, user_id, "Empty", &[]);
+    let foreign = create_album_with_media(&pool, other_id, "Theirs", &[]);
+    let token = create_test_token(user_id, "cover_owner", "user");
+
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post(&format!("/api/v1/album/{}/cover/generate", foreign))
+        .authorization_bearer(&token)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .get(&format!("/api/v1/album/{}/cover", foreign))
+        .authorization_bearer(&token)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    server
+        .post(&format!("/api/v1/album/{}/cover/generate", empty))
+        .authorization_bearer(&token)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .get(&format!("/api/v1/album/{}/cover", empty))
+        .authorization_bearer(&token)
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_album_cover_skips_trashed_media() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "cover_trash", "cover_trash@example.com");
+    let media_id = create_test_media(&pool, "cover_trashed.jpg");
+    grant_media_access(&pool, media_id, user_id);
+    let album_id = create_album_with_media(&pool, user_id, "Trashed", &[media_id]);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_metadata SET thumbnail_path = 'cover_trashed.jpg' WHERE media_id = ?",
+            [media_id],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE media_access SET deleted_at = CURRENT_TIMESTAMP WHERE media_id = ? AND user_id = ?",
+            [media_id, user_id],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post(&format!("/api/v1/album/{}/cover/generate", album_id))
+        .authorization_bearer(create_test_token(user_id, "cover_trash", "user"))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+async fn list_album_names(server: &TestServer, token: &str) -> Vec<String> {
+    let response = server
+        .post("/api/v1/album/list")
+        .authorization_bearer(token)
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    body["albums"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|album| album["name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_reorder_albums_persists_positions() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "album_sorter", "album_sorter@example.com");
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "album_sorter", "user");
+
+    let mut ids = Vec::new();
+    for name in ["A", "B", "C"] {
+        let response = server
+            .post("/api/v1/album/create")
+            .authorization_bearer(&token)
+            .json(&json!({"name": name}))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        ids.push(body["id"].as_i64().unwrap());
+    }
+    assert_eq!(list_album_names(&server, &token).await, ["A", "B", "C"]);
+
+    server
+        .post("/api/v1/album/list/reorder")
+        .authorization_bearer(&token)
+        .json(&json!({"albumIds": [ids[2], ids[0], ids[1]]}))
+        .await
+        .assert_status_ok();
+    assert_eq!(list_album_names(&server, &token).await, ["C", "A", "B"]);
+
+    server
+        .post("/api/v1/album/list/reorder")
+        .authorization_bearer(&token)
+        .json(&json!({"albumIds": [ids[1], ids[2], ids[0]]}))
+        .await
+        .assert_status_ok();
+    assert_eq!(list_album_names(&server, &token).await, ["B", "C", "A"]);
+
+    let response = server
+        .post("/api/v1/album/create")
+        .authorization_bearer(&token)
+        .json(&json!({"name": "D"}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        list_album_names(&server, &token).await,
+        ["B", "C", "A", "D"]
+    );
+
+    let response = server
+        .post("/api/v1/album/list")
+        .authorization_bearer(&token)
+        .json(&json!({"limit": 2}))
+        .await;
+    response.assert_status_ok();
+    let first: serde_json::Value = response.json();
+    let response = server
+        .post("/api/v1/album/list")
+        .authorization_bearer(&token)
+        .json(&json!({"limit": 2, "cursor": first["nextCursor"]}))
+        .await;
+    response.assert_status_ok();
+    let rest: serde_json::Value = response.json();
+    let paged: Vec<&str> = first["albums"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .chain(rest["albums"].as_array().unwrap())
+        .map(|album| album["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(paged, ["B", "C", "A", "D"]);
+}
+
+#[tokio::test]
+async fn test_reorder_albums_rejects_foreign_album() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "sort_owner", "sort_owner@example.com");
+    let other_id = create_test_user(&pool, "sort_other", "sort_other@example.com");
+    let mine = create_album_with_media(&pool, user_id, "Mine", &[]);
+    let theirs = create_album_with_media(&pool, other_id, "Theirs", &[]);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/album/list/reorder")
+        .authorization_bearer(create_test_token(user_id, "sort_owner", "user"))
+        .json(&json!({"albumIds": [theirs, mine]}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let conn = pool.get().unwrap();
+    let position: i64 = conn
+        .query_row(
+            "SELECT position FROM albums WHERE id = ?",
+            [theirs],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(position, 0);
+}
+
+#[tokio::test]
+async fn test_clear_album_media_keeps_empty_album() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "album_clearer", "album_clearer@example.com");
+    let stranger_id = create_test_user(&pool, "album_stranger", "album_stranger@example.com");
+    let media: Vec<i64> = (0..3)
+        .map(|i| {
+            let media_id = create_test_media(&pool, &format!("clear_{}.jpg", i));
+            grant_media_access(&pool, media_id, user_id);
+            media_id
+        })
+        .collect();
+    let album_id = create_album_with_media(&pool, user_id, "To Clear", &media);
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE albums SET cover_media_id = ? WHERE id = ?",
+            [media[0], album_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .delete(&format!("/api/v1/album/{}/media/all", album_id))
+        .authorization_bearer(create_test_token(stranger_id, "album_stranger", "user"))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let token = create_test_token(user_id, "album_clearer", "user");
+    let response = server
+        .delete(&format!("/api/v1/album/{}/media/all", album_id))
+        .authorization_bearer(token.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["removedCount"], 3);
+
+    let album: serde_json::Value = server
+        .post("/api/v1/album/get")
+        .authorization_bearer(token)
+        .json(&json!({"albumId": album_id}))
+        .await
+        .json();
+    assert_eq!(album["name"], "To Clear");
+    assert_eq!(album["media"], json!([]));
+    assert!(album["coverMediaId"].is_null());
+}
+
+#[tokio::test]
+async fn test_clear_album_media_refuses_shared_viewer() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "clear_owner", "clear_owner@example.com");
+    let viewer_id = create_test_user(&pool, "clear_viewer", "clear_viewer@example.com");
+    let media_id = create_test_media(&pool, "clear_shared.jpg");
+    let album_id = create_album_with_media(&pool, owner_id, "Shared", &[media_id]);
+    share_album(&pool, album_id, viewer_id, 1);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .delete(&format!("/api/v1/album/{}/media/all", album_id))
+        .authorization_bearer(create_test_token(viewer_id, "clear_viewer", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let remaining: i64 = pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT COUNT(*) FROM album_media WHERE album_id = ?",
+            [album_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(remaining, 1);
+}
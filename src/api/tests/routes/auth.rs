@@ -0,0 +1,324 @@
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum_test::TestServer;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use momento_api::auth::{
+    create_password_reset_token, create_refresh_token, hash_password, verify_password,
+};
+use momento_api::config::Config;
+use momento_api::database::DbPool;
+use momento_api::routes::cleanup_expired_refresh_tokens;
+use serde_json::json;
+
+use crate::test_utils::{create_test_app, create_test_token, create_test_user};
+
+fn insert_reset_token(pool: &DbPool, user_id: i64, expire_minutes: i64) -> String {
+    let (raw_token, token_hash, expires_at) = create_password_reset_token(expire_minutes);
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES (?, ?, ?)",
+        rusqlite::params![user_id, token_hash, expires_at.to_rfc3339()],
+    )
+    .expect("Failed to insert reset token");
+    raw_token
+}
+
+fn stored_password_hash(pool: &DbPool, user_id: i64) -> String {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.query_row(
+        "SELECT hashed_password FROM users WHERE id = ?",
+        [user_id],
+        |row| row.get(0),
+    )
+    .expect("Failed to read password hash")
+}
+
+fn insert_refresh_token(pool: &DbPool, user_id: i64, family: &str) -> String {
+    let (raw_token, token_hash, expires_at) = create_refresh_token(user_id, &Config::default());
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO refresh_tokens (token_hash, user_id, expires_at, token_family) VALUES (?, ?, ?, ?)",
+        rusqlite::params![token_hash, user_id, expires_at.to_rfc3339(), family],
+    )
+    .expect("Failed to insert refresh token");
+    raw_token
+}
+
+fn count_reset_tokens(pool: &DbPool, user_id: i64) -> i64 {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.query_row(
+        "SELECT COUNT(*) FROM password_reset_tokens WHERE user_id = ?",
+        [user_id],
+        |row| row.get(0),
+    )
+    .expect("Failed to count reset tokens")
+}
+
+#[tokio::test]
+async fn test_forgot_password_same_response_for_unknown_email() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "forgot_user", "forgot_user@example.com");
+    let server = TestServer::new(app).unwrap();
+
+    let known = server
+        .post("/api/v1/user/forgot-password")
+        .json(&json!({"email": "forgot_user@example.com"}))
+        .await;
+    let unknown = server
+        .post("/api/v1/user/forgot-password")
+        .json(&json!({"email": "nobody@example.com"}))
+        .await;
+
+    known.assert_status_ok();
+    unknown.assert_status_ok();
+    assert_eq!(known.text(), unknown.text());
+    assert_eq!(count_reset_tokens(&pool, user_id), 1);
+}
+
+#[tokio::test]
+async fn test_reset_password_with_valid_token() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "reset_user", "reset_user@example.com");
+    let token = insert_reset_token(&pool, user_id, 60);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .post("/api/v1/user/reset-password")
+        .json(&json!({"token": token, "newPassword": "new-secret-123"}))
+        .await;
+
+    response.assert_status_ok();
+    assert!(verify_password(
+        "new-secret-123",
+        &stored_password_hash(&pool, user_id)
+    ));
+
+    let reused = server
+        .post("/api/v1/user/reset-password")
+        .json(&json!({"token": token, "newPassword": "another-secret-123"}))
+        .await;
+    reused.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_reset_password_token_is_used_by_only_one_concurrent_request() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "race_user", "race_user@example.com");
+    let token = insert_reset_token(&pool, user_id, 60);
+    let server = TestServer::new(app).unwrap();
+
+    let (first, second) = tokio::join!(
+        server
+            .post("/api/v1/user/reset-password")
+            .json(&json!({"token": token, "newPassword": "first-secret-123"})),
+        server
+            .post("/api/v1/user/reset-password")
+            .json(&json!({"token": token, "newPassword": "second-secret-123"})),
+    );
+
+    let statuses = [first.status_code(), second.status_code()];
+    assert_eq!(
+        statuses.iter().filter(|s| **s == StatusCode::OK).count(),
+        1,
+        "exactly one reset should succeed: {:?}",
+        statuses
+    );
+    assert!(statuses.contains(&StatusCode::BAD_REQUEST));
+    let winner = if first.status_code() == StatusCode::OK {
+        "first-secret-123"
+    } else {
+        "second-secret-123"
+    };
+    assert!(verify_password(
+        winner,
+        &stored_password_hash(&pool, user_id)
+    ));
+}
+
+#[tokio::test]
+async fn test_reset_password_rejects_expired_token() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "expired_user", "expired_user@example.com");
+    let token = insert_reset_token(&pool, user_id, -1);
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .post("/api/v1/user/reset-password")
+        .json(&json!({"token": token, "newPassword": "new-secret-123"}))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    assert_eq!(
+        stored_password_hash(&pool, user_id),
+        "hashed_password_placeholder"
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_token_reuse_revokes_family() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "family_user", "family_user@example.com");
+    let stolen = insert_refresh_token(&pool, user_id, "family-a");
+    let other_session = insert_refresh_token(&pool, user_id, "family-b");
+    let server = TestServer::new(app).unwrap();
+
+    let response = server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": stolen}))
+        .await;
+    response.assert_status_ok();
+    let rotated = response.json::<serde_json::Value>()["refreshToken"]
+        .as_str()
+        .expect("refresh token should be a string")
+        .to_string();
+
+    let response = server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": stolen}))
+        .await;
+    response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["message"],
+        "Token reuse detected; this session has been revoked"
+    );
+
+    server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": rotated}))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    // Once the family is dead a replay is an ordinary revoked token
+    server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": stolen}))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": other_session}))
+        .await
+        .assert_status_ok();
+}
+
+async fn login(server: &TestServer, username: &str, device: Option<&'static str>) -> String {
+    let credentials = STANDARD.encode(format!("{}:session-password", username));
+    let mut request = server.post("/api/v1/user/authenticate").add_header(
+        HeaderName::from_static("authorization"),
+        HeaderValue::from_str(&format!("Basic {}", credentials)).unwrap(),
+    );
+    if let Some(device) = device {
+        request = request.add_header(
+            HeaderName::from_static("x-device-name"),
+            HeaderValue::from_static(device),
+        );
+    }
+    let response = request.await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    body["refreshToken"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_sessions_list_and_revoke() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "session_user", "session_user@example.com");
+    let other_id = create_test_user(&pool, "session_other", "session_other@example.com");
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE users SET hashed_password = ? WHERE id = ?",
+            rusqlite::params![hash_password("session-password").unwrap(), user_id],
+        )
+        .unwrap();
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "session_user", "user");
+
+    let phone_refresh = login(&server, "session_user", Some("iPhone")).await;
+    login(&server, "session_user", None).await;
+
+    let response = server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": phone_refresh}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let rotated_refresh = body["refreshToken"].as_str().unwrap().to_string();
+
+    let response = server
+        .get("/api/v1/user/sessions")
+        .authorization_bearer(&token)
+        .await;
+    response.assert_status_ok();
+    let sessions: Vec<serde_json::Value> = response.json();
+    assert_eq!(sessions.len(), 2);
+    let phone = sessions
+        .iter()
+        .find(|session| session["deviceName"] == "iPhone")
+        .expect("Device name should survive token rotation");
+    let phone_id = phone["id"].as_i64().unwrap();
+    assert!(phone["createdAt"].is_string());
+    assert!(phone["expiresAt"].is_string());
+
+    server
+        .delete(&format!("/api/v1/user/sessions/{}", phone_id))
+        .authorization_bearer(create_test_token(other_id, "session_other", "user"))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    server
+        .delete(&format!("/api/v1/user/sessions/{}", phone_id))
+        .authorization_bearer(&token)
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/v1/user/sessions")
+        .authorization_bearer(&token)
+        .await;
+    let sessions: Vec<serde_json::Value> = response.json();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0]["deviceName"].is_null());
+
+    server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": rotated_refresh}))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_cleanup_expired_refresh_tokens_keeps_live_families() {
+    let (_app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "cleanup_tokens", "cleanup_tokens@example.com");
+    insert_refresh_token(&pool, user_id, "family-live");
+    insert_refresh_token(&pool, user_id, "family-live");
+    insert_refresh_token(&pool, user_id, "family-expired");
+    let conn = pool.get().expect("Failed to get connection");
+    let past = (chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339();
+    // One rotated-out token of the live family and the whole other family
+    // have expired
+    conn.execute(
+        "UPDATE refresh_tokens SET expires_at = ?, revoked = 1 \
+         WHERE id = (SELECT MIN(id) FROM refresh_tokens WHERE token_family = 'family-live')",
+        [&past],
+    )
+    .unwrap();
+    conn.execute(
+        "UPDATE refresh_tokens SET expires_at = ? WHERE token_family = 'family-expired'",
+        [&past],
+    )
+    .unwrap();
+
+    assert_eq!(cleanup_expired_refresh_tokens(&conn).unwrap(), 1);
+
+    let remaining: Vec<String> = conn
+        .prepare("SELECT token_family FROM refresh_tokens WHERE user_id = ? ORDER BY id")
+        .unwrap()
+        .query_map([user_id], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(remaining, vec!["family-live", "family-live"]);
+}
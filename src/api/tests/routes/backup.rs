@@ -0,0 +1,64 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use momento_api::app::create_app;
+use momento_api::config::{BackupDestination, Config};
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::test_utils::{create_test_app, create_test_db, create_test_token, create_test_user};
+
+#[tokio::test]
+async fn test_trigger_backup_writes_local_snapshot() {
+    let backup_dir = tempfile::tempdir().unwrap();
+    let pool = create_test_db();
+    let mut config = Config::default();
+    config.backup.destination = BackupDestination::Local {
+        path: backup_dir.path().to_path_buf(),
+    };
+    let app = create_app(Arc::new(config), pool.clone());
+
+    let admin_id = create_test_user(&pool, "backup_admin", "backup_admin@example.com");
+    {
+        let conn = pool.get().unwrap();
+        conn.execute("UPDATE users SET role = 'admin' WHERE id = ?", [admin_id])
+            .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/admin/backup/trigger")
+        .authorization_bearer(create_test_token(admin_id, "backup_admin", "admin"))
+        .json(&json!({}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let location = std::path::PathBuf::from(body["location"].as_str().unwrap());
+    assert_eq!(location.parent().unwrap(), backup_dir.path());
+    let file_name = location.file_name().unwrap().to_string_lossy().to_string();
+    assert!(file_name.starts_with("momento_backup_"));
+    assert!(file_name.ends_with(".sqlite"));
+
+    let snapshot = rusqlite::Connection::open(&location).unwrap();
+    let users: i64 = snapshot
+        .query_row(
+            "SELECT COUNT(*) FROM users WHERE username = 'backup_admin'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(users, 1);
+}
+
+#[tokio::test]
+async fn test_trigger_backup_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "backup_user", "backup_user@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/admin/backup/trigger")
+        .authorization_bearer(create_test_token(user_id, "backup_user", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
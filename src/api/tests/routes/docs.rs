@@ -0,0 +1,39 @@
+use axum_test::TestServer;
+
+use crate::test_utils::create_test_app;
+
+#[tokio::test]
+async fn test_openapi_spec_documents_routes_and_errors() {
+    let (app, _pool) = create_test_app();
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/api/openapi.json").await;
+
+    response.assert_status_ok();
+    let spec: serde_json::Value = response.json();
+    assert_eq!(spec["servers"][0]["url"], "/api/v1");
+    assert!(spec["paths"]["/album/create"]["post"].is_object());
+    assert!(spec["paths"]["/media/file/{media_id}"]["get"].is_object());
+    assert!(spec["paths"]["/user/sessions/{session_id}"]["delete"].is_object());
+
+    let responses = &spec["paths"]["/media/update"]["post"]["responses"];
+    for status in [
+        "200", "400", "401", "403", "404", "409", "422", "429", "500", "503",
+    ] {
+        assert!(responses[status].is_object(), "missing {} response", status);
+    }
+    assert!(spec["components"]["schemas"]["ErrorBody"].is_object());
+    assert!(spec["components"]["schemas"]["ErrorCode"].is_object());
+    assert!(spec["components"]["securitySchemes"]["bearer"].is_object());
+}
+
+#[tokio::test]
+async fn test_swagger_ui_is_served() {
+    let (app, _pool) = create_test_app();
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/api/docs/").await;
+
+    response.assert_status_ok();
+    assert!(response.text().contains("swagger"));
+}
@@ -0,0 +1,97 @@
+use axum_test::TestServer;
+use momento_api::app::{create_app, create_app_with_shutdown};
+use momento_api::auth::ShutdownSignal;
+use momento_api::config::Config;
+use momento_api::constants::DEFAULT_API_CSP;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::test_utils::{create_test_app, create_test_db};
+
+#[tokio::test]
+async fn test_health_returns_ok_with_version() {
+    let (app, _pool) = create_test_app();
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/api/v1/health").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["version"], momento_api::VERSION);
+}
+
+#[tokio::test]
+async fn test_ready_returns_ok_when_database_reachable() {
+    let (app, _pool) = create_test_app();
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/api/v1/ready").await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn test_ready_returns_503_when_pool_exhausted() {
+    let pool = Pool::builder()
+        .max_size(1)
+        .connection_timeout(Duration::from_millis(100))
+        .build(SqliteConnectionManager::memory())
+        .expect("Failed to create test database pool");
+    let _held = pool.get().expect("Failed to get connection");
+
+    let app = create_app(Arc::new(Config::default()), pool.clone());
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/api/v1/ready").await;
+
+    response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "degraded");
+    assert!(body["error"].is_string());
+}
+
+#[tokio::test]
+async fn test_health_reports_shutting_down_while_draining() {
+    let shutdown = ShutdownSignal::default();
+    let app = create_app_with_shutdown(
+        Arc::new(Config::default()),
+        create_test_db(),
+        shutdown.clone(),
+    );
+    let server = TestServer::new(app).unwrap();
+
+    server.get("/api/v1/health").await.assert_status_ok();
+    shutdown.trigger();
+    shutdown.triggered().await;
+
+    for path in ["/api/v1/health", "/api/v1/ready"] {
+        let response = server.get(path).await;
+        response.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["status"], "shutting_down");
+    }
+}
+
+#[tokio::test]
+async fn test_api_responses_carry_content_security_policy() {
+    let (app, _pool) = create_test_app();
+    let server = TestServer::new(app).unwrap();
+
+    let response = server.get("/api/v1/health").await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-security-policy"), DEFAULT_API_CSP);
+
+    let mut config = Config::default();
+    config.server.csp_policy = Some("default-src 'self'".to_string());
+    let server = TestServer::new(create_app(Arc::new(config), create_test_db())).unwrap();
+    let response = server.get("/api/v1/health").await;
+    assert_eq!(
+        response.header("content-security-policy"),
+        "default-src 'self'"
+    );
+}
@@ -0,0 +1,242 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use momento_api::app::create_app;
+use momento_api::config::Config;
+use momento_api::database::DbPool;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::test_utils::{create_test_app, create_test_db, create_test_token, create_test_user};
+
+fn insert_import_job(pool: &DbPool, status: &str, successful: i64) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO import_jobs (job_type, status, total_files, processed_files, successful_imports, failed_imports, started_at, completed_at, errors_json) \
+         VALUES ('local', ?, ?, ?, ?, 0, '2024-01-15T10:30:00+00:00', NULL, '[]')",
+        rusqlite::params![status, successful, successful, successful],
+    )
+    .expect("Failed to insert import job");
+}
+
+fn create_admin(pool: &DbPool, username: &str) -> String {
+    let user_id = create_test_user(pool, username, &format!("{}@example.com", username));
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute("UPDATE users SET role = 'admin' WHERE id = ?", [user_id])
+        .expect("Failed to update role");
+    create_test_token(user_id, username, "admin")
+}
+
+#[tokio::test]
+async fn test_import_status_reads_persisted_job_when_idle() {
+    let (app, pool) = create_test_app();
+    let token = create_admin(&pool, "import_admin");
+    insert_import_job(&pool, "completed", 3);
+    insert_import_job(&pool, "completed", 7);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/import/status")
+        .authorization_bearer(token)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "completed");
+    assert_eq!(body["successfulImports"], 7);
+    assert_eq!(body["startedAt"], "2024-01-15T10:30:00+00:00");
+}
+
+#[tokio::test]
+async fn test_import_status_reports_interrupted_job_as_failed() {
+    let (app, pool) = create_test_app();
+    let token = create_admin(&pool, "import_admin_interrupted");
+    insert_import_job(&pool, "running", 1);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/import/status")
+        .authorization_bearer(token)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["status"], "failed");
+}
+
+#[test]
+fn test_migrations_are_idempotent() {
+    let (_app, pool) = create_test_app();
+    let conn = pool.get().expect("Failed to get connection");
+
+    momento_api::database::init_database(&conn).expect("Failed to re-run migrations");
+
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .expect("Failed to read user_version");
+    assert!(version >= 1);
+}
+
+#[tokio::test]
+async fn test_local_directory_import_enforces_allowed_roots() {
+    let root = tempfile::tempdir().unwrap();
+    let library = root.path().join("library");
+    std::fs::create_dir_all(library.join("2024")).unwrap();
+    let outside = tempfile::tempdir().unwrap();
+
+    let mut config = Config::default();
+    config.import.allowed_import_roots = vec![root.path().to_path_buf()];
+    let pool = create_test_db();
+    let token = create_admin(&pool, "directory_admin");
+    let owner_id = create_test_user(&pool, "directory_owner", "directory_owner@example.com");
+    let server = TestServer::new(create_app(Arc::new(config), pool)).unwrap();
+
+    let outside_path = outside.path().to_string_lossy().to_string();
+    let traversal = library
+        .join("..")
+        .join("..")
+        .join(outside.path().file_name().unwrap())
+        .to_string_lossy()
+        .to_string();
+    for path in [outside_path, traversal] {
+        server
+            .post("/api/v1/import/local-directory")
+            .authorization_bearer(token.clone())
+            .json(&json!({"path": path, "userId": owner_id}))
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    server
+        .post("/api/v1/import/local-directory")
+        .authorization_bearer(token.clone())
+        .json(&json!({"path": library.join("missing"), "userId": owner_id}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .post("/api/v1/import/local-directory")
+        .authorization_bearer(token.clone())
+        .json(&json!({"path": library, "userId": 999_999}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    let response = server
+        .post("/api/v1/import/local-directory")
+        .authorization_bearer(token)
+        .json(&json!({"path": library, "userId": owner_id, "recursive": true}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["status"], "running");
+}
+
+#[tokio::test]
+async fn test_local_directory_import_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "directory_user", "directory_user@example.com");
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post("/api/v1/import/local-directory")
+        .authorization_bearer(create_test_token(user_id, "directory_user", "user"))
+        .json(&json!({"path": "/", "userId": user_id}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_import_history_pages_newest_first_with_truncated_errors() {
+    let (app, pool) = create_test_app();
+    let token = create_admin(&pool, "history_admin");
+    insert_import_job(&pool, "completed", 1);
+    insert_import_job(&pool, "completed", 2);
+    insert_import_job(&pool, "failed", 3);
+    let errors: Vec<String> = (0..8).map(|i| format!("bad file {}", i)).collect();
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE import_jobs SET errors_json = ?, failed_imports = 8 WHERE successful_imports = 3",
+            [serde_json::to_string(&errors).unwrap()],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get("/api/v1/import/history")
+        .add_query_param("limit", 2)
+        .authorization_bearer(token.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["totalJobs"], 3);
+    assert_eq!(body["jobs"][0]["status"], "failed");
+    assert_eq!(body["jobs"][0]["errorCount"], 8);
+    assert_eq!(body["jobs"][0]["errors"].as_array().unwrap().len(), 5);
+    assert_eq!(body["jobs"][1]["successfulImports"], 2);
+
+    let response = server
+        .get("/api/v1/import/history")
+        .add_query_param("limit", 2)
+        .add_query_param("offset", 2)
+        .authorization_bearer(token)
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["jobs"].as_array().unwrap().len(), 1);
+    assert_eq!(body["jobs"][0]["successfulImports"], 1);
+}
+
+#[tokio::test]
+async fn test_import_history_purge_keeps_running_and_recent_jobs() {
+    let (app, pool) = create_test_app();
+    let token = create_admin(&pool, "purge_admin");
+    insert_import_job(&pool, "completed", 1);
+    insert_import_job(&pool, "running", 2);
+    insert_import_job(&pool, "completed", 3);
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE import_jobs SET started_at = ? WHERE successful_imports = 3",
+            [chrono::Utc::now().to_rfc3339()],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .delete("/api/v1/import/history")
+        .add_query_param("older_than_days", 7)
+        .authorization_bearer(token.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["deletedCount"], 1);
+
+    let remaining: Vec<i64> = pool
+        .get()
+        .unwrap()
+        .prepare("SELECT successful_imports FROM import_jobs ORDER BY id")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(remaining, vec![2, 3]);
+
+    server
+        .delete("/api/v1/import/history")
+        .add_query_param("older_than_days", i64::MAX)
+        .authorization_bearer(token)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_import_history_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "history_user", "history_user@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .get("/api/v1/import/history")
+        .authorization_bearer(create_test_token(user_id, "history_user", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
@@ -1,10 +1,14 @@
 use crate::test_utils::{
-    create_test_db, create_test_media_with_gps, create_test_media_with_gps_and_date,
-    create_test_user, grant_media_access,
+    create_test_app, create_test_db, create_test_media_with_gps,
+    create_test_media_with_gps_and_date, create_test_token, create_test_user, grant_media_access,
 };
+use axum_test::TestServer;
+use momento_api::constants::GEOHASH_PRECISE_PRECISION;
 use momento_api::database::{fetch_all, queries, DbPool};
 use momento_api::error::{AppError, AppResult};
 use momento_api::models::{BoundingBox, Cluster, MapClustersRequest, MapClustersResponse};
+use momento_api::processor::media_processor::calculate_geohash;
+use serde_json::json;
 use std::time::{Duration, Instant};
 
 fn zoom_to_geohash_precision(zoom: u8) -> usize {
@@ -205,7 +209,7 @@ fn test_map_clusters_antimeridian_bounds() {
     let response = get_clusters_sync(&pool, user_id, &req).unwrap();
 
     assert_eq!(response.total_count, 2);
-    assert!(response.clusters.len() >= 1);
+    assert!(!response.clusters.is_empty());
 }
 
 #[test]
@@ -359,3 +363,318 @@ fn test_zoom_to_geohash_precision() {
     assert_eq!(zoom_to_geohash_precision(19), 7);
     assert_eq!(zoom_to_geohash_precision(25), 7);
 }
+
+fn set_lens_model(pool: &DbPool, media_id: i64, lens_model: &str) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media_metadata SET lens_model = ? WHERE media_id = ?",
+        rusqlite::params![lens_model, media_id],
+    )
+    .expect("Failed to set lens model");
+}
+
+#[tokio::test]
+async fn test_map_lens_filter_only_counts_matching_lens() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "lens_user", "lens_user@example.com");
+    let tele = create_test_media_with_gps(&pool, "tele.jpg", 40.7128, -74.0060);
+    let wide = create_test_media_with_gps(&pool, "wide.jpg", 40.7130, -74.0062);
+    grant_media_access(&pool, tele, user_id);
+    grant_media_access(&pool, wide, user_id);
+    set_lens_model(&pool, tele, "70-200mm f/2.8");
+    set_lens_model(&pool, wide, "16-35mm f/4");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/map/lens-filter")
+        .authorization_bearer(create_test_token(user_id, "lens_user", "user"))
+        .json(&json!({
+            "lensModel": "70-200mm f/2.8",
+            "bounds": {"north": 90.0, "south": -90.0, "east": 180.0, "west": -180.0}
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["totalCount"], 1);
+    assert_eq!(body["clusters"][0]["representativeId"], tele);
+}
+
+#[tokio::test]
+async fn test_map_lens_filter_empty_lens_model_returns_empty() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "lens_empty", "lens_empty@example.com");
+    let media_id = create_test_media_with_gps(&pool, "no_lens.jpg", 40.7128, -74.0060);
+    grant_media_access(&pool, media_id, user_id);
+    set_lens_model(&pool, media_id, "");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/map/lens-filter")
+        .authorization_bearer(create_test_token(user_id, "lens_empty", "user"))
+        .json(&json!({
+            "lensModel": "  ",
+            "bounds": {"north": 90.0, "south": -90.0, "east": 180.0, "west": -180.0},
+            "zoom": 4
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["totalCount"], 0);
+    assert!(body["clusters"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_lens_models_distinct_sorted_and_skips_empty() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "lens_lister", "lens_lister@example.com");
+    for (filename, lens) in [
+        ("a.jpg", "70-200mm f/2.8"),
+        ("b.jpg", "16-35mm f/4"),
+        ("c.jpg", "70-200mm f/2.8"),
+        ("d.jpg", ""),
+    ] {
+        let media_id = create_test_media_with_gps(&pool, filename, 1.0, 1.0);
+        grant_media_access(&pool, media_id, user_id);
+        set_lens_model(&pool, media_id, lens);
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get("/api/v1/media/lens-models")
+        .authorization_bearer(create_test_token(user_id, "lens_lister", "user"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["lensModels"], json!(["16-35mm f/4", "70-200mm f/2.8"]));
+}
+
+#[tokio::test]
+async fn test_map_nearby_filters_by_distance_and_access() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "nearby_user", "nearby_user@example.com");
+    let other_id = create_test_user(&pool, "nearby_other", "nearby_other@example.com");
+
+    // Roughly 0 m, 45 m and 2.2 km north of the search point
+    let center = create_test_media_with_gps(&pool, "nearby_center.jpg", 48.85837, 2.29448);
+    let close = create_test_media_with_gps(&pool, "nearby_close.jpg", 48.85877, 2.29448);
+    let far = create_test_media_with_gps(&pool, "nearby_far.jpg", 48.87837, 2.29448);
+    let hidden = create_test_media_with_gps(&pool, "nearby_hidden.jpg", 48.85840, 2.29450);
+    for media_id in [center, close, far] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    grant_media_access(&pool, hidden, other_id);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "nearby_user", "user");
+
+    let response = server
+        .post("/api/v1/map/nearby")
+        .authorization_bearer(token.clone())
+        .json(&json!({"latitude": 48.85837, "longitude": 2.29448, "radiusMeters": 100.0}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let ids: Vec<i64> = body["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![center, close]);
+
+    let response = server
+        .post("/api/v1/map/nearby")
+        .authorization_bearer(token.clone())
+        .json(&json!({"latitude": 48.85837, "longitude": 2.29448, "radiusMeters": 5000.0}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["items"].as_array().unwrap().len(), 3);
+
+    server
+        .post("/api/v1/map/nearby")
+        .authorization_bearer(token)
+        .json(&json!({"latitude": 48.85837, "longitude": 2.29448, "radiusMeters": 0.0}))
+        .await
+        .assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_map_nearby_crosses_the_antimeridian_and_poles() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "nearby_edges", "nearby_edges@example.com");
+
+    let east = create_test_media_with_gps(&pool, "nearby_east.jpg", -16.5, 179.9995);
+    let west = create_test_media_with_gps(&pool, "nearby_west.jpg", -16.5, -179.9995);
+    let pole_near = create_test_media_with_gps(&pool, "nearby_pole_a.jpg", 89.9995, 10.0);
+    let pole_far = create_test_media_with_gps(&pool, "nearby_pole_b.jpg", 89.9995, -170.0);
+    for media_id in [east, west, pole_near, pole_far] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "nearby_edges", "user");
+    let nearby_ids = |body: serde_json::Value| -> Vec<i64> {
+        body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_i64().unwrap())
+            .collect()
+    };
+
+    let response = server
+        .post("/api/v1/map/nearby")
+        .authorization_bearer(token.clone())
+        .json(&json!({"latitude": -16.5, "longitude": 179.9999, "radiusMeters": 200.0}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(nearby_ids(response.json()), vec![east, west]);
+
+    let response = server
+        .post("/api/v1/map/nearby")
+        .authorization_bearer(token)
+        .json(&json!({"latitude": 90.0, "longitude": 0.0, "radiusMeters": 100.0}))
+        .await;
+    response.assert_status_ok();
+    let mut ids = nearby_ids(response.json());
+    ids.sort_unstable();
+    assert_eq!(ids, vec![pole_near, pole_far]);
+}
+
+#[tokio::test]
+async fn test_map_geohash_search_returns_cell_media_and_bounds() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "geohash_user", "geohash_user@example.com");
+
+    let new_york = create_test_media_with_gps(&pool, "geohash_nyc.jpg", 40.7128, -74.0060);
+    let paris = create_test_media_with_gps(&pool, "geohash_paris.jpg", 48.85837, 2.29448);
+    for media_id in [new_york, paris] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "geohash_user", "user");
+    let precise = calculate_geohash(40.7128, -74.0060, GEOHASH_PRECISE_PRECISION).unwrap();
+
+    for query in [" DR5R ".to_string(), precise.clone()] {
+        let response = server
+            .post("/api/v1/map/geohash-search")
+            .authorization_bearer(token.clone())
+            .json(&json!({ "query": query }))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        let ids: Vec<i64> = body["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(ids, vec![new_york]);
+        assert_eq!(body["geohash"], query.trim().to_lowercase());
+
+        let bounds = &body["bounds"];
+        assert!(bounds["south"].as_f64().unwrap() <= 40.7128);
+        assert!(bounds["north"].as_f64().unwrap() >= 40.7128);
+        assert!(bounds["west"].as_f64().unwrap() <= -74.0060);
+        assert!(bounds["east"].as_f64().unwrap() >= -74.0060);
+    }
+
+    for query in ["nyc!", "drsea", "", "d", "dr5regw3pg"] {
+        server
+            .post("/api/v1/map/geohash-search")
+            .authorization_bearer(token.clone())
+            .json(&json!({ "query": query }))
+            .await
+            .assert_status(axum::http::StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_map_clusters_cached_until_gps_media_changes() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "cluster_cache", "cluster_cache@example.com");
+    let first = create_test_media_with_gps(&pool, "first.jpg", 40.7128, -74.0060);
+    grant_media_access(&pool, first, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "cluster_cache", "user");
+    let body = json!({
+        "bounds": {"north": 90.0, "south": -90.0, "east": 180.0, "west": -180.0},
+        "zoom": 4
+    });
+    let total_count = || async {
+        let response = server
+            .post("/api/v1/map/clusters")
+            .authorization_bearer(&token)
+            .json(&body)
+            .await;
+        response.assert_status_ok();
+        response.json::<serde_json::Value>()["totalCount"].clone()
+    };
+
+    assert_eq!(total_count().await, 1);
+
+    // Written behind the API's back, so the cached response still stands
+    let second = create_test_media_with_gps(&pool, "second.jpg", 48.8566, 2.3522);
+    grant_media_access(&pool, second, user_id);
+    let third = create_test_media_with_gps(&pool, "third.jpg", 51.5074, -0.1278);
+    grant_media_access(&pool, third, user_id);
+    assert_eq!(total_count().await, 1);
+
+    server
+        .post("/api/v1/media/delete")
+        .authorization_bearer(&token)
+        .json(&json!({"mediaId": first}))
+        .await
+        .assert_status_ok();
+    assert_eq!(total_count().await, 2);
+}
+
+#[tokio::test]
+async fn test_map_clusters_cache_invalidated_by_media_transfer() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(
+        &pool,
+        "cluster_xfer_admin",
+        "cluster_xfer_admin@example.com",
+    );
+    pool.get()
+        .unwrap()
+        .execute("UPDATE users SET role = 'admin' WHERE id = ?", [admin_id])
+        .unwrap();
+    let from_id = create_test_user(&pool, "cluster_xfer_from", "cluster_xfer_from@example.com");
+    let to_id = create_test_user(&pool, "cluster_xfer_to", "cluster_xfer_to@example.com");
+    let media_id = create_test_media_with_gps(&pool, "xfer.jpg", 40.7128, -74.0060);
+    grant_media_access(&pool, media_id, from_id);
+
+    let server = TestServer::new(app).unwrap();
+    let to_token = create_test_token(to_id, "cluster_xfer_to", "user");
+    let body = json!({
+        "bounds": {"north": 90.0, "south": -90.0, "east": 180.0, "west": -180.0},
+        "zoom": 4
+    });
+    let total_count = || async {
+        let response = server
+            .post("/api/v1/map/clusters")
+            .authorization_bearer(&to_token)
+            .json(&body)
+            .await;
+        response.assert_status_ok();
+        response.json::<serde_json::Value>()["totalCount"].clone()
+    };
+
+    assert_eq!(total_count().await, 0);
+
+    server
+        .post("/api/v1/admin/media/transfer")
+        .authorization_bearer(create_test_token(admin_id, "cluster_xfer_admin", "admin"))
+        .json(&json!({"fromUserId": from_id, "toUserId": to_id}))
+        .await
+        .assert_status_ok();
+    assert_eq!(total_count().await, 1);
+}
@@ -0,0 +1,2861 @@
+use axum::http::StatusCode;
+use axum_test::multipart::{MultipartForm, Part};
+use axum_test::TestServer;
+use momento_api::app::create_app;
+use momento_api::auth::create_download_token;
+use momento_api::config::Config;
+use momento_api::constants::{
+    MAX_MEDIA_BATCH_SIZE, MEDIA_CSP, MEDIA_EDIT_HISTORY_LIMIT, PREVIEWS_DIR,
+};
+use momento_api::database::DbPool;
+use momento_api::processor::media_processor::{
+    process_media_file, MediaProcessingContext, ProcessingResult,
+};
+use momento_api::utils::hash::calculate_file_hash;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::test_utils::{
+    create_test_app, create_test_db, create_test_db_at_version, create_test_media,
+    create_test_media_with_gps, create_test_media_with_gps_and_date, create_test_token,
+    create_test_user, grant_media_access,
+};
+
+fn grant_owner_access(pool: &DbPool, media_id: i64, user_id: i64) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO media_access (media_id, user_id, access_level) VALUES (?, ?, 2)",
+        rusqlite::params![media_id, user_id],
+    )
+    .expect("Failed to grant owner access");
+}
+
+fn set_role(pool: &DbPool, user_id: i64, role: &str) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE users SET role = ? WHERE id = ?",
+        rusqlite::params![role, user_id],
+    )
+    .expect("Failed to update role");
+}
+
+fn count_access_rows(pool: &DbPool, media_id: i64) -> i64 {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.query_row(
+        "SELECT COUNT(*) FROM media_access WHERE media_id = ?",
+        [media_id],
+        |row| row.get(0),
+    )
+    .expect("Failed to count access rows")
+}
+
+fn count_media_rows(pool: &DbPool) -> i64 {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.query_row("SELECT COUNT(*) FROM media", [], |row| row.get(0))
+        .expect("Failed to count media rows")
+}
+
+#[tokio::test]
+async fn test_duplicate_media_for_other_user_as_admin() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "dup_admin", "dup_admin@example.com");
+    let other_id = create_test_user(&pool, "dup_other", "dup_other@example.com");
+    set_role(&pool, admin_id, "admin");
+    let media_id = create_test_media(&pool, "dup_photo.jpg");
+    grant_owner_access(&pool, media_id, admin_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/duplicate")
+        .authorization_bearer(create_test_token(admin_id, "dup_admin", "admin"))
+        .json(&json!({"mediaId": media_id, "targetUserId": other_id}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["id"], media_id);
+    assert_eq!(count_media_rows(&pool), 1);
+    assert_eq!(count_access_rows(&pool, media_id), 2);
+}
+
+#[tokio::test]
+async fn test_duplicate_media_for_other_user_requires_admin() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "dup_owner", "dup_owner@example.com");
+    let other_id = create_test_user(&pool, "dup_target", "dup_target@example.com");
+    let media_id = create_test_media(&pool, "dup_forbidden.jpg");
+    grant_owner_access(&pool, media_id, owner_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/duplicate")
+        .authorization_bearer(create_test_token(owner_id, "dup_owner", "user"))
+        .json(&json!({"mediaId": media_id, "targetUserId": other_id}))
+        .await;
+
+    response.assert_status(StatusCode::FORBIDDEN);
+    assert_eq!(count_access_rows(&pool, media_id), 1);
+}
+
+#[tokio::test]
+async fn test_duplicate_media_requires_ownership() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "dup_real_owner", "dup_real_owner@example.com");
+    let viewer_id = create_test_user(&pool, "dup_viewer", "dup_viewer@example.com");
+    let media_id = create_test_media(&pool, "dup_viewer.jpg");
+    grant_owner_access(&pool, media_id, owner_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/duplicate")
+        .authorization_bearer(create_test_token(viewer_id, "dup_viewer", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await;
+
+    response.assert_status(StatusCode::NOT_FOUND);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "MEDIA_NOT_FOUND");
+    assert_eq!(body["message"], "Media not found");
+}
+
+#[tokio::test]
+async fn test_content_hash_dedup_and_duplicate_share_single_file() {
+    let (app, pool) = create_test_app();
+    let first_id = create_test_user(&pool, "dedup_first", "dedup_first@example.com");
+    let second_id = create_test_user(&pool, "dedup_second", "dedup_second@example.com");
+    let third_id = create_test_user(&pool, "dedup_admin", "dedup_admin@example.com");
+    set_role(&pool, first_id, "admin");
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let source_path = temp_dir.path().join("dedup.jpg");
+    std::fs::write(&source_path, b"dedup test contents").expect("Failed to write file");
+    let content_hash = calculate_file_hash(&source_path)
+        .await
+        .expect("Failed to hash file");
+
+    let media_id = create_test_media(&pool, "dedup.jpg");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media SET content_hash = ?, file_path = ? WHERE id = ?",
+            rusqlite::params![content_hash, source_path.to_string_lossy(), media_id],
+        )
+        .expect("Failed to set content hash");
+    }
+    grant_owner_access(&pool, media_id, first_id);
+
+    let context = MediaProcessingContext {
+        user_id: second_id,
+        thumbnails: Config::default().thumbnails,
+        reverse_geocoding: None,
+        max_file_size_bytes: None,
+        pool: pool.clone(),
+        post_processors: Config::default().post_processors,
+        cluster_cache: None,
+    };
+    let result = process_media_file(&source_path, &context).await;
+    assert_eq!(
+        result,
+        ProcessingResult::DuplicateAccessGranted { media_id }
+    );
+    let result = process_media_file(&source_path, &context).await;
+    assert_eq!(result, ProcessingResult::AlreadyOwned { media_id });
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/duplicate")
+        .authorization_bearer(create_test_token(first_id, "dedup_first", "admin"))
+        .json(&json!({"mediaId": media_id, "targetUserId": third_id}))
+        .await
+        .assert_status_ok();
+
+    assert_eq!(count_media_rows(&pool), 1);
+    assert_eq!(count_access_rows(&pool, media_id), 3);
+    assert_eq!(std::fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+}
+
+fn create_media_with_camera(
+    pool: &DbPool,
+    user_id: i64,
+    filename: &str,
+    iso: i32,
+    focal: f64,
+) -> i64 {
+    let media_id = create_test_media(pool, filename);
+    grant_owner_access(pool, media_id, user_id);
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media_metadata SET iso = ?, focal_length_35mm = ? WHERE media_id = ?",
+        rusqlite::params![iso, focal, media_id],
+    )
+    .expect("Failed to set camera settings");
+    media_id
+}
+
+fn response_ids(body: &serde_json::Value) -> Vec<i64> {
+    body["items"]
+        .as_array()
+        .expect("items should be an array")
+        .iter()
+        .map(|item| item["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_list_media_filters_by_iso_range() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "iso_user", "iso_user@example.com");
+    let _low = create_media_with_camera(&pool, user_id, "iso_100.jpg", 100, 50.0);
+    let mid = create_media_with_camera(&pool, user_id, "iso_800.jpg", 800, 24.0);
+    let high = create_media_with_camera(&pool, user_id, "iso_12800.jpg", 12800, 16.0);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "iso_user", "user");
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"isoMin": 6400}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![high]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"isoMin": 400, "isoMax": 1600}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![mid]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"isoMin": 400, "focalLength35mmMax": 20.0}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![high]);
+}
+
+#[tokio::test]
+async fn test_list_media_sorted_by_file_size_pages_through_ties() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "size_sort", "size_sort@example.com");
+    let mut by_size = Vec::new();
+    for (filename, size) in [("big.jpg", 3000), ("tie_a.jpg", 2000), ("tie_b.jpg", 2000)] {
+        let media_id = create_test_media(&pool, filename);
+        grant_media_access(&pool, media_id, user_id);
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE media SET file_size = ? WHERE id = ?",
+                rusqlite::params![size, media_id],
+            )
+            .unwrap();
+        by_size.push(media_id);
+    }
+    let small = create_test_media(&pool, "small.jpg");
+    grant_media_access(&pool, small, user_id);
+    pool.get()
+        .unwrap()
+        .execute("UPDATE media SET file_size = 10 WHERE id = ?", [small])
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "size_sort", "user");
+    let mut seen = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let response = server
+            .post("/api/v1/media/list")
+            .authorization_bearer(token.clone())
+            .json(&json!({"sortBy": "fileSize", "sortDir": "asc", "limit": 2, "cursor": cursor}))
+            .await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        seen.extend(response_ids(&body));
+        match body["nextCursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => break,
+        }
+    }
+
+    // Ties on size fall back to ascending id
+    assert_eq!(seen, vec![small, by_size[1], by_size[2], by_size[0]]);
+}
+
+#[tokio::test]
+async fn test_list_media_sorted_by_camera_model_puts_missing_last() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "model_sort", "model_sort@example.com");
+    let unknown_a = create_test_media(&pool, "unknown_a.jpg");
+    let canon = create_test_media(&pool, "canon.jpg");
+    let unknown_b = create_test_media(&pool, "unknown_b.jpg");
+    let nikon = create_test_media(&pool, "nikon.jpg");
+    let conn = pool.get().unwrap();
+    for media_id in [unknown_a, canon, unknown_b, nikon] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    conn.execute(
+        "UPDATE media_metadata SET camera_model = 'EOS R5' WHERE media_id = ?",
+        [canon],
+    )
+    .unwrap();
+    conn.execute(
+        "UPDATE media_metadata SET camera_model = 'Z 8' WHERE media_id = ?",
+        [nikon],
+    )
+    .unwrap();
+    conn.execute(
+        "UPDATE media_metadata SET camera_model = '' WHERE media_id = ?",
+        [unknown_b],
+    )
+    .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "model_sort", "user");
+    let first = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"sortBy": "cameraModel", "limit": 3}))
+        .await;
+    first.assert_status_ok();
+    let first: serde_json::Value = first.json();
+    assert_eq!(response_ids(&first), vec![nikon, canon, unknown_b]);
+
+    let second = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"sortBy": "cameraModel", "limit": 3, "cursor": first["nextCursor"]}))
+        .await;
+    second.assert_status_ok();
+    let second: serde_json::Value = second.json();
+    assert_eq!(response_ids(&second), vec![unknown_a]);
+    assert_eq!(second["hasMore"], false);
+}
+
+#[tokio::test]
+async fn test_list_media_sort_rejects_group_by() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "sort_group", "sort_group@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(user_id, "sort_group", "user"))
+        .json(&json!({"sortBy": "fileSize", "groupBy": "month"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_list_media_filters_by_lens_model_case_insensitively() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "lens_user", "lens_user@example.com");
+    let zoom = create_media_with_camera(&pool, user_id, "lens_zoom.jpg", 100, 24.0);
+    let prime = create_media_with_camera(&pool, user_id, "lens_prime.jpg", 100, 50.0);
+    let conn = pool.get().expect("Failed to get connection");
+    for (media_id, lens) in [(zoom, "FE 24-70mm F2.8 GM"), (prime, "FE 50mm F1.2 GM")] {
+        conn.execute(
+            "UPDATE media_metadata SET lens_make = 'Sony', lens_model = ? WHERE media_id = ?",
+            rusqlite::params![lens, media_id],
+        )
+        .expect("Failed to set lens");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(user_id, "lens_user", "user"))
+        .json(&json!({"lensModel": "fe 24-70mm f2.8 gm"}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(response_ids(&body), vec![zoom]);
+    assert_eq!(body["items"][0]["lensMake"], "Sony");
+    assert_eq!(body["items"][0]["lensModel"], "FE 24-70mm F2.8 GM");
+}
+
+#[tokio::test]
+async fn test_cameras_groups_media_and_lists_by_camera() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "camera_user", "camera_user@example.com");
+    let first = create_media_with_camera(&pool, user_id, "camera_a1.jpg", 100, 24.0);
+    let second = create_media_with_camera(&pool, user_id, "camera_a2.jpg", 100, 24.0);
+    let other = create_media_with_camera(&pool, user_id, "camera_b.jpg", 100, 24.0);
+    let unknown_make = create_media_with_camera(&pool, user_id, "camera_c.jpg", 100, 24.0);
+    create_media_with_camera(&pool, user_id, "camera_none.jpg", 100, 24.0);
+    let conn = pool.get().expect("Failed to get connection");
+    for (media_id, make, model) in [
+        (first, Some("Canon"), "EOS R5"),
+        (second, Some("Canon"), "EOS R5"),
+        (other, Some("Sony"), "A7 IV"),
+        (unknown_make, None, "Scanner"),
+    ] {
+        conn.execute(
+            "UPDATE media_metadata SET camera_make = ?, camera_model = ? WHERE media_id = ?",
+            rusqlite::params![make, model, media_id],
+        )
+        .expect("Failed to set camera");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "camera_user", "user");
+    let cameras: serde_json::Value = server
+        .get("/api/v1/media/cameras")
+        .authorization_bearer(token.clone())
+        .await
+        .json();
+    assert_eq!(
+        cameras,
+        json!([
+            {"make": "Canon", "model": "EOS R5", "count": 2},
+            {"make": "", "model": "Scanner", "count": 1},
+            {"make": "Sony", "model": "A7 IV", "count": 1},
+        ])
+    );
+
+    let page: serde_json::Value = server
+        .post("/api/v1/media/list-by-camera")
+        .authorization_bearer(token.clone())
+        .json(&json!({"make": "Canon", "model": "EOS R5", "limit": 1, "includeTotal": true}))
+        .await
+        .json();
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+    assert_eq!(page["hasMore"], true);
+    assert_eq!(page["totalCount"], 2);
+    let next: serde_json::Value = server
+        .post("/api/v1/media/list-by-camera")
+        .authorization_bearer(token.clone())
+        .json(&json!({"make": "Canon", "model": "EOS R5", "cursor": page["nextCursor"]}))
+        .await
+        .json();
+    assert!(next.get("totalCount").is_none());
+    let mut ids = [response_ids(&page), response_ids(&next)].concat();
+    ids.sort();
+    assert_eq!(ids, vec![first, second]);
+
+    let scanner: serde_json::Value = server
+        .post("/api/v1/media/list-by-camera")
+        .authorization_bearer(token)
+        .json(&json!({"make": "", "model": "Scanner"}))
+        .await
+        .json();
+    assert_eq!(response_ids(&scanner), vec![unknown_make]);
+}
+
+#[tokio::test]
+async fn test_focal_lengths_buckets_media_by_range() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "focal_user", "focal_user@example.com");
+    let other_id = create_test_user(&pool, "focal_other", "focal_other@example.com");
+    for (index, focal) in [16.0, 24.0, 35.0, 49.9, 85.0, 200.0, 400.0]
+        .iter()
+        .enumerate()
+    {
+        create_media_with_camera(&pool, user_id, &format!("focal_{}.jpg", index), 100, *focal);
+    }
+    create_media_with_camera(&pool, other_id, "focal_other.jpg", 100, 50.0);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get("/api/v1/media/focal-lengths")
+        .authorization_bearer(create_test_token(user_id, "focal_user", "user"))
+        .await;
+    response.assert_status_ok();
+    let buckets: serde_json::Value = response.json();
+    assert_eq!(
+        buckets,
+        json!([
+            {"label": "<24mm", "minMm": null, "maxMm": 24.0, "count": 1},
+            {"label": "24-50mm", "minMm": 24.0, "maxMm": 50.0, "count": 3},
+            {"label": "50-85mm", "minMm": 50.0, "maxMm": 85.0, "count": 0},
+            {"label": "85-135mm", "minMm": 85.0, "maxMm": 135.0, "count": 1},
+            {"label": "135-200mm", "minMm": 135.0, "maxMm": 200.0, "count": 0},
+            {"label": ">200mm", "minMm": 200.0, "maxMm": null, "count": 2},
+        ])
+    );
+}
+
+#[tokio::test]
+async fn test_slideshow_orders_album_and_tag_media_and_shuffles_by_seed() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "slides_user", "slides_user@example.com");
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO albums (user_id, name) VALUES (?, 'Trip')",
+        [user_id],
+    )
+    .expect("Failed to create album");
+    let album_id = conn.last_insert_rowid();
+    conn.execute(
+        "INSERT INTO album_access (album_id, user_id, access_level) VALUES (?, ?, 2)",
+        [album_id, user_id],
+    )
+    .expect("Failed to grant album access");
+    conn.execute("INSERT INTO tags (name) VALUES ('beach')", [])
+        .expect("Failed to create tag");
+    let tag_id = conn.last_insert_rowid();
+
+    let mut by_date = Vec::new();
+    for (index, date) in ["2024-03-01", "2024-01-01", "2024-02-01"]
+        .iter()
+        .enumerate()
+    {
+        let media_id = create_test_media(&pool, &format!("slide_{}.jpg", index));
+        grant_owner_access(&pool, media_id, user_id);
+        conn.execute(
+            "UPDATE media_metadata SET date_taken = ? WHERE media_id = ?",
+            rusqlite::params![format!("{}T12:00:00", date), media_id],
+        )
+        .expect("Failed to set date");
+        conn.execute(
+            "INSERT INTO album_media (album_id, media_id, position) VALUES (?, ?, ?)",
+            [album_id, media_id, index as i64],
+        )
+        .expect("Failed to add to album");
+        conn.execute(
+            "INSERT INTO media_tags (media_id, tag_id) VALUES (?, ?)",
+            [media_id, tag_id],
+        )
+        .expect("Failed to tag media");
+        by_date.push((date.to_string(), media_id));
+    }
+    by_date.sort();
+    let expected: Vec<i64> = by_date.into_iter().map(|(_, id)| id).collect();
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "slides_user", "user");
+    let album: serde_json::Value = server
+        .post("/api/v1/media/slideshow")
+        .authorization_bearer(token.clone())
+        .json(&json!({"albumId": album_id}))
+        .await
+        .json();
+    assert_eq!(album["mediaIds"], json!(expected));
+    let tag: serde_json::Value = server
+        .post("/api/v1/media/slideshow")
+        .authorization_bearer(token.clone())
+        .json(&json!({"tag": "beach"}))
+        .await
+        .json();
+    assert_eq!(tag["mediaIds"], json!(expected));
+    assert_ne!(tag["sequenceId"], album["sequenceId"]);
+
+    let shuffled = |seed: u64| {
+        server
+            .post("/api/v1/media/slideshow")
+            .authorization_bearer(token.clone())
+            .json(&json!({"albumId": album_id, "shuffle": true, "seed": seed}))
+    };
+    let first: serde_json::Value = shuffled(7).await.json();
+    let again: serde_json::Value = shuffled(7).await.json();
+    assert_eq!(first, again);
+    assert_eq!(first["seed"], json!(7));
+    assert!(album.get("seed").is_none());
+    let random: serde_json::Value = server
+        .post("/api/v1/media/slideshow")
+        .authorization_bearer(token.clone())
+        .json(&json!({"albumId": album_id, "shuffle": true}))
+        .await
+        .json();
+    let replayed: serde_json::Value = shuffled(random["seed"].as_u64().unwrap()).await.json();
+    assert_eq!(replayed, random);
+    let mut ids: Vec<i64> = serde_json::from_value(first["mediaIds"].clone()).unwrap();
+    ids.sort();
+    let mut sorted_expected = expected.clone();
+    sorted_expected.sort();
+    assert_eq!(ids, sorted_expected);
+    assert_ne!(first["sequenceId"], album["sequenceId"]);
+
+    server
+        .post("/api/v1/media/slideshow")
+        .authorization_bearer(token.clone())
+        .json(&json!({"albumId": album_id, "tag": "beach"}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+    server
+        .post("/api/v1/media/slideshow")
+        .authorization_bearer(token)
+        .json(&json!({"tag": "missing"}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_list_media_filters_by_upload_date_independently_of_date_taken() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "upload_user", "upload_user@example.com");
+    let old_scan = create_media_with_camera(&pool, user_id, "upload_scan.jpg", 100, 50.0);
+    let recent = create_media_with_camera(&pool, user_id, "upload_recent.jpg", 800, 24.0);
+    let archived = create_media_with_camera(&pool, user_id, "upload_archived.jpg", 800, 24.0);
+    let conn = pool.get().expect("Failed to get connection");
+    for (media_id, created_at, date_taken) in [
+        (old_scan, "2024-06-03 09:00:00", "1998-07-14T12:00:00"),
+        (recent, "2024-06-05 18:30:00", "2024-06-01T08:00:00"),
+        (archived, "2023-01-10 10:00:00", "2024-06-04T08:00:00"),
+    ] {
+        conn.execute(
+            "UPDATE media SET created_at = ? WHERE id = ?",
+            rusqlite::params![created_at, media_id],
+        )
+        .expect("Failed to set created_at");
+        conn.execute(
+            "UPDATE media_metadata SET date_taken = ? WHERE media_id = ?",
+            rusqlite::params![date_taken, media_id],
+        )
+        .expect("Failed to set date_taken");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "upload_user", "user");
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"uploadedAfter": "2024-06-01"}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![recent, old_scan]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"uploadedAfter": "2024-06-01", "uploadedBefore": "2024-06-04"}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![old_scan]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"uploadedBefore": "2024-01-01", "isoMin": 400}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![archived]);
+}
+
+#[tokio::test]
+async fn test_list_media_include_total_counts_filtered_rows() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "total_user", "total_user@example.com");
+    for (index, iso) in [100, 800, 1600, 3200].into_iter().enumerate() {
+        create_media_with_camera(&pool, user_id, &format!("total_{}.jpg", index), iso, 35.0);
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "total_user", "user");
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"isoMin": 800, "limit": 1, "includeTotal": true}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(response_ids(&body).len(), 1);
+    assert_eq!(body["totalCount"], 3);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"limit": 2, "includeTotal": true}))
+        .await;
+    assert_eq!(response.json::<serde_json::Value>()["totalCount"], 4);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"sortBy": "fileSize", "isoMax": 800, "limit": 1, "includeTotal": true}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["totalCount"], 2);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"limit": 2}))
+        .await;
+    assert!(response
+        .json::<serde_json::Value>()
+        .get("totalCount")
+        .is_none());
+}
+
+#[test]
+fn test_search_text_migration_backfills_existing_media() {
+    // Migration 5 adds search_text
+    let pool = create_test_db_at_version(4);
+    let media_id = create_test_media(&pool, "kyoto_trip.jpg");
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media_metadata SET keywords = 'temple', camera_model = 'X100V', \
+         location_city = 'Kyoto', location_country = 'Japan' WHERE media_id = ?",
+        [media_id],
+    )
+    .expect("Failed to set metadata");
+
+    momento_api::database::init_database(&conn).expect("Failed to re-run migrations");
+
+    let search_text: String = conn
+        .query_row(
+            "SELECT search_text FROM media WHERE id = ?",
+            [media_id],
+            |row| row.get(0),
+        )
+        .expect("Failed to read search text");
+    assert_eq!(search_text, "kyoto_trip.jpg temple X100V Kyoto Japan");
+
+    let matched: i64 = conn
+        .query_row(
+            "SELECT rowid FROM fts_media WHERE fts_media MATCH 'temple'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("Failed to query FTS index");
+    assert_eq!(matched, media_id);
+}
+
+#[tokio::test]
+async fn test_list_media_filters_paginate_with_cursor() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "iso_pager", "iso_pager@example.com");
+    let first = create_media_with_camera(&pool, user_id, "page_1.jpg", 3200, 35.0);
+    let second = create_media_with_camera(&pool, user_id, "page_2.jpg", 6400, 35.0);
+    let _excluded = create_media_with_camera(&pool, user_id, "page_3.jpg", 200, 35.0);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "iso_pager", "user");
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token.clone())
+        .json(&json!({"isoMin": 1000, "limit": 1}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(response_ids(&body), vec![second]);
+    assert_eq!(body["hasMore"], true);
+    let cursor = body["nextCursor"]
+        .as_str()
+        .expect("cursor expected")
+        .to_string();
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(token)
+        .json(&json!({"isoMin": 1000, "limit": 1, "cursor": cursor}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(response_ids(&body), vec![first]);
+    assert_eq!(body["hasMore"], false);
+}
+
+#[tokio::test]
+async fn test_list_media_rejects_out_of_range_limits_and_malformed_cursors() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "bad_page", "bad_page@example.com");
+    create_media_with_camera(&pool, user_id, "bad_page.jpg", 800, 35.0);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "bad_page", "user");
+    for body in [
+        json!({"limit": 0}),
+        json!({"limit": i32::MAX}),
+        json!({"groupBy": "month", "limit": -1}),
+        json!({"isoMin": 100, "cursor": "not-a-cursor"}),
+        json!({"cursor": "not-a-cursor"}),
+        json!({"groupBy": "month", "cursor": "2024-01-01_x"}),
+        json!({"sortBy": "fileSize", "cursor": "big_1"}),
+        json!({"sortBy": "fileSize", "cursor": "no-id"}),
+    ] {
+        server
+            .post("/api/v1/media/list")
+            .authorization_bearer(token.clone())
+            .json(&body)
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_list_media_grouped_by_month_applies_filters() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "group_filter", "group_filter@example.com");
+    let high = create_media_with_camera(&pool, user_id, "group_high.jpg", 6400, 35.0);
+    let _low = create_media_with_camera(&pool, user_id, "group_low.jpg", 100, 35.0);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(user_id, "group_filter", "user"))
+        .json(&json!({"groupBy": "month", "isoMin": 1000, "limit": 10, "includeTotal": true}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let groups = body["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["date"], "2024-01");
+    let ids: Vec<i64> = groups[0]["media"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|media| media["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![high]);
+    assert_eq!(body["totalCount"], 1);
+}
+
+fn set_date_taken(pool: &DbPool, media_id: i64, date_taken: &str) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media_metadata SET date_taken = ? WHERE media_id = ?",
+        rusqlite::params![date_taken, media_id],
+    )
+    .expect("Failed to set date taken");
+}
+
+#[tokio::test]
+async fn test_date_mismatch_flag_and_admin_listing() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "mismatch_admin", "mismatch_admin@example.com");
+    set_role(&pool, admin_id, "admin");
+    let wrong_clock = create_test_media(&pool, "wrong_clock.jpg");
+    let recent = create_test_media(&pool, "recent.jpg");
+    grant_owner_access(&pool, wrong_clock, admin_id);
+    grant_owner_access(&pool, recent, admin_id);
+    set_date_taken(&pool, wrong_clock, "2001-01-01T00:00:00");
+    set_date_taken(
+        &pool,
+        recent,
+        &chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+    );
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(admin_id, "mismatch_admin", "admin");
+
+    let response = server
+        .post("/api/v1/media/get-batch")
+        .authorization_bearer(token.clone())
+        .json(&json!({"ids": [wrong_clock, recent]}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["items"][0]["hasDateMismatch"], true);
+    assert_eq!(body["items"][1]["hasDateMismatch"], false);
+
+    let response = server
+        .get("/api/v1/media/date-mismatches")
+        .authorization_bearer(token)
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![wrong_clock]);
+}
+
+#[tokio::test]
+async fn test_date_mismatches_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "mismatch_user", "mismatch_user@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .get("/api/v1/media/date-mismatches")
+        .authorization_bearer(create_test_token(user_id, "mismatch_user", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_update_media_normalizes_date_taken_to_utc() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "date_editor", "date_editor@example.com");
+    let media_id = create_test_media(&pool, "date_edit.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/update")
+        .authorization_bearer(create_test_token(user_id, "date_editor", "user"))
+        .json(&json!({"mediaId": media_id, "dateTaken": "2023-06-15T14:30:00+05:30"}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["dateTaken"], "2023-06-15T09:00:00+00:00");
+}
+
+#[tokio::test]
+async fn test_update_media_rejects_invalid_date_taken() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "date_garbage", "date_garbage@example.com");
+    let media_id = create_test_media(&pool, "date_garbage.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/update")
+        .authorization_bearer(create_test_token(user_id, "date_garbage", "user"))
+        .json(&json!({"mediaId": media_id, "dateTaken": "last tuesday"}))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+    let conn = pool.get().unwrap();
+    let stored: String = conn
+        .query_row(
+            "SELECT date_taken FROM media_metadata WHERE media_id = ?",
+            [media_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(stored, "2024-01-15T10:30:00");
+}
+
+#[tokio::test]
+async fn test_update_media_records_edit_history_for_owner() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "history_owner", "history_owner@example.com");
+    let viewer_id = create_test_user(&pool, "history_viewer", "history_viewer@example.com");
+    let media_id = create_test_media(&pool, "history.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+    grant_media_access(&pool, media_id, viewer_id);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "history_owner", "user");
+    for _ in 0..2 {
+        server
+            .post("/api/v1/media/update")
+            .authorization_bearer(token.clone())
+            .json(&json!({
+                "mediaId": media_id,
+                "dateTaken": "2023-06-15T09:00:00Z",
+                "gpsLatitude": 48.5,
+            }))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get(&format!("/api/v1/media/{}/edit-history", media_id))
+        .authorization_bearer(token)
+        .await;
+    response.assert_status_ok();
+    let history: serde_json::Value = response.json();
+    let history = history.as_array().unwrap();
+    // The repeated update changed nothing, so only the first is recorded
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0]["fieldName"], "gps_latitude");
+    assert_eq!(history[0]["oldValue"], serde_json::Value::Null);
+    assert_eq!(history[0]["newValue"], "48.5");
+    assert_eq!(history[1]["fieldName"], "date_taken");
+    assert_eq!(history[1]["oldValue"], "2024-01-15T10:30:00");
+    assert_eq!(history[1]["newValue"], "2023-06-15T09:00:00+00:00");
+    assert_eq!(history[1]["userId"], user_id);
+
+    server
+        .get(&format!("/api/v1/media/{}/edit-history", media_id))
+        .authorization_bearer(create_test_token(viewer_id, "history_viewer", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_media_edit_history_is_pruned_to_limit() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "history_prune", "history_prune@example.com");
+    let media_id = create_test_media(&pool, "history_prune.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+    {
+        let conn = pool.get().unwrap();
+        for i in 0..MEDIA_EDIT_HISTORY_LIMIT {
+            conn.execute(
+                "INSERT INTO media_edits (media_id, user_id, field_name, old_value, new_value) \
+                 VALUES (?, ?, 'date_taken', NULL, ?)",
+                rusqlite::params![media_id, user_id, i.to_string()],
+            )
+            .unwrap();
+        }
+    }
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/update")
+        .authorization_bearer(create_test_token(user_id, "history_prune", "user"))
+        .json(&json!({"mediaId": media_id, "gpsLongitude": 2.25}))
+        .await
+        .assert_status_ok();
+
+    let conn = pool.get().unwrap();
+    let (count, oldest): (i64, i64) = conn
+        .query_row(
+            "SELECT COUNT(*), MIN(CAST(new_value AS INTEGER)) FROM media_edits \
+             WHERE media_id = ? AND field_name = 'date_taken'",
+            [media_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    // The new longitude edit pushed out the single oldest entry
+    assert_eq!(count + 1, MEDIA_EDIT_HISTORY_LIMIT);
+    assert_eq!(oldest, 1);
+}
+
+fn date_taken_of(pool: &DbPool, media_id: i64) -> Option<String> {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.query_row(
+        "SELECT date_taken FROM media_metadata WHERE media_id = ?",
+        [media_id],
+        |row| row.get(0),
+    )
+    .expect("Failed to read date taken")
+}
+
+#[tokio::test]
+async fn test_date_correction_shifts_owned_dates_and_records_history() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "date_fix", "date_fix@example.com");
+    let first = create_test_media(&pool, "date_fix_1.jpg");
+    let second = create_test_media(&pool, "date_fix_2.jpg");
+    let undated = create_test_media(&pool, "date_fix_3.jpg");
+    for media_id in [first, second, undated] {
+        grant_owner_access(&pool, media_id, user_id);
+    }
+    set_date_taken(&pool, second, "2024-03-31T23:30:00+00:00");
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_metadata SET date_taken = NULL WHERE media_id = ?",
+            [undated],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "date_fix", "user");
+    let response = server
+        .post("/api/v1/media/date-correction")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaIds": [second, first, undated, first], "offsetSeconds": 3600}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["affectedCount"], 2);
+    assert_eq!(body["sampleNewDate"], "2024-01-15T11:30:00+00:00");
+
+    assert_eq!(
+        date_taken_of(&pool, first).as_deref(),
+        Some("2024-01-15T11:30:00+00:00")
+    );
+    assert_eq!(
+        date_taken_of(&pool, second).as_deref(),
+        Some("2024-04-01T00:30:00+00:00")
+    );
+    assert_eq!(date_taken_of(&pool, undated), None);
+
+    let response = server
+        .get(&format!("/api/v1/media/{}/edit-history", second))
+        .authorization_bearer(token.clone())
+        .await;
+    response.assert_status_ok();
+    let history: serde_json::Value = response.json();
+    assert_eq!(history[0]["fieldName"], "date_taken");
+    assert_eq!(history[0]["oldValue"], "2024-03-31T23:30:00+00:00");
+    assert_eq!(history[0]["newValue"], "2024-04-01T00:30:00+00:00");
+
+    server
+        .post("/api/v1/media/date-correction")
+        .authorization_bearer(token)
+        .json(&json!({"mediaIds": [first], "offsetSeconds": 0}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_date_correction_requires_ownership_of_every_id() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "date_fix_owner", "date_fix_owner@example.com");
+    let owned = create_test_media(&pool, "date_fix_owned.jpg");
+    let shared = create_test_media(&pool, "date_fix_shared.jpg");
+    grant_owner_access(&pool, owned, user_id);
+    grant_media_access(&pool, shared, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/date-correction")
+        .authorization_bearer(create_test_token(user_id, "date_fix_owner", "user"))
+        .json(&json!({"mediaIds": [owned, shared], "offsetSeconds": -7200}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    // The batch is rejected as a whole, so the owned item is unchanged too
+    assert_eq!(
+        date_taken_of(&pool, owned).as_deref(),
+        Some("2024-01-15T10:30:00")
+    );
+}
+
+#[tokio::test]
+async fn test_update_metadata_sets_clears_and_keeps_fields() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "exif_editor", "exif_editor@example.com");
+    let media_id = create_media_with_camera(&pool, user_id, "exif_edit.jpg", 400, 50.0);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/update-metadata")
+        .authorization_bearer(create_test_token(user_id, "exif_editor", "user"))
+        .json(&json!({"mediaId": media_id, "cameraModel": "X-T5", "iso": null}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cameraModel"], "X-T5");
+    assert!(body["iso"].is_null());
+    assert_eq!(body["focalLength35mm"], 50.0);
+    assert_eq!(body["dateTaken"], "2024-01-15T10:30:00");
+
+    let conn = pool.get().unwrap();
+    let search_text: String = conn
+        .query_row(
+            "SELECT search_text FROM media WHERE id = ?",
+            [media_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(search_text.contains("X-T5"));
+    assert_eq!(
+        edit_history(&pool, media_id),
+        vec![
+            ("camera_model".to_string(), None, Some("X-T5".to_string())),
+            ("iso".to_string(), Some("400".to_string()), None),
+        ]
+    );
+}
+
+fn edit_history(pool: &DbPool, media_id: i64) -> Vec<(String, Option<String>, Option<String>)> {
+    pool.get()
+        .unwrap()
+        .prepare("SELECT field_name, old_value, new_value FROM media_edits WHERE media_id = ? ORDER BY id")
+        .unwrap()
+        .query_map([media_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_update_metadata_requires_media_access() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "exif_owner", "exif_owner@example.com");
+    let stranger_id = create_test_user(&pool, "exif_stranger", "exif_stranger@example.com");
+    let media_id = create_media_with_camera(&pool, owner_id, "exif_private.jpg", 200, 35.0);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/update-metadata")
+        .authorization_bearer(create_test_token(stranger_id, "exif_stranger", "user"))
+        .json(&json!({"mediaId": media_id, "cameraMake": "Leica"}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+fn create_media_taken_at(pool: &DbPool, user_id: i64, filename: &str, date_taken: &str) -> i64 {
+    let media_id = create_test_media(pool, filename);
+    grant_owner_access(pool, media_id, user_id);
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media_metadata SET date_taken = ? WHERE media_id = ?",
+        rusqlite::params![date_taken, media_id],
+    )
+    .expect("Failed to set date taken");
+    media_id
+}
+
+#[tokio::test]
+async fn test_timeline_dates_counts_buckets_per_group() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "calendar", "calendar@example.com");
+    let other_id = create_test_user(&pool, "calendar_other", "calendar_other@example.com");
+    create_media_taken_at(&pool, user_id, "cal_a.jpg", "2024-01-15T10:30:00");
+    create_media_taken_at(&pool, user_id, "cal_b.jpg", "2024-01-20T08:00:00");
+    create_media_taken_at(&pool, user_id, "cal_c.jpg", "2024-03-02T12:00:00");
+    create_media_taken_at(&pool, user_id, "cal_d.jpg", "2023-06-01T09:00:00");
+    let trashed = create_media_taken_at(&pool, user_id, "cal_e.jpg", "2024-01-01T00:00:00");
+    create_media_taken_at(&pool, other_id, "cal_f.jpg", "2024-01-05T00:00:00");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media_access SET deleted_at = CURRENT_TIMESTAMP WHERE media_id = ?",
+            [trashed],
+        )
+        .expect("Failed to trash media");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "calendar", "user");
+
+    let months: serde_json::Value = server
+        .get("/api/v1/timeline/dates")
+        .authorization_bearer(token.clone())
+        .await
+        .json();
+    assert_eq!(
+        months["buckets"],
+        json!([
+            {"date": "2024-03", "count": 1},
+            {"date": "2024-01", "count": 2},
+            {"date": "2023-06", "count": 1}
+        ])
+    );
+
+    let years: serde_json::Value = server
+        .get("/api/v1/timeline/dates?group_by=year")
+        .authorization_bearer(token.clone())
+        .await
+        .json();
+    assert_eq!(
+        years["buckets"],
+        json!([{"date": "2024", "count": 3}, {"date": "2023", "count": 1}])
+    );
+
+    let days: serde_json::Value = server
+        .get("/api/v1/timeline/dates?group_by=day")
+        .authorization_bearer(token.clone())
+        .await
+        .json();
+    assert_eq!(days["buckets"].as_array().unwrap().len(), 4);
+
+    server
+        .get("/api/v1/timeline/dates?group_by=week")
+        .authorization_bearer(token)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+fn active_access(pool: &DbPool, user_id: i64) -> Vec<(i64, i64)> {
+    let conn = pool.get().expect("Failed to get connection");
+    let mut stmt = conn
+        .prepare(
+            "SELECT media_id, access_level FROM media_access \
+             WHERE user_id = ? AND deleted_at IS NULL ORDER BY media_id",
+        )
+        .unwrap();
+    stmt.query_map([user_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_transfer_media_moves_active_access_and_merges_existing() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "xfer_admin", "xfer_admin@example.com");
+    set_role(&pool, admin_id, "admin");
+    let from_id = create_test_user(&pool, "xfer_from", "xfer_from@example.com");
+    let to_id = create_test_user(&pool, "xfer_to", "xfer_to@example.com");
+    let moved = create_test_media(&pool, "xfer_moved.jpg");
+    let shared = create_test_media(&pool, "xfer_shared.jpg");
+    let trashed = create_test_media(&pool, "xfer_trashed.jpg");
+    for media_id in [moved, shared, trashed] {
+        grant_owner_access(&pool, media_id, from_id);
+    }
+    grant_media_access(&pool, shared, to_id);
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media_access SET deleted_at = datetime('now') WHERE media_id = ?",
+            [trashed],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/admin/media/transfer")
+        .authorization_bearer(create_test_token(admin_id, "xfer_admin", "admin"))
+        .json(&json!({"fromUserId": from_id, "toUserId": to_id}))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["transferredCount"], 2);
+    assert_eq!(active_access(&pool, to_id), vec![(moved, 2), (shared, 2)]);
+    assert_eq!(active_access(&pool, from_id), vec![]);
+    assert_eq!(count_access_rows(&pool, trashed), 1);
+}
+
+#[tokio::test]
+async fn test_transfer_media_limits_to_listed_ids_and_checks_users() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "xfer2_admin", "xfer2_admin@example.com");
+    set_role(&pool, admin_id, "admin");
+    let from_id = create_test_user(&pool, "xfer2_from", "xfer2_from@example.com");
+    let to_id = create_test_user(&pool, "xfer2_to", "xfer2_to@example.com");
+    let picked = create_test_media(&pool, "xfer2_picked.jpg");
+    let kept = create_test_media(&pool, "xfer2_kept.jpg");
+    grant_owner_access(&pool, picked, from_id);
+    grant_owner_access(&pool, kept, from_id);
+
+    let server = TestServer::new(app).unwrap();
+    let admin_token = create_test_token(admin_id, "xfer2_admin", "admin");
+    let response = server
+        .post("/api/v1/admin/media/transfer")
+        .authorization_bearer(admin_token.clone())
+        .json(&json!({"fromUserId": from_id, "toUserId": to_id, "mediaIds": [picked]}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["transferredCount"], 1);
+    assert_eq!(active_access(&pool, to_id), vec![(picked, 2)]);
+    assert_eq!(active_access(&pool, from_id), vec![(kept, 2)]);
+
+    server
+        .post("/api/v1/admin/media/transfer")
+        .authorization_bearer(admin_token)
+        .json(&json!({"fromUserId": from_id, "toUserId": 999_999}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    server
+        .post("/api/v1/admin/media/transfer")
+        .authorization_bearer(create_test_token(from_id, "xfer2_from", "user"))
+        .json(&json!({"fromUserId": from_id, "toUserId": to_id}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_duplicates_report_lists_no_groups_for_unique_hashes() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "dup_admin", "dup_admin@example.com");
+    set_role(&pool, admin_id, "admin");
+    create_test_media(&pool, "unique_a.jpg");
+    create_test_media(&pool, "unique_b.jpg");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/admin/duplicates")
+        .authorization_bearer(create_test_token(admin_id, "dup_admin", "admin"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["groups"], json!([]));
+    assert_eq!(body["totalWastedBytes"], 0);
+}
+
+#[tokio::test]
+async fn test_duplicates_report_groups_media_by_perceptual_hash() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "dup_groups", "dup_groups@example.com");
+    set_role(&pool, admin_id, "admin");
+    let original = create_test_media(&pool, "dup_original.jpg");
+    let resized = create_test_media(&pool, "dup_resized.jpg");
+    let unrelated = create_test_media(&pool, "dup_unrelated.jpg");
+    {
+        let conn = pool.get().unwrap();
+        for (media_id, phash, file_size) in [
+            (original, -1_i64, 3000_i64),
+            (resized, -1, 1200),
+            (unrelated, 0x0f0f, 500),
+        ] {
+            conn.execute(
+                "UPDATE media SET phash = ?, file_size = ? WHERE id = ?",
+                rusqlite::params![phash, file_size, media_id],
+            )
+            .unwrap();
+        }
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/admin/duplicates")
+        .authorization_bearer(create_test_token(admin_id, "dup_groups", "admin"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["groups"],
+        json!([{"phash": "ffffffffffffffff", "mediaIds": [original, resized], "count": 2}])
+    );
+    assert_eq!(body["totalWastedBytes"], 1200);
+}
+
+#[tokio::test]
+async fn test_duplicates_report_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "dup_user", "dup_user@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/admin/duplicates")
+        .authorization_bearer(create_test_token(user_id, "dup_user", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_check_duplicate_reports_only_accessible_media() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "hash_owner", "hash_owner@example.com");
+    let other_id = create_test_user(&pool, "hash_other", "hash_other@example.com");
+    let media_id = create_test_media(&pool, "hashed.jpg");
+    grant_owner_access(&pool, media_id, owner_id);
+    let content_hash = "ab".repeat(32);
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media SET content_hash = ? WHERE id = ?",
+            rusqlite::params![content_hash, media_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let found: serde_json::Value = server
+        .post("/api/v1/media/check-duplicate")
+        .authorization_bearer(create_test_token(owner_id, "hash_owner", "user"))
+        .json(&json!({ "contentHash": content_hash }))
+        .await
+        .json();
+    assert_eq!(found, json!({ "exists": true, "mediaId": media_id }));
+
+    let hidden: serde_json::Value = server
+        .post("/api/v1/media/check-duplicate")
+        .authorization_bearer(create_test_token(other_id, "hash_other", "user"))
+        .json(&json!({ "contentHash": content_hash }))
+        .await
+        .json();
+    assert_eq!(hidden, json!({ "exists": false, "mediaId": null }));
+
+    server
+        .post("/api/v1/media/check-duplicate")
+        .authorization_bearer(create_test_token(owner_id, "hash_owner", "user"))
+        .json(&json!({ "contentHash": "AB".repeat(32) }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_media_response_includes_audio_details() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "audio_user", "audio_user@example.com");
+    let media_id = create_test_media(&pool, "clip.mp4");
+    grant_owner_access(&pool, media_id, user_id);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_metadata SET audio_codec = 'aac', audio_channels = 2, audio_sample_rate = 48000 WHERE media_id = ?",
+            [media_id],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/get-batch")
+        .authorization_bearer(create_test_token(user_id, "audio_user", "user"))
+        .json(&json!({"ids": [media_id]}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["items"][0]["audioCodec"], "aac");
+    assert_eq!(body["items"][0]["audioChannels"], 2);
+    assert_eq!(body["items"][0]["audioSampleRate"], 48000);
+}
+
+#[tokio::test]
+async fn test_get_batch_omits_inaccessible_media_and_caps_size() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "batch_user", "batch_user@example.com");
+    let other_id = create_test_user(&pool, "batch_other", "batch_other@example.com");
+    let first = create_test_media(&pool, "batch_first.jpg");
+    let second = create_test_media(&pool, "batch_second.jpg");
+    let foreign = create_test_media(&pool, "batch_foreign.jpg");
+    let trashed = create_test_media(&pool, "batch_trashed.jpg");
+    grant_owner_access(&pool, first, user_id);
+    grant_owner_access(&pool, second, user_id);
+    grant_owner_access(&pool, foreign, other_id);
+    grant_owner_access(&pool, trashed, user_id);
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media_access SET deleted_at = datetime('now') WHERE media_id = ?",
+            [trashed],
+        )
+        .expect("Failed to trash media");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "batch_user", "user");
+
+    let response = server
+        .post("/api/v1/media/get-batch")
+        .authorization_bearer(token.clone())
+        .json(&json!({"ids": [second, foreign, trashed, first]}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![second, first]);
+
+    let too_many: Vec<i64> = (1..=101).collect();
+    server
+        .post("/api/v1/media/get-batch")
+        .authorization_bearer(token)
+        .json(&json!({ "ids": too_many }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+async fn spawn_fake_geocoder() -> String {
+    let app = axum::Router::new().route(
+        "/reverse",
+        axum::routing::get(|| async {
+            axum::Json(json!({
+                "address": {"city": "Lisbon", "state": "Lisboa", "country": "Portugal"}
+            }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    format!("http://{}/reverse", addr)
+}
+
+#[tokio::test]
+async fn test_manual_geocode_updates_location_and_rtree() {
+    let pool = create_test_db();
+    let mut config = Config::default();
+    config.reverse_geocoding.base_url = spawn_fake_geocoder().await;
+    config.reverse_geocoding.rate_limit_seconds = 0.0;
+    let app = create_app(Arc::new(config), pool.clone());
+
+    let user_id = create_test_user(&pool, "geocode_user", "geocode_user@example.com");
+    let media_id = create_test_media(&pool, "indoors.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/manual-geocode")
+        .authorization_bearer(create_test_token(user_id, "geocode_user", "user"))
+        .json(&json!({"mediaId": media_id, "latitude": 38.7223, "longitude": -9.1393}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["gpsLatitude"], 38.7223);
+    assert_eq!(body["gpsLongitude"], -9.1393);
+    assert_eq!(body["locationCity"], "Lisbon");
+    assert_eq!(body["locationState"], "Lisboa");
+    assert_eq!(body["locationCountry"], "Portugal");
+
+    let conn = pool.get().unwrap();
+    let (geohash, rtree_count): (Option<String>, i64) = conn
+        .query_row(
+            "SELECT mm.geohash, (SELECT COUNT(*) FROM media_rtree WHERE media_id = ?1) \
+             FROM media_metadata AS mm WHERE mm.media_id = ?1",
+            [media_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert!(geohash.is_some());
+    assert_eq!(rtree_count, 1);
+    assert_eq!(
+        edit_history(&pool, media_id),
+        vec![
+            (
+                "gps_latitude".to_string(),
+                None,
+                Some("38.7223".to_string())
+            ),
+            (
+                "gps_longitude".to_string(),
+                None,
+                Some("-9.1393".to_string())
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_manual_geocode_rejects_out_of_range_coordinates() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "geocode_bad", "geocode_bad@example.com");
+    let media_id = create_test_media(&pool, "nowhere.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/manual-geocode")
+        .authorization_bearer(create_test_token(user_id, "geocode_bad", "user"))
+        .json(&json!({"mediaId": media_id, "latitude": 91.0, "longitude": 0.0}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_strip_location_clears_gps_fields() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "strip_owner", "strip_owner@example.com");
+    let media_id = create_test_media_with_gps(&pool, "located.jpg", 48.8566, 2.3522);
+    grant_owner_access(&pool, media_id, user_id);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO media_rtree (media_id, min_lat, max_lat, min_lon, max_lon) VALUES (?, 48.8566, 48.8566, 2.3522, 2.3522)",
+            [media_id],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/strip-location")
+        .authorization_bearer(create_test_token(user_id, "strip_owner", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["gpsLatitude"].is_null());
+    assert!(body["gpsLongitude"].is_null());
+    assert!(body["locationCountry"].is_null());
+
+    let conn = pool.get().unwrap();
+    let (geohash, rtree_count): (Option<String>, i64) = conn
+        .query_row(
+            "SELECT mm.geohash, (SELECT COUNT(*) FROM media_rtree WHERE media_id = ?1) \
+             FROM media_metadata AS mm WHERE mm.media_id = ?1",
+            [media_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert!(geohash.is_none());
+    assert_eq!(rtree_count, 0);
+}
+
+#[tokio::test]
+async fn test_strip_location_requires_owner() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "strip_viewer", "strip_viewer@example.com");
+    let media_id = create_test_media_with_gps(&pool, "shared.jpg", 48.8566, 2.3522);
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/strip-location")
+        .authorization_bearer(create_test_token(user_id, "strip_viewer", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_strip_location_keeps_shared_original() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "strip_first", "strip_first@example.com");
+    let other_id = create_test_user(&pool, "strip_second", "strip_second@example.com");
+    let media_id = create_test_media_with_gps(&pool, "strip_dedup.jpg", 48.8566, 2.3522);
+    grant_owner_access(&pool, media_id, user_id);
+    grant_owner_access(&pool, media_id, other_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/strip-location")
+        .authorization_bearer(create_test_token(user_id, "strip_first", "user"))
+        .json(&json!({"mediaId": media_id, "modifyOriginal": true}))
+        .await
+        .assert_status(StatusCode::CONFLICT);
+
+    let latitude: Option<f64> = pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT gps_latitude FROM media_metadata WHERE media_id = ?",
+            [media_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(latitude, Some(48.8566));
+}
+
+#[tokio::test]
+async fn test_rotate_rejects_unsupported_angle() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "rotate_angle", "rotate_angle@example.com");
+    let media_id = create_test_media(&pool, "tilted.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/rotate")
+        .authorization_bearer(create_test_token(user_id, "rotate_angle", "user"))
+        .json(&json!({"mediaId": media_id, "degrees": 45}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_rotate_non_jpeg_returns_422() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "rotate_png", "rotate_png@example.com");
+    let media_id = create_test_media(&pool, "screenshot.png");
+    grant_owner_access(&pool, media_id, user_id);
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media SET mime_type = 'image/png' WHERE id = ?",
+            [media_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/rotate")
+        .authorization_bearer(create_test_token(user_id, "rotate_png", "user"))
+        .json(&json!({"mediaId": media_id, "degrees": 90}))
+        .await;
+
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "UNSUPPORTED_MEDIA");
+}
+
+#[tokio::test]
+async fn test_detect_orientation_non_jpeg_returns_422() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "orient_png", "orient_png@example.com");
+    let media_id = create_test_media(&pool, "diagram.png");
+    grant_owner_access(&pool, media_id, user_id);
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media SET mime_type = 'image/png' WHERE id = ?",
+            [media_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/detect-orientation")
+        .authorization_bearer(create_test_token(user_id, "orient_png", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await;
+
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "UNSUPPORTED_MEDIA");
+}
+
+#[tokio::test]
+async fn test_delete_preview_removes_only_that_media() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "preview_purge", "preview_purge@example.com");
+    let media_id = create_test_media(&pool, "blurry_preview_src.heic");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let user_previews = PREVIEWS_DIR.join(user_id.to_string());
+    std::fs::create_dir_all(&user_previews).unwrap();
+    let jpeg = user_previews.join("blurry_preview_src_preview.jpg");
+    let webp = user_previews.join("blurry_preview_src_preview.webp");
+    let other = user_previews.join("sharp_preview_src_preview.jpg");
+    for path in [&jpeg, &webp, &other] {
+        std::fs::write(path, b"preview").unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .delete(&format!("/api/v1/preview/{}", media_id))
+        .authorization_bearer(create_test_token(user_id, "preview_purge", "user"))
+        .await
+        .assert_status(StatusCode::NO_CONTENT);
+
+    assert!(!jpeg.exists());
+    assert!(!webp.exists());
+    assert!(other.exists());
+    std::fs::remove_file(other).unwrap();
+}
+
+#[tokio::test]
+async fn test_delete_preview_requires_owner() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "preview_viewer", "preview_viewer@example.com");
+    let media_id = create_test_media(&pool, "shared_preview_src.heic");
+    pool.get()
+        .unwrap()
+        .execute(
+            "INSERT INTO media_access (media_id, user_id, access_level) VALUES (?, ?, 1)",
+            rusqlite::params![media_id, user_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .delete(&format!("/api/v1/preview/{}", media_id))
+        .authorization_bearer(create_test_token(user_id, "preview_viewer", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+fn create_stats_fixture(
+    pool: &DbPool,
+    user_id: i64,
+    filename: &str,
+    media_type: &str,
+    camera: (&str, &str),
+    location: (&str, &str),
+    date_taken: &str,
+) {
+    let media_id = create_test_media(pool, filename);
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media SET media_type = ?, file_size = 1000 WHERE id = ?",
+        rusqlite::params![media_type, media_id],
+    )
+    .expect("Failed to update media");
+    conn.execute(
+        "UPDATE media_metadata SET camera_make = ?, camera_model = ?, location_city = ?, \
+         location_country = ?, date_taken = ? WHERE media_id = ?",
+        rusqlite::params![camera.0, camera.1, location.0, location.1, date_taken, media_id],
+    )
+    .expect("Failed to update media metadata");
+    grant_owner_access(pool, media_id, user_id);
+}
+
+#[tokio::test]
+async fn test_media_stats_aggregates_library() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "stats_user", "stats_user@example.com");
+    let other_id = create_test_user(&pool, "stats_other", "stats_other@example.com");
+
+    let canon = ("Canon", "EOS R5");
+    let paris = ("Paris", "France");
+    create_stats_fixture(
+        &pool,
+        user_id,
+        "a.jpg",
+        "image",
+        canon,
+        paris,
+        "2020-05-01T00:00:00",
+    );
+    create_stats_fixture(
+        &pool,
+        user_id,
+        "b.jpg",
+        "image",
+        canon,
+        paris,
+        "2021-05-01T00:00:00",
+    );
+    create_stats_fixture(
+        &pool,
+        user_id,
+        "c.jpg",
+        "image",
+        ("Sony", "A7 IV"),
+        ("Berlin", "Germany"),
+        "2022-05-01T00:00:00",
+    );
+    create_stats_fixture(
+        &pool,
+        user_id,
+        "d.mp4",
+        "video",
+        ("Apple", "iPhone 15"),
+        paris,
+        "2023-05-01T00:00:00",
+    );
+
+    // Trashed and foreign media are excluded
+    let trashed = create_test_media(&pool, "trashed.jpg");
+    grant_owner_access(&pool, trashed, user_id);
+    let foreign = create_test_media(&pool, "foreign.jpg");
+    grant_owner_access(&pool, foreign, other_id);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_access SET deleted_at = datetime('now') WHERE media_id = ?",
+            [trashed],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get("/api/v1/media/stats")
+        .authorization_bearer(create_test_token(user_id, "stats_user", "user"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["totalMedia"], 4);
+    assert_eq!(body["totalPhotos"], 3);
+    assert_eq!(body["totalVideos"], 1);
+    assert_eq!(body["totalSizeBytes"], 4000);
+    assert_eq!(body["oldestDate"], "2020-05-01T00:00:00");
+    assert_eq!(body["newestDate"], "2023-05-01T00:00:00");
+    assert_eq!(body["topCameras"][0]["camera"], "Canon EOS R5");
+    assert_eq!(body["topCameras"][0]["count"], 2);
+    assert_eq!(body["topCameras"].as_array().unwrap().len(), 3);
+    assert_eq!(body["topLocations"][0]["city"], "Paris");
+    assert_eq!(body["topLocations"][0]["country"], "France");
+    assert_eq!(body["topLocations"][0]["count"], 3);
+    assert_eq!(body["topLocations"][1]["city"], "Berlin");
+}
+
+#[tokio::test]
+async fn test_media_locations_facets_and_heatmap() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "facet_user", "facet_user@example.com");
+    let places = [
+        (48.8566, 2.3522, "France", "Paris"),
+        (48.86, 2.35, "France", "Paris"),
+        (52.52, 13.405, "Germany", "Berlin"),
+    ];
+    for (i, (lat, lon, country, city)) in places.into_iter().enumerate() {
+        let media_id = create_test_media_with_gps(&pool, &format!("facet_{}.jpg", i), lat, lon);
+        grant_owner_access(&pool, media_id, user_id);
+        pool.get()
+            .unwrap()
+            .execute(
+                "UPDATE media_metadata SET location_country = ?, location_city = ? \
+                 WHERE media_id = ?",
+                rusqlite::params![country, city, media_id],
+            )
+            .unwrap();
+    }
+    let unplaced = create_test_media(&pool, "facet_unplaced.jpg");
+    grant_owner_access(&pool, unplaced, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get("/api/v1/media/locations")
+        .authorization_bearer(create_test_token(user_id, "facet_user", "user"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(
+        body["locations"],
+        json!([
+            {"country": "France", "state": null, "city": "Paris", "count": 2},
+            {"country": "Germany", "state": null, "city": "Berlin", "count": 1},
+        ])
+    );
+    let heatmap = body["heatmap"].as_array().unwrap();
+    assert_eq!(heatmap.len(), 2);
+    assert_eq!(heatmap[0]["geohash"], "u0");
+    assert_eq!(heatmap[0]["count"], 2);
+    assert_eq!(heatmap[1]["geohash"], "u3");
+    let latitude = heatmap[0]["latitude"].as_f64().unwrap();
+    assert!((45.0..50.7).contains(&latitude));
+}
+
+#[tokio::test]
+async fn test_smart_album_suggestions_group_by_place_and_week() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "smart_album", "smart_album@example.com");
+
+    let first = create_test_media_with_gps_and_date(
+        &pool,
+        "paris_1.jpg",
+        48.8566,
+        2.3522,
+        "2024-03-01T10:00:00",
+    );
+    let second = create_test_media_with_gps_and_date(
+        &pool,
+        "paris_2.jpg",
+        48.8606,
+        2.3376,
+        "2024-03-04T18:30:00",
+    );
+    let later = create_test_media_with_gps_and_date(
+        &pool,
+        "paris_later.jpg",
+        48.8566,
+        2.3522,
+        "2024-04-20T09:00:00",
+    );
+    let berlin = create_test_media_with_gps_and_date(
+        &pool,
+        "berlin.jpg",
+        52.5200,
+        13.4050,
+        "2024-03-02T12:00:00",
+    );
+    for media_id in [first, second, later, berlin] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media_metadata SET location_city = 'Paris' WHERE media_id IN (?, ?)",
+            [first, second],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/smart-album/suggest")
+        .authorization_bearer(create_test_token(user_id, "smart_album", "user"))
+        .json(&json!({}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let suggestions = body["suggestions"].as_array().unwrap();
+    assert_eq!(suggestions.len(), 1);
+    assert_eq!(suggestions[0]["suggestedName"], "Paris, March 2024");
+    assert_eq!(suggestions[0]["mediaIds"], json!([first, second]));
+    let center_lat = suggestions[0]["centerLat"].as_f64().unwrap();
+    assert!((center_lat - 48.8586).abs() < 1e-6);
+
+    server
+        .post("/api/v1/media/smart-album/suggest")
+        .authorization_bearer(create_test_token(user_id, "smart_album", "user"))
+        .json(&json!({"windowDays": i64::MAX}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_download_token_serves_file_without_bearer() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "download_owner", "download_owner@example.com");
+    let media_id = create_test_media(&pool, "download.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("download.jpg");
+    std::fs::write(&file_path, b"jpeg bytes").unwrap();
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media SET file_path = ? WHERE id = ?",
+            rusqlite::params![file_path.to_string_lossy(), media_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/download-token")
+        .authorization_bearer(create_test_token(user_id, "download_owner", "user"))
+        .json(&json!({"mediaId": media_id, "expiresInSeconds": 60}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let url = body["url"].as_str().unwrap();
+    assert_eq!(
+        url,
+        format!(
+            "/api/v1/media/file/{}/download/{}",
+            media_id,
+            body["token"].as_str().unwrap()
+        )
+    );
+
+    let download = server.get(url).await;
+    download.assert_status_ok();
+    assert_eq!(download.as_bytes().as_ref(), b"jpeg bytes");
+
+    server
+        .get(&format!(
+            "/api/v1/media/file/{}/download/{}",
+            media_id + 1,
+            body["token"].as_str().unwrap()
+        ))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_download_token_stops_working_once_trashed() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "download_trasher", "download_trasher@example.com");
+    let media_id = create_test_media(&pool, "download_trashed.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let dir = tempfile::tempdir().unwrap();
+    let file_path = dir.path().join("download_trashed.jpg");
+    std::fs::write(&file_path, b"jpeg bytes").unwrap();
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media SET file_path = ? WHERE id = ?",
+            rusqlite::params![file_path.to_string_lossy(), media_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let body: serde_json::Value = server
+        .post("/api/v1/media/download-token")
+        .authorization_bearer(create_test_token(user_id, "download_trasher", "user"))
+        .json(&json!({"mediaId": media_id, "expiresInSeconds": 60}))
+        .await
+        .json();
+    let url = body["url"].as_str().unwrap();
+    server.get(url).await.assert_status_ok();
+
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media_access SET deleted_at = CURRENT_TIMESTAMP WHERE media_id = ? AND user_id = ?",
+            [media_id, user_id],
+        )
+        .unwrap();
+    server.get(url).await.assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_download_token_rejects_expired_token() {
+    let (app, pool) = create_test_app();
+    let media_id = create_test_media(&pool, "expired.jpg");
+    let user_id = create_test_user(&pool, "expired_owner", "expired_owner@example.com");
+    grant_media_access(&pool, media_id, user_id);
+    let expired = create_download_token(
+        media_id,
+        user_id,
+        chrono::Utc::now().timestamp() - 1,
+        &Config::default(),
+    );
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .get(&format!(
+            "/api/v1/media/file/{}/download/{}",
+            media_id, expired
+        ))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_regenerate_thumbnail_rejects_negative_offset() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "regen_offset", "regen_offset@example.com");
+    let media_id = create_test_media(&pool, "clip.mp4");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/regenerate-thumbnail")
+        .authorization_bearer(create_test_token(user_id, "regen_offset", "user"))
+        .json(&json!({"mediaId": media_id, "offsetSeconds": -1.0}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_regenerate_thumbnail_requires_owner() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "regen_viewer", "regen_viewer@example.com");
+    let media_id = create_test_media(&pool, "shared_clip.mp4");
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/regenerate-thumbnail")
+        .authorization_bearer(create_test_token(user_id, "regen_viewer", "user"))
+        .json(&json!({"mediaId": media_id, "offsetSeconds": 5.0}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_media_list_is_gzipped_above_threshold() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "gzip_user", "gzip_user@example.com");
+    let empty_id = create_test_user(&pool, "gzip_empty", "gzip_empty@example.com");
+    for index in 0..10 {
+        let media_id = create_test_media(&pool, &format!("gzip_{}.jpg", index));
+        grant_media_access(&pool, media_id, user_id);
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(user_id, "gzip_user", "user"))
+        .add_header(
+            axum::http::header::ACCEPT_ENCODING,
+            axum::http::HeaderValue::from_static("gzip"),
+        )
+        .json(&json!({}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("content-encoding"), "gzip");
+    assert!(response
+        .header("vary")
+        .to_str()
+        .unwrap()
+        .to_ascii_lowercase()
+        .contains("accept-encoding"));
+    assert_eq!(&response.as_bytes()[..2], &[0x1f, 0x8b]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(create_test_token(empty_id, "gzip_empty", "user"))
+        .add_header(
+            axum::http::header::ACCEPT_ENCODING,
+            axum::http::HeaderValue::from_static("gzip"),
+        )
+        .json(&json!({}))
+        .await;
+    response.assert_status_ok();
+    assert!(response.maybe_header("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn test_bulk_delete_trashes_owned_and_reports_skipped() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "bulk_delete", "bulk_delete@example.com");
+    let other_id = create_test_user(&pool, "bulk_other", "bulk_other@example.com");
+    let first = create_test_media(&pool, "bulk_1.jpg");
+    let second = create_test_media(&pool, "bulk_2.jpg");
+    let foreign = create_test_media(&pool, "bulk_foreign.jpg");
+    grant_media_access(&pool, first, user_id);
+    grant_media_access(&pool, second, user_id);
+    grant_media_access(&pool, foreign, other_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/bulk-delete")
+        .authorization_bearer(create_test_token(user_id, "bulk_delete", "user"))
+        .json(&json!({"mediaIds": [first, foreign, second, 999_999, first]}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["deletedCount"], 2);
+    assert_eq!(body["skippedIds"], json!([foreign, 999_999]));
+
+    let conn = pool.get().unwrap();
+    let trashed: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM media_access WHERE user_id = ? AND deleted_at IS NOT NULL",
+            [user_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(trashed, 2);
+    let foreign_trashed: Option<String> = conn
+        .query_row(
+            "SELECT deleted_at FROM media_access WHERE media_id = ? AND user_id = ?",
+            [foreign, other_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(foreign_trashed.is_none());
+}
+
+#[tokio::test]
+async fn test_bulk_delete_rejects_too_many_ids() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "bulk_limit", "bulk_limit@example.com");
+    let ids: Vec<i64> = (1..=MAX_MEDIA_BATCH_SIZE as i64 + 1).collect();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/bulk-delete")
+        .authorization_bearer(create_test_token(user_id, "bulk_limit", "user"))
+        .json(&json!({ "mediaIds": ids }))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_favorite_is_tracked_per_user() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "fav_owner", "fav_owner@example.com");
+    let viewer_id = create_test_user(&pool, "fav_viewer", "fav_viewer@example.com");
+    let shared = create_test_media(&pool, "fav_shared.jpg");
+    let other = create_test_media(&pool, "fav_other.jpg");
+    grant_owner_access(&pool, shared, owner_id);
+    grant_owner_access(&pool, other, owner_id);
+    grant_media_access(&pool, shared, viewer_id);
+
+    let server = TestServer::new(app).unwrap();
+    let owner_token = create_test_token(owner_id, "fav_owner", "user");
+    let viewer_token = create_test_token(viewer_id, "fav_viewer", "user");
+
+    let response = server
+        .post("/api/v1/media/favorite")
+        .authorization_bearer(owner_token.clone())
+        .json(&json!({"mediaId": shared, "isFavorite": true}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["isFavorite"], true);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(owner_token.clone())
+        .json(&json!({"favoritesOnly": true}))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response_ids(&response.json()), vec![shared]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(viewer_token.clone())
+        .json(&json!({"favoritesOnly": true}))
+        .await;
+    response.assert_status_ok();
+    assert!(response_ids(&response.json()).is_empty());
+
+    server
+        .post("/api/v1/media/favorite")
+        .authorization_bearer(viewer_token.clone())
+        .json(&json!({"mediaId": shared, "isFavorite": true}))
+        .await
+        .assert_status_ok();
+    server
+        .post("/api/v1/media/favorite")
+        .authorization_bearer(owner_token.clone())
+        .json(&json!({"mediaId": shared, "isFavorite": false}))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(viewer_token)
+        .json(&json!({"favoritesOnly": true}))
+        .await;
+    assert_eq!(response_ids(&response.json()), vec![shared]);
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(owner_token)
+        .json(&json!({"favoritesOnly": true}))
+        .await;
+    assert!(response_ids(&response.json()).is_empty());
+}
+
+#[tokio::test]
+async fn test_favorite_requires_media_access() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "fav_private", "fav_private@example.com");
+    let stranger_id = create_test_user(&pool, "fav_stranger", "fav_stranger@example.com");
+    let media_id = create_test_media(&pool, "fav_private.jpg");
+    grant_owner_access(&pool, media_id, owner_id);
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/favorite")
+        .authorization_bearer(create_test_token(stranger_id, "fav_stranger", "user"))
+        .json(&json!({"mediaId": media_id, "isFavorite": true}))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_thumbnail_batch_honours_if_none_match() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "thumb_cache", "thumb_cache@example.com");
+    let media_id = create_test_media(&pool, "thumb_cache.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let thumbnail_path = temp_dir.path().join("thumb_cache.jpg");
+    std::fs::write(&thumbnail_path, b"thumbnail bytes").expect("Failed to write thumbnail");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media_metadata SET thumbnail_path = ? WHERE media_id = ?",
+            rusqlite::params![thumbnail_path.to_string_lossy(), media_id],
+        )
+        .expect("Failed to set thumbnail path");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "thumb_cache", "user");
+
+    let first = server
+        .post("/api/v1/thumbnail/get")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaIds": [media_id]}))
+        .await;
+    first.assert_status_ok();
+    let body: serde_json::Value = first.json();
+    assert!(body["thumbnails"][media_id.to_string()]
+        .as_str()
+        .unwrap()
+        .starts_with("data:image/jpeg;base64,"));
+    let etag = first.header("etag");
+
+    let second = server
+        .post("/api/v1/thumbnail/get")
+        .authorization_bearer(token.clone())
+        .add_header(axum::http::header::IF_NONE_MATCH, etag.clone())
+        .json(&json!({"mediaIds": [media_id]}))
+        .await;
+    second.assert_status(StatusCode::NOT_MODIFIED);
+    assert_eq!(second.header("etag"), etag);
+
+    // A different set of ids is a different response, even though the
+    // newest file behind it is the same
+    let other_id = create_test_media(&pool, "thumb_cache_other.jpg");
+    grant_owner_access(&pool, other_id, user_id);
+    let widened = server
+        .post("/api/v1/thumbnail/get")
+        .authorization_bearer(token.clone())
+        .add_header(axum::http::header::IF_NONE_MATCH, etag.clone())
+        .json(&json!({"mediaIds": [media_id, other_id]}))
+        .await;
+    widened.assert_status_ok();
+    assert_ne!(widened.header("etag"), etag);
+
+    // The id order does not matter
+    let reordered = server
+        .post("/api/v1/thumbnail/get")
+        .authorization_bearer(token)
+        .add_header(axum::http::header::IF_NONE_MATCH, widened.header("etag"))
+        .json(&json!({"mediaIds": [other_id, media_id]}))
+        .await;
+    reordered.assert_status(StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_single_thumbnail_serves_existing_file_with_cache_header() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "thumb_single", "thumb_single@example.com");
+    let media_id = create_test_media(&pool, "thumb_single.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let thumbnail_path = temp_dir.path().join("thumb_single.jpg");
+    std::fs::write(&thumbnail_path, b"thumbnail bytes").expect("Failed to write thumbnail");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media_metadata SET thumbnail_path = ? WHERE media_id = ?",
+            rusqlite::params![thumbnail_path.to_string_lossy(), media_id],
+        )
+        .expect("Failed to set thumbnail path");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get(&format!("/api/v1/thumbnail/{}", media_id))
+        .authorization_bearer(create_test_token(user_id, "thumb_single", "user"))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("content-type"), "image/jpeg");
+    assert_eq!(response.header("cache-control"), "private, max-age=86400");
+    assert_eq!(response.header("content-security-policy"), MEDIA_CSP);
+    assert_eq!(response.as_bytes().as_ref(), b"thumbnail bytes");
+
+    let last_modified = response.header("last-modified");
+    let revalidated = server
+        .get(&format!("/api/v1/thumbnail/{}", media_id))
+        .authorization_bearer(create_test_token(user_id, "thumb_single", "user"))
+        .add_header(axum::http::header::IF_MODIFIED_SINCE, last_modified.clone())
+        .await;
+    revalidated.assert_status(StatusCode::NOT_MODIFIED);
+    assert_eq!(revalidated.header("last-modified"), last_modified);
+    assert!(revalidated.as_bytes().is_empty());
+}
+
+#[tokio::test]
+async fn test_single_thumbnail_falls_back_to_placeholder() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "thumb_missing", "thumb_missing@example.com");
+    let stranger_id = create_test_user(&pool, "thumb_stranger", "thumb_stranger@example.com");
+    let media_id = create_test_media(&pool, "thumb_missing_original.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get(&format!("/api/v1/thumbnail/{}", media_id))
+        .authorization_bearer(create_test_token(user_id, "thumb_missing", "user"))
+        .await;
+
+    response.assert_status_ok();
+    assert_eq!(response.header("cache-control"), "no-cache");
+    let placeholder =
+        image::load_from_memory(response.as_bytes()).expect("Placeholder is an image");
+    assert_eq!((placeholder.width(), placeholder.height()), (1, 1));
+
+    server
+        .get(&format!("/api/v1/thumbnail/{}", media_id))
+        .authorization_bearer(create_test_token(stranger_id, "thumb_stranger", "user"))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+fn set_phash(pool: &DbPool, media_id: i64, phash: i64) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media SET phash = ? WHERE id = ?",
+        rusqlite::params![phash, media_id],
+    )
+    .expect("Failed to set phash");
+}
+
+#[tokio::test]
+async fn test_similar_media_returns_close_hashes_by_distance() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "similar_user", "similar_user@example.com");
+    let stranger_id = create_test_user(&pool, "similar_other", "similar_other@example.com");
+    let base = 0x5a3c_0f96_e1d2_7b48_i64;
+
+    let original = create_test_media(&pool, "similar_original.jpg");
+    let near = create_test_media(&pool, "similar_near.jpg");
+    let nearer = create_test_media(&pool, "similar_nearer.jpg");
+    let unrelated = create_test_media(&pool, "similar_unrelated.jpg");
+    let private = create_test_media(&pool, "similar_private.jpg");
+    for media_id in [original, near, nearer, unrelated] {
+        grant_owner_access(&pool, media_id, user_id);
+    }
+    grant_owner_access(&pool, private, stranger_id);
+
+    set_phash(&pool, original, base);
+    set_phash(&pool, near, base ^ 0b1011_0001);
+    set_phash(&pool, nearer, base ^ 0b1);
+    set_phash(&pool, unrelated, !base);
+    set_phash(&pool, private, base);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/similar")
+        .authorization_bearer(create_test_token(user_id, "similar_user", "user"))
+        .json(&json!({"mediaId": original}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(response_ids(&body), vec![nearer, near]);
+    assert_eq!(body["items"][0]["distance"], 1);
+    assert_eq!(body["items"][1]["distance"], 4);
+
+    let strict: serde_json::Value = server
+        .post("/api/v1/media/similar")
+        .authorization_bearer(create_test_token(user_id, "similar_user", "user"))
+        .json(&json!({"mediaId": original, "threshold": 2}))
+        .await
+        .json();
+    assert_eq!(response_ids(&strict), vec![nearer]);
+
+    server
+        .post("/api/v1/media/similar")
+        .authorization_bearer(create_test_token(user_id, "similar_user", "user"))
+        .json(&json!({"mediaId": original, "threshold": 65}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_upload_reports_partial_failures_per_file() {
+    let pool = create_test_db();
+    let mut config = Config::default();
+    config.import.max_file_size_bytes = Some(1024);
+    let app = create_app(Arc::new(config), pool.clone());
+    let owner_id = create_test_user(&pool, "upload_owner", "upload_owner@example.com");
+    let user_id = create_test_user(&pool, "uploader", "uploader@example.com");
+
+    let contents = b"uploaded duplicate contents".to_vec();
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let existing_path = temp_dir.path().join("existing.jpg");
+    std::fs::write(&existing_path, &contents).expect("Failed to write file");
+    let content_hash = calculate_file_hash(&existing_path)
+        .await
+        .expect("Failed to hash file");
+    let media_id = create_test_media(&pool, "existing.jpg");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media SET content_hash = ? WHERE id = ?",
+            rusqlite::params![content_hash, media_id],
+        )
+        .expect("Failed to set content hash");
+    }
+    grant_owner_access(&pool, media_id, owner_id);
+
+    let form = MultipartForm::new()
+        .add_part("files", Part::bytes(contents).file_name("holiday.jpg"))
+        .add_part(
+            "files",
+            Part::bytes(b"hello".to_vec()).file_name("notes.txt"),
+        )
+        .add_part("files", Part::bytes(vec![0u8; 2048]).file_name("huge.jpg"));
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/upload")
+        .authorization_bearer(create_test_token(user_id, "uploader", "user"))
+        .multipart(form)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["uploaded"].as_array().unwrap().len(), 1);
+    assert_eq!(body["uploaded"][0]["id"], media_id);
+    assert_eq!(
+        body["failed"],
+        json!([
+            {"filename": "notes.txt", "reason": "unsupported file type"},
+            {"filename": "huge.jpg", "reason": "File too large"}
+        ])
+    );
+    assert_eq!(count_access_rows(&pool, media_id), 2);
+}
+
+#[tokio::test]
+async fn test_upload_requires_authentication() {
+    let (app, _pool) = create_test_app();
+    let form = MultipartForm::new().add_part("files", Part::bytes(vec![1u8]).file_name("a.jpg"));
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/upload")
+        .multipart(form)
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+}
+
+fn exiftool_available() -> bool {
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| dir.join("exiftool").is_file())
+    })
+}
+
+#[tokio::test]
+async fn test_media_exif_returns_exiftool_json() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "exif_viewer", "exif_viewer@example.com");
+    let stranger_id = create_test_user(&pool, "exif_outsider", "exif_outsider@example.com");
+    let media_id = create_test_media(&pool, "exif_fixture.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let fixture_path = temp_dir.path().join("exif_fixture.jpg");
+    image::RgbImage::from_pixel(8, 8, image::Rgb([200, 120, 40]))
+        .save(&fixture_path)
+        .expect("Failed to write fixture JPEG");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media SET file_path = ? WHERE id = ?",
+            rusqlite::params![fixture_path.to_string_lossy(), media_id],
+        )
+        .expect("Failed to set file path");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .get(&format!("/api/v1/media/exif/{}", media_id))
+        .authorization_bearer(create_test_token(user_id, "exif_viewer", "user"))
+        .await;
+
+    if exiftool_available() {
+        response.assert_status_ok();
+        assert_eq!(response.header("content-type"), "application/json");
+        let body: serde_json::Value = response.json();
+        assert_eq!(body[0]["FileType"], "JPEG");
+        assert_eq!(body[0]["ImageWidth"], 8);
+    } else {
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["code"], "SERVICE_UNAVAILABLE");
+    }
+
+    server
+        .get(&format!("/api/v1/media/exif/{}", media_id))
+        .authorization_bearer(create_test_token(stranger_id, "exif_outsider", "user"))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_import_url_rejects_non_https_and_private_hosts() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "url_importer", "url_importer@example.com");
+    let token = create_test_token(user_id, "url_importer", "user");
+    let server = TestServer::new(app).unwrap();
+
+    for (url, message) in [
+        (
+            "http://example.com/photo.jpg",
+            "Only https:// URLs can be imported",
+        ),
+        (
+            "https://192.168.1.10/photo.jpg",
+            "URL resolves to a private address",
+        ),
+        (
+            "https://127.0.0.1:8443/photo.jpg",
+            "URL resolves to a private address",
+        ),
+        ("not a url", "Invalid URL: relative URL without a base"),
+    ] {
+        let response = server
+            .post("/api/v1/media/import-url")
+            .authorization_bearer(&token)
+            .json(&json!({ "url": url }))
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+        assert_eq!(response.json::<serde_json::Value>()["message"], message);
+    }
+}
+
+#[tokio::test]
+async fn test_export_metadata_streams_csv_of_visible_media() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "exporter", "exporter@example.com");
+    let other_id = create_test_user(&pool, "export_other", "export_other@example.com");
+    let mine = create_test_media_with_gps(&pool, "export_mine.jpg", 35.0116, 135.7681);
+    let theirs = create_test_media(&pool, "export_theirs.jpg");
+    grant_media_access(&pool, mine, user_id);
+    grant_media_access(&pool, theirs, other_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/media/export-metadata")
+        .authorization_bearer(create_test_token(user_id, "exporter", "user"))
+        .json(&json!({"format": "csv"}))
+        .await;
+
+    response.assert_status_ok();
+    assert!(response
+        .header("content-type")
+        .to_str()
+        .unwrap()
+        .starts_with("text/csv"));
+    let disposition = response.header("content-disposition");
+    let disposition = disposition.to_str().unwrap();
+    assert!(disposition.starts_with("attachment; filename=\"momento_export_"));
+    assert!(disposition.ends_with(".csv\""));
+
+    let body = response.text();
+    let mut lines = body.lines();
+    let header: Vec<&str> = lines.next().unwrap().split(',').collect();
+    assert_eq!(header[0], "id");
+    assert!(header.contains(&"originalFilename"));
+    assert!(header.contains(&"gpsLatitude"));
+    assert!(header.contains(&"hasDateMismatch"));
+
+    let rows: Vec<&str> = lines.collect();
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].starts_with(&format!("{},export_mine.jpg,", mine)));
+    assert!(rows[0].contains("35.0116"));
+}
+
+#[tokio::test]
+async fn test_reprocess_media_keeps_fields_the_file_does_not_yield() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "reprocessor", "reprocessor@example.com");
+    let viewer_id = create_test_user(&pool, "reprocess_viewer", "reprocess_viewer@example.com");
+    let media_id = create_test_media(&pool, "reprocess_fixture.jpg");
+    grant_owner_access(&pool, media_id, user_id);
+    grant_media_access(&pool, media_id, viewer_id);
+
+    let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let fixture_path = temp_dir.path().join("reprocess_fixture.jpg");
+    image::RgbImage::from_pixel(8, 8, image::Rgb([40, 120, 200]))
+        .save(&fixture_path)
+        .expect("Failed to write fixture JPEG");
+    {
+        let conn = pool.get().expect("Failed to get connection");
+        conn.execute(
+            "UPDATE media SET file_path = ? WHERE id = ?",
+            rusqlite::params![fixture_path.to_string_lossy(), media_id],
+        )
+        .expect("Failed to set file path");
+        conn.execute(
+            "INSERT INTO media_metadata (media_id, width, height, date_taken, camera_model) \
+             VALUES (?, 1, 1, '2001-01-01T00:00:00+00:00', 'Stale Camera') \
+             ON CONFLICT(media_id) DO UPDATE SET width = 1, height = 1, \
+             date_taken = excluded.date_taken, camera_model = excluded.camera_model",
+            [media_id],
+        )
+        .expect("Failed to seed stale metadata");
+    }
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/media/reprocess")
+        .authorization_bearer(create_test_token(viewer_id, "reprocess_viewer", "user"))
+        .json(&json!({"mediaId": media_id, "regenerateMetadata": true}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let token = create_test_token(user_id, "reprocessor", "user");
+    server
+        .post("/api/v1/media/reprocess")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = server
+        .post("/api/v1/media/reprocess")
+        .authorization_bearer(token)
+        .json(&json!({"mediaId": media_id, "regenerateMetadata": true}))
+        .await;
+    if exiftool_available() {
+        response.assert_status_ok();
+    } else {
+        response.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    // Whatever the file did not yield keeps its stored value
+    let conn = pool.get().unwrap();
+    let (width, date_taken, camera_model): (Option<i32>, String, Option<String>) = conn
+        .query_row(
+            "SELECT width, date_taken, camera_model FROM media_metadata WHERE media_id = ?",
+            [media_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(camera_model.as_deref(), Some("Stale Camera"));
+    assert_eq!(date_taken, "2001-01-01T00:00:00+00:00");
+    assert_eq!(width, Some(if exiftool_available() { 8 } else { 1 }));
+}
+
+#[tokio::test]
+async fn test_media_list_filters_by_filename_pattern() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "pattern_user", "pattern_user@example.com");
+    let matching = create_test_media(&pool, "IMG_20240115.jpg");
+    let other = create_test_media(&pool, "photo.jpg");
+    let renamed = create_test_media(&pool, "upload_1.jpg");
+    for media_id in [matching, other, renamed] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media SET original_filename = 'IMG_20241231.jpg' WHERE id = ?",
+            [renamed],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "pattern_user", "user");
+    let list_ids = |pattern: &'static str| {
+        let request = server
+            .post("/api/v1/media/list")
+            .authorization_bearer(token.clone())
+            .json(&json!({"filenamePattern": pattern}));
+        async move {
+            let response = request.await;
+            response.assert_status_ok();
+            let body: serde_json::Value = response.json();
+            let mut ids: Vec<i64> = body["items"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_i64().unwrap())
+                .collect();
+            ids.sort();
+            ids
+        }
+    };
+
+    let mut expected = vec![matching, renamed];
+    expected.sort();
+    assert_eq!(list_ids("IMG_2024*.jpg").await, expected);
+    assert_eq!(list_ids("IMG_20240?15.jpg").await, vec![matching]);
+    assert_eq!(list_ids("hoto").await, vec![other]);
+    assert!(list_ids("IMG%").await.is_empty());
+}
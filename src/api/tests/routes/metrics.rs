@@ -0,0 +1,70 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use momento_api::app::create_app;
+use momento_api::config::Config;
+use std::sync::Arc;
+
+use crate::test_utils::{
+    create_test_app, create_test_db, create_test_media, create_test_token, create_test_user,
+};
+
+#[tokio::test]
+async fn test_metrics_reports_requests_by_route_and_media_per_user() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "metrics_user", "metrics_user@example.com");
+    let media_id = create_test_media(&pool, "metrics.jpg");
+    pool.get()
+        .unwrap()
+        .execute(
+            "INSERT INTO media_access (media_id, user_id, access_level) VALUES (?, ?, 2)",
+            [media_id, user_id],
+        )
+        .unwrap();
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .get(&format!("/api/v1/media/{}/edit-history", media_id))
+        .authorization_bearer(create_test_token(user_id, "metrics_user", "user"))
+        .await
+        .assert_status_ok();
+
+    let response = server.get("/metrics").await;
+    response.assert_status_ok();
+    let body = response.text();
+    assert!(body.contains(
+        r#"momento_http_requests_total{method="GET",path="/api/v1/media/:media_id/edit-history",status="200"}"#
+    ));
+    assert!(body.contains("momento_http_request_duration_seconds_bucket"));
+    assert!(!body.contains(&format!("path=\"/api/v1/media/{}/edit-history\"", media_id)));
+    assert!(body.contains(&format!("momento_media_total{{user_id=\"{}\"}} 1", user_id)));
+
+    pool.get()
+        .unwrap()
+        .execute("DELETE FROM users WHERE id = ?", [user_id])
+        .unwrap();
+    let body = server.get("/metrics").await.text();
+    assert!(!body.contains(&format!("momento_media_total{{user_id=\"{}\"}}", user_id)));
+    assert_eq!(body.matches("# TYPE momento_media_total gauge").count(), 1);
+}
+
+#[tokio::test]
+async fn test_metrics_requires_configured_bearer_token() {
+    let mut config = Config::default();
+    config.metrics.bearer_token = Some("scrape-secret".to_string());
+    let server = TestServer::new(create_app(Arc::new(config), create_test_db())).unwrap();
+
+    server
+        .get("/metrics")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get("/metrics")
+        .authorization_bearer("wrong-secret")
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+    server
+        .get("/metrics")
+        .authorization_bearer("scrape-secret")
+        .await
+        .assert_status_ok();
+}
@@ -1 +1,15 @@
+mod albums;
+mod auth;
+mod backup;
+mod docs;
+mod health;
+mod imports;
 mod map;
+mod media;
+mod metrics;
+mod share;
+mod storage;
+mod tags;
+mod trash;
+mod users;
+mod webdav;
@@ -0,0 +1,382 @@
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum_test::{TestServer, TestServerConfig};
+use momento_api::app::create_app;
+use momento_api::config::{Config, ServerConfig};
+use momento_api::constants::SHARE_REPORT_RATE_LIMIT;
+use momento_api::database::DbPool;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::test_utils::{
+    create_test_app, create_test_db, create_test_media, create_test_token, create_test_user,
+    grant_media_access,
+};
+
+#[tokio::test]
+async fn test_view_only_share_refuses_download() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "share_view", "share_view@example.com");
+    let media_id = create_test_media(&pool, "view_only.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(create_test_token(user_id, "share_view", "user"))
+        .json(&json!({"mediaId": media_id, "permissions": "view_only"}))
+        .await;
+    response.assert_status_ok();
+    let share: serde_json::Value = response.json();
+    assert_eq!(share["permissions"], "view_only");
+    let token = share["token"].as_str().unwrap();
+
+    server
+        .get(&format!("/api/v1/public/share/{}", token))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!(
+            "/api/v1/public/share/{}/media/{}",
+            token, media_id
+        ))
+        .await;
+    response.assert_status(StatusCode::FORBIDDEN);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "FORBIDDEN");
+}
+
+#[tokio::test]
+async fn test_share_defaults_to_download_permission() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "share_dl", "share_dl@example.com");
+    let media_id = create_test_media(&pool, "downloadable.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(create_test_token(user_id, "share_dl", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await;
+    response.assert_status_ok();
+    let share: serde_json::Value = response.json();
+    assert_eq!(share["permissions"], "download");
+
+    // The test media has no file on disk, so getting past the permission
+    // check surfaces as a missing file rather than a 403.
+    server
+        .get(&format!(
+            "/api/v1/public/share/{}/media/{}",
+            share["token"].as_str().unwrap(),
+            media_id
+        ))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_extend_share_link_updates_and_clears_expiry() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "share_extend", "share_extend@example.com");
+    let media_id = create_test_media(&pool, "extend.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "share_extend", "user");
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaId": media_id, "expiresInDays": 1}))
+        .await
+        .json();
+    let share_id = share["id"].as_i64().unwrap();
+    let original_expiry = share["expiresAt"].as_str().unwrap().to_string();
+
+    let response = server
+        .post("/api/v1/share/extend")
+        .authorization_bearer(token.clone())
+        .json(&json!({"shareId": share_id, "expiresInDays": 30}))
+        .await;
+    response.assert_status_ok();
+    let extended: serde_json::Value = response.json();
+    assert!(extended["expiresAt"].as_str().unwrap() > original_expiry.as_str());
+    assert_eq!(extended["token"], share["token"]);
+
+    server
+        .post("/api/v1/share/extend")
+        .authorization_bearer(token.clone())
+        .json(&json!({"shareId": share_id, "expiresInDays": u32::MAX}))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = server
+        .post("/api/v1/share/extend")
+        .authorization_bearer(token)
+        .json(&json!({"shareId": share_id}))
+        .await;
+    response.assert_status_ok();
+    assert!(response.json::<serde_json::Value>()["expiresAt"].is_null());
+}
+
+#[tokio::test]
+async fn test_share_link_updates_require_ownership() {
+    let (app, pool) = create_test_app();
+    let owner_id = create_test_user(&pool, "share_owner", "share_owner@example.com");
+    let other_id = create_test_user(&pool, "share_other", "share_other@example.com");
+    let media_id = create_test_media(&pool, "owned_share.jpg");
+    grant_media_access(&pool, media_id, owner_id);
+
+    let server = TestServer::new(app).unwrap();
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(create_test_token(owner_id, "share_owner", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .json();
+    let share_id = share["id"].as_i64().unwrap();
+
+    let other_token = create_test_token(other_id, "share_other", "user");
+    for path in ["/api/v1/share/extend", "/api/v1/share/rotate-token"] {
+        server
+            .post(path)
+            .authorization_bearer(other_token.clone())
+            .json(&json!({"shareId": share_id, "expiresInDays": 7}))
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn test_rotate_share_token_invalidates_old_token() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "share_rotate", "share_rotate@example.com");
+    let media_id = create_test_media(&pool, "rotate.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "share_rotate", "user");
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaId": media_id, "permissions": "view_only"}))
+        .await
+        .json();
+    let old_token = share["token"].as_str().unwrap().to_string();
+
+    let response = server
+        .post("/api/v1/share/rotate-token")
+        .authorization_bearer(token)
+        .json(&json!({"shareId": share["id"]}))
+        .await;
+    response.assert_status_ok();
+    let rotated: serde_json::Value = response.json();
+    let new_token = rotated["token"].as_str().unwrap();
+    assert_ne!(new_token, old_token);
+    assert_eq!(rotated["permissions"], "view_only");
+
+    server
+        .get(&format!("/api/v1/public/share/{}", old_token))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+    server
+        .get(&format!("/api/v1/public/share/{}", new_token))
+        .await
+        .assert_status_ok();
+}
+
+#[test]
+fn test_public_share_url_joins_base_url_and_token() {
+    let mut server = ServerConfig::default();
+    assert_eq!(server.public_share_url("abc"), None);
+    assert_eq!(server.base_path(), "");
+
+    server.base_url = Some("https://photos.example.com/momento/".to_string());
+    assert_eq!(
+        server.public_share_url("abc").as_deref(),
+        Some("https://photos.example.com/momento/public/share/abc")
+    );
+    assert_eq!(server.base_path(), "/momento");
+}
+
+#[tokio::test]
+async fn test_share_links_include_public_url_when_base_url_set() {
+    let pool = create_test_db();
+    let mut config = Config::default();
+    config.server.base_url = Some("https://photos.example.com".to_string());
+    let server = TestServer::new(create_app(Arc::new(config), pool.clone())).unwrap();
+    let user_id = create_test_user(&pool, "share_url", "share_url@example.com");
+    let media_id = create_test_media(&pool, "share_url.jpg");
+    grant_media_access(&pool, media_id, user_id);
+    let token = create_test_token(user_id, "share_url", "user");
+
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(token.clone())
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .json();
+    let expected = format!(
+        "https://photos.example.com/public/share/{}",
+        share["token"].as_str().unwrap()
+    );
+    assert_eq!(share["publicUrl"], expected);
+
+    let list: serde_json::Value = server
+        .post("/api/v1/share/list")
+        .authorization_bearer(token)
+        .await
+        .json();
+    assert_eq!(list["shares"][0]["publicUrl"], expected);
+}
+
+#[tokio::test]
+async fn test_share_links_omit_public_url_without_base_url() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "share_nourl", "share_nourl@example.com");
+    let media_id = create_test_media(&pool, "share_nourl.jpg");
+    grant_media_access(&pool, media_id, user_id);
+
+    let server = TestServer::new(app).unwrap();
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(create_test_token(user_id, "share_nourl", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .json();
+    assert_eq!(share["publicUrl"], serde_json::Value::Null);
+}
+
+/// Serves over a real socket, since reporters are told apart by the
+/// address they connect from.
+fn report_server(config: Config, pool: DbPool) -> TestServer {
+    let app =
+        create_app(Arc::new(config), pool).into_make_service_with_connect_info::<SocketAddr>();
+    TestServer::new_with_config(app, TestServerConfig::builder().http_transport().build()).unwrap()
+}
+
+#[tokio::test]
+async fn test_share_reports_suspend_share_after_threshold() {
+    let mut config = Config::default();
+    config.share.report_suspend_threshold = 1;
+    config.server.trusted_proxies = vec!["127.0.0.0/8".to_string()];
+    let pool = create_test_db();
+    let user_id = create_test_user(&pool, "share_reported", "share_reported@example.com");
+    let admin_id = create_test_user(&pool, "report_admin", "report_admin@example.com");
+    pool.get()
+        .unwrap()
+        .execute("UPDATE users SET role = 'admin' WHERE id = ?", [admin_id])
+        .unwrap();
+    let media_id = create_test_media(&pool, "reported.jpg");
+    grant_media_access(&pool, media_id, user_id);
+    let server = report_server(config, pool);
+
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(create_test_token(user_id, "share_reported", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .json();
+    let token = share["token"].as_str().unwrap().to_string();
+    let report = |ip: &'static str, reason: &str| {
+        server
+            .post(&format!("/api/v1/public/share/{}/report", token))
+            .add_header(
+                HeaderName::from_static("x-forwarded-for"),
+                HeaderValue::from_static(ip),
+            )
+            .json(&json!({"reason": reason}))
+    };
+
+    report("203.0.113.1", "  too short ")
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Repeated reports from one client count once
+    report("203.0.113.1", "Contains someone else's photos")
+        .await
+        .assert_status_ok();
+    report("203.0.113.1", "Contains someone else's photos")
+        .await
+        .assert_status_ok();
+    server
+        .get(&format!("/api/v1/public/share/{}", token))
+        .await
+        .assert_status_ok();
+
+    report("198.51.100.7", "Inappropriate content in this album")
+        .await
+        .assert_status_ok();
+    server
+        .get(&format!("/api/v1/public/share/{}", token))
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Unknown tokens are accepted the same way, revealing nothing
+    server
+        .post("/api/v1/public/share/AAAAAAAAAAAAAAAAAAAAAA/report")
+        .json(&json!({"reason": "Reporting a link that does not exist"}))
+        .await
+        .assert_status_ok();
+
+    server
+        .get("/api/v1/admin/reports")
+        .authorization_bearer(create_test_token(user_id, "share_reported", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+    let reports: serde_json::Value = server
+        .get("/api/v1/admin/reports")
+        .authorization_bearer(create_test_token(admin_id, "report_admin", "admin"))
+        .await
+        .json();
+    let reports = reports.as_array().unwrap();
+    assert_eq!(reports.len(), 4);
+    assert_eq!(reports[0]["token"], "AAAAAAAAAAAAAAAAAAAAAA");
+    assert!(reports[0]["shareId"].is_null());
+    assert_eq!(reports[1]["token"], token);
+    assert_eq!(reports[1]["shareId"], share["id"]);
+    assert!(reports[1]["shareSuspendedAt"].is_string());
+}
+
+#[tokio::test]
+async fn test_share_reports_ignore_forwarded_headers_from_untrusted_peers() {
+    let mut config = Config::default();
+    config.share.report_suspend_threshold = 1;
+    let pool = create_test_db();
+    let user_id = create_test_user(&pool, "share_spoofed", "share_spoofed@example.com");
+    let media_id = create_test_media(&pool, "spoofed.jpg");
+    grant_media_access(&pool, media_id, user_id);
+    let server = report_server(config, pool);
+
+    let share: serde_json::Value = server
+        .post("/api/v1/share/create")
+        .authorization_bearer(create_test_token(user_id, "share_spoofed", "user"))
+        .json(&json!({"mediaId": media_id}))
+        .await
+        .json();
+    let token = share["token"].as_str().unwrap().to_string();
+
+    // Every spoofed address is the same peer, so they count as one reporter
+    for i in 0..SHARE_REPORT_RATE_LIMIT {
+        server
+            .post(&format!("/api/v1/public/share/{}/report", token))
+            .add_header(
+                HeaderName::from_static("x-forwarded-for"),
+                HeaderValue::from_str(&format!("203.0.113.{}", i + 1)).unwrap(),
+            )
+            .json(&json!({"reason": "Spoofing distinct reporters"}))
+            .await
+            .assert_status_ok();
+    }
+    server
+        .get(&format!("/api/v1/public/share/{}", token))
+        .await
+        .assert_status_ok();
+
+    server
+        .post(&format!("/api/v1/public/share/{}/report", token))
+        .json(&json!({"reason": "One report too many from here"}))
+        .await
+        .assert_status(StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,148 @@
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use momento_api::constants::{ALBUM_COVERS_DIR, THUMBNAILS_DIR};
+use momento_api::database::DbPool;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::test_utils::{
+    create_test_app, create_test_media, create_test_token, create_test_user, grant_media_access,
+};
+
+fn create_admin(pool: &DbPool, username: &str) -> i64 {
+    let admin_id = create_test_user(pool, username, &format!("{}@example.com", username));
+    let conn = pool.get().unwrap();
+    conn.execute("UPDATE users SET role = 'admin' WHERE id = ?", [admin_id])
+        .unwrap();
+    admin_id
+}
+
+#[tokio::test]
+async fn test_analyze_storage_reports_usage_and_trash() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_admin(&pool, "storage_admin");
+    let user_id = create_test_user(&pool, "storage_user", "storage_user@example.com");
+    let kept = create_test_media(&pool, "storage_kept.jpg");
+    let trashed = create_test_media(&pool, "storage_trashed.jpg");
+    grant_media_access(&pool, kept, user_id);
+    grant_media_access(&pool, trashed, user_id);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_metadata SET date_taken = '2024-03-10T12:00:00+00:00' WHERE media_id = ?",
+            [kept],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE media_access SET deleted_at = datetime('now') WHERE media_id = ?",
+            [trashed],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(admin_id, "storage_admin", "admin");
+    let response = server
+        .post("/api/v1/admin/storage/analyze")
+        .authorization_bearer(token.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+
+    let usage = body["usage"].as_array().unwrap();
+    assert_eq!(usage.len(), 1);
+    assert_eq!(usage[0]["userId"], user_id);
+    assert_eq!(usage[0]["month"], "2024-03");
+    assert_eq!(usage[0]["mediaType"], "image");
+    assert_eq!(usage[0]["totalBytes"], 1024000);
+    assert_eq!(usage[0]["fileCount"], 1);
+    assert_eq!(body["trash"][0]["userId"], user_id);
+    assert_eq!(body["trash"][0]["trashBytes"], 1024000);
+
+    // Served from the cache, so media added since is not reflected yet
+    let extra = create_test_media(&pool, "storage_extra.jpg");
+    grant_media_access(&pool, extra, user_id);
+    let cached: serde_json::Value = server
+        .post("/api/v1/admin/storage/analyze")
+        .authorization_bearer(token)
+        .await
+        .json();
+    assert_eq!(cached, body);
+}
+
+#[tokio::test]
+async fn test_analyze_storage_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "storage_plain", "storage_plain@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/admin/storage/analyze")
+        .authorization_bearer(create_test_token(user_id, "storage_plain", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_cleanup_orphans_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "orphan_user", "orphan_user@example.com");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .post("/api/v1/admin/storage/cleanup-orphans")
+        .authorization_bearer(create_test_token(user_id, "orphan_user", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
+
+fn write_old_file(path: &Path) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, b"bytes").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(SystemTime::now() - Duration::from_secs(2 * 60 * 60)))
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_cleanup_orphans_keeps_referenced_thumbnails_and_album_covers() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_admin(&pool, "orphan_admin");
+    let media_id = create_test_media(&pool, "orphan_referenced.jpg");
+
+    let folder = format!("orphan_cleanup_{}", media_id);
+    let referenced = THUMBNAILS_DIR.join(&folder).join("referenced.jpg");
+    let orphan = THUMBNAILS_DIR.join(&folder).join("orphan.jpg");
+    let cover = ALBUM_COVERS_DIR.join(format!("{}.jpg", media_id));
+    for path in [&referenced, &orphan, &cover] {
+        write_old_file(path);
+    }
+    pool.get()
+        .unwrap()
+        .execute(
+            "UPDATE media_metadata SET thumbnail_path = ? WHERE media_id = ?",
+            rusqlite::params![format!("{}/referenced.jpg", folder), media_id],
+        )
+        .unwrap();
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/admin/storage/cleanup-orphans")
+        .authorization_bearer(create_test_token(admin_id, "orphan_admin", "admin"))
+        .await;
+    response.assert_status_ok();
+    assert!(
+        response.json::<serde_json::Value>()["deletedFiles"]
+            .as_i64()
+            .unwrap()
+            >= 1
+    );
+
+    assert!(!orphan.exists());
+    assert!(referenced.exists());
+    assert!(cover.exists());
+    std::fs::remove_dir_all(THUMBNAILS_DIR.join(&folder)).unwrap();
+    std::fs::remove_file(cover).unwrap();
+}
@@ -0,0 +1,112 @@
+use axum_test::TestServer;
+use momento_api::database::DbPool;
+use serde_json::json;
+
+use crate::test_utils::{create_test_app, create_test_media, create_test_token, create_test_user};
+
+fn create_tag(pool: &DbPool, name: &str) -> i64 {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute("INSERT INTO tags (name) VALUES (?)", [name])
+        .expect("Failed to insert tag");
+    conn.last_insert_rowid()
+}
+
+fn tag_media(pool: &DbPool, media_id: i64, tag_id: i64) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO media_tags (media_id, tag_id) VALUES (?, ?)",
+        rusqlite::params![media_id, tag_id],
+    )
+    .expect("Failed to tag media");
+}
+
+fn make_admin(pool: &DbPool, user_id: i64) {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute("UPDATE users SET role = 'admin' WHERE id = ?", [user_id])
+        .expect("Failed to promote user");
+}
+
+#[tokio::test]
+async fn test_merge_tags_consolidates_three_way_duplicates() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "tag_merge_admin", "tag_merge_admin@example.com");
+    make_admin(&pool, admin_id);
+
+    let lower = create_tag(&pool, "vacation");
+    let title = create_tag(&pool, "Vacation");
+    let upper = create_tag(&pool, "VACATION");
+
+    let a = create_test_media(&pool, "a.jpg");
+    let b = create_test_media(&pool, "b.jpg");
+    let c = create_test_media(&pool, "c.jpg");
+    tag_media(&pool, a, lower);
+    tag_media(&pool, a, title);
+    tag_media(&pool, b, title);
+    tag_media(&pool, c, upper);
+    tag_media(&pool, c, title);
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/tag/merge")
+        .authorization_bearer(create_test_token(admin_id, "tag_merge_admin", "admin"))
+        .json(&json!({"sourceTagIds": [title, upper], "targetTagId": lower}))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["mergedCount"], 2);
+    assert_eq!(body["affectedMedia"], 3);
+
+    let conn = pool.get().unwrap();
+    let remaining_tags: i64 = conn
+        .query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(remaining_tags, 1);
+    let mut stmt = conn
+        .prepare("SELECT media_id FROM media_tags WHERE tag_id = ? ORDER BY media_id")
+        .unwrap();
+    let tagged: Vec<i64> = stmt
+        .query_map([lower], |row| row.get(0))
+        .unwrap()
+        .map(|r| r.unwrap())
+        .collect();
+    assert_eq!(tagged, vec![a, b, c]);
+    let total_rows: i64 = conn
+        .query_row("SELECT COUNT(*) FROM media_tags", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(total_rows, 3);
+}
+
+#[tokio::test]
+async fn test_merge_tags_rejects_unknown_tag() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_test_user(&pool, "tag_merge_missing", "tag_merge_missing@example.com");
+    make_admin(&pool, admin_id);
+    let target = create_tag(&pool, "beach");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/tag/merge")
+        .authorization_bearer(create_test_token(admin_id, "tag_merge_missing", "admin"))
+        .json(&json!({"sourceTagIds": [target + 100], "targetTagId": target}))
+        .await;
+
+    response.assert_status_not_found();
+}
+
+#[tokio::test]
+async fn test_merge_tags_requires_admin() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "tag_merge_user", "tag_merge_user@example.com");
+    let target = create_tag(&pool, "city");
+    let source = create_tag(&pool, "City");
+
+    let server = TestServer::new(app).unwrap();
+    let response = server
+        .post("/api/v1/tag/merge")
+        .authorization_bearer(create_test_token(user_id, "tag_merge_user", "user"))
+        .json(&json!({"sourceTagIds": [source], "targetTagId": target}))
+        .await;
+
+    response.assert_status_forbidden();
+}
@@ -0,0 +1,94 @@
+use chrono::{Duration, Utc};
+use momento_api::config::TrashConfig;
+use momento_api::constants::PREVIEWS_DIR;
+use momento_api::database::DbPool;
+use momento_api::routes::cleanup_expired_trash;
+
+use crate::test_utils::{create_test_db, create_test_media, create_test_user, grant_media_access};
+
+fn trash_media(pool: &DbPool, media_id: i64, user_id: i64, days_ago: i64) {
+    let deleted_at = (Utc::now() - Duration::days(days_ago)).to_rfc3339();
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "UPDATE media_access SET deleted_at = ? WHERE media_id = ? AND user_id = ?",
+        rusqlite::params![deleted_at, media_id, user_id],
+    )
+    .expect("Failed to trash media");
+}
+
+fn media_exists(pool: &DbPool, media_id: i64) -> bool {
+    let conn = pool.get().expect("Failed to get connection");
+    conn.query_row(
+        "SELECT COUNT(*) FROM media WHERE id = ?",
+        [media_id],
+        |row| row.get::<_, i64>(0),
+    )
+    .expect("Failed to count media")
+        > 0
+}
+
+#[tokio::test]
+async fn test_cleanup_expired_trash_uses_retention_per_role() {
+    let pool = create_test_db();
+    let user_id = create_test_user(&pool, "trash_user", "trash_user@example.com");
+    let admin_id = create_test_user(&pool, "trash_admin", "trash_admin@example.com");
+    pool.get()
+        .unwrap()
+        .execute("UPDATE users SET role = 'admin' WHERE id = ?", [admin_id])
+        .unwrap();
+
+    let user_media = create_test_media(&pool, "trash_user.jpg");
+    let admin_media = create_test_media(&pool, "trash_admin.jpg");
+    grant_media_access(&pool, user_media, user_id);
+    grant_media_access(&pool, admin_media, admin_id);
+    trash_media(&pool, user_media, user_id, 20);
+    trash_media(&pool, admin_media, admin_id, 20);
+
+    let config = TrashConfig {
+        retention_days_user: 7,
+        retention_days_admin: 60,
+    };
+    let conn = pool.get().unwrap();
+    let purged = cleanup_expired_trash(&conn, &config).unwrap();
+
+    assert_eq!(purged, 1);
+    assert!(!media_exists(&pool, user_media));
+    assert!(media_exists(&pool, admin_media));
+
+    let out_of_range = TrashConfig {
+        retention_days_user: 7,
+        retention_days_admin: i64::MAX,
+    };
+    assert!(cleanup_expired_trash(&conn, &out_of_range).is_err());
+    assert!(media_exists(&pool, admin_media));
+}
+
+#[tokio::test]
+async fn test_cleanup_expired_trash_removes_previews_of_trashed_media() {
+    let pool = create_test_db();
+    let user_id = create_test_user(&pool, "trash_preview", "trash_preview@example.com");
+    let trashed = create_test_media(&pool, "trashed_preview_src.jpg");
+    let kept = create_test_media(&pool, "kept_preview_src.jpg");
+    grant_media_access(&pool, trashed, user_id);
+    grant_media_access(&pool, kept, user_id);
+    trash_media(&pool, trashed, user_id, 1);
+
+    let user_previews = PREVIEWS_DIR.join(user_id.to_string());
+    std::fs::create_dir_all(&user_previews).unwrap();
+    let trashed_preview = user_previews.join("trashed_preview_src_preview.webp");
+    let trashed_jpeg_preview = user_previews.join("trashed_preview_src_preview.jpg");
+    let kept_preview = user_previews.join("kept_preview_src_preview.webp");
+    std::fs::write(&trashed_preview, b"preview").unwrap();
+    std::fs::write(&trashed_jpeg_preview, b"preview").unwrap();
+    std::fs::write(&kept_preview, b"preview").unwrap();
+
+    let conn = pool.get().unwrap();
+    let purged = cleanup_expired_trash(&conn, &TrashConfig::default()).unwrap();
+
+    assert_eq!(purged, 0);
+    assert!(media_exists(&pool, trashed));
+    assert!(!trashed_preview.exists());
+    assert!(!trashed_jpeg_preview.exists());
+    assert!(kept_preview.exists());
+    std::fs::remove_file(kept_preview).unwrap();
+}
@@ -0,0 +1,223 @@
+use axum::http::{header::AUTHORIZATION, HeaderValue, StatusCode};
+use axum_test::TestServer;
+use base64::Engine;
+use momento_api::auth::{create_refresh_token, hash_password};
+use momento_api::config::Config;
+use momento_api::database::DbPool;
+use serde_json::json;
+
+use crate::test_utils::{
+    create_test_app, create_test_media, create_test_token, create_test_user, grant_media_access,
+};
+
+fn create_admin(pool: &DbPool, username: &str) -> i64 {
+    let admin_id = create_test_user(pool, username, &format!("{}@example.com", username));
+    let conn = pool.get().unwrap();
+    conn.execute("UPDATE users SET role = 'admin' WHERE id = ?", [admin_id])
+        .unwrap();
+    admin_id
+}
+
+fn basic_auth(username: &str, password: &str) -> HeaderValue {
+    let credentials =
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+    HeaderValue::from_str(&format!("Basic {}", credentials)).unwrap()
+}
+
+#[tokio::test]
+async fn test_temporary_password_forces_password_change() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_admin(&pool, "temp_pw_admin");
+    let user_id = create_test_user(&pool, "temp_pw_user", "temp_pw_user@example.com");
+    let (raw_refresh, token_hash, expires_at) = create_refresh_token(user_id, &Config::default());
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO refresh_tokens (token_hash, user_id, expires_at) VALUES (?, ?, ?)",
+            rusqlite::params![token_hash, user_id, expires_at.to_rfc3339()],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+
+    server
+        .post(&format!("/api/v1/admin/users/{}/reset-password", user_id))
+        .authorization_bearer(create_test_token(user_id, "temp_pw_user", "user"))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let response = server
+        .post(&format!("/api/v1/admin/users/{}/reset-password", user_id))
+        .authorization_bearer(create_test_token(admin_id, "temp_pw_admin", "admin"))
+        .await;
+    response.assert_status_ok();
+    let temporary_password = response.json::<serde_json::Value>()["temporaryPassword"]
+        .as_str()
+        .expect("temporary password should be a string")
+        .to_string();
+    assert_eq!(temporary_password.len(), 16);
+
+    server
+        .post("/api/v1/user/refresh")
+        .json(&json!({"refreshToken": raw_refresh}))
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED);
+
+    let response = server
+        .post("/api/v1/user/authenticate")
+        .add_header(
+            AUTHORIZATION,
+            basic_auth("temp_pw_user", &temporary_password),
+        )
+        .await;
+    response.assert_status_ok();
+    let access_token = response.json::<serde_json::Value>()["accessToken"]
+        .as_str()
+        .expect("access token should be a string")
+        .to_string();
+
+    let response = server
+        .post("/api/v1/media/list")
+        .authorization_bearer(access_token.clone())
+        .json(&json!({}))
+        .await;
+    response.assert_status(StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json::<serde_json::Value>()["code"],
+        "MUST_CHANGE_PASSWORD"
+    );
+
+    let response = server
+        .post("/api/v1/user/get")
+        .authorization_bearer(access_token.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        response.json::<serde_json::Value>()["mustChangePassword"],
+        true
+    );
+
+    server
+        .post("/api/v1/user/change-password")
+        .authorization_bearer(access_token.clone())
+        .json(&json!({
+            "currentPassword": temporary_password,
+            "newPassword": "my-own-password",
+        }))
+        .await
+        .assert_status_ok();
+
+    server
+        .post("/api/v1/media/list")
+        .authorization_bearer(access_token)
+        .json(&json!({}))
+        .await
+        .assert_status_ok();
+}
+
+fn set_password(pool: &DbPool, user_id: i64, password: &str) {
+    let conn = pool.get().unwrap();
+    conn.execute(
+        "UPDATE users SET hashed_password = ? WHERE id = ?",
+        rusqlite::params![hash_password(password).unwrap(), user_id],
+    )
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_delete_account_removes_user_and_unshared_media() {
+    let (app, pool) = create_test_app();
+    let user_id = create_test_user(&pool, "leaving_user", "leaving_user@example.com");
+    let other_id = create_test_user(&pool, "staying_user", "staying_user@example.com");
+    set_password(&pool, user_id, "correct horse");
+
+    let own_media = create_test_media(&pool, "own.jpg");
+    let trashed_media = create_test_media(&pool, "trashed.jpg");
+    let shared_media = create_test_media(&pool, "shared.jpg");
+    for media_id in [own_media, trashed_media, shared_media] {
+        grant_media_access(&pool, media_id, user_id);
+    }
+    grant_media_access(&pool, shared_media, other_id);
+    {
+        let conn = pool.get().unwrap();
+        conn.execute(
+            "UPDATE media_access SET deleted_at = datetime('now') WHERE media_id = ?",
+            [trashed_media],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO share_links (user_id, media_id, token) VALUES (?, ?, 'leaving-share')",
+            rusqlite::params![user_id, own_media],
+        )
+        .unwrap();
+    }
+
+    let server = TestServer::new(app).unwrap();
+    let token = create_test_token(user_id, "leaving_user", "user");
+
+    for (body, status) in [
+        (
+            json!({"password": "correct horse"}),
+            StatusCode::BAD_REQUEST,
+        ),
+        (
+            json!({"password": "wrong", "confirm": true}),
+            StatusCode::BAD_REQUEST,
+        ),
+    ] {
+        server
+            .delete("/api/v1/user/account")
+            .authorization_bearer(&token)
+            .json(&body)
+            .await
+            .assert_status(status);
+    }
+
+    server
+        .delete("/api/v1/user/account")
+        .authorization_bearer(&token)
+        .json(&json!({"password": "correct horse", "confirm": true}))
+        .await
+        .assert_status_ok();
+
+    let conn = pool.get().unwrap();
+    let count = |sql: &str| -> i64 { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(
+        count(&format!(
+            "SELECT COUNT(*) FROM users WHERE id = {}",
+            user_id
+        )),
+        0
+    );
+    assert_eq!(
+        count(&format!(
+            "SELECT COUNT(*) FROM media WHERE id IN ({}, {})",
+            own_media, trashed_media
+        )),
+        0
+    );
+    assert_eq!(
+        count(&format!(
+            "SELECT COUNT(*) FROM media_access WHERE media_id = {} AND user_id = {}",
+            shared_media, other_id
+        )),
+        1
+    );
+    assert_eq!(count("SELECT COUNT(*) FROM share_links"), 0);
+}
+
+#[tokio::test]
+async fn test_delete_account_refuses_admin() {
+    let (app, pool) = create_test_app();
+    let admin_id = create_admin(&pool, "self_delete_admin");
+    set_password(&pool, admin_id, "admin password");
+
+    let server = TestServer::new(app).unwrap();
+    server
+        .delete("/api/v1/user/account")
+        .authorization_bearer(create_test_token(admin_id, "self_delete_admin", "admin"))
+        .json(&json!({"password": "admin password", "confirm": true}))
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+}
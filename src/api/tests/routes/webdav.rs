@@ -0,0 +1,99 @@
+use axum::http::{HeaderName, HeaderValue, Method, StatusCode};
+use axum_test::TestServer;
+use base64::Engine;
+use std::sync::Arc;
+
+use momento_api::app::create_app;
+use momento_api::auth::hash_password;
+use momento_api::config::Config;
+use momento_api::constants::{WEBDAV_DIR, WEBDAV_WELCOME_FILENAME, WEBDAV_WELCOME_TEXT};
+use momento_api::database::DbPool;
+
+use crate::test_utils::create_test_db;
+
+fn create_webdav_app() -> (TestServer, DbPool) {
+    create_webdav_app_with_config(Config::default())
+}
+
+fn create_webdav_app_with_config(mut config: Config) -> (TestServer, DbPool) {
+    let pool = create_test_db();
+    config.webdav.enabled = true;
+    let app = create_app(Arc::new(config), pool.clone());
+    (TestServer::new(app).unwrap(), pool)
+}
+
+fn basic_auth(pool: &DbPool, username: &str, password: &str) -> HeaderValue {
+    let hashed = hash_password(password).expect("Failed to hash password");
+    let conn = pool.get().expect("Failed to get connection");
+    conn.execute(
+        "INSERT INTO users (username, email, hashed_password, role, must_change_password, is_active) \
+         VALUES (?, ?, ?, 'user', 0, 1)",
+        rusqlite::params![username, format!("{}@example.com", username), hashed],
+    )
+    .expect("Failed to insert WebDAV user");
+
+    let credentials =
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+    HeaderValue::from_str(&format!("Basic {}", credentials)).unwrap()
+}
+
+#[tokio::test]
+async fn test_propfind_infinite_depth_is_forbidden() {
+    let (server, pool) = create_webdav_app();
+    let auth = basic_auth(&pool, "dav_depth", "dav-password");
+
+    let response = server
+        .method(Method::from_bytes(b"PROPFIND").unwrap(), "/webdav/")
+        .add_header(HeaderName::from_static("authorization"), auth)
+        .add_header(
+            HeaderName::from_static("depth"),
+            HeaderValue::from_static("infinity"),
+        )
+        .await;
+
+    response.assert_status(StatusCode::FORBIDDEN);
+    assert!(response.text().contains("propfind-finite-depth"));
+}
+
+#[tokio::test]
+async fn test_propfind_hrefs_use_base_url_path() {
+    let mut config = Config::default();
+    config.server.base_url = Some("https://photos.example.com/momento".to_string());
+    let (server, pool) = create_webdav_app_with_config(config);
+    let auth = basic_auth(&pool, "dav_prefix", "dav-password");
+
+    let response = server
+        .method(Method::from_bytes(b"PROPFIND").unwrap(), "/webdav/")
+        .add_header(HeaderName::from_static("authorization"), auth)
+        .add_header(
+            HeaderName::from_static("depth"),
+            HeaderValue::from_static("0"),
+        )
+        .await;
+
+    response.assert_status(StatusCode::MULTI_STATUS);
+    assert!(response
+        .text()
+        .contains("<D:href>/momento/webdav/</D:href>"));
+}
+
+#[tokio::test]
+async fn test_first_login_creates_user_root_with_welcome_file() {
+    let user_root = WEBDAV_DIR.join("dav_welcome");
+    let _ = std::fs::remove_dir_all(&user_root);
+    let (server, pool) = create_webdav_app();
+    let auth = basic_auth(&pool, "dav_welcome", "dav-password");
+
+    server
+        .method(Method::from_bytes(b"PROPFIND").unwrap(), "/webdav/")
+        .add_header(HeaderName::from_static("authorization"), auth)
+        .add_header(
+            HeaderName::from_static("depth"),
+            HeaderValue::from_static("1"),
+        )
+        .await
+        .assert_status(StatusCode::MULTI_STATUS);
+
+    let welcome = std::fs::read_to_string(user_root.join(WEBDAV_WELCOME_FILENAME)).unwrap();
+    assert_eq!(welcome, WEBDAV_WELCOME_TEXT);
+}
@@ -6,27 +6,40 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use momento_api::app::create_app;
+use momento_api::auth::create_access_token;
 use momento_api::config::Config;
-use momento_api::database::{init_database, DbPool};
+use momento_api::database::{init_database, init_database_at_version, DbPool};
 
 static MEDIA_ID_COUNTER: AtomicI64 = AtomicI64::new(1);
 static USER_ID_COUNTER: AtomicI64 = AtomicI64::new(1);
 
 pub fn create_test_db() -> DbPool {
+    let pool = create_empty_test_pool();
+    let conn = pool.get().expect("Failed to get connection from pool");
+    init_database(&conn).expect("Failed to initialize test database schema");
+
+    pool
+}
+
+/// A test database whose schema stops at `version`, for migration tests.
+pub fn create_test_db_at_version(version: usize) -> DbPool {
+    let pool = create_empty_test_pool();
+    let conn = pool.get().expect("Failed to get connection from pool");
+    init_database_at_version(&conn, version).expect("Failed to initialize test database schema");
+
+    pool
+}
+
+fn create_empty_test_pool() -> DbPool {
     let manager = SqliteConnectionManager::memory().with_init(|conn| {
         conn.execute_batch("PRAGMA foreign_keys = ON")?;
         Ok(())
     });
 
-    let pool = Pool::builder()
+    Pool::builder()
         .max_size(5)
         .build(manager)
-        .expect("Failed to create test database pool");
-
-    let conn = pool.get().expect("Failed to get connection from pool");
-    init_database(&conn).expect("Failed to initialize test database schema");
-
-    pool
+        .expect("Failed to create test database pool")
 }
 
 pub fn create_test_app() -> (Router, DbPool) {
@@ -50,6 +63,11 @@ pub fn create_test_user(pool: &DbPool, username: &str, email: &str) -> i64 {
     user_id
 }
 
+pub fn create_test_token(user_id: i64, username: &str, role: &str) -> String {
+    create_access_token(user_id, username, role, &Config::default())
+        .expect("Failed to create test token")
+}
+
 pub fn create_test_media_with_gps(
     pool: &DbPool,
     filename: &str,
@@ -100,9 +118,10 @@ pub fn create_test_media_with_gps_and_date(
 
     conn.execute(
         "INSERT INTO media_metadata (
-            media_id, width, height, date_taken, gps_latitude, gps_longitude, geohash
-        ) VALUES (?, ?, ?, ?, ?, ?, ?)",
-        rusqlite::params![media_id, 1920, 1080, date_taken, latitude, longitude, geohash,],
+            media_id, width, height, date_taken, gps_latitude, gps_longitude, geohash,
+            geohash_precise
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![media_id, 1920, 1080, date_taken, latitude, longitude, geohash, geohash,],
     )
     .expect("Failed to insert test media metadata");
 
@@ -0,0 +1,98 @@
+use momento_api::utils::download::{
+    content_disposition_filename, is_blocked_address, resolve_public_address,
+};
+use reqwest::Url;
+use std::net::{IpAddr, SocketAddr};
+
+fn ip(address: &str) -> IpAddr {
+    address.parse().unwrap()
+}
+
+#[test]
+fn test_blocked_addresses_cover_private_ranges() {
+    for address in [
+        "10.1.2.3",
+        "172.16.0.1",
+        "172.31.255.255",
+        "192.168.1.10",
+        "127.0.0.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:192.168.0.1",
+        "0.1.2.3",
+        "198.18.0.1",
+        "198.19.255.255",
+        "224.0.0.251",
+        "240.0.0.1",
+        "ff02::fb",
+        "64:ff9b::a9fe:a9fe",
+    ] {
+        assert!(
+            is_blocked_address(ip(address)),
+            "{} should be blocked",
+            address
+        );
+    }
+
+    for address in [
+        "93.184.216.34",
+        "172.32.0.1",
+        "198.20.0.1",
+        "223.255.255.254",
+        "2606:4700::1111",
+        "64:ff9c::1",
+    ] {
+        assert!(
+            !is_blocked_address(ip(address)),
+            "{} should be allowed",
+            address
+        );
+    }
+}
+
+#[test]
+fn test_content_disposition_filename() {
+    assert_eq!(
+        content_disposition_filename(r#"attachment; filename="beach.jpg""#).as_deref(),
+        Some("beach.jpg")
+    );
+    assert_eq!(
+        content_disposition_filename("inline; FILENAME=clip.mp4").as_deref(),
+        Some("clip.mp4")
+    );
+    assert_eq!(content_disposition_filename("inline"), None);
+    assert_eq!(
+        content_disposition_filename(r#"attachment; filename="""#),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_resolve_public_address_accepts_public_hosts_only() {
+    for (url, expected) in [
+        ("https://93.184.216.34/photo.jpg", "93.184.216.34:443"),
+        (
+            "https://[2606:4700::1111]:8443/clip.mp4",
+            "[2606:4700::1111]:8443",
+        ),
+    ] {
+        let address = resolve_public_address(&Url::parse(url).unwrap())
+            .await
+            .unwrap_or_else(|e| panic!("{} should resolve: {}", url, e));
+        assert_eq!(address, expected.parse::<SocketAddr>().unwrap());
+    }
+
+    for url in [
+        "https://127.0.0.1/photo.jpg",
+        "https://198.18.0.1/photo.jpg",
+        "https://[64:ff9b::7f00:1]/photo.jpg",
+    ] {
+        assert!(resolve_public_address(&Url::parse(url).unwrap())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use image::{DynamicImage, Rgb, RgbImage};
+use momento_api::utils::hash::{
+    calculate_quick_fingerprint, perceptual_hash_distance, perceptual_hash_of,
+};
+
+fn scene(width: u32, height: u32) -> DynamicImage {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        let fx = x as f64 / width as f64;
+        let fy = y as f64 / height as f64;
+        let in_sun = (fx - 0.7).powi(2) + (fy - 0.3).powi(2) < 0.02;
+        if in_sun {
+            Rgb([250, 220, 90])
+        } else if fy > 0.6 + 0.1 * (fx * 6.0).sin() {
+            Rgb([40, 110, 50])
+        } else {
+            let sky = (120.0 + 100.0 * fy) as u8;
+            Rgb([sky / 2, sky, 230])
+        }
+    });
+    DynamicImage::ImageRgb8(image)
+}
+
+fn stripes(width: u32, height: u32) -> DynamicImage {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+        if (x * 5 / width + y * 3 / height).is_multiple_of(2) {
+            Rgb([20, 20, 20])
+        } else {
+            Rgb([235, 235, 235])
+        }
+    });
+    DynamicImage::ImageRgb8(image)
+}
+
+#[test]
+fn test_perceptual_hash_matches_resized_and_rotated_copies() {
+    let original = perceptual_hash_of(&scene(400, 300));
+    let resized = perceptual_hash_of(&scene(400, 300).resize_exact(
+        120,
+        90,
+        image::imageops::FilterType::Lanczos3,
+    ));
+    let rotated = perceptual_hash_of(&scene(400, 300).rotate90());
+    let upside_down = perceptual_hash_of(&scene(400, 300).rotate180());
+
+    assert!(perceptual_hash_distance(original, resized) <= 8);
+    assert!(perceptual_hash_distance(original, rotated) <= 8);
+    assert!(perceptual_hash_distance(original, upside_down) <= 8);
+}
+
+#[test]
+fn test_perceptual_hash_separates_different_images() {
+    let landscape = perceptual_hash_of(&scene(400, 300));
+    let pattern = perceptual_hash_of(&stripes(400, 300));
+
+    assert!(perceptual_hash_distance(landscape, pattern) > 8);
+}
+
+#[tokio::test]
+async fn test_quick_fingerprint_covers_head_and_size() {
+    let dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let head = vec![7u8; 64 * 1024];
+    let write = |name: &str, tail: &[u8]| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, [head.as_slice(), tail].concat()).unwrap();
+        path
+    };
+
+    let original = write("a.jpg", b"tail");
+    let copy = write("b.jpg", b"tail");
+    let changed_tail = write("c.jpg", b"TAIL");
+    let longer = write("d.jpg", b"tail!");
+
+    let fingerprint = calculate_quick_fingerprint(&original).await.unwrap();
+    assert_eq!(
+        fingerprint,
+        calculate_quick_fingerprint(&copy).await.unwrap()
+    );
+    // Bytes past the first 64 KiB are deliberately not read
+    assert_eq!(
+        fingerprint,
+        calculate_quick_fingerprint(&changed_tail).await.unwrap()
+    );
+    assert_ne!(
+        fingerprint,
+        calculate_quick_fingerprint(&longer).await.unwrap()
+    );
+}
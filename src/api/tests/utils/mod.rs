@@ -0,0 +1,5 @@
+mod download;
+mod hash;
+mod network;
+mod shuffle;
+mod validation;
@@ -0,0 +1,43 @@
+use axum::http::{HeaderMap, HeaderValue};
+use momento_api::utils::network::resolve_client_ip;
+use std::net::IpAddr;
+
+fn forwarded_for(value: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-for", HeaderValue::from_static(value));
+    headers
+}
+
+#[test]
+fn test_resolve_client_ip_ignores_headers_from_untrusted_peers() {
+    let peer: IpAddr = "198.51.100.20".parse().unwrap();
+    let headers = forwarded_for("203.0.113.9");
+
+    assert_eq!(resolve_client_ip(&headers, peer, &[]), peer);
+    assert_eq!(
+        resolve_client_ip(&headers, peer, &["10.0.0.0/8".to_string()]),
+        peer
+    );
+}
+
+#[test]
+fn test_resolve_client_ip_skips_trusted_hops() {
+    let trusted = vec!["10.0.0.0/8".to_string(), "fd00::1".to_string()];
+    let peer: IpAddr = "10.1.2.3".parse().unwrap();
+
+    // A client-supplied first hop cannot hide the address the proxy saw
+    let headers = forwarded_for("192.0.2.1, 203.0.113.9, 10.4.5.6");
+    assert_eq!(
+        resolve_client_ip(&headers, peer, &trusted),
+        "203.0.113.9".parse::<IpAddr>().unwrap()
+    );
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-real-ip", HeaderValue::from_static("203.0.113.10"));
+    assert_eq!(
+        resolve_client_ip(&headers, "fd00::1".parse().unwrap(), &trusted),
+        "203.0.113.10".parse::<IpAddr>().unwrap()
+    );
+
+    assert_eq!(resolve_client_ip(&HeaderMap::new(), peer, &trusted), peer);
+}
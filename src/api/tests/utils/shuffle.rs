@@ -0,0 +1,37 @@
+use momento_api::utils::shuffle::seeded_shuffle;
+
+#[test]
+fn test_seeded_shuffle_is_deterministic_per_seed() {
+    let original: Vec<i64> = (1..=50).collect();
+
+    let mut first = original.clone();
+    seeded_shuffle(&mut first, 42);
+    let mut second = original.clone();
+    seeded_shuffle(&mut second, 42);
+    let mut other_seed = original.clone();
+    seeded_shuffle(&mut other_seed, 43);
+
+    assert_eq!(first, second);
+    assert_ne!(first, original);
+    assert_ne!(first, other_seed);
+
+    let mut sorted = first.clone();
+    sorted.sort();
+    assert_eq!(sorted, original);
+}
+
+#[test]
+fn test_seeded_shuffle_handles_zero_seed_and_short_input() {
+    let mut items: Vec<i64> = (1..=10).collect();
+    seeded_shuffle(&mut items, 0);
+    let mut sorted = items.clone();
+    sorted.sort();
+    assert_eq!(sorted, (1..=10).collect::<Vec<_>>());
+
+    let mut empty: Vec<i64> = Vec::new();
+    seeded_shuffle(&mut empty, 7);
+    assert!(empty.is_empty());
+    let mut single = vec![5];
+    seeded_shuffle(&mut single, 7);
+    assert_eq!(single, vec![5]);
+}
@@ -0,0 +1,17 @@
+use momento_api::utils::validation::validate_share_token_format;
+
+#[test]
+fn test_share_token_format_accepts_generated_shape() {
+    assert!(validate_share_token_format("aB3dE5fG7hJ9kL1mN2pQ4r"));
+    assert!(validate_share_token_format("0123456789012345678901"));
+}
+
+#[test]
+fn test_share_token_format_rejects_invalid_tokens() {
+    assert!(!validate_share_token_format(""));
+    assert!(!validate_share_token_format("aB3dE5fG7hJ9kL1mN2pQ4"));
+    assert!(!validate_share_token_format("aB3dE5fG7hJ9kL1mN2pQ4rX"));
+    assert!(!validate_share_token_format("aB3dE5fG7hJ9kL1mN2pQ-r"));
+    assert!(!validate_share_token_format("' OR 1=1 --aaaaaaaaaaa"));
+    assert!(!validate_share_token_format("aB3dE5fG7hJ9kL1mN2pQé"));
+}
@@ -15,6 +15,8 @@ interface ImportStatus {
   totalFiles: number
   processedFiles: number
   successfulImports: number
+  newImports: number
+  duplicateImports: number
   failedImports: number
   startedAt: string | null
   completedAt: string | null
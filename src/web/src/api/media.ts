@@ -35,6 +35,9 @@ interface TimelineListResponse {
 
 export type { GroupBy }
 
+// Server-side limit on ids per /media/get-batch request
+const MEDIA_BATCH_SIZE = 100
+
 // Cache for blob URLs to avoid re-fetching
 const blobUrlCache = new Map<string, string>()
 // Cache for in-flight requests to avoid duplicate fetches
@@ -219,8 +222,16 @@ export const mediaApi = {
 
   getBatch: async (mediaIds: number[]): Promise<Media[]> => {
     if (mediaIds.length === 0) return []
-    const response = await apiClient.post<MediaBatchResponse>('/media/get-batch', { ids: mediaIds } as MediaBatchRequest)
-    return response.data.items
+    const chunks: number[][] = []
+    for (let i = 0; i < mediaIds.length; i += MEDIA_BATCH_SIZE) {
+      chunks.push(mediaIds.slice(i, i + MEDIA_BATCH_SIZE))
+    }
+    const responses = await Promise.all(
+      chunks.map((ids) =>
+        apiClient.post<MediaBatchResponse>('/media/get-batch', { ids } as MediaBatchRequest)
+      )
+    )
+    return responses.flatMap((response) => response.data.items)
   },
 
   listMapMedia: async (): Promise<Media[]> => {
@@ -25,6 +25,7 @@ export interface Media {
   focalLength35mm: number | null
   keywords: string | null
   createdAt: string
+  isFavorite: boolean
 }
 
 export interface Album {
@@ -49,6 +50,7 @@ export interface ShareLink {
   hasPassword: boolean
   expiresAt: string | null
   viewCount: number
+  permissions: 'view_only' | 'download'
   createdAt: string
 }
 
@@ -56,6 +58,7 @@ export interface PaginatedResponse<T> {
   items: T[]
   nextCursor: string | null
   hasMore: boolean
+  totalCount?: number
 }
 
 export interface TimelineGroup {
@@ -76,8 +76,8 @@ export default function UserManagement() {
       setShowCreateModal(false)
       loadUsers()
     } catch (err) {
-      if (err instanceof AxiosError && err.response?.data?.detail) {
-        setServerError(err.response.data.detail)
+      if (err instanceof AxiosError && err.response?.data?.message) {
+        setServerError(err.response.data.message)
       } else {
         const message = err instanceof Error ? err.message : 'Failed to create user'
         setServerError(message)